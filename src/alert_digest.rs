@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{error, info};
+
+use crate::alert_tier::AlertTier;
+use crate::config::AlertsConfig;
+use crate::telegram::TelegramNotifier;
+use crate::utils::{sleep_or_shutdown, Shutdown};
+
+/// Возможность, ожидающая отправки в сводке.
+#[derive(Debug, Clone)]
+pub struct PendingAlert {
+    pub symbol: String,
+    pub reference_name: String,
+    pub reference_price: f64,
+    pub other_name: String,
+    pub other_price: f64,
+    /// Знаков после запятой в ценах, см. `SharedState::price_decimals`.
+    pub price_decimals: usize,
+    pub difference: f64,
+    pub net_difference: f64,
+    /// Уровень лучшего сигнала пары за интервал.
+    pub tier: AlertTier,
+    /// Сколько раз пара срабатывала за интервал.
+    pub hits: u32,
+}
+
+/// Сводка арбитражных сигналов (ALERT_DIGEST_SECS).
+///
+/// Вместо отдельного сообщения на каждый сигнал возможности копятся в очереди и раз в
+/// интервал уходят одним сообщением. Повторы одной пары за интервал схлопываются в одну
+/// строку с максимальным спредом.
+#[derive(Debug)]
+pub struct AlertDigest {
+    interval: Duration,
+    /// (символ, биржа 1, биржа 2) -> лучший сигнал за интервал.
+    pending: Mutex<HashMap<(String, String, String), PendingAlert>>,
+}
+
+impl AlertDigest {
+    /// ALERT_DIGEST_SECS из конфига. Если не задан или равен 0 – сводка отключена и
+    /// сигналы отправляются сразу.
+    pub fn from_config(config: &AlertsConfig) -> Option<Self> {
+        let secs = config.digest_secs?;
+        if secs == 0 {
+            return None;
+        }
+        info!("Batching arbitrage alerts into a digest every {}s", secs);
+        Some(Self {
+            interval: Duration::from_secs(secs),
+            pending: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Ставит сигнал в очередь. Для уже стоящей пары оставляет больший спред.
+    pub fn push(&self, alert: PendingAlert) {
+        let key = (
+            alert.symbol.clone(),
+            alert.reference_name.clone(),
+            alert.other_name.clone(),
+        );
+        let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+        match pending.get_mut(&key) {
+            Some(existing) => {
+                let hits = existing.hits + 1;
+                if alert.net_difference > existing.net_difference {
+                    *existing = alert;
+                }
+                existing.hits = hits;
+            }
+            None => {
+                pending.insert(key, PendingAlert { hits: 1, ..alert });
+            }
+        }
+    }
+
+    /// Забирает накопленные сигналы, отсортированные по убыванию спреда после комиссий.
+    fn take(&self) -> Vec<PendingAlert> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()));
+        let mut alerts: Vec<PendingAlert> = pending.into_values().collect();
+        alerts.sort_by(|a, b| b.net_difference.total_cmp(&a.net_difference));
+        alerts
+    }
+
+    async fn flush(&self, telegram: &TelegramNotifier) {
+        let alerts = self.take();
+        if alerts.is_empty() {
+            return;
+        }
+        if let Err(e) = telegram.send_arbitrage_digest(&alerts, self.interval).await {
+            error!("Failed to send Telegram alert digest ({} alerts): {}", alerts.len(), e);
+        }
+    }
+
+    /// Раз в интервал отправляет сводку; при остановке отправляет остаток и выходит.
+    pub async fn flush_periodically(&self, telegram: &TelegramNotifier, mut shutdown: Shutdown) {
+        loop {
+            let stop = sleep_or_shutdown(self.interval, &mut shutdown).await;
+            self.flush(telegram).await;
+            if stop {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(symbol: &str, other_name: &str, net_difference: f64) -> PendingAlert {
+        PendingAlert {
+            symbol: symbol.to_string(),
+            reference_name: "Bybit".to_string(),
+            reference_price: 100.0,
+            other_name: other_name.to_string(),
+            other_price: 100.0 + net_difference,
+            price_decimals: 8,
+            difference: net_difference + 0.1,
+            net_difference,
+            tier: AlertTier::Info,
+            hits: 0,
+        }
+    }
+
+    fn digest() -> AlertDigest {
+        let config = AlertsConfig { digest_secs: Some(60), ..AlertsConfig::default() };
+        AlertDigest::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn zero_interval_disables_digest() {
+        assert!(AlertDigest::from_config(&AlertsConfig::default()).is_none());
+        let config = AlertsConfig { digest_secs: Some(0), ..AlertsConfig::default() };
+        assert!(AlertDigest::from_config(&config).is_none());
+    }
+
+    #[test]
+    fn repeated_pair_keeps_best_spread_and_counts_hits() {
+        let digest = digest();
+        digest.push(alert("BTCUSDT", "Hyperliquid", 0.5));
+        digest.push(alert("BTCUSDT", "Hyperliquid", 0.9));
+        digest.push(PendingAlert { tier: AlertTier::Urgent, ..alert("BTCUSDT", "Hyperliquid", 0.7) });
+
+        let alerts = digest.take();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].net_difference, 0.9);
+        assert_eq!(alerts[0].tier, AlertTier::Info);
+        assert_eq!(alerts[0].hits, 3);
+    }
+
+    #[test]
+    fn take_sorts_by_net_spread_and_clears_queue() {
+        let digest = digest();
+        digest.push(alert("ETHUSDT", "Hyperliquid", 0.3));
+        digest.push(alert("BTCUSDT", "Hyperliquid", 1.2));
+        digest.push(alert("BTCUSDT", "ASTER", 0.8));
+
+        let order: Vec<(String, f64)> = digest
+            .take()
+            .into_iter()
+            .map(|a| (format!("{} {}", a.symbol, a.other_name), a.net_difference))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("BTCUSDT Hyperliquid".to_string(), 1.2),
+                ("BTCUSDT ASTER".to_string(), 0.8),
+                ("ETHUSDT Hyperliquid".to_string(), 0.3),
+            ]
+        );
+        assert!(digest.take().is_empty());
+
+        digest.push(alert("ETHUSDT", "Hyperliquid", 0.4));
+        assert_eq!(digest.take()[0].hits, 1);
+    }
+}
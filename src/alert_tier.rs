@@ -0,0 +1,115 @@
+use log::warn;
+
+use crate::config::AlertsConfig;
+
+/// Во сколько раз спред должен превысить порог для уровня WARN и URGENT по умолчанию.
+const DEFAULT_WARN_MULTIPLIER: f64 = 2.0;
+const DEFAULT_URGENT_MULTIPLIER: f64 = 5.0;
+
+/// Уровень сигнала по величине спреда относительно порога MIN_SPREAD_PERCENT.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlertTier {
+    Info,
+    Warn,
+    Urgent,
+}
+
+impl AlertTier {
+    pub fn label(self) -> &'static str {
+        match self {
+            AlertTier::Info => "INFO",
+            AlertTier::Warn => "WARN",
+            AlertTier::Urgent => "URGENT",
+        }
+    }
+
+    pub fn emoji(self) -> &'static str {
+        match self {
+            AlertTier::Info => "🔔",
+            AlertTier::Warn => "⚠️",
+            AlertTier::Urgent => "🚨",
+        }
+    }
+}
+
+/// Границы уровней сигналов: множители порога MIN_SPREAD_PERCENT
+/// (ALERT_WARN_MULTIPLIER, ALERT_URGENT_MULTIPLIER).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AlertTiers {
+    warn: f64,
+    urgent: f64,
+}
+
+impl Default for AlertTiers {
+    fn default() -> Self {
+        Self {
+            warn: DEFAULT_WARN_MULTIPLIER,
+            urgent: DEFAULT_URGENT_MULTIPLIER,
+        }
+    }
+}
+
+impl AlertTiers {
+    /// Множители меньше 1 или URGENT ниже WARN заменяются значениями по умолчанию.
+    pub fn from_config(config: &AlertsConfig) -> Self {
+        let warn = match config.warn_multiplier {
+            Some(v) if v >= 1.0 && v.is_finite() => v,
+            Some(v) => {
+                warn!("Invalid ALERT_WARN_MULTIPLIER '{}', using default {}", v, DEFAULT_WARN_MULTIPLIER);
+                DEFAULT_WARN_MULTIPLIER
+            }
+            None => DEFAULT_WARN_MULTIPLIER,
+        };
+        let urgent = match config.urgent_multiplier {
+            Some(v) if v >= warn && v.is_finite() => v,
+            Some(v) => {
+                let default = DEFAULT_URGENT_MULTIPLIER.max(warn);
+                warn!(
+                    "Invalid ALERT_URGENT_MULTIPLIER '{}' (must be at least the WARN multiplier {}), using {}",
+                    v, warn, default
+                );
+                default
+            }
+            None => DEFAULT_URGENT_MULTIPLIER.max(warn),
+        };
+        Self { warn, urgent }
+    }
+
+    /// Уровень сигнала со спредом после комиссий `net_difference` при пороге `min_spread`
+    /// (оба в процентах). Без положительного порога все сигналы – INFO.
+    pub fn classify(&self, net_difference: f64, min_spread: f64) -> AlertTier {
+        if min_spread <= 0.0 {
+            return AlertTier::Info;
+        }
+        let ratio = net_difference / min_spread;
+        if ratio >= self.urgent {
+            AlertTier::Urgent
+        } else if ratio >= self.warn {
+            AlertTier::Warn
+        } else {
+            AlertTier::Info
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_follow_multiples_of_threshold() {
+        let tiers = AlertTiers::default();
+        assert_eq!(tiers.classify(0.11, 0.1), AlertTier::Info);
+        assert_eq!(tiers.classify(0.2, 0.1), AlertTier::Warn);
+        assert_eq!(tiers.classify(0.5, 0.1), AlertTier::Urgent);
+        assert_eq!(tiers.classify(5.0, 0.0), AlertTier::Info);
+
+        let config = AlertsConfig {
+            warn_multiplier: Some(3.0),
+            urgent_multiplier: Some(2.0),
+            ..Default::default()
+        };
+        // URGENT ниже WARN – берется значение по умолчанию
+        assert_eq!(AlertTiers::from_config(&config), AlertTiers { warn: 3.0, urgent: 5.0 });
+    }
+}
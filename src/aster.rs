@@ -1,11 +1,12 @@
-use crate::share_state::SharedState;
-use std::{sync::Arc, time::Duration};
-use tokio::time::sleep;
-use log::{error, info, warn};
+use crate::config::AsterConfig;
+use crate::funding::{fetch_binance_style_funding, poll_funding_rates};
+use crate::share_state::{BookSideUpdate, SharedState, ASTER};
+use crate::utils::{canonical_symbol, connect_ws, run_ws_with_reconnect, Shutdown};
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::Arc;
+use log::{info, warn};
 use serde::Deserialize;
-use std::env;
-use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 
 #[derive(Debug, Deserialize)]
 struct ExchangeInfoResponse {
@@ -26,14 +27,19 @@ pub struct AsterStruct {
     api_secret: String,
     base_url: String,
     ws_url: String,
+    client: reqwest::Client,
 }
 
 impl AsterStruct {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let api_key = env::var("ASTER_API_KEY")
-            .map_err(|_| "ASTER_API_KEY not found in environment")?;
-        let api_secret = env::var("ASTER_API_SECRET")
-            .map_err(|_| "ASTER_API_SECRET not found in environment")?;
+    pub fn new(config: &AsterConfig, client: reqwest::Client) -> Result<Self, Box<dyn std::error::Error>> {
+        let api_key = config
+            .api_key
+            .clone()
+            .ok_or("ASTER_API_KEY not found in config or environment")?;
+        let api_secret = config
+            .api_secret
+            .clone()
+            .ok_or("ASTER_API_SECRET not found in config or environment")?;
 
         if api_key.is_empty() || api_secret.is_empty() {
             return Err("ASTER_API_KEY or ASTER_API_SECRET is empty".into());
@@ -44,196 +50,190 @@ impl AsterStruct {
             api_secret,
             base_url: "https://fapi.asterdex.com".to_string(),
             ws_url: "wss://fstream.asterdex.com".to_string(),
+            client,
         })
     }
 
-    pub async fn get_tickers(&self) -> Vec<String> {
+    /// Торгуемые символы ASTER. Ошибка запроса или разбора возвращается вызывающему, чтобы
+    /// сбой сети не выглядел как биржа без символов.
+    pub async fn get_tickers(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
         // Получаем список всех символов из exchangeInfo
         let exchange_info_url = format!("{}/fapi/v1/exchangeInfo", self.base_url);
-        
-        match reqwest::get(&exchange_info_url).await {
-            Ok(response) => {
-                match response.json::<ExchangeInfoResponse>().await {
-                    Ok(exchange_info) => {
-                        let tickers: Vec<String> = exchange_info
-                            .symbols
-                            .into_iter()
-                            .filter(|s| s.status == "TRADING")
-                            .map(|s| s.symbol)
-                            .collect();
-                        info!("Retrieved {} ASTER tickers", tickers.len());
-                        tickers
-                    }
-                    Err(e) => {
-                        error!("Failed to parse ASTER exchangeInfo: {}", e);
-                        Vec::new()
-                    }
-                }
-            }
-            Err(e) => {
-                error!("Failed to get ASTER exchangeInfo: {}", e);
-                Vec::new()
-            }
+        let exchange_info: ExchangeInfoResponse = self.client.get(&exchange_info_url).send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let tickers: Vec<String> = exchange_info
+            .symbols
+            .into_iter()
+            .filter(|s| s.status == "TRADING")
+            .map(|s| s.symbol)
+            .collect();
+        info!("Retrieved {} ASTER tickers", tickers.len());
+        Ok(tickers)
+    }
+
+    /// Опрос ставок фандинга ASTER (API совместим с Binance); не держит ссылку на `self`.
+    pub fn funding_poller<'a>(&self, shared_state: &'a Arc<SharedState>, shutdown: Shutdown) -> impl Future<Output = ()> + 'a {
+        let base_url = self.base_url.clone();
+        let client = self.client.clone();
+        async move {
+            poll_funding_rates(ASTER, shared_state, || fetch_binance_style_funding(&client, &base_url), shutdown).await;
         }
     }
 
-    pub async fn aster_ws(self, shared_state: &Arc<SharedState>) {
-        const MAX_RECONNECT_ATTEMPTS: u32 = 0; // 0 = бесконечные попытки
-        const RECONNECT_DELAY: Duration = Duration::from_secs(5);
-        const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
-        
-        let mut reconnect_count = 0u32;
-        
-        // Внешний цикл для переподключений
-        loop {
-            // Подключаемся к WebSocket
-            let ws_url = format!("{}/stream?streams=!ticker@arr", self.ws_url);
-            let (mut ws_stream, _) = match connect_async(&ws_url).await {
-                Ok(stream) => {
-                    if reconnect_count == 0 {
-                        info!("ASTER WebSocket connected successfully");
-                    } else {
-                        info!("ASTER WebSocket reconnected (attempt {})", reconnect_count + 1);
-                    }
-                    reconnect_count = 0; // Сбрасываем счетчик при успешном подключении
-                    stream
-                }
-                Err(e) => {
-                    error!("Failed to connect to ASTER WebSocket: {}", e);
-                    reconnect_count += 1;
-                    if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
-                        error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
-                        return;
-                    }
-                    warn!("Retrying in {:?}...", RECONNECT_DELAY);
-                    sleep(RECONNECT_DELAY).await;
-                    continue;
-                }
-            };
-
-            // Внутренний цикл для обработки сообщений
-            let mut last_message_time = std::time::Instant::now();
-            let mut connection_alive = true;
-            
-            while connection_alive {
-                // Используем timeout для обнаружения "тихих" разрывов соединения
-                match tokio::time::timeout(HEARTBEAT_TIMEOUT, ws_stream.next()).await {
-                    Ok(Some(Ok(Message::Text(text)))) => {
-                        last_message_time = std::time::Instant::now();
-                        
-                        // Парсим сообщение
-                        match serde_json::from_str::<serde_json::Value>(&text) {
-                            Ok(json) => {
-                                // Проверяем, что это сообщение с данными тикера
-                                if let Some(data) = json.get("data") {
-                                    if let Some(data_array) = data.as_array() {
-                                        // Обрабатываем массив тикеров
-                                        for ticker_data in data_array {
-                                            if let Some(symbol) = ticker_data.get("s").and_then(|s| s.as_str()) {
-                                                if let Some(price_str) = ticker_data.get("c").and_then(|p| p.as_str()) {
-                                                    let price: f64 = match price_str.parse::<f64>() {
-                                                        Ok(p) => {
-                                                            if p <= 0.0 || !p.is_finite() {
-                                                                warn!("Invalid price for {}: {}", symbol, p);
-                                                                continue;
-                                                            }
-                                                            p
-                                                        }
-                                                        Err(e) => {
-                                                            warn!("Failed to parse price for {}: {} (value: {})", symbol, e, price_str);
-                                                            continue;
-                                                        }
-                                                    };
-                                                    
-                                                    {
-                                                        let mut aster_prices = shared_state.aster_prices.write().await;
-                                                        aster_prices.insert(symbol.to_string(), price);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    } else if let Some(symbol) = data.get("s").and_then(|s| s.as_str()) {
-                                        // Обрабатываем одиночный тикер
-                                        if let Some(price_str) = data.get("c").and_then(|p| p.as_str()) {
-                                            let price: f64 = match price_str.parse::<f64>() {
-                                                Ok(p) => {
-                                                    if p <= 0.0 || !p.is_finite() {
-                                                        warn!("Invalid price for {}: {}", symbol, p);
-                                                        continue;
-                                                    }
-                                                    p
-                                                }
-                                                Err(e) => {
-                                                    warn!("Failed to parse price for {}: {} (value: {})", symbol, e, price_str);
-                                                    continue;
-                                                }
-                                            };
-                                            
-                                            {
-                                                let mut aster_prices = shared_state.aster_prices.write().await;
-                                                aster_prices.insert(symbol.to_string(), price);
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                warn!("Failed to parse ASTER WebSocket message: {} (text: {})", e, text);
-                            }
-                        }
-                    }
-                    Ok(Some(Ok(Message::Ping(_)))) => {
-                        // Отвечаем на ping
-                        if let Err(e) = ws_stream.send(Message::Pong(vec![])).await {
-                            warn!("Failed to send pong: {}", e);
-                            connection_alive = false;
-                        }
-                    }
-                    Ok(Some(Ok(Message::Pong(_)))) => {
-                        // Игнорируем pong сообщения
-                    }
-                    Ok(Some(Ok(Message::Binary(_)))) => {
-                        // Игнорируем binary сообщения (если они появятся)
-                    }
-                    Ok(Some(Ok(Message::Close(_)))) => {
-                        warn!("ASTER WebSocket connection closed by server");
-                        connection_alive = false;
-                    }
-                    Ok(Some(Err(e))) => {
-                        error!("ASTER WebSocket error: {}", e);
-                        connection_alive = false;
-                    }
-                    Ok(None) => {
-                        warn!("ASTER WebSocket stream ended");
-                        connection_alive = false;
-                    }
-                    Err(_) => {
-                        // Timeout - возможно соединение тихо разорвано
-                        let elapsed = last_message_time.elapsed();
-                        warn!("No messages received from ASTER for {:?}. Connection may be lost.", elapsed);
-                        connection_alive = false;
-                    }
-                }
-            }
+    /// Подписывается на `!ticker@arr` и обновляет цены ASTER в `SharedState`.
+    ///
+    /// Поток присылает все символы биржи; сохраняются только тикеры из `common_tickers`
+    /// (канонические), как и в `bybit_ws`.
+    pub async fn aster_ws(
+        self,
+        common_tickers: &HashSet<String>,
+        shared_state: &Arc<SharedState>,
+        shutdown: Shutdown,
+    ) {
+        // Лучшие bid/ask (`!bookTicker`) нужны только для проверки спреда по стакану
+        let streams = if shared_state.depth_check_notional.is_some() {
+            "!ticker@arr/!bookTicker"
+        } else {
+            "!ticker@arr"
+        };
+        let ws_url = format!("{}/stream?streams={}", self.ws_url, streams);
+        let ws_url = &ws_url;
 
-            // Соединение потеряно, пытаемся переподключиться
-            error!("ASTER WebSocket connection lost. Attempting to reconnect...");
-            reconnect_count += 1;
-            
-            if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
-                error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
+        run_ws_with_reconnect(
+            ASTER,
+            shared_state,
+            &shared_state.reconnect_config(ASTER),
+            shutdown,
+            move || async move { connect_ws(ws_url).await.map(|(stream, _)| stream) },
+            move |text| Self::handle_ticker_message(shared_state, common_tickers, text),
+        )
+        .await;
+    }
+
+    /// Разбирает сообщение потока `!ticker@arr` или `!bookTicker` и обновляет цены.
+    /// Вид сообщения определяет `ticker_items`.
+    ///
+    /// Сначала разбираются все тикеры сообщения, затем цены записываются одним вызовом.
+    async fn handle_ticker_message(
+        shared_state: &Arc<SharedState>,
+        common_tickers: &HashSet<String>,
+        text: String,
+    ) {
+        // Парсим сообщение
+        let json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse ASTER WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(ASTER);
                 return;
             }
-            
-            warn!("Reconnecting in {:?}... (attempt {}{})", 
-                  RECONNECT_DELAY, 
-                  reconnect_count,
-                  if MAX_RECONNECT_ATTEMPTS > 0 {
-                      format!("/{}", MAX_RECONNECT_ATTEMPTS)
-                  } else {
-                      "".to_string()
-                  });
-            sleep(RECONNECT_DELAY).await;
+        };
+
+        let data = json.get("data").unwrap_or(&json);
+        let is_book_ticker = json.get("stream").and_then(|s| s.as_str()) == Some("!bookTicker")
+            || data.get("e").and_then(|e| e.as_str()) == Some("bookTicker");
+        if is_book_ticker {
+            if let Some((symbol, bid, ask)) = Self::parse_book_ticker(data) {
+                if common_tickers.contains(&symbol) {
+                    shared_state.update_book(ASTER, &symbol, bid, ask);
+                }
+            }
+            return;
+        }
+        let prices: Vec<(String, f64)> = Self::ticker_items(&json)
+            .into_iter()
+            .filter_map(Self::parse_ticker)
+            .filter(|(symbol, _)| common_tickers.contains(symbol))
+            .collect();
+
+        if !prices.is_empty() {
+            shared_state.set_prices(ASTER, prices);
         }
     }
+
+    /// Тикеры сообщения независимо от эндпоинта:
+    /// - combined stream (`/stream?streams=`): `{"stream": "...", "data": [...]}` или с одиночным тикером в `data`;
+    /// - raw stream `!ticker@arr` (`/ws/...`): массив тикеров верхнего уровня;
+    /// - raw stream одного символа: объект тикера верхнего уровня.
+    ///
+    /// Служебные сообщения (ответы на подписку) дают элементы без `s`/`c`, их отбрасывает `parse_ticker`.
+    fn ticker_items(json: &serde_json::Value) -> Vec<&serde_json::Value> {
+        let data = json.get("data").unwrap_or(json);
+        match data.as_array() {
+            Some(items) => items.iter().collect(),
+            None => vec![data],
+        }
+    }
+
+    /// Канонический символ и лучшие bid/ask из `bookTicker` (`b`/`B` – bid и объем, `a`/`A` – ask).
+    /// Объем 0 – сторона пуста.
+    fn parse_book_ticker(data: &serde_json::Value) -> Option<(String, BookSideUpdate, BookSideUpdate)> {
+        let canonical = canonical_symbol(data.get("s").and_then(|s| s.as_str())?);
+        let level = |price_key: &str, size_key: &str| {
+            let parse = |key: &str| data.get(key)?.as_str()?.parse::<f64>().ok();
+            let level = parse(price_key)
+                .zip(parse(size_key))
+                .map(|(price, size)| (canonical.unit_price(price), canonical.unit_size(size)));
+            BookSideUpdate::from_levels(level)
+        };
+        let (bid, ask) = (level("b", "B"), level("a", "A"));
+        Some((canonical.symbol, bid, ask))
+    }
+
+    /// Канонический символ и цена за монету из одного тикера (`s` – символ, `c` – последняя цена).
+    fn parse_ticker(ticker_data: &serde_json::Value) -> Option<(String, f64)> {
+        let symbol = ticker_data.get("s").and_then(|s| s.as_str())?;
+        let price_str = ticker_data.get("c").and_then(|p| p.as_str())?;
+        let price = match price_str.parse::<f64>() {
+            Ok(p) if p <= 0.0 || !p.is_finite() => {
+                warn!("Invalid price for {}: {}", symbol, p);
+                return None;
+            }
+            Ok(p) => p,
+            Err(e) => {
+                warn!("Failed to parse price for {}: {} (value: {})", symbol, e, price_str);
+                return None;
+            }
+        };
+
+        let canonical = canonical_symbol(symbol);
+        let price = canonical.unit_price(price);
+        Some((canonical.symbol, price))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(text: &str) -> Vec<(String, f64)> {
+        let json: serde_json::Value = serde_json::from_str(text).unwrap();
+        AsterStruct::ticker_items(&json)
+            .into_iter()
+            .filter_map(AsterStruct::parse_ticker)
+            .collect()
+    }
+
+    #[test]
+    fn tickers_are_read_from_combined_and_raw_streams() {
+        let expected = vec![("BTCUSDT".to_string(), 65000.5), ("ETHUSDT".to_string(), 3500.0)];
+
+        let combined = r#"{"stream":"!ticker@arr","data":[{"e":"24hrTicker","s":"BTCUSDT","c":"65000.5"},{"e":"24hrTicker","s":"ETHUSDT","c":"3500"}]}"#;
+        assert_eq!(prices(combined), expected);
+
+        let raw_array = r#"[{"e":"24hrTicker","s":"BTCUSDT","c":"65000.5"},{"e":"24hrTicker","s":"ETHUSDT","c":"3500"}]"#;
+        assert_eq!(prices(raw_array), expected);
+
+        let raw_single = r#"{"e":"24hrTicker","s":"BTCUSDT","c":"65000.5"}"#;
+        assert_eq!(prices(raw_single), expected[..1]);
+
+        let combined_single = r#"{"stream":"btcusdt@ticker","data":{"e":"24hrTicker","s":"BTCUSDT","c":"65000.5"}}"#;
+        assert_eq!(prices(combined_single), expected[..1]);
+
+        assert!(prices(r#"{"result":null,"id":1}"#).is_empty());
+    }
 }
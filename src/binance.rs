@@ -0,0 +1,151 @@
+use crate::funding::{fetch_binance_style_funding, poll_funding_rates};
+use crate::share_state::{SharedState, BINANCE};
+use crate::utils::{canonical_symbol, connect_ws, run_ws_with_reconnect, Shutdown};
+use std::future::Future;
+use std::sync::Arc;
+use log::{error, info, warn};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct ExchangeInfoResponse {
+    symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SymbolInfo {
+    symbol: String,
+    status: String,
+}
+
+
+pub struct BinanceStruct {
+    base_url: String,
+    ws_url: String,
+    client: reqwest::Client,
+}
+
+impl BinanceStruct {
+    /// Публичные эндпоинты Binance USD-M не требуют ключей.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            base_url: "https://fapi.binance.com".to_string(),
+            ws_url: "wss://fstream.binance.com".to_string(),
+            client,
+        }
+    }
+
+    pub async fn get_tickers(&self) -> Vec<String> {
+        // Получаем список всех символов из exchangeInfo
+        let exchange_info_url = format!("{}/fapi/v1/exchangeInfo", self.base_url);
+        
+        match self.client.get(&exchange_info_url).send().await {
+            Ok(response) => {
+                match response.json::<ExchangeInfoResponse>().await {
+                    Ok(exchange_info) => {
+                        let tickers: Vec<String> = exchange_info
+                            .symbols
+                            .into_iter()
+                            .filter(|s| s.status == "TRADING")
+                            .map(|s| s.symbol)
+                            .collect();
+                        info!("Retrieved {} Binance tickers", tickers.len());
+                        tickers
+                    }
+                    Err(e) => {
+                        error!("Failed to parse Binance exchangeInfo: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to get Binance exchangeInfo: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Опрос ставок фандинга Binance; не держит ссылку на `self`, так что фид можно запустить отдельно.
+    pub fn funding_poller<'a>(&self, shared_state: &'a Arc<SharedState>, shutdown: Shutdown) -> impl Future<Output = ()> + 'a {
+        let base_url = self.base_url.clone();
+        let client = self.client.clone();
+        async move {
+            poll_funding_rates(BINANCE, shared_state, || fetch_binance_style_funding(&client, &base_url), shutdown).await;
+        }
+    }
+
+    pub async fn binance_ws(self, shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        let ws_url = format!("{}/stream?streams=!ticker@arr", self.ws_url);
+        let ws_url = &ws_url;
+
+        run_ws_with_reconnect(
+            BINANCE,
+            shared_state,
+            &shared_state.reconnect_config(BINANCE),
+            shutdown,
+            move || async move { connect_ws(ws_url).await.map(|(stream, _)| stream) },
+            move |text| Self::handle_ticker_message(shared_state, text),
+        )
+        .await;
+    }
+
+    /// Разбирает сообщение потока `!ticker@arr` (массив тикеров или одиночный тикер) и обновляет цены.
+    async fn handle_ticker_message(shared_state: &Arc<SharedState>, text: String) {
+        // Парсим сообщение
+        match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => {
+                // Проверяем, что это сообщение с данными тикера
+                if let Some(data) = json.get("data") {
+                    if let Some(data_array) = data.as_array() {
+                        // Обрабатываем массив тикеров
+                        for ticker_data in data_array {
+                            if let Some(symbol) = ticker_data.get("s").and_then(|s| s.as_str()) {
+                                if let Some(price_str) = ticker_data.get("c").and_then(|p| p.as_str()) {
+                                    let price: f64 = match price_str.parse::<f64>() {
+                                        Ok(p) => {
+                                            if p <= 0.0 || !p.is_finite() {
+                                                warn!("Invalid price for {}: {}", symbol, p);
+                                                continue;
+                                            }
+                                            p
+                                        }
+                                        Err(e) => {
+                                            warn!("Failed to parse price for {}: {} (value: {})", symbol, e, price_str);
+                                            continue;
+                                        }
+                                    };
+
+                                    let canonical = canonical_symbol(symbol);
+                                    shared_state.set_price(BINANCE, &canonical.symbol, canonical.unit_price(price));
+                                }
+                            }
+                        }
+                    } else if let Some(symbol) = data.get("s").and_then(|s| s.as_str()) {
+                        // Обрабатываем одиночный тикер
+                        if let Some(price_str) = data.get("c").and_then(|p| p.as_str()) {
+                            let price: f64 = match price_str.parse::<f64>() {
+                                Ok(p) => {
+                                    if p <= 0.0 || !p.is_finite() {
+                                        warn!("Invalid price for {}: {}", symbol, p);
+                                        return;
+                                    }
+                                    p
+                                }
+                                Err(e) => {
+                                    warn!("Failed to parse price for {}: {} (value: {})", symbol, e, price_str);
+                                    return;
+                                }
+                            };
+
+                            let canonical = canonical_symbol(symbol);
+                            shared_state.set_price(BINANCE, &canonical.symbol, canonical.unit_price(price));
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                warn!("Failed to parse Binance WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(BINANCE);
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
-use std::collections::HashMap;
-use std::env;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use hmac::{Hmac, Mac};
 use log::{error, info, warn};
@@ -9,9 +11,41 @@ use serde::Deserialize;
 use serde_json::Value;
 use sha2::Sha256;
 use thiserror::Error;
+use tokio::sync::OnceCell;
+
+use crate::compare_price::prices_equal;
+use crate::config::BingXConfig;
+use crate::share_state::BINGX;
+use crate::utils::{canonical_symbol, from_canonical};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Боевой API BingX. Другой адрес (тестовая среда, mock-сервер) задается через BINGX_BASE_URL.
+const MAINNET_BASE_URL: &str = "https://open-api.bingx.com";
+const DEFAULT_TAKE_PROFIT_PERCENT: f64 = 3.0;
+/// Окно, в течение которого BingX принимает подписанный запрос после timestamp.
+const DEFAULT_RECV_WINDOW_MS: u64 = 5000;
+/// Коды BingX для timestamp вне recvWindow.
+const TIMESTAMP_ERROR_CODES: &[i32] = &[100421, 109400];
+/// Повторы подписанных запросов при временных ошибках.
+const RETRY_ATTEMPTS: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+/// Коды BingX, которые считаются временными: rate limit, внутренняя ошибка, сервер занят.
+const DEFAULT_RETRYABLE_CODES: &[i32] = &[100410, 100500, 100503, 80012];
+const DEFAULT_DEPOSIT_FRACTION: f64 = 0.75;
+const DEFAULT_LEVERAGE: u32 = 10;
+/// Точность цены, если BingX не прислал pricePrecision для контракта.
+const DEFAULT_PRICE_PRECISION: u32 = 8;
+/// Ниже этого свободного баланса новые позиции не открываются: ордер все равно отклонят по минимуму.
+const DEFAULT_MIN_BALANCE_USDT: f64 = 10.0;
+/// Сколько переиспользуется число открытых позиций (BINGX_POSITION_CACHE_MS).
+const DEFAULT_POSITION_CACHE_TTL: Duration = Duration::from_secs(2);
+/// Наибольший откат трейлинг-стопа в процентах: BingX принимает priceRate не больше 1.
+const MAX_TRAILING_PERCENT: f64 = 100.0;
+/// Сколько раз опрашиваем статус маркет-ордера и с каким интервалом.
+const ORDER_STATUS_POLL_ATTEMPTS: u32 = 10;
+const ORDER_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
 /// Клиент для работы с BingX Perpetual Futures.
 ///
 /// Задачи:
@@ -24,6 +58,111 @@ pub struct BingXClient {
     api_secret: String,
     http_client: Client,
     base_url: String,
+    /// Кэш параметров контрактов (точность и минимальный объем), загружается один раз.
+    contract_specs: OnceCell<HashMap<String, ContractSpec>>,
+    /// Кэш режима позиций аккаунта (hedge или one-way), загружается при старте.
+    position_mode: OnceCell<PositionMode>,
+    /// Доля депозита, используемая как маржа (BINGX_DEPOSIT_FRACTION, по умолчанию 0.75).
+    deposit_fraction: f64,
+    /// Плечо для новых позиций (BINGX_LEVERAGE, по умолчанию 10).
+    leverage: u32,
+    /// recvWindow для подписанных запросов (BINGX_RECV_WINDOW_MS, по умолчанию 5000).
+    recv_window_ms: u64,
+    /// Поправка к локальным часам по времени сервера BingX, мс.
+    time_offset_ms: AtomicI64,
+    /// Коды ошибок API, при которых запрос повторяется (BINGX_RETRYABLE_CODES).
+    retryable_codes: Vec<i32>,
+    /// Take profit в процентах от входа (BINGX_TAKE_PROFIT_PERCENT, по умолчанию 3).
+    take_profit_percent: f64,
+    /// Stop loss в процентах от входа (BINGX_STOP_LOSS_PERCENT), None – без stop loss.
+    stop_loss_percent: Option<f64>,
+    /// Откат трейлинг-стопа в процентах (BINGX_TRAILING_PCT), None – без трейлинг-стопа.
+    trailing_percent: Option<f64>,
+    /// Режим без отправки ордеров (BINGX_DRY_RUN, по умолчанию включен): параметры ордера
+    /// считаются и логируются, но на биржу не уходят.
+    dry_run: bool,
+    /// Минимальный свободный баланс для открытия позиции (BINGX_MIN_BALANCE_USDT, по умолчанию 10).
+    min_balance_usdt: f64,
+    /// Стоп на открытие новых позиций – включается при превышении дневного лимита убытка.
+    trading_halted: AtomicBool,
+    /// Последний полученный свободный баланс USDT – для проверки минимальной стоимости ордера
+    /// до запросов к API (см. `min_notional_skip`).
+    last_available_usdt: Mutex<Option<f64>>,
+    /// Сколько переиспользовать число открытых позиций (BINGX_POSITION_CACHE_MS, по умолчанию 2 с).
+    position_cache_ttl: Duration,
+    /// Последнее число открытых позиций и когда оно получено (см. `count_open_positions`).
+    open_positions_cache: Mutex<Option<(Instant, usize)>>,
+    /// Растет при каждом сбросе кэша позиций: ответ, запрошенный до ордера, не попадет в кэш после него.
+    open_positions_generation: AtomicU64,
+}
+
+/// Ограничения на количество для символа BingX.
+#[derive(Debug, Clone, Copy)]
+struct ContractSpec {
+    quantity_precision: u32,
+    min_qty: f64,
+    step_size: f64,
+    /// Минимальная стоимость ордера в USDT (`tradeMinUSDT`), 0 – не ограничена.
+    min_notional: f64,
+    /// Число знаков после запятой в цене ордера (`pricePrecision`).
+    price_precision: u32,
+}
+
+/// Сколько действует лимитный ордер.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeInForce {
+    /// Висит в стакане до отмены.
+    Gtc,
+    /// Исполняется сразу, неисполненный остаток отменяется.
+    Ioc,
+    /// Исполняется сразу целиком или отменяется полностью.
+    Fok,
+}
+
+impl TimeInForce {
+    fn as_str(self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+        }
+    }
+}
+
+/// Режим позиций аккаунта BingX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionMode {
+    /// Отдельные позиции LONG и SHORT по символу (`dualSidePosition` = true).
+    Hedge,
+    /// Одна позиция на символ с positionSide = BOTH, направление – знак объема.
+    OneWay,
+}
+
+impl PositionMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PositionMode::Hedge => "hedge",
+            PositionMode::OneWay => "one-way",
+        }
+    }
+
+    /// positionSide ордера для направления LONG/SHORT.
+    fn position_side(self, direction: &str) -> &str {
+        match self {
+            PositionMode::Hedge => direction,
+            PositionMode::OneWay => "BOTH",
+        }
+    }
+
+    /// Добавляет positionSide, а закрывающим ордерам в one-way режиме – reduceOnly=true.
+    /// В hedge-режиме reduceOnly BingX не принимает: закрытие задается парой side/positionSide.
+    fn insert_position_params(self, params: &mut HashMap<String, String>, direction: &str, closing: bool) {
+        params.insert("positionSide".to_string(), self.position_side(direction).to_string());
+        if closing && self == PositionMode::OneWay {
+            params.insert("reduceOnly".to_string(), "true".to_string());
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -35,10 +174,43 @@ pub enum BingXTradeOutcome {
         quantity: f64,
         leverage: f64,
         entry_price: f64,
-        take_profit_price: f64,
+        take_profit_price: Option<f64>,
+        /// Удалось ли выставить отдельный take profit ордер.
+        take_profit_placed: bool,
+        stop_loss_price: Option<f64>,
+        /// Удалось ли выставить stop loss ордер.
+        stop_loss_placed: bool,
+        /// Откат трейлинг-стопа в процентах, None – трейлинг-стоп не запрашивался.
+        trailing_stop_percent: Option<f64>,
+        /// Удалось ли выставить трейлинг-стоп.
+        trailing_stop_placed: bool,
+    },
+    /// Лимитный ордер принят биржей; исполнение не ожидается.
+    LimitPlaced {
+        symbol: String,
+        direction: String, // LONG / SHORT
+        order_id: String,
+        quantity: f64,
+        price: f64,
+        time_in_force: TimeInForce,
+    },
+    /// Позиция была закрыта маркет-ордером.
+    Closed {
+        symbol: String,
+        direction: String, // LONG / SHORT / BOTH
+        quantity: f64,
     },
     /// Ничего не сделали (например, уже есть открытая позиция).
     Skipped { reason: String },
+    /// Режим BINGX_DRY_RUN: ордер рассчитан, но не отправлен.
+    DryRun {
+        symbol: String,
+        direction: String, // LONG / SHORT / BOTH
+        side: String,      // BUY / SELL
+        quantity: f64,
+        /// Параметры ордера в том виде, в котором они ушли бы в BingX.
+        params: BTreeMap<String, String>,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -51,8 +223,77 @@ pub enum BingXError {
     Serde(#[from] serde_json::Error),
     #[error("api error: {0}")]
     Api(String),
+    #[error("api error {code}: {msg}")]
+    ApiCode { code: i32, msg: String },
     #[error("internal error: {0}")]
     Internal(String),
+    #[error("order {order_id} for {symbol} was rejected, status {status}")]
+    OrderRejected {
+        symbol: String,
+        order_id: String,
+        status: String,
+    },
+    #[error("order {order_id} for {symbol} was not filled within {waited:?}")]
+    OrderNotFilled {
+        symbol: String,
+        order_id: String,
+        waited: Duration,
+    },
+    #[error("protective order failed for {symbol}: {reason}")]
+    ProtectionFailed { symbol: String, reason: String },
+    #[error("quantity {quantity} for {symbol} is below the minimum {min_qty}")]
+    QuantityBelowMinimum {
+        symbol: String,
+        quantity: f64,
+        min_qty: f64,
+    },
+    #[error("order value {notional} USDT for {symbol} is below the minimum {min_notional} USDT")]
+    NotionalBelowMinimum {
+        symbol: String,
+        notional: f64,
+        min_notional: f64,
+    },
+}
+
+/// Вид защитного условного ордера.
+#[derive(Debug, Clone, Copy)]
+enum ProtectiveOrder {
+    TakeProfit,
+    StopLoss,
+}
+
+impl ProtectiveOrder {
+    fn label(self) -> &'static str {
+        match self {
+            ProtectiveOrder::TakeProfit => "take profit",
+            ProtectiveOrder::StopLoss => "stop loss",
+        }
+    }
+
+    fn order_type(self) -> &'static str {
+        match self {
+            ProtectiveOrder::TakeProfit => "TAKE_PROFIT_MARKET",
+            ProtectiveOrder::StopLoss => "STOP_MARKET",
+        }
+    }
+
+    /// Имя параметра для endpoint-а positionTPSL.
+    fn position_param(self) -> &'static str {
+        match self {
+            ProtectiveOrder::TakeProfit => "takeProfit",
+            ProtectiveOrder::StopLoss => "stopLoss",
+        }
+    }
+
+    /// Цена срабатывания: TP выше входа для LONG и ниже для SHORT, SL – наоборот.
+    fn trigger_price(self, direction: &str, entry_price: f64, percent: f64) -> f64 {
+        let profit_side = matches!(self, ProtectiveOrder::TakeProfit) == (direction == "LONG");
+        if profit_side {
+            entry_price * (1.0 + percent / 100.0)
+        } else {
+            entry_price * (1.0 - percent / 100.0)
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,7 +307,6 @@ where
     data: Option<T>,
 }
 
-#[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 struct Position {
     symbol: String,
@@ -74,6 +314,42 @@ struct Position {
     position_side: Option<String>,
     #[serde(rename = "positionAmt")]
     position_amt: Option<String>,
+    #[serde(rename = "avgPrice", default)]
+    avg_price: Option<String>,
+    #[serde(rename = "unrealizedProfit", default)]
+    unrealized_profit: Option<String>,
+}
+
+/// Открытая позиция BingX для отчетов (/status) и логики закрытия.
+#[derive(Debug, Clone)]
+pub struct OpenPosition {
+    pub symbol: String,
+    /// LONG / SHORT; в one-way режиме определяется по знаку объема.
+    pub side: String,
+    /// Объем позиции в базовой валюте, всегда положительный.
+    pub size: f64,
+    pub entry_price: f64,
+    pub unrealized_pnl: f64,
+}
+
+impl Position {
+    /// None для позиций с нулевым объемом.
+    fn to_open_position(&self) -> Option<OpenPosition> {
+        let parse = |v: &Option<String>| v.as_deref().and_then(|s| s.parse::<f64>().ok());
+        let amount = parse(&self.position_amt).filter(|amt| amt.abs() > 0.0)?;
+        let side = match self.position_side.as_deref() {
+            Some(side) if !side.eq_ignore_ascii_case("BOTH") => side.to_uppercase(),
+            _ if amount > 0.0 => "LONG".to_string(),
+            _ => "SHORT".to_string(),
+        };
+        Some(OpenPosition {
+            symbol: self.symbol.clone(),
+            side,
+            size: amount.abs(),
+            entry_price: parse(&self.avg_price).unwrap_or(0.0),
+            unrealized_pnl: parse(&self.unrealized_profit).unwrap_or(0.0),
+        })
+    }
 }
 
 #[allow(dead_code)]
@@ -96,31 +372,133 @@ struct BalanceData {
     balances: Vec<BalanceItem>,
 }
 
+/// Запись `/openApi/swap/v2/user/income`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IncomeRecord {
+    #[serde(default)]
+    income_type: String,
+    /// BingX присылает сумму строкой, но на всякий случай принимаем и число.
+    #[serde(default)]
+    income: Value,
+}
+
+/// Виды дохода, из которых складывается реализованный результат торговли.
+const REALIZED_INCOME_TYPES: &[&str] = &["REALIZED_PNL", "TRADING_FEE", "FUNDING_FEE"];
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContractInfo {
+    symbol: String,
+    quantity_precision: u32,
+    #[serde(default)]
+    trade_min_quantity: Option<f64>,
+    #[serde(default, rename = "tradeMinUSDT")]
+    trade_min_usdt: Option<f64>,
+    #[serde(default)]
+    price_precision: Option<u32>,
+}
+
+/// Ответ `/openApi/swap/v2/user/positionMode`: `dualSidePosition` = "true" в hedge-режиме.
 #[derive(Debug, Deserialize, Default)]
-struct OrderResponse {
-    #[allow(dead_code)]
-    order_id: Option<String>,
+#[serde(rename_all = "camelCase")]
+struct PositionModeData {
+    /// BingX отдает флаг строкой, но на всякий случай принимаем и bool.
+    #[serde(default)]
+    dual_side_position: Value,
 }
 
-impl BingXClient {
-    /// Приводим тикер из формата проекта (`AXSUSDT`) к формату BingX (`AXS-USDT`).
-    /// Если символ уже содержит `-`, возвращаем как есть.
-    fn normalize_symbol(symbol: &str) -> String {
-        let s = symbol.trim();
-        if s.contains('-') {
-            return s.to_string();
+impl PositionModeData {
+    fn mode(&self) -> Option<PositionMode> {
+        match &self.dual_side_position {
+            Value::Bool(true) => Some(PositionMode::Hedge),
+            Value::Bool(false) => Some(PositionMode::OneWay),
+            Value::String(s) if s.eq_ignore_ascii_case("true") => Some(PositionMode::Hedge),
+            Value::String(s) if s.eq_ignore_ascii_case("false") => Some(PositionMode::OneWay),
+            _ => None,
         }
-        if let Some(base) = s.strip_suffix("USDT") {
-            return format!("{}-USDT", base);
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ServerTime {
+    server_time: i64,
+}
+
+/// Ответ на создание и запрос ордера: `data: { order: {...} }`.
+#[derive(Debug, Deserialize, Default)]
+struct OrderResponse {
+    #[serde(default)]
+    order: Option<OrderDetails>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct OrderDetails {
+    /// BingX отдает orderId числом, но на всякий случай принимаем и строку.
+    #[serde(default)]
+    order_id: Option<Value>,
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    executed_qty: Option<String>,
+    #[serde(default)]
+    avg_price: Option<String>,
+}
+
+impl OrderDetails {
+    fn id(&self) -> Option<String> {
+        match self.order_id.as_ref()? {
+            Value::String(s) if !s.is_empty() => Some(s.clone()),
+            Value::Number(n) => Some(n.to_string()),
+            _ => None,
         }
-        s.to_string()
     }
 
-    pub fn from_env() -> Result<Self, BingXError> {
-        let api_key = env::var("BINGX_API_KEY")
-            .map_err(|_| BingXError::MissingEnv("BINGX_API_KEY".into()))?;
-        let api_secret = env::var("BINGX_API_SECRET")
-            .map_err(|_| BingXError::MissingEnv("BINGX_API_SECRET".into()))?;
+    fn executed_qty(&self) -> f64 {
+        self.executed_qty
+            .as_deref()
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    }
+
+    fn avg_price(&self) -> f64 {
+        self.avg_price
+            .as_deref()
+            .and_then(|s| s.parse::<f64>().ok())
+            .unwrap_or(0.0)
+    }
+}
+
+/// Фактическое исполнение маркет-ордера.
+#[derive(Debug, Clone, Copy)]
+struct OrderFill {
+    quantity: f64,
+    avg_price: f64,
+}
+
+impl BingXClient {
+    /// Создает клиента из секции `[bingx]` конфига. Адрес API берется из `base_url`, по умолчанию – боевой.
+    ///
+    /// Предполагается, что подпись запросов и пути эндпоинтов в другой среде такие же, как в боевой.
+    pub fn from_config(config: &BingXConfig, http_client: Client) -> Result<Self, BingXError> {
+        let base_url = match config.base_url.as_deref().map(str::trim) {
+            Some(url) if !url.is_empty() => {
+                info!("BingX: using custom base URL {}", url);
+                url.to_string()
+            }
+            _ => MAINNET_BASE_URL.to_string(),
+        };
+
+        let api_key = config
+            .api_key
+            .clone()
+            .ok_or_else(|| BingXError::MissingEnv("BINGX_API_KEY".into()))?;
+        let api_secret = config
+            .api_secret
+            .clone()
+            .ok_or_else(|| BingXError::MissingEnv("BINGX_API_SECRET".into()))?;
 
         if api_key.is_empty() {
             return Err(BingXError::MissingEnv(
@@ -133,21 +511,235 @@ impl BingXClient {
             ));
         }
 
-        let http_client = Client::new();
+        let deposit_fraction = match config.deposit_fraction {
+            Some(v) if v > 0.0 && v <= 1.0 => v,
+            Some(v) => {
+                warn!(
+                    "Invalid BINGX_DEPOSIT_FRACTION '{}' (expected a number in (0, 1]), using default {}",
+                    v, DEFAULT_DEPOSIT_FRACTION
+                );
+                DEFAULT_DEPOSIT_FRACTION
+            }
+            None => DEFAULT_DEPOSIT_FRACTION,
+        };
+
+        let leverage = match config.leverage {
+            Some(v) if v > 0 => v,
+            Some(v) => {
+                warn!(
+                    "Invalid BINGX_LEVERAGE '{}' (expected a positive integer), using default {}",
+                    v, DEFAULT_LEVERAGE
+                );
+                DEFAULT_LEVERAGE
+            }
+            None => DEFAULT_LEVERAGE,
+        };
+
+        let recv_window_ms = match config.recv_window_ms {
+            Some(v) if v > 0 => v,
+            Some(v) => {
+                warn!(
+                    "Invalid BINGX_RECV_WINDOW_MS '{}', using default {}",
+                    v, DEFAULT_RECV_WINDOW_MS
+                );
+                DEFAULT_RECV_WINDOW_MS
+            }
+            None => DEFAULT_RECV_WINDOW_MS,
+        };
+
+        let retryable_codes = config
+            .retryable_codes
+            .clone()
+            .unwrap_or_else(|| DEFAULT_RETRYABLE_CODES.to_vec());
+
+        let take_profit_percent =
+            Self::positive_percent("BINGX_TAKE_PROFIT_PERCENT", config.take_profit_percent)
+                .unwrap_or(DEFAULT_TAKE_PROFIT_PERCENT);
+        let stop_loss_percent = Self::positive_percent("BINGX_STOP_LOSS_PERCENT", config.stop_loss_percent);
+        let trailing_percent = match Self::positive_percent("BINGX_TRAILING_PCT", config.trailing_percent) {
+            Some(v) if v > MAX_TRAILING_PERCENT => {
+                warn!(
+                    "Invalid BINGX_TRAILING_PCT '{}' (BingX allows at most {}%), ignoring",
+                    v, MAX_TRAILING_PERCENT
+                );
+                None
+            }
+            v => v,
+        };
+
+        // Пока оператор явно не выключил dry run, реальные ордера не отправляются
+        let dry_run = config.dry_run.unwrap_or(true);
+
+        let min_balance_usdt = match config.min_balance_usdt {
+            Some(v) if v >= 0.0 && v.is_finite() => v,
+            Some(v) => {
+                warn!(
+                    "Invalid BINGX_MIN_BALANCE_USDT '{}', using default {}",
+                    v, DEFAULT_MIN_BALANCE_USDT
+                );
+                DEFAULT_MIN_BALANCE_USDT
+            }
+            None => DEFAULT_MIN_BALANCE_USDT,
+        };
+
+        let position_cache_ttl = config
+            .position_cache_ms
+            .map_or(DEFAULT_POSITION_CACHE_TTL, Duration::from_millis);
 
         Ok(Self {
             api_key,
             api_secret,
             http_client,
-            base_url: "https://open-api.bingx.com".to_string(),
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+            contract_specs: OnceCell::new(),
+            position_mode: OnceCell::new(),
+            deposit_fraction,
+            leverage,
+            recv_window_ms,
+            time_offset_ms: AtomicI64::new(0),
+            retryable_codes,
+            take_profit_percent,
+            stop_loss_percent,
+            trailing_percent,
+            dry_run,
+            min_balance_usdt,
+            trading_halted: AtomicBool::new(false),
+            last_available_usdt: Mutex::new(None),
+            position_cache_ttl,
+            open_positions_cache: Mutex::new(None),
+            open_positions_generation: AtomicU64::new(0),
         })
     }
 
-    fn timestamp_ms() -> u64 {
+    /// Включает или снимает стоп на открытие новых позиций (дневной лимит убытка).
+    pub fn set_trading_halted(&self, halted: bool) {
+        self.trading_halted.store(halted, Ordering::Relaxed);
+    }
+
+    pub fn is_trading_halted(&self) -> bool {
+        self.trading_halted.load(Ordering::Relaxed)
+    }
+
+    /// Включен ли режим BINGX_DRY_RUN.
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Логирует ордер, который был бы отправлен, и возвращает исход `DryRun`.
+    fn dry_run_outcome(
+        symbol: &str,
+        direction: &str,
+        side: &str,
+        quantity: f64,
+        params: HashMap<String, String>,
+    ) -> BingXTradeOutcome {
+        let params: BTreeMap<String, String> = params.into_iter().collect();
+        info!(
+            "BingX [dry run]: would place {} {} order on {} with qty={}, params={:?}",
+            side, direction, symbol, quantity, params
+        );
+        BingXTradeOutcome::DryRun {
+            symbol: symbol.to_string(),
+            direction: direction.to_string(),
+            side: side.to_string(),
+            quantity,
+            params,
+        }
+    }
+
+    /// Доля депозита под маржу должна быть в (0, 1]: больше 1 – маржа больше баланса,
+    /// 0 или меньше – нулевой или отрицательный объем ордера.
+    fn validate_deposit_fraction(fraction: f64) -> Result<f64, BingXError> {
+        if fraction > 0.0 && fraction <= 1.0 {
+            Ok(fraction)
+        } else {
+            Err(BingXError::Internal(format!(
+                "open_on_fraction_of_deposit must be in (0, 1], got {}",
+                fraction
+            )))
+        }
+    }
+
+    /// Проверяет, что процент из конфига положительный. Некорректное значение игнорируется с предупреждением.
+    fn positive_percent(name: &str, value: Option<f64>) -> Option<f64> {
+        match value? {
+            v if v > 0.0 && v.is_finite() => Some(v),
+            v => {
+                warn!("Invalid {} '{}', ignoring", name, v);
+                None
+            }
+        }
+    }
+
+    fn local_time_ms() -> i64 {
         SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap_or_default()
-            .as_millis() as u64
+            .as_millis() as i64
+    }
+
+    /// Текущее время для подписи с учетом поправки к часам сервера BingX.
+    fn timestamp_ms(&self) -> i64 {
+        Self::local_time_ms() + self.time_offset_ms.load(Ordering::Relaxed)
+    }
+
+    /// Добавляет timestamp и recvWindow, подписывает и возвращает готовую строку запроса.
+    fn signed_query(&self, mut params: HashMap<String, String>) -> Result<String, BingXError> {
+        params.insert("timestamp".to_string(), self.timestamp_ms().to_string());
+        params.insert("recvWindow".to_string(), self.recv_window_ms.to_string());
+        let query = Self::build_query(&params);
+        let signature = self.sign(&query)?;
+        Ok(format!("{}&signature={}", query, signature))
+    }
+
+    /// Запрашивает время сервера BingX и запоминает разницу с локальными часами.
+    async fn sync_server_time(&self) -> Result<(), BingXError> {
+        let started = Self::local_time_ms();
+        let server: ServerTime = self.get_public("/openApi/swap/v2/server/time").await?;
+        let finished = Self::local_time_ms();
+
+        // Считаем, что сервер ответил в середине запроса
+        let offset = server.server_time - (started + finished) / 2;
+        self.time_offset_ms.store(offset, Ordering::Relaxed);
+        info!(
+            "BingX: server time {}, local clock offset {} ms (round trip {} ms)",
+            server.server_time, offset, finished - started
+        );
+        Ok(())
+    }
+
+    /// Ошибка из-за расхождения timestamp запроса с часами сервера (вне recvWindow).
+    fn is_timestamp_error(error: &BingXError) -> bool {
+        match error {
+            BingXError::ApiCode { code, msg } => {
+                TIMESTAMP_ERROR_CODES.contains(code) || msg.to_ascii_lowercase().contains("timestamp")
+            }
+            _ => false,
+        }
+    }
+
+    /// Выполняет запрос; если BingX отклонил его из-за timestamp, синхронизирует часы и
+    /// повторяет один раз.
+    async fn with_clock_resync<T, F, Fut>(&self, mut request: F) -> Result<T, BingXError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, BingXError>>,
+    {
+        match request().await {
+            Err(e) if Self::is_timestamp_error(&e) => {
+                warn!(
+                    "BingX: request rejected due to clock skew ({}), local time {}. Resyncing with server time...",
+                    e,
+                    Self::local_time_ms()
+                );
+                if let Err(sync_err) = self.sync_server_time().await {
+                    warn!("BingX: failed to get server time: {}", sync_err);
+                    return Err(e);
+                }
+                request().await
+            }
+            result => result,
+        }
     }
 
     fn sign(&self, query: &str) -> Result<String, BingXError> {
@@ -160,15 +752,31 @@ impl BingXClient {
         Ok(hex::encode(result))
     }
 
+    /// Подписанный GET с повторами при временных ошибках (чтение безопасно повторять).
     async fn get_signed<T: for<'de> Deserialize<'de> + Default>(
         &self,
         path: &str,
-        mut params: HashMap<String, String>,
+        params: HashMap<String, String>,
     ) -> Result<T, BingXError> {
-        params.insert("timestamp".to_string(), Self::timestamp_ms().to_string());
-        let query = Self::build_query(&params);
-        let signature = self.sign(&query)?;
-        let full_query = format!("{}&signature={}", query, signature);
+        self.with_retry(path, || self.get_signed_once(path, params.clone()))
+            .await
+    }
+
+    async fn get_signed_once<T: for<'de> Deserialize<'de> + Default>(
+        &self,
+        path: &str,
+        params: HashMap<String, String>,
+    ) -> Result<T, BingXError> {
+        self.with_clock_resync(|| self.get_signed_raw(path, params.clone()))
+            .await
+    }
+
+    async fn get_signed_raw<T: for<'de> Deserialize<'de> + Default>(
+        &self,
+        path: &str,
+        params: HashMap<String, String>,
+    ) -> Result<T, BingXError> {
+        let full_query = self.signed_query(params)?;
 
         let url = format!("{}{}?{}", self.base_url, path, full_query);
         let resp = self
@@ -178,32 +786,42 @@ impl BingXClient {
             .send()
             .await
             .map_err(BingXError::Http)?;
+        Self::parse_response(resp).await
+    }
 
-        let text = resp.text().await.map_err(BingXError::Http)?;
-        let api_resp: ApiResponse<T> = serde_json::from_str(&text).map_err(BingXError::Serde)?;
-
-        if api_resp.code != 0 {
-            return Err(BingXError::Api(
-                api_resp
-                    .msg
-                    .unwrap_or_else(|| format!("unknown error, body: {}", text)),
-            ));
-        }
-
-        api_resp
-            .data
-            .ok_or_else(|| BingXError::Api("missing data field in response".into()))
+    /// Подписанный POST с повторами. Только для идемпотентных запросов (плечо, TP/SL позиции) –
+    /// повтор не должен привести к двойному действию.
+    async fn post_signed_idempotent<T: for<'de> Deserialize<'de> + Default>(
+        &self,
+        path: &str,
+        params: HashMap<String, String>,
+    ) -> Result<T, BingXError> {
+        self.with_retry(path, || self.post_signed(path, params.clone()))
+            .await
     }
 
+    /// Подписанный POST, одна попытка. Используется для выставления ордеров: при таймауте ордер
+    /// мог уже исполниться, и повтор открыл бы вторую позицию.
     async fn post_signed<T: for<'de> Deserialize<'de> + Default>(
         &self,
         path: &str,
-        mut params: HashMap<String, String>,
+        params: HashMap<String, String>,
     ) -> Result<T, BingXError> {
-        params.insert("timestamp".to_string(), Self::timestamp_ms().to_string());
-        let query = Self::build_query(&params);
-        let signature = self.sign(&query)?;
-        let full_body = format!("{}&signature={}", query, signature);
+        // Ордер, отклоненный из-за timestamp, не исполнен – его безопасно отправить повторно
+        let result = self
+            .with_clock_resync(|| self.post_signed_raw(path, params.clone()))
+            .await;
+        // Даже при ошибке ордер мог исполниться (таймаут), так что кэш позиций сбрасываем всегда
+        self.invalidate_open_positions();
+        result
+    }
+
+    async fn post_signed_raw<T: for<'de> Deserialize<'de> + Default>(
+        &self,
+        path: &str,
+        params: HashMap<String, String>,
+    ) -> Result<T, BingXError> {
+        let full_body = self.signed_query(params)?;
 
         let url = format!("{}{}", self.base_url, path);
         let resp = self
@@ -215,16 +833,44 @@ impl BingXClient {
             .send()
             .await
             .map_err(BingXError::Http)?;
+        Self::parse_response(resp).await
+    }
+
+    async fn get_public<T: for<'de> Deserialize<'de> + Default>(
+        &self,
+        path: &str,
+    ) -> Result<T, BingXError> {
+        let url = format!("{}{}", self.base_url, path);
+        let resp = self
+            .http_client
+            .get(&url)
+            .send()
+            .await
+            .map_err(BingXError::Http)?;
+        Self::parse_response(resp).await
+    }
+
+    /// Разбирает ответ BingX: 5xx – ошибка HTTP, ненулевой code – `ApiCode`, иначе поле data.
+    async fn parse_response<T: for<'de> Deserialize<'de> + Default>(
+        resp: reqwest::Response,
+    ) -> Result<T, BingXError> {
+        // 5xx превращаем в ошибку HTTP, чтобы ее можно было повторить
+        let resp = if resp.status().is_server_error() {
+            resp.error_for_status().map_err(BingXError::Http)?
+        } else {
+            resp
+        };
 
         let text = resp.text().await.map_err(BingXError::Http)?;
         let api_resp: ApiResponse<T> = serde_json::from_str(&text).map_err(BingXError::Serde)?;
 
         if api_resp.code != 0 {
-            return Err(BingXError::Api(
-                api_resp
+            return Err(BingXError::ApiCode {
+                code: api_resp.code,
+                msg: api_resp
                     .msg
                     .unwrap_or_else(|| format!("unknown error, body: {}", text)),
-            ));
+            });
         }
 
         api_resp
@@ -232,6 +878,45 @@ impl BingXClient {
             .ok_or_else(|| BingXError::Api("missing data field in response".into()))
     }
 
+    /// Повторяет запрос при временных ошибках: до `RETRY_ATTEMPTS` попыток с удвоением задержки.
+    /// Невременные ошибки (подпись, баланс, параметры) возвращаются сразу.
+    async fn with_retry<T, F, Fut>(&self, what: &str, mut request: F) -> Result<T, BingXError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, BingXError>>,
+    {
+        let mut delay = RETRY_BASE_DELAY;
+        let mut attempt = 1;
+        loop {
+            match request().await {
+                Err(e) if attempt < RETRY_ATTEMPTS && self.is_retryable(&e) => {
+                    warn!(
+                        "BingX: {} failed (attempt {}/{}): {}. Retrying in {:?}...",
+                        what, attempt, RETRY_ATTEMPTS, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Временная ли ошибка: сетевые сбои, таймауты, 5xx и коды из `retryable_codes`.
+    fn is_retryable(&self, error: &BingXError) -> bool {
+        match error {
+            BingXError::Http(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.is_request()
+                    || e.status().map(|s| s.is_server_error()).unwrap_or(false)
+            }
+            BingXError::ApiCode { code, .. } => self.retryable_codes.contains(code),
+            _ => false,
+        }
+    }
+
     fn build_query(params: &HashMap<String, String>) -> String {
         let mut items: Vec<(String, String)> = params.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
         items.sort_by(|a, b| a.0.cmp(&b.0));
@@ -242,10 +927,8 @@ impl BingXClient {
             .join("&")
     }
 
-    /// Возвращает количество открытых позиций на BingX (по всем символам).
-    ///
-    /// Твое требование: если есть ХОТЯ БЫ ОДНА открытая позиция — не открывать ничего нового.
-    pub async fn count_open_positions(&self) -> Result<usize, BingXError> {
+    /// Получает все позиции аккаунта BingX.
+    async fn fetch_positions(&self) -> Result<Vec<Position>, BingXError> {
         let params: HashMap<String, String> = HashMap::new();
 
         // Важно: у BingX структура data может отличаться.
@@ -279,29 +962,352 @@ impl BingXClient {
             raw.clone()
         };
 
-        let positions: Vec<Position> = match serde_json::from_value(positions_val) {
-            Ok(v) => v,
+        match serde_json::from_value(positions_val) {
+            Ok(v) => Ok(v),
             Err(e) => {
                 error!(
                     "BingX: unexpected positions response format. raw_data={}. error={}",
                     raw, e
                 );
-                return Err(BingXError::Serde(e));
+                Err(BingXError::Serde(e))
             }
-        };
+        }
+    }
 
-        let open_count = positions
+    /// Реализованный результат (PnL, комиссии и фандинг) в USDT с момента `start_ms`.
+    pub async fn realized_pnl_since(&self, start_ms: i64) -> Result<f64, BingXError> {
+        let mut params = HashMap::new();
+        params.insert("startTime".to_string(), start_ms.to_string());
+        params.insert("limit".to_string(), "1000".to_string());
+
+        let records: Vec<IncomeRecord> = self
+            .get_signed("/openApi/swap/v2/user/income", params)
+            .await?;
+
+        Ok(records
             .iter()
-            .filter(|p| {
-                p.position_amt
-                    .as_ref()
-                    .and_then(|s| s.parse::<f64>().ok())
-                    .map(|v| v.abs() > 0.0)
-                    .unwrap_or(false)
+            .filter(|r| REALIZED_INCOME_TYPES.contains(&r.income_type.as_str()))
+            .filter_map(|r| match &r.income {
+                Value::String(s) => s.parse::<f64>().ok(),
+                Value::Number(n) => n.as_f64(),
+                _ => None,
+            })
+            .sum())
+    }
+
+    /// Возвращает количество открытых позиций на BingX (по всем символам).
+    ///
+    /// Твое требование: если есть ХОТЯ БЫ ОДНА открытая позиция — не открывать ничего нового.
+    ///
+    /// Результат кэшируется на `position_cache_ttl`, чтобы сигналы подряд не тратили rate limit
+    /// на одинаковые запросы; любой ордер сбрасывает кэш (см. `invalidate_open_positions`).
+    pub async fn count_open_positions(&self) -> Result<usize, BingXError> {
+        if let Some(count) = self.cached_open_positions() {
+            return Ok(count);
+        }
+        let generation = self.open_positions_generation.load(Ordering::SeqCst);
+        let count = self.get_open_positions().await?.len();
+        if let Ok(mut cache) = self.open_positions_cache.lock() {
+            // Пока шел запрос, мог уйти ордер – тогда ответ уже устарел
+            if self.open_positions_generation.load(Ordering::SeqCst) == generation {
+                *cache = Some((Instant::now(), count));
+            }
+        }
+        Ok(count)
+    }
+
+    fn cached_open_positions(&self) -> Option<usize> {
+        let cache = self.open_positions_cache.lock().ok()?;
+        let (fetched_at, count) = (*cache)?;
+        (fetched_at.elapsed() < self.position_cache_ttl).then_some(count)
+    }
+
+    /// Сбрасывает кэш числа открытых позиций – после любого ордера оно могло измениться.
+    fn invalidate_open_positions(&self) {
+        if let Ok(mut cache) = self.open_positions_cache.lock() {
+            self.open_positions_generation.fetch_add(1, Ordering::SeqCst);
+            *cache = None;
+        }
+    }
+
+    /// Открытые позиции BingX (с ненулевым объемом) со средней ценой входа и нереализованным PnL.
+    pub async fn get_open_positions(&self) -> Result<Vec<OpenPosition>, BingXError> {
+        let positions = self.fetch_positions().await?;
+        Ok(positions.iter().filter_map(Position::to_open_position).collect())
+    }
+
+    /// Параметры контрактов из `/openApi/swap/v2/quote/contracts`.
+    /// Список загружается при первом обращении и кэшируется; при ошибке загрузка повторится в следующий раз.
+    async fn contract_specs(&self) -> Result<&HashMap<String, ContractSpec>, BingXError> {
+        self.contract_specs
+            .get_or_try_init(|| async {
+                let contracts: Vec<ContractInfo> =
+                    self.get_public("/openApi/swap/v2/quote/contracts").await?;
+                info!("BingX: loaded {} contract specs", contracts.len());
+                Ok::<_, BingXError>(
+                    contracts
+                        .into_iter()
+                        .map(|c| {
+                            let step_size = 10f64.powi(-(c.quantity_precision as i32));
+                            let spec = ContractSpec {
+                                quantity_precision: c.quantity_precision,
+                                min_qty: c.trade_min_quantity.unwrap_or(step_size),
+                                step_size,
+                                min_notional: c.trade_min_usdt.unwrap_or(0.0),
+                                price_precision: c.price_precision.unwrap_or(DEFAULT_PRICE_PRECISION),
+                            };
+                            (c.symbol, spec)
+                        })
+                        .collect(),
+                )
+            })
+            .await
+    }
+
+    /// Режим позиций аккаунта из `/openApi/swap/v2/user/positionMode`.
+    /// Запрашивается при старте и кэшируется; при ошибке запрос повторится при следующем обращении.
+    /// Если режим переключить на бирже во время работы, бота нужно перезапустить.
+    pub async fn position_mode(&self) -> Result<PositionMode, BingXError> {
+        self.position_mode
+            .get_or_try_init(|| async {
+                let data: PositionModeData = self
+                    .get_signed("/openApi/swap/v2/user/positionMode", HashMap::new())
+                    .await?;
+                let mode = data.mode().ok_or_else(|| {
+                    BingXError::Api(format!(
+                        "unexpected dualSidePosition in positionMode response: {}",
+                        data.dual_side_position
+                    ))
+                })?;
+                info!("BingX: account position mode is {}", mode.as_str());
+                Ok::<_, BingXError>(mode)
+            })
+            .await
+            .copied()
+    }
+
+    /// Шаг количества контракта BingX для канонического тикера, в монетах.
+    pub async fn quantity_step(&self, symbol: &str) -> Result<f64, BingXError> {
+        let (bingx_symbol, multiplier) = self.resolve_contract(symbol).await?;
+        Ok(self.contract_spec(&bingx_symbol).await?.step_size * multiplier)
+    }
+
+    /// Открытая позиция `direction` по каноническому тикеру: объем в монетах и цена входа за монету.
+    /// None – позиции нет.
+    pub async fn position_quantity(&self, symbol: &str, direction: &str) -> Result<Option<(f64, f64)>, BingXError> {
+        let (bingx_symbol, multiplier) = self.resolve_contract(symbol).await?;
+        Ok(self
+            .get_open_positions()
+            .await?
+            .into_iter()
+            .find(|p| p.symbol == bingx_symbol && p.side == direction)
+            .map(|p| (p.size * multiplier, p.entry_price / multiplier)))
+    }
+
+    async fn contract_spec(&self, bingx_symbol: &str) -> Result<ContractSpec, BingXError> {
+        self.contract_specs().await?.get(bingx_symbol).copied().ok_or_else(|| {
+            BingXError::Api(format!("contract {} not found in BingX contracts", bingx_symbol))
+        })
+    }
+
+    /// Находит контракт BingX для канонического тикера проекта (`PEPEUSDT`) и множитель,
+    /// в котором BingX котирует его цену (`1000PEPE-USDT` -> 1000).
+    async fn resolve_contract(&self, symbol: &str) -> Result<(String, f64), BingXError> {
+        let specs = self.contract_specs().await?;
+        let direct = from_canonical(BINGX, symbol);
+        if specs.contains_key(&direct) {
+            return Ok((direct, 1.0));
+        }
+        specs
+            .keys()
+            .find_map(|bingx_symbol| {
+                let canonical = canonical_symbol(bingx_symbol);
+                (canonical.symbol == symbol).then(|| (bingx_symbol.clone(), canonical.multiplier))
             })
-            .count();
+            .ok_or_else(|| BingXError::Api(format!("no BingX contract for {}", symbol)))
+    }
+
+    /// Округляет количество вниз до шага контракта и возвращает его вместе со строкой для ордера.
+    async fn round_quantity(&self, bingx_symbol: &str, quantity: f64) -> Result<(f64, String), BingXError> {
+        let spec = self.contract_spec(bingx_symbol).await?;
+        // Небольшой допуск, чтобы 12.345 / 0.001 = 12344.999... не теряло шаг
+        let steps = (quantity / spec.step_size + 1e-9).floor();
+        let rounded = steps * spec.step_size;
+
+        if rounded < spec.min_qty || rounded <= 0.0 {
+            return Err(BingXError::QuantityBelowMinimum {
+                symbol: bingx_symbol.to_string(),
+                quantity: rounded,
+                min_qty: spec.min_qty,
+            });
+        }
+
+        let formatted = format!("{:.*}", spec.quantity_precision as usize, rounded);
+        Ok((rounded, formatted))
+    }
+
+    /// Округляет цену до `pricePrecision` контракта, иначе BingX отклоняет ордер.
+    fn round_price(spec: &ContractSpec, price: f64) -> (f64, String) {
+        let formatted = format!("{:.*}", spec.price_precision as usize, price);
+        (formatted.parse().unwrap_or(price), formatted)
+    }
+
+    /// Проверяет, что стоимость ордера по ожидаемой цене не меньше минимума контракта.
+    fn check_min_notional(spec: &ContractSpec, bingx_symbol: &str, quantity: f64, price: f64) -> Result<(), BingXError> {
+        let notional = quantity * price;
+        if notional < spec.min_notional {
+            return Err(BingXError::NotionalBelowMinimum {
+                symbol: bingx_symbol.to_string(),
+                notional,
+                min_notional: spec.min_notional,
+            });
+        }
+        Ok(())
+    }
+
+    /// Опрашивает `GET /openApi/swap/v2/trade/order`, пока ордер не перейдет в конечный статус.
+    ///
+    /// Возвращает фактически исполненное количество и среднюю цену. Если ордер отменен без
+    /// исполнения – `OrderRejected`, если не исполнился за отведенное время – `OrderNotFilled`.
+    async fn wait_for_fill(&self, bingx_symbol: &str, order_id: &str) -> Result<OrderFill, BingXError> {
+        let mut last_status = String::from("UNKNOWN");
+
+        for attempt in 1..=ORDER_STATUS_POLL_ATTEMPTS {
+            tokio::time::sleep(ORDER_STATUS_POLL_INTERVAL).await;
+
+            let mut params = HashMap::new();
+            params.insert("symbol".to_string(), bingx_symbol.to_string());
+            params.insert("orderId".to_string(), order_id.to_string());
+
+            let order = match self
+                .get_signed::<OrderResponse>("/openApi/swap/v2/trade/order", params)
+                .await
+            {
+                Ok(resp) => match resp.order {
+                    Some(order) => order,
+                    None => {
+                        warn!("BingX: order {} status response has no order data (attempt {})", order_id, attempt);
+                        continue;
+                    }
+                },
+                Err(e) => {
+                    // Ордер может еще не появиться в истории – пробуем снова
+                    warn!("BingX: failed to query order {} status (attempt {}): {}", order_id, attempt, e);
+                    continue;
+                }
+            };
+
+            let status = order.status.clone().unwrap_or_default();
+            let executed_qty = order.executed_qty();
+            match status.as_str() {
+                "FILLED" => {
+                    return Ok(OrderFill {
+                        quantity: executed_qty,
+                        avg_price: order.avg_price(),
+                    });
+                }
+                "CANCELED" | "CANCELLED" | "FAILED" | "EXPIRED" | "REJECTED" => {
+                    // Частично исполненный и затем отмененный ордер все равно открыл позицию
+                    if executed_qty > 0.0 {
+                        warn!(
+                            "BingX: order {} for {} ended with status {} after partial fill of {}",
+                            order_id, bingx_symbol, status, executed_qty
+                        );
+                        return Ok(OrderFill {
+                            quantity: executed_qty,
+                            avg_price: order.avg_price(),
+                        });
+                    }
+                    return Err(BingXError::OrderRejected {
+                        symbol: bingx_symbol.to_string(),
+                        order_id: order_id.to_string(),
+                        status,
+                    });
+                }
+                _ => {
+                    info!(
+                        "BingX: order {} for {} is {} (executed {}), waiting... (attempt {}/{})",
+                        order_id, bingx_symbol, status, executed_qty, attempt, ORDER_STATUS_POLL_ATTEMPTS
+                    );
+                    last_status = status;
+                }
+            }
+        }
 
-        Ok(open_count)
+        error!(
+            "BingX: order {} for {} still {} after polling. Check the position manually.",
+            order_id, bingx_symbol, last_status
+        );
+        Err(BingXError::OrderNotFilled {
+            symbol: bingx_symbol.to_string(),
+            order_id: order_id.to_string(),
+            waited: ORDER_STATUS_POLL_INTERVAL * ORDER_STATUS_POLL_ATTEMPTS,
+        })
+    }
+
+    /// Ордер на открытие не подтвердился (`wait_for_fill` вернул ошибку), но мог исполниться
+    /// целиком или частично. Проверяет фактическую позицию: если она есть, ставит stop loss
+    /// на ее объем, а если стоп не задан или не встал – закрывает позицию.
+    /// Возвращает исходную ошибку: открытой ботом позиция не считается.
+    async fn secure_unconfirmed_fill(
+        &self,
+        bingx_symbol: &str,
+        direction: &str,
+        mode: PositionMode,
+        stop_loss_percent: Option<f64>,
+        spec: &ContractSpec,
+        error: BingXError,
+    ) -> BingXError {
+        let positions = match self.get_open_positions().await {
+            Ok(positions) => positions,
+            Err(e) => {
+                error!(
+                    "BingX: {} order on {} was not confirmed ({}) and positions could not be checked: {}. MANUAL ACTION REQUIRED.",
+                    direction, bingx_symbol, error, e
+                );
+                return error;
+            }
+        };
+        let Some(position) = positions
+            .into_iter()
+            .find(|p| p.symbol == bingx_symbol && p.side == direction)
+        else {
+            warn!("BingX: {} order on {} was not confirmed ({}), no position is open", direction, bingx_symbol, error);
+            return error;
+        };
+        warn!(
+            "BingX: {} order on {} was not confirmed ({}), but a position of {} is open",
+            direction, bingx_symbol, error, position.size
+        );
+
+        if let Some(pct) = stop_loss_percent.filter(|_| position.entry_price > 0.0) {
+            let price = ProtectiveOrder::StopLoss.trigger_price(direction, position.entry_price, pct);
+            let quantity = format!("{:.*}", spec.quantity_precision as usize, position.size);
+            match self
+                .place_protective_order(bingx_symbol, direction, mode, &quantity, ProtectiveOrder::StopLoss, price)
+                .await
+            {
+                Ok(()) => {
+                    warn!(
+                        "BingX: stop loss at {} placed for the unconfirmed {} position on {}",
+                        price, direction, bingx_symbol
+                    );
+                    return error;
+                }
+                Err(e) => error!(
+                    "BingX: stop loss for the unconfirmed {} position on {} failed: {}. Closing the position.",
+                    direction, bingx_symbol, e
+                ),
+            }
+        }
+
+        if let Err(close_err) = self.close_position(bingx_symbol, direction).await {
+            error!(
+                "BingX: failed to close the unconfirmed {} position on {}: {}. MANUAL ACTION REQUIRED.",
+                direction, bingx_symbol, close_err
+            );
+        }
+        error
     }
 
     /// Получаем доступный баланс USDT на фьючерсном аккаунте.
@@ -329,6 +1335,9 @@ impl BingXClient {
         for bal in balances {
             if bal.asset.eq_ignore_ascii_case("USDT") {
                 if let Ok(v) = bal.available_balance.parse::<f64>() {
+                    if let Ok(mut last) = self.last_available_usdt.lock() {
+                        *last = Some(v);
+                    }
                     return Ok(v);
                 }
             }
@@ -340,13 +1349,29 @@ impl BingXClient {
         ))
     }
 
-    /// Выставляем кросс маржу и плечо для символа (если требуется отдельным вызовом).
-    pub async fn ensure_cross_margin_10x(&self, symbol: &str, position_side: &str) {
-        let bingx_symbol = Self::normalize_symbol(symbol);
+    /// Проверка на пути обнаружения спреда, до запросов позиций и баланса: позиция на заданную
+    /// долю последнего известного баланса с плечом меньше минимальной стоимости ордера
+    /// (`tradeMinUSDT`) – BingX ее все равно отклонит. Возвращает причину пропуска.
+    ///
+    /// Пока баланс не запрашивался или контракт не найден, проверка не выполняется – ордер
+    /// проверит `open_market_position`.
+    pub async fn min_notional_skip(&self, symbol: &str) -> Option<String> {
+        let available_usdt = (*self.last_available_usdt.lock().ok()?)?;
+        let (bingx_symbol, _) = self.resolve_contract(symbol).await.ok()?;
+        let spec = self.contract_spec(&bingx_symbol).await.ok()?;
+        let notional = available_usdt * self.deposit_fraction * f64::from(self.leverage);
+        Self::check_min_notional(&spec, &bingx_symbol, notional, 1.0)
+            .err()
+            .map(|e| format!("below min notional: {}", e))
+    }
+
+    /// Выставляем кросс маржу и настроенное плечо для символа (если требуется отдельным вызовом).
+    pub async fn ensure_cross_margin(&self, symbol: &str, position_side: &str) {
+        let bingx_symbol = from_canonical(BINGX, symbol);
         let mut params = HashMap::new();
         params.insert("symbol".to_string(), bingx_symbol.clone());
         params.insert("marginMode".to_string(), "CROSSED".to_string());
-        params.insert("leverage".to_string(), "10".to_string());
+        params.insert("leverage".to_string(), self.leverage.to_string());
         // BingX требует side для установки плеча со значениями LONG, SHORT или BOTH
         params.insert("side".to_string(), position_side.to_string());
 
@@ -354,51 +1379,45 @@ impl BingXClient {
         // Здесь мы сознательно игнорируем ошибку, чтобы не блокировать основную торговлю,
         // но логируем все детали.
         match self
-            .post_signed::<serde_json::Value>("/openApi/swap/v2/trade/leverage", params)
+            .post_signed_idempotent::<serde_json::Value>("/openApi/swap/v2/trade/leverage", params)
             .await
         {
             Ok(_) => {
                 info!(
-                    "BingX: successfully ensured cross margin 10x for symbol {}",
-                    bingx_symbol
+                    "BingX: successfully ensured cross margin {}x for symbol {}",
+                    self.leverage, bingx_symbol
                 );
             }
             Err(e) => {
                 warn!(
-                    "BingX: failed to ensure cross margin 10x for {}: {}. Please verify API endpoint and params.",
-                    bingx_symbol, e
+                    "BingX: failed to ensure cross margin {}x for {}: {}. Please verify API endpoint and params.",
+                    self.leverage, bingx_symbol, e
                 );
             }
         }
     }
 
-    /// Устанавливает take profit ордер для открытой позиции.
+    /// Выставляет защитный условный ордер (take profit или stop loss) на всю позицию.
     ///
     /// - symbol: символ (например, "AXS-USDT")
     /// - direction: "LONG" или "SHORT"
-    /// - quantity: количество позиции
-    /// - entry_price: цена входа
-    /// - take_profit_percent: процент прибыли (например, 3.0 для 3%)
-    async fn set_take_profit(
+    /// - quantity: количество позиции (уже округленное до шага контракта)
+    /// - trigger_price: цена срабатывания
+    /// - mode: режим позиций аккаунта, от него зависят positionSide и reduceOnly
+    async fn place_protective_order(
         &self,
         symbol: &str,
         direction: &str,
-        quantity: f64,
-        entry_price: f64,
-        take_profit_percent: f64,
+        mode: PositionMode,
+        quantity: &str,
+        kind: ProtectiveOrder,
+        trigger_price: f64,
     ) -> Result<(), BingXError> {
-        let bingx_symbol = Self::normalize_symbol(symbol);
-        
-        // Рассчитываем цену take profit: для LONG +3%, для SHORT -3%
-        let take_profit_price = if direction == "LONG" {
-            entry_price * (1.0 + take_profit_percent / 100.0)
-        } else {
-            entry_price * (1.0 - take_profit_percent / 100.0)
-        };
+        let bingx_symbol = from_canonical(BINGX, symbol);
 
         info!(
-            "BingX: setting take profit for {} {} position. entry_price={}, take_profit_price={} (+{}%)",
-            direction, bingx_symbol, entry_price, take_profit_price, take_profit_percent
+            "BingX: setting {} for {} {} position at {}",
+            kind.label(), direction, bingx_symbol, trigger_price
         );
 
         // Определяем сторону для закрытия позиции (противоположная открытию)
@@ -407,7 +1426,8 @@ impl BingXClient {
             "SHORT" => "BUY",  // Закрываем SHORT покупкой
             _ => {
                 return Err(BingXError::Internal(format!(
-                    "unknown direction for take profit: {}",
+                    "unknown direction for {}: {}",
+                    kind.label(),
                     direction
                 )));
             }
@@ -416,15 +1436,11 @@ impl BingXClient {
         let mut params = HashMap::new();
         params.insert("symbol".to_string(), bingx_symbol.clone());
         params.insert("side".to_string(), close_side.to_string());
-        params.insert("positionSide".to_string(), direction.to_string());
-        params.insert("type".to_string(), "TAKE_PROFIT_MARKET".to_string());
-        // Форматируем количество с достаточной точностью, но без лишних нулей
-        let quantity_str = format!("{:.8}", quantity).trim_end_matches('0').trim_end_matches('.').to_string();
-        params.insert("quantity".to_string(), quantity_str);
-        // stopPrice - это триггерная цена, при достижении которой сработает take profit
-        params.insert("stopPrice".to_string(), format!("{:.8}", take_profit_price));
-        // Используем takeProfitPrice вместо takeProfit (согласно документации)
-        params.insert("takeProfitPrice".to_string(), format!("{:.8}", take_profit_price));
+        mode.insert_position_params(&mut params, direction, true);
+        params.insert("type".to_string(), kind.order_type().to_string());
+        params.insert("quantity".to_string(), quantity.to_string());
+        // stopPrice - это триггерная цена, при достижении которой сработает ордер
+        params.insert("stopPrice".to_string(), format!("{:.8}", trigger_price));
         params.insert("marginMode".to_string(), "CROSSED".to_string());
         // workingType определяет, по какой цене проверяется триггер
         params.insert("workingType".to_string(), "MARK_PRICE".to_string());
@@ -432,89 +1448,175 @@ impl BingXClient {
         params.insert("timeInForce".to_string(), "GTC".to_string());
 
         info!(
-            "BingX: sending take profit order with params: symbol={}, side={}, positionSide={}, type=TAKE_PROFIT_MARKET, quantity={}, takeProfitPrice={}, stopPrice={}",
-            bingx_symbol, close_side, direction, quantity, take_profit_price, take_profit_price
+            "BingX: sending {} order with params: symbol={}, side={}, positionSide={}, type={}, quantity={}, stopPrice={}",
+            kind.label(), bingx_symbol, close_side, mode.position_side(direction), kind.order_type(), quantity, trigger_price
         );
 
-        // Используем прямой вызов для получения полного ответа
-        params.insert("timestamp".to_string(), Self::timestamp_ms().to_string());
-        let query = Self::build_query(&params);
-        let signature = self.sign(&query)?;
-        let full_body = format!("{}&signature={}", query, signature);
-
-        let url = format!("{}/openApi/swap/v2/trade/order", self.base_url);
-        let resp = self
-            .http_client
-            .post(&url)
-            .header("X-BX-APIKEY", &self.api_key)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(full_body)
-            .send()
+        match self
+            .post_signed_raw::<serde_json::Value>("/openApi/swap/v2/trade/order", params)
             .await
-            .map_err(BingXError::Http)?;
+        {
+            Ok(resp) => info!("BingX: {} order response: {}", kind.label(), resp),
+            Err(BingXError::ApiCode { code, msg }) => {
+                warn!(
+                    "BingX: failed to set {} via order endpoint for {} {} position. Code: {}, Message: {}. Trying alternative method...",
+                    kind.label(), direction, bingx_symbol, code, msg
+                );
 
-        let text = resp.text().await.map_err(BingXError::Http)?;
-        info!("BingX: take profit order response: {}", text);
+                // Пробуем альтернативный метод - установка TP/SL через отдельный endpoint
+                return self
+                    .place_protective_order_alternative(&bingx_symbol, direction, mode, kind, trigger_price)
+                    .await;
+            }
+            Err(e) => return Err(e),
+        }
 
-        let api_resp: ApiResponse<serde_json::Value> = serde_json::from_str(&text)
-            .map_err(|e| BingXError::Serde(e))?;
+        info!(
+            "BingX: successfully set {} at {} for {} {} position",
+            kind.label(), trigger_price, direction, bingx_symbol
+        );
+        Ok(())
+    }
 
-        if api_resp.code != 0 {
-            let error_msg = api_resp
-                .msg
-                .unwrap_or_else(|| format!("unknown error, body: {}", text));
-            warn!(
-                "BingX: failed to set take profit via order endpoint for {} {} position. Code: {}, Message: {}. Trying alternative method...",
-                direction, bingx_symbol, api_resp.code, error_msg
-            );
-            
-            // Пробуем альтернативный метод - установка TP через отдельный endpoint
-            return self.set_take_profit_alternative(&bingx_symbol, direction, take_profit_price).await;
+    /// priceRate BingX для отката `callback_rate` в процентах; вне (0, 100] – ошибка до запросов к бирже.
+    fn trailing_price_rate(callback_rate: f64) -> Result<f64, BingXError> {
+        if callback_rate > 0.0 && callback_rate <= MAX_TRAILING_PERCENT {
+            Ok(callback_rate / 100.0)
+        } else {
+            Err(BingXError::Internal(format!(
+                "trailing stop callback rate must be in (0, {}]%, got {}",
+                MAX_TRAILING_PERCENT, callback_rate
+            )))
+        }
+    }
+
+    /// Выставляет трейлинг-стоп (`TRAILING_STOP_MARKET`) на всю открытую позицию.
+    ///
+    /// - symbol: канонический символ (например, "AXSUSDT")
+    /// - position_side: "LONG" или "SHORT"
+    /// - callback_rate: откат от лучшей цены в процентах, (0, 100]
+    /// - activation_price: цена за монету, с которой стоп начинает следовать за рынком; None – сразу
+    ///
+    /// Объем берется из текущей позиции. В режиме BINGX_DRY_RUN ордер только логируется.
+    #[allow(dead_code)]
+    pub async fn set_trailing_stop(
+        &self,
+        symbol: &str,
+        position_side: &str,
+        callback_rate: f64,
+        activation_price: Option<f64>,
+    ) -> Result<(), BingXError> {
+        let price_rate = Self::trailing_price_rate(callback_rate)?;
+        let (bingx_symbol, multiplier) = self.resolve_contract(symbol).await?;
+        let direction = position_side.to_uppercase();
+
+        let size = self
+            .get_open_positions()
+            .await?
+            .into_iter()
+            .find(|p| p.symbol == bingx_symbol && p.side == direction)
+            .map(|p| p.size)
+            .ok_or_else(|| {
+                BingXError::Internal(format!("no open {} position for {}", direction, bingx_symbol))
+            })?;
+        let spec = self.contract_spec(&bingx_symbol).await?;
+        let quantity = format!("{:.*}", spec.quantity_precision as usize, size);
+        let mode = self.position_mode().await?;
+
+        // activation_price – за монету, BingX ждет цену контракта
+        let activation_price = activation_price.map(|price| price * multiplier);
+        self.place_trailing_stop(&bingx_symbol, &direction, mode, &quantity, price_rate, activation_price)
+            .await
+    }
+
+    /// Отправляет `TRAILING_STOP_MARKET` на закрытие `quantity` позиции `direction`.
+    async fn place_trailing_stop(
+        &self,
+        bingx_symbol: &str,
+        direction: &str,
+        mode: PositionMode,
+        quantity: &str,
+        price_rate: f64,
+        activation_price: Option<f64>,
+    ) -> Result<(), BingXError> {
+        let close_side = match direction {
+            "LONG" => "SELL",
+            "SHORT" => "BUY",
+            _ => {
+                return Err(BingXError::Internal(format!(
+                    "unknown direction for trailing stop: {}",
+                    direction
+                )));
+            }
+        };
+
+        let mut params = HashMap::new();
+        params.insert("symbol".to_string(), bingx_symbol.to_string());
+        params.insert("side".to_string(), close_side.to_string());
+        mode.insert_position_params(&mut params, direction, true);
+        params.insert("type".to_string(), "TRAILING_STOP_MARKET".to_string());
+        params.insert("quantity".to_string(), quantity.to_string());
+        // priceRate – откат от лучшей цены долей (0.015 = 1.5%)
+        params.insert("priceRate".to_string(), price_rate.to_string());
+        if let Some(price) = activation_price {
+            params.insert("activationPrice".to_string(), format!("{:.8}", price));
         }
+        params.insert("workingType".to_string(), "MARK_PRICE".to_string());
 
         info!(
-            "BingX: successfully set take profit at {} for {} {} position",
-            take_profit_price, direction, bingx_symbol
+            "BingX: setting trailing stop for {} {} position: qty={}, priceRate={}, activationPrice={:?}",
+            direction, bingx_symbol, quantity, price_rate, activation_price
         );
+
+        if self.dry_run {
+            info!("BingX: dry run, trailing stop for {} not sent", bingx_symbol);
+            return Ok(());
+        }
+
+        self.post_signed::<serde_json::Value>("/openApi/swap/v2/trade/order", params)
+            .await?;
+        info!("BingX: trailing stop set for {} {} position", direction, bingx_symbol);
         Ok(())
     }
 
-    /// Альтернативный метод установки take profit через отдельный endpoint (если основной не работает)
-    async fn set_take_profit_alternative(
+    /// Альтернативный метод установки TP/SL через отдельный endpoint (если основной не работает)
+    async fn place_protective_order_alternative(
         &self,
         symbol: &str,
         direction: &str,
-        take_profit_price: f64,
+        mode: PositionMode,
+        kind: ProtectiveOrder,
+        trigger_price: f64,
     ) -> Result<(), BingXError> {
         // Пробуем использовать endpoint для установки TP/SL на позицию
         let mut params = HashMap::new();
         params.insert("symbol".to_string(), symbol.to_string());
-        params.insert("positionSide".to_string(), direction.to_string());
-        params.insert("takeProfit".to_string(), take_profit_price.to_string());
-        
+        params.insert("positionSide".to_string(), mode.position_side(direction).to_string());
+        params.insert(kind.position_param().to_string(), trigger_price.to_string());
+
         info!(
-            "BingX: trying alternative take profit method for {} {} position at {}",
-            direction, symbol, take_profit_price
+            "BingX: trying alternative {} method for {} {} position at {}",
+            kind.label(), direction, symbol, trigger_price
         );
 
         // Пробуем endpoint для изменения TP/SL позиции (если такой существует)
         // Если этот endpoint не существует, вернем ошибку
         match self
-            .post_signed::<serde_json::Value>("/openApi/swap/v2/trade/positionTPSL", params)
+            .post_signed_idempotent::<serde_json::Value>("/openApi/swap/v2/trade/positionTPSL", params)
             .await
         {
             Ok(_) => {
                 info!(
-                    "BingX: successfully set take profit (alternative method) at {} for {} {} position",
-                    take_profit_price, direction, symbol
+                    "BingX: successfully set {} (alternative method) at {} for {} {} position",
+                    kind.label(), trigger_price, direction, symbol
                 );
                 Ok(())
             }
             Err(e) => {
                 // Если альтернативный метод тоже не работает, возвращаем ошибку
                 warn!(
-                    "BingX: alternative take profit method also failed for {} {}: {}",
-                    direction, symbol, e
+                    "BingX: alternative {} method also failed for {} {}: {}",
+                    kind.label(), direction, symbol, e
                 );
                 Err(e)
             }
@@ -524,9 +1626,20 @@ impl BingXClient {
     /// Открытие маркет-позиции на BingX.
     ///
     /// - direction: \"LONG\" или \"SHORT\"
-    /// - open_on_fraction_of_deposit: доля депозита, которую хотим использовать как маржу (например, 0.75).
+    /// - open_on_fraction_of_deposit: доля депозита, которую хотим использовать как маржу (например, 0.75),
+    ///   в пределах (0, 1]; иначе – `BingXError::Internal` до любых запросов к бирже.
     /// - leverage: плечо (например, 10).
-    /// - take_profit_percent: процент прибыли для take profit (например, 3.0 для 3%)
+    /// - take_profit_percent: процент прибыли для take profit (например, 3.0 для 3%), None – без TP
+    /// - stop_loss_percent: процент убытка для stop loss (например, 2.0 для 2%), None – без SL
+    /// - trailing_percent: откат трейлинг-стопа в процентах, None – без трейлинг-стопа
+    ///
+    /// Если stop loss был запрошен, но выставить его не удалось, позиция сразу закрывается,
+    /// чтобы не оставлять ее без защиты. Неудачный трейлинг-стоп только отражается в результате.
+    /// Если исполнение ордера не подтвердилось, фактическая позиция защищается стопом или
+    /// закрывается до возврата ошибки (см. `secure_unconfirmed_fill`).
+    ///
+    /// В режиме BINGX_DRY_RUN ордер только рассчитывается и логируется – возвращается `DryRun`.
+    #[allow(clippy::too_many_arguments)]
     pub async fn open_market_position(
         &self,
         symbol: &str,
@@ -534,9 +1647,13 @@ impl BingXClient {
         open_on_fraction_of_deposit: f64,
         leverage: f64,
         reference_price: f64,
-        take_profit_percent: f64,
+        take_profit_percent: Option<f64>,
+        stop_loss_percent: Option<f64>,
+        trailing_percent: Option<f64>,
     ) -> Result<BingXTradeOutcome, BingXError> {
-        let bingx_symbol = Self::normalize_symbol(symbol);
+        let open_on_fraction_of_deposit = Self::validate_deposit_fraction(open_on_fraction_of_deposit)?;
+        let trailing_rate = trailing_percent.map(Self::trailing_price_rate).transpose()?;
+        let bingx_symbol = from_canonical(BINGX, symbol);
         if reference_price <= 0.0 {
             return Err(BingXError::Internal(
                 "reference_price must be positive".into(),
@@ -550,7 +1667,7 @@ impl BingXClient {
             ));
         }
 
-        // Подход: используем 75% депозита как маржу под позицию с плечом.
+        // Подход: используем заданную долю депозита (по умолчанию 75%) как маржу под позицию с плечом.
         // Итоговый notional = deposit * fraction * leverage.
         let margin_to_use = available_usdt * open_on_fraction_of_deposit;
         let notional = margin_to_use * leverage;
@@ -561,11 +1678,12 @@ impl BingXClient {
             ));
         }
 
-        let quantity = notional / reference_price;
+        let raw_quantity = notional / reference_price;
+        let (quantity, quantity_str) = self.round_quantity(&bingx_symbol, raw_quantity).await?;
 
         info!(
-            "BingX: preparing to open {} market position on {}. available_usdt={}, margin_to_use={}, leverage={}, notional={}, qty={}, reference_price={}",
-            direction, bingx_symbol, available_usdt, margin_to_use, leverage, notional, quantity, reference_price
+            "BingX: preparing to open {} market position on {}. available_usdt={}, margin_to_use={}, leverage={}, notional={}, raw_qty={}, qty={}, reference_price={}",
+            direction, bingx_symbol, available_usdt, margin_to_use, leverage, notional, raw_quantity, quantity_str, reference_price
         );
 
         if quantity <= 0.0 {
@@ -573,6 +1691,9 @@ impl BingXClient {
                 "computed quantity for order is non-positive".into(),
             ));
         }
+        // Ордер дешевле tradeMinUSDT BingX все равно отклонит
+        let spec = self.contract_spec(&bingx_symbol).await?;
+        Self::check_min_notional(&spec, &bingx_symbol, quantity, reference_price)?;
 
         let side = match direction {
             "LONG" => "BUY",
@@ -585,56 +1706,126 @@ impl BingXClient {
             }
         };
 
-        // Убедимся, что включена кросс маржа и 10x плечо (если API это требует отдельным вызовом)
-        self.ensure_cross_margin_10x(&bingx_symbol, direction).await;
-
-        // Рассчитываем цену take profit заранее
-        let take_profit_price = if direction == "LONG" {
-            reference_price * (1.0 + take_profit_percent / 100.0)
-        } else {
-            reference_price * (1.0 - take_profit_percent / 100.0)
-        };
+        // В hedge-режиме positionSide – LONG или SHORT, в one-way – BOTH
+        let mode = self.position_mode().await?;
 
         let mut params = HashMap::new();
         params.insert("symbol".to_string(), bingx_symbol.clone());
         params.insert("side".to_string(), side.to_string());
-        params.insert("positionSide".to_string(), direction.to_string()); // BingX требует positionSide: LONG или SHORT
+        mode.insert_position_params(&mut params, direction, false);
         params.insert("type".to_string(), "MARKET".to_string());
         // BingX требует quantity (в базовой валюте) или quoteOrderQty (в USDT)
         // Используем quantity для количества контрактов/базовой валюты
-        params.insert("quantity".to_string(), quantity.to_string());
+        params.insert("quantity".to_string(), quantity_str.clone());
         params.insert("marginMode".to_string(), "CROSSED".to_string());
         params.insert("leverage".to_string(), format!("{:.0}", leverage));
-        // Пробуем установить take profit сразу при открытии позиции
-        params.insert("takeProfitPrice".to_string(), format!("{:.8}", take_profit_price));
+        // Пробуем установить take profit сразу при открытии позиции (от ожидаемой цены входа)
+        if let Some(pct) = take_profit_percent {
+            let price = ProtectiveOrder::TakeProfit.trigger_price(direction, reference_price, pct);
+            params.insert("takeProfitPrice".to_string(), format!("{:.8}", price));
+        }
 
-        let _resp: OrderResponse = self
+        // В dry run не трогаем и настройки маржи/плеча – это тоже изменение аккаунта
+        if self.dry_run {
+            return Ok(Self::dry_run_outcome(&bingx_symbol, direction, side, quantity, params));
+        }
+
+        // Убедимся, что включена кросс маржа и нужное плечо (если API это требует отдельным вызовом)
+        self.ensure_cross_margin(&bingx_symbol, mode.position_side(direction)).await;
+
+        let resp: OrderResponse = self
             .post_signed("/openApi/swap/v2/trade/order", params)
             .await?;
+        let order_id = resp
+            .order
+            .as_ref()
+            .and_then(OrderDetails::id)
+            .ok_or_else(|| BingXError::Api("order id missing in BingX order response".into()))?;
+
+        info!("BingX: market order {} placed for {}, waiting for fill", order_id, bingx_symbol);
+        let fill = match self.wait_for_fill(&bingx_symbol, &order_id).await {
+            Ok(fill) => fill,
+            Err(e) => {
+                return Err(self
+                    .secure_unconfirmed_fill(&bingx_symbol, direction, mode, stop_loss_percent, &spec, e)
+                    .await)
+            }
+        };
 
-        // Используем reference_price как цену входа (приблизительную)
-        // В реальности цена входа может немного отличаться из-за проскальзывания
-        let entry_price = reference_price;
+        let quantity = fill.quantity;
+        let quantity_str = format!("{:.*}", spec.quantity_precision as usize, quantity);
+        // Если средняя цена не пришла, используем reference_price как приблизительную цену входа
+        let entry_price = if fill.avg_price > 0.0 { fill.avg_price } else { reference_price };
+
+        // Отдельные TP/SL считаем от фактической цены входа
+        let take_profit_price = take_profit_percent
+            .map(|pct| ProtectiveOrder::TakeProfit.trigger_price(direction, entry_price, pct));
+        let stop_loss_price = stop_loss_percent
+            .map(|pct| ProtectiveOrder::StopLoss.trigger_price(direction, entry_price, pct));
 
         info!(
-            "BingX: successfully opened {} market position on {} with qty={}, leverage={}, entry_price={}, take_profit_price={}",
-            direction, bingx_symbol, quantity, leverage, entry_price, take_profit_price
+            "BingX: successfully opened {} market position on {} with qty={}, leverage={}, entry_price={}, take_profit_price={:?}, stop_loss_price={:?}",
+            direction, bingx_symbol, quantity, leverage, entry_price, take_profit_price, stop_loss_price
         );
 
-        // Если take profit не был установлен при открытии позиции (через takeProfitPrice),
-        // пробуем установить его отдельным ордером
-        // Небольшая задержка, чтобы позиция успела полностью открыться
-        tokio::time::sleep(tokio::time::Duration::from_millis(1000)).await;
-
         // Устанавливаем take profit ордер отдельно (на случай, если takeProfitPrice в основном ордере не сработал)
-        if let Err(e) = self
-            .set_take_profit(&bingx_symbol, direction, quantity, entry_price, take_profit_percent)
-            .await
-        {
-            warn!(
-                "BingX: position opened but take profit setup failed for {}: {}. TP may have been set via takeProfitPrice parameter.",
-                bingx_symbol, e
-            );
+        let mut take_profit_placed = false;
+        if let Some(price) = take_profit_price {
+            match self
+                .place_protective_order(&bingx_symbol, direction, mode, &quantity_str, ProtectiveOrder::TakeProfit, price)
+                .await
+            {
+                Ok(()) => take_profit_placed = true,
+                Err(e) => {
+                    error!(
+                        "BingX: position opened but take profit setup failed for {}: {}. TP may have been set via takeProfitPrice parameter.",
+                        bingx_symbol, e
+                    );
+                }
+            }
+        }
+
+        let mut stop_loss_placed = false;
+        if let Some(price) = stop_loss_price {
+            match self
+                .place_protective_order(&bingx_symbol, direction, mode, &quantity_str, ProtectiveOrder::StopLoss, price)
+                .await
+            {
+                Ok(()) => stop_loss_placed = true,
+                Err(e) => {
+                    // Позиция без стопа – закрываем ее, чтобы убыток не был неограниченным
+                    error!(
+                        "BingX: stop loss setup failed for {} {}: {}. Closing the position.",
+                        direction, bingx_symbol, e
+                    );
+                    if let Err(close_err) = self.close_position(&bingx_symbol, direction).await {
+                        error!(
+                            "BingX: failed to close unprotected {} position on {}: {}. MANUAL ACTION REQUIRED.",
+                            direction, bingx_symbol, close_err
+                        );
+                    }
+                    return Err(BingXError::ProtectionFailed {
+                        symbol: bingx_symbol,
+                        reason: format!("stop loss could not be placed: {}", e),
+                    });
+                }
+            }
+        }
+
+        let mut trailing_stop_placed = false;
+        if let Some(rate) = trailing_rate {
+            match self
+                .place_trailing_stop(&bingx_symbol, direction, mode, &quantity_str, rate, None)
+                .await
+            {
+                Ok(()) => trailing_stop_placed = true,
+                Err(e) => {
+                    error!(
+                        "BingX: position opened but trailing stop setup failed for {} {}: {}",
+                        direction, bingx_symbol, e
+                    );
+                }
+            }
         }
 
         Ok(BingXTradeOutcome::Opened {
@@ -644,22 +1835,365 @@ impl BingXClient {
             leverage,
             entry_price,
             take_profit_price,
+            take_profit_placed,
+            stop_loss_price,
+            stop_loss_placed,
+            trailing_stop_percent: trailing_percent,
+            trailing_stop_placed,
+        })
+    }
+
+    /// Выставляет лимитный ордер на открытие позиции.
+    ///
+    /// - symbol: канонический тикер проекта (`PEPEUSDT`), контракт BingX (`1000PEPE-USDT`) находится сам
+    /// - direction: "LONG" или "SHORT"
+    /// - quantity: объем в монетах, округляется вниз до шага контракта
+    /// - price: лимитная цена за монету, округляется до pricePrecision контракта
+    ///
+    /// В отличие от маркет-ордера, исполнения не ждет: возвращает `LimitPlaced` с id ордера.
+    /// В режиме BINGX_DRY_RUN ордер только рассчитывается и логируется – возвращается `DryRun`.
+    #[allow(dead_code)]
+    pub async fn open_limit_position(
+        &self,
+        symbol: &str,
+        direction: &str,
+        quantity: f64,
+        price: f64,
+        time_in_force: TimeInForce,
+    ) -> Result<BingXTradeOutcome, BingXError> {
+        if price <= 0.0 || !price.is_finite() {
+            return Err(BingXError::Internal("limit price must be positive".into()));
+        }
+
+        let side = match direction {
+            "LONG" => "BUY",
+            "SHORT" => "SELL",
+            other => {
+                return Err(BingXError::Internal(format!(
+                    "unknown direction: {}",
+                    other
+                )))
+            }
+        };
+
+        // Цены и объемы в проекте – за одну монету, а контракт BingX может быть на 1000 монет
+        let (bingx_symbol, multiplier) = self.resolve_contract(symbol).await?;
+        let spec = self.contract_spec(&bingx_symbol).await?;
+        let (contracts, quantity_str) = self.round_quantity(&bingx_symbol, quantity / multiplier).await?;
+        let (contract_price, price_str) = Self::round_price(&spec, price * multiplier);
+        Self::check_min_notional(&spec, &bingx_symbol, contracts, contract_price)?;
+
+        let mode = self.position_mode().await?;
+
+        let mut params = HashMap::new();
+        params.insert("symbol".to_string(), bingx_symbol.clone());
+        params.insert("side".to_string(), side.to_string());
+        mode.insert_position_params(&mut params, direction, false);
+        params.insert("type".to_string(), "LIMIT".to_string());
+        params.insert("quantity".to_string(), quantity_str.clone());
+        params.insert("price".to_string(), price_str.clone());
+        params.insert("timeInForce".to_string(), time_in_force.as_str().to_string());
+        params.insert("marginMode".to_string(), "CROSSED".to_string());
+        params.insert("leverage".to_string(), self.leverage.to_string());
+
+        info!(
+            "BingX: placing {} limit {} order on {}. qty={}, price={}",
+            time_in_force.as_str(), direction, bingx_symbol, quantity_str, price_str
+        );
+
+        if self.dry_run {
+            return Ok(Self::dry_run_outcome(&bingx_symbol, direction, side, contracts * multiplier, params));
+        }
+
+        self.ensure_cross_margin(&bingx_symbol, mode.position_side(direction)).await;
+
+        let resp: OrderResponse = self
+            .post_signed("/openApi/swap/v2/trade/order", params)
+            .await?;
+        let order_id = resp
+            .order
+            .as_ref()
+            .and_then(OrderDetails::id)
+            .ok_or_else(|| BingXError::Api("order id missing in BingX order response".into()))?;
+
+        info!("BingX: limit order {} placed for {}", order_id, bingx_symbol);
+
+        Ok(BingXTradeOutcome::LimitPlaced {
+            symbol: bingx_symbol,
+            direction: direction.to_string(),
+            order_id,
+            quantity: contracts * multiplier,
+            price: contract_price / multiplier,
+            time_in_force,
+        })
+    }
+
+    /// Открывает маркет-позицию заданного объема без TP/SL – для ноги хеджа, где позицию
+    /// защищает противоположная нога на другой бирже.
+    ///
+    /// - symbol: канонический тикер проекта (`PEPEUSDT`), контракт BingX (`1000PEPE-USDT`) находится сам
+    /// - quantity: объем в монетах, округляется вниз до шага контракта
+    /// - reference_price: ожидаемая цена одной монеты, для проверки минимальной стоимости ордера
+    ///
+    /// Ждет исполнения; объем и цена входа в `Opened` – в монетах и за монету.
+    /// В режиме BINGX_DRY_RUN ордер только рассчитывается и логируется – возвращается `DryRun`.
+    pub async fn open_market_quantity(
+        &self,
+        symbol: &str,
+        direction: &str,
+        quantity: f64,
+        reference_price: f64,
+    ) -> Result<BingXTradeOutcome, BingXError> {
+        if reference_price <= 0.0 || !reference_price.is_finite() {
+            return Err(BingXError::Internal(
+                "reference_price must be positive".into(),
+            ));
+        }
+        self.market_order_quantity(symbol, direction, quantity, Some(reference_price))
+            .await
+    }
+
+    /// Закрывает часть позиции `direction` маркет-ордером на `quantity` монет (reduce-only в
+    /// one-way режиме) – например, открытую ногу хеджа, если вторая не открылась.
+    /// Возвращает `Closed` с объемом в монетах или `DryRun`.
+    pub async fn close_market_quantity(
+        &self,
+        symbol: &str,
+        direction: &str,
+        quantity: f64,
+    ) -> Result<BingXTradeOutcome, BingXError> {
+        self.market_order_quantity(symbol, direction, quantity, None).await
+    }
+
+    /// Маркет-ордер на `quantity` монет по каноническому тикеру. `reference_price` = Some – открытие
+    /// позиции `direction`, None – закрытие.
+    async fn market_order_quantity(
+        &self,
+        symbol: &str,
+        direction: &str,
+        quantity: f64,
+        reference_price: Option<f64>,
+    ) -> Result<BingXTradeOutcome, BingXError> {
+        let closing = reference_price.is_none();
+        let side = match (direction, closing) {
+            ("LONG", false) | ("SHORT", true) => "BUY",
+            ("SHORT", false) | ("LONG", true) => "SELL",
+            (other, _) => {
+                return Err(BingXError::Internal(format!(
+                    "unknown direction: {}",
+                    other
+                )))
+            }
+        };
+
+        // Цены и объемы в проекте – за одну монету, а контракт BingX может быть на 1000 монет
+        let (bingx_symbol, multiplier) = self.resolve_contract(symbol).await?;
+        let spec = self.contract_spec(&bingx_symbol).await?;
+        let (contracts, contracts_str) = self.round_quantity(&bingx_symbol, quantity / multiplier).await?;
+        if let Some(price) = reference_price {
+            Self::check_min_notional(&spec, &bingx_symbol, contracts, price * multiplier)?;
+        }
+        let mode = self.position_mode().await?;
+
+        let mut params = HashMap::new();
+        params.insert("symbol".to_string(), bingx_symbol.clone());
+        params.insert("side".to_string(), side.to_string());
+        mode.insert_position_params(&mut params, direction, closing);
+        params.insert("type".to_string(), "MARKET".to_string());
+        params.insert("quantity".to_string(), contracts_str.clone());
+        if !closing {
+            params.insert("marginMode".to_string(), "CROSSED".to_string());
+            params.insert("leverage".to_string(), self.leverage.to_string());
+        }
+
+        info!(
+            "BingX: {} {} position on {} with market {} order, qty={}",
+            if closing { "closing" } else { "opening" },
+            direction, bingx_symbol, side, contracts_str
+        );
+
+        if self.dry_run {
+            return Ok(Self::dry_run_outcome(&bingx_symbol, direction, side, contracts * multiplier, params));
+        }
+
+        if !closing {
+            self.ensure_cross_margin(&bingx_symbol, mode.position_side(direction)).await;
+        }
+
+        let resp: OrderResponse = self
+            .post_signed("/openApi/swap/v2/trade/order", params)
+            .await?;
+        let order_id = resp
+            .order
+            .as_ref()
+            .and_then(OrderDetails::id)
+            .ok_or_else(|| BingXError::Api("order id missing in BingX order response".into()))?;
+        let fill = self.wait_for_fill(&bingx_symbol, &order_id).await?;
+        let quantity = fill.quantity * multiplier;
+
+        if closing {
+            info!("BingX: closed {} {} of {} position on {}", quantity, symbol, direction, bingx_symbol);
+            return Ok(BingXTradeOutcome::Closed {
+                symbol: bingx_symbol,
+                direction: direction.to_string(),
+                quantity,
+            });
+        }
+
+        let entry_price = if fill.avg_price > 0.0 {
+            fill.avg_price / multiplier
+        } else {
+            reference_price.unwrap_or(0.0)
+        };
+        info!(
+            "BingX: opened {} position on {} with qty={} {}, entry_price={}",
+            direction, bingx_symbol, quantity, symbol, entry_price
+        );
+        Ok(BingXTradeOutcome::Opened {
+            symbol: bingx_symbol,
+            direction: direction.to_string(),
+            quantity,
+            leverage: f64::from(self.leverage),
+            entry_price,
+            take_profit_price: None,
+            take_profit_placed: false,
+            stop_loss_price: None,
+            stop_loss_placed: false,
+            trailing_stop_percent: None,
+            trailing_stop_placed: false,
+        })
+    }
+
+    /// Закрывает позицию по символу маркет-ордером на весь объем.
+    ///
+    /// - position_side: "LONG" / "SHORT" или "BOTH" – любая позиция в one-way режиме.
+    ///
+    /// В hedge-режиме позиция закрывается противоположной стороной с тем же positionSide
+    /// (reduceOnly BingX в этом режиме не принимает), в one-way режиме – ордером с positionSide=BOTH
+    /// и reduceOnly=true; LONG/SHORT там означают знак объема позиции.
+    /// В режиме BINGX_DRY_RUN ордер не отправляется – возвращается `DryRun`.
+    pub async fn close_position(
+        &self,
+        symbol: &str,
+        position_side: &str,
+    ) -> Result<BingXTradeOutcome, BingXError> {
+        let bingx_symbol = from_canonical(BINGX, symbol);
+        let requested = position_side.to_uppercase();
+        if !matches!(requested.as_str(), "LONG" | "SHORT" | "BOTH") {
+            return Err(BingXError::Internal(format!(
+                "unknown position side: {}",
+                requested
+            )));
+        }
+
+        let mode = self.position_mode().await?;
+        // В one-way режиме у символа одна позиция BOTH, LONG/SHORT – знак ее объема
+        let position_side = match mode {
+            PositionMode::Hedge => requested.clone(),
+            PositionMode::OneWay => "BOTH".to_string(),
+        };
+        let sign_matches = |amt: &f64| match (mode, requested.as_str()) {
+            (PositionMode::OneWay, "LONG") => *amt > 0.0,
+            (PositionMode::OneWay, "SHORT") => *amt < 0.0,
+            _ => amt.abs() > 0.0,
+        };
+
+        let positions = self.fetch_positions().await?;
+        let position_amt = positions
+            .iter()
+            .filter(|p| p.symbol == bingx_symbol)
+            .filter(|p| {
+                p.position_side
+                    .as_deref()
+                    .map(|side| side.eq_ignore_ascii_case(&position_side))
+                    .unwrap_or(position_side == "BOTH")
+            })
+            .filter_map(|p| p.position_amt.as_ref().and_then(|s| s.parse::<f64>().ok()))
+            .find(sign_matches);
+
+        let position_amt = match position_amt {
+            Some(amt) => amt,
+            None => {
+                info!(
+                    "BingX: no open {} position for {}, nothing to close.",
+                    requested, bingx_symbol
+                );
+                return Ok(BingXTradeOutcome::Skipped {
+                    reason: format!("no open {} position for {}", requested, bingx_symbol),
+                });
+            }
+        };
+        let quantity = position_amt.abs();
+
+        let side = match position_side.as_str() {
+            "LONG" => "SELL",
+            "SHORT" => "BUY",
+            "BOTH" if position_amt > 0.0 => "SELL",
+            "BOTH" => "BUY",
+            other => {
+                return Err(BingXError::Internal(format!(
+                    "unknown position side: {}",
+                    other
+                )))
+            }
+        };
+
+        let mut params = HashMap::new();
+        params.insert("symbol".to_string(), bingx_symbol.clone());
+        params.insert("side".to_string(), side.to_string());
+        mode.insert_position_params(&mut params, &position_side, true);
+        params.insert("type".to_string(), "MARKET".to_string());
+        params.insert("quantity".to_string(), quantity.to_string());
+
+        info!(
+            "BingX: closing {} position on {}. side={}, qty={}",
+            position_side, bingx_symbol, side, quantity
+        );
+
+        if self.dry_run {
+            return Ok(Self::dry_run_outcome(&bingx_symbol, &position_side, side, quantity, params));
+        }
+
+        let _resp: OrderResponse = self
+            .post_signed("/openApi/swap/v2/trade/order", params)
+            .await?;
+
+        info!(
+            "BingX: successfully closed {} position on {} with qty={}",
+            position_side, bingx_symbol, quantity
+        );
+
+        Ok(BingXTradeOutcome::Closed {
+            symbol: bingx_symbol,
+            direction: position_side,
+            quantity,
         })
     }
 
     /// Основной обработчик арбитражной возможности.
     ///
     /// Логика:
+    /// 0. Если сработал дневной лимит убытка (DAILY_LOSS_LIMIT_USDT) – НИЧЕГО не делать.
     /// 1. Проверить, есть ли уже открытая позиция по символу – если да, НИЧЕГО не делать.
-    /// 2. Определить направление (LONG/SHORT) по разнице цен.
-    /// 3. Открыть маркет-позицию в кросс марже с 10x плечом на 75% от депозита.
+    /// 2. Определить направление (LONG/SHORT) по бирже с наибольшим расхождением с референсной
+    ///    биржей (REFERENCE_VENUE, по умолчанию Bybit), цена которой передается в `reference_price`.
+    /// 3. Проверить, что свободный баланс не меньше BINGX_MIN_BALANCE_USDT – иначе пропустить.
+    /// 4. Открыть маркет-позицию в кросс марже с настроенным плечом на заданную долю депозита.
     pub async fn handle_arbitrage_opportunity(
         &self,
         symbol: &str,
-        bybit_price: f64,
-        hyperliquid_price: f64,
-        aster_price: f64,
+        reference_price: f64,
+        hyperliquid_price: Option<f64>,
+        aster_price: Option<f64>,
     ) -> Result<BingXTradeOutcome, BingXError> {
+        // 0. Дневной лимит убытка исчерпан – до полуночи UTC ничего не открываем
+        if self.is_trading_halted() {
+            info!("BingX: daily loss limit reached. Skipping new order for {}.", symbol);
+            return Ok(BingXTradeOutcome::Skipped {
+                reason: "daily loss limit reached".to_string(),
+            });
+        }
+
         // 1. КРИТИЧНО: проверка общего числа открытых позиций.
         // Если есть хотя бы одна открытая позиция — НИЧЕГО не открываем.
         match self.count_open_positions().await {
@@ -687,17 +2221,11 @@ impl BingXClient {
             }
         }
 
-        // 2. Определяем направление по разнице цен
-        // SHORT если Price_Hyperliquid > Price_Bybit ИЛИ Price_ASTER > Price_Bybit
-        // LONG если Price_Bybit > Price_Hyperliquid ИЛИ Price_Bybit > Price_ASTER
-        let direction = if hyperliquid_price > bybit_price || aster_price > bybit_price {
-            "SHORT"
-        } else if bybit_price > hyperliquid_price || bybit_price > aster_price {
-            "LONG"
-        } else {
+        // 2. Определяем направление по бирже с наибольшим расхождением с референсной
+        let Some((direction, venue)) = trade_direction(reference_price, hyperliquid_price, aster_price) else {
             warn!(
-                "BingX: bybit_price == hyperliquid_price == aster_price for {} – no trade direction.",
-                symbol
+                "BingX: no price deviates from the reference for {} (reference={}, hyperliquid={:?}, aster={:?}) – no trade direction.",
+                symbol, reference_price, hyperliquid_price, aster_price
             );
             return Ok(BingXTradeOutcome::Skipped {
                 reason: "prices equal".to_string(),
@@ -705,17 +2233,44 @@ impl BingXClient {
         };
 
         info!(
-            "BingX: arbitrage detected for {}. bybit_price={}, hyperliquid_price={}, direction={}",
-            symbol, bybit_price, hyperliquid_price, direction
+            "BingX: arbitrage detected for {}. reference_price={}, hyperliquid_price={:?}, aster_price={:?}, leading venue={}, direction={}",
+            symbol, reference_price, hyperliquid_price, aster_price, venue, direction
         );
 
-        // 3. Открываем позицию – 75% от депозита, 10x, маркет.
-        // В качестве референсной цены берем цену Bybit (как более ликвидную/центральную).
-        // Устанавливаем take profit на +3% от точки входа (без учета плеча).
-        let reference_price = bybit_price;
-        let take_profit_percent = 3.0; // 3% прибыли
+        // 3. На почти пустом счете позиция получится пылевой и будет отклонена по минимуму
+        let available_usdt = self.get_available_usdt().await?;
+        if available_usdt < self.min_balance_usdt {
+            info!(
+                "BingX: available balance {} USDT is below the minimum {} USDT. Skipping new order for {}.",
+                available_usdt, self.min_balance_usdt, symbol
+            );
+            return Ok(BingXTradeOutcome::Skipped {
+                reason: format!(
+                    "available balance {:.2} USDT is below BINGX_MIN_BALANCE_USDT {:.2} USDT",
+                    available_usdt, self.min_balance_usdt
+                ),
+            });
+        }
+
+        // Цены в проекте – за одну монету, а BingX может котировать контракт на 1000 монет
+        let (bingx_symbol, multiplier) = self.resolve_contract(symbol).await?;
+
+        // 4. Открываем позицию – доля депозита и плечо из настроек (по умолчанию 75%, 10x), маркет.
+        // В качестве референсной цены берем цену референсной биржи (по умолчанию Bybit как более
+        // ликвидную/центральную). Устанавливаем take profit на +3% от точки входа (без учета плеча).
+        let reference_price = reference_price * multiplier;
+        let take_profit_percent = Some(self.take_profit_percent); // 3% прибыли по умолчанию
         let outcome = match self
-            .open_market_position(symbol, direction, 0.75, 10.0, reference_price, take_profit_percent)
+            .open_market_position(
+                &bingx_symbol,
+                direction,
+                self.deposit_fraction,
+                f64::from(self.leverage),
+                reference_price,
+                take_profit_percent,
+                self.stop_loss_percent,
+                self.trailing_percent,
+            )
             .await
         {
             Ok(o) => o,
@@ -801,3 +2356,241 @@ impl BingXClient {
     }
 }
 
+/// Выбирает направление сделки по бирже с наибольшим абсолютным расхождением с референсной ценой.
+///
+/// Если цена на этой бирже выше референсной – SHORT, ниже – LONG; вторая биржа не учитывается.
+/// Биржи без цены пропускаются. Возвращает `None`, если расхождения нет: цены, равные
+/// с точностью до `prices_equal`, расхождением не считаются.
+fn trade_direction(
+    reference_price: f64,
+    hyperliquid_price: Option<f64>,
+    aster_price: Option<f64>,
+) -> Option<(&'static str, &'static str)> {
+    if reference_price <= 0.0 {
+        return None;
+    }
+
+    let (venue, price) = [("Hyperliquid", hyperliquid_price), ("ASTER", aster_price)]
+        .into_iter()
+        .filter_map(|(venue, price)| Some((venue, price.filter(|p| *p > 0.0)?)))
+        .max_by(|a, b| (a.1 - reference_price).abs().total_cmp(&(b.1 - reference_price).abs()))?;
+
+    if prices_equal(price, reference_price) {
+        None
+    } else if price > reference_price {
+        Some(("SHORT", venue))
+    } else {
+        Some(("LONG", venue))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        trade_direction, BingXClient, BingXError, ContractSpec, Position, PositionMode, PositionModeData,
+    };
+    use std::collections::HashMap;
+
+    #[test]
+    fn hyperliquid_high_aster_low_follows_larger_spread() {
+        // HL выше на 5%, ASTER ниже на 2% – решает Hyperliquid
+        assert_eq!(trade_direction(100.0, Some(105.0), Some(98.0)), Some(("SHORT", "Hyperliquid")));
+        // HL выше на 1%, ASTER ниже на 4% – решает ASTER
+        assert_eq!(trade_direction(100.0, Some(101.0), Some(96.0)), Some(("LONG", "ASTER")));
+    }
+
+    #[test]
+    fn both_high_is_short_on_largest_spread() {
+        assert_eq!(trade_direction(100.0, Some(103.0), Some(107.0)), Some(("SHORT", "ASTER")));
+        assert_eq!(trade_direction(100.0, Some(108.0), Some(102.0)), Some(("SHORT", "Hyperliquid")));
+    }
+
+    #[test]
+    fn both_low_is_long() {
+        assert_eq!(trade_direction(100.0, Some(95.0), Some(97.0)), Some(("LONG", "Hyperliquid")));
+    }
+
+    #[test]
+    fn equal_prices_give_no_direction() {
+        assert_eq!(trade_direction(100.0, Some(100.0), Some(100.0)), None);
+        // Разница на уровне погрешности f64 – не расхождение
+        assert_eq!(trade_direction(0.1 + 0.2, Some(0.3), None), None);
+    }
+
+    #[test]
+    fn missing_prices_are_ignored() {
+        assert_eq!(trade_direction(100.0, None, Some(104.0)), Some(("SHORT", "ASTER")));
+        assert_eq!(trade_direction(100.0, None, None), None);
+        assert_eq!(trade_direction(0.0, Some(105.0), Some(95.0)), None);
+    }
+
+    #[test]
+    fn sub_penny_prices_keep_direction() {
+        assert_eq!(
+            trade_direction(0.000001, Some(0.00000105), Some(0.00000099)),
+            Some(("SHORT", "Hyperliquid"))
+        );
+        assert_eq!(trade_direction(0.00001234, None, Some(0.00001233)), Some(("LONG", "ASTER")));
+    }
+
+    #[test]
+    fn one_way_position_side_follows_amount_sign() {
+        let position: Position = serde_json::from_value(serde_json::json!({
+            "symbol": "BTC-USDT",
+            "positionSide": "BOTH",
+            "positionAmt": "-0.5",
+            "avgPrice": "60000.5",
+            "unrealizedProfit": "-12.3"
+        }))
+        .unwrap();
+        let open = position.to_open_position().unwrap();
+        assert_eq!(open.side, "SHORT");
+        assert_eq!(open.size, 0.5);
+        assert_eq!(open.entry_price, 60000.5);
+        assert_eq!(open.unrealized_pnl, -12.3);
+
+        let flat: Position = serde_json::from_value(serde_json::json!({
+            "symbol": "BTC-USDT",
+            "positionSide": "LONG",
+            "positionAmt": "0"
+        }))
+        .unwrap();
+        assert!(flat.to_open_position().is_none());
+    }
+
+    #[test]
+    fn order_params_follow_position_mode() {
+        let mode = |value: serde_json::Value| {
+            serde_json::from_value::<PositionModeData>(value).unwrap().mode()
+        };
+        assert_eq!(mode(serde_json::json!({"dualSidePosition": "true"})), Some(PositionMode::Hedge));
+        assert_eq!(mode(serde_json::json!({"dualSidePosition": "false"})), Some(PositionMode::OneWay));
+        assert_eq!(mode(serde_json::json!({"dualSidePosition": false})), Some(PositionMode::OneWay));
+        assert_eq!(mode(serde_json::json!({})), None);
+
+        let mut hedge = HashMap::new();
+        PositionMode::Hedge.insert_position_params(&mut hedge, "SHORT", true);
+        assert_eq!(hedge.get("positionSide").map(String::as_str), Some("SHORT"));
+        assert!(!hedge.contains_key("reduceOnly"));
+
+        let mut open = HashMap::new();
+        PositionMode::OneWay.insert_position_params(&mut open, "LONG", false);
+        assert_eq!(open.get("positionSide").map(String::as_str), Some("BOTH"));
+        assert!(!open.contains_key("reduceOnly"));
+
+        let mut close = HashMap::new();
+        PositionMode::OneWay.insert_position_params(&mut close, "LONG", true);
+        assert_eq!(close.get("positionSide").map(String::as_str), Some("BOTH"));
+        assert_eq!(close.get("reduceOnly").map(String::as_str), Some("true"));
+    }
+
+    #[test]
+    fn deposit_fraction_must_be_within_zero_to_one() {
+        assert_eq!(BingXClient::validate_deposit_fraction(1.0).unwrap(), 1.0);
+        for fraction in [0.0, 1.5, -0.1, f64::NAN] {
+            assert!(
+                matches!(BingXClient::validate_deposit_fraction(fraction), Err(BingXError::Internal(_))),
+                "fraction {} must be rejected",
+                fraction
+            );
+        }
+    }
+
+    #[test]
+    fn order_below_min_notional_is_rejected() {
+        let spec = ContractSpec {
+            quantity_precision: 3,
+            min_qty: 0.001,
+            step_size: 0.001,
+            min_notional: 5.0,
+            price_precision: 2,
+        };
+        // 0.002 * 2000 = 4 USDT < 5 USDT
+        assert!(matches!(
+            BingXClient::check_min_notional(&spec, "ETH-USDT", 0.002, 2000.0),
+            Err(BingXError::NotionalBelowMinimum { .. })
+        ));
+        assert!(BingXClient::check_min_notional(&spec, "ETH-USDT", 0.003, 2000.0).is_ok());
+    }
+
+    #[tokio::test]
+    async fn min_notional_skip_uses_last_known_balance() {
+        use crate::config::BingXConfig;
+
+        let config = BingXConfig {
+            api_key: Some("key".to_string()),
+            api_secret: Some("secret".to_string()),
+            deposit_fraction: Some(0.5),
+            leverage: Some(2),
+            ..Default::default()
+        };
+        let client = BingXClient::from_config(&config, reqwest::Client::new()).unwrap();
+        let spec = ContractSpec {
+            quantity_precision: 0,
+            min_qty: 1.0,
+            step_size: 1.0,
+            min_notional: 50.0,
+            price_precision: 6,
+        };
+        client.contract_specs.set(HashMap::from([("PEPE-USDT".to_string(), spec)])).unwrap();
+
+        // Баланс еще не запрашивался – проверку делает сам ордер
+        assert_eq!(client.min_notional_skip("PEPEUSDT").await, None);
+
+        // 40 * 0.5 * 2 = 40 USDT < 50 USDT
+        *client.last_available_usdt.lock().unwrap() = Some(40.0);
+        let reason = client.min_notional_skip("PEPEUSDT").await.unwrap();
+        assert!(reason.starts_with("below min notional"), "{}", reason);
+
+        *client.last_available_usdt.lock().unwrap() = Some(60.0);
+        assert_eq!(client.min_notional_skip("PEPEUSDT").await, None);
+    }
+
+    #[tokio::test]
+    async fn open_position_count_is_cached_until_invalidated() {
+        use crate::config::BingXConfig;
+        use std::time::{Duration, Instant};
+
+        let config = BingXConfig {
+            api_key: Some("key".to_string()),
+            api_secret: Some("secret".to_string()),
+            // Недоступный адрес: любой запрос к API завершится ошибкой
+            base_url: Some("http://127.0.0.1:9".to_string()),
+            ..Default::default()
+        };
+        let client = BingXClient::from_config(&config, reqwest::Client::new()).unwrap();
+        *client.open_positions_cache.lock().unwrap() = Some((Instant::now(), 1));
+        assert_eq!(client.count_open_positions().await.unwrap(), 1);
+
+        client.invalidate_open_positions();
+        assert!(client.count_open_positions().await.is_err());
+
+        // Устаревший результат не используется
+        *client.open_positions_cache.lock().unwrap() = Some((Instant::now() - Duration::from_secs(3), 0));
+        assert!(client.count_open_positions().await.is_err());
+    }
+
+    #[test]
+    fn trailing_callback_rate_is_validated() {
+        assert_eq!(BingXClient::trailing_price_rate(1.5).unwrap(), 0.015);
+        assert_eq!(BingXClient::trailing_price_rate(100.0).unwrap(), 1.0);
+        for rate in [0.0, -1.0, 100.5, f64::NAN] {
+            assert!(BingXClient::trailing_price_rate(rate).is_err(), "{}", rate);
+        }
+    }
+
+    #[test]
+    fn limit_price_is_rounded_to_price_precision() {
+        let spec = ContractSpec {
+            quantity_precision: 0,
+            min_qty: 1.0,
+            step_size: 1.0,
+            min_notional: 0.0,
+            price_precision: 4,
+        };
+        assert_eq!(
+            BingXClient::round_price(&spec, 0.123456),
+            (0.1235, "0.1235".to_string())
+        );
+    }
+}
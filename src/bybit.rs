@@ -1,32 +1,161 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::Duration;
 
 use futures_util::{SinkExt, StreamExt};
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
-use log::{error, info, warn};
+use tokio_tungstenite::{tungstenite, tungstenite::protocol::Message};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
 
 use crate::{
-    compare_price::compare_prices,
-    share_state::SharedState,
-    utils::{BybitApiResponse, BybitWsResponse},
+    config::BybitConfig,
+    funding::{per_funding_period, poll_funding_rates},
+    share_state::{BookSideUpdate, FeedStatus, InstrumentSpec, SharedState, BYBIT},
+    utils::{canonical_symbol, connect_ws, sleep_or_shutdown, wait_for_shutdown, Backoff, CanonicalSymbol, BybitApiResponse, BybitList, BybitWsResponse, Shutdown, WsStream},
 };
 
+const DEFAULT_SUBSCRIBE_BATCH_SIZE: usize = 10;
+const REST_BASE_URL: &str = "https://api.bybit.com";
+const WS_BASE_URL: &str = "wss://stream.bybit.com/v5/public";
+/// Интервал фандинга, если Bybit не вернул fundingIntervalHour.
+const DEFAULT_FUNDING_INTERVAL_HOURS: f64 = 8.0;
+
+#[derive(Debug, Deserialize)]
+struct TickersResponse {
+    result: TickersResult,
+}
+
+#[derive(Debug, Deserialize)]
+struct TickersResult {
+    list: Vec<TickerFunding>,
+}
+
+/// Фандинг из `/v5/market/tickers`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerFunding {
+    symbol: String,
+    #[serde(default)]
+    funding_rate: String,
+    #[serde(default)]
+    funding_interval_hour: Option<String>,
+}
+
+/// Какой поток Bybit используется как источник цены.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BybitStream {
+    /// `tickers.{symbol}` – последняя цена сделки в реальном времени.
+    Tickers,
+    /// `kline.D.{symbol}` – close дневной свечи (старое поведение).
+    Kline,
+}
+
+impl BybitStream {
+    fn topic_prefix(self) -> &'static str {
+        match self {
+            BybitStream::Tickers => "tickers.",
+            BybitStream::Kline => "kline.D.",
+        }
+    }
+}
+/// Категория рынков Bybit: от нее зависят REST-запросы, WebSocket-эндпоинт и отбор тикеров.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BybitCategory {
+    /// USDT/USDC perpetual-контракты (по умолчанию).
+    Linear,
+    /// Спот – референсная нога для арбитража спот против перпетуалов. Фандинга нет.
+    Spot,
+    /// Инверсные контракты с расчетами в монете.
+    Inverse,
+}
+
+impl BybitCategory {
+    fn as_str(self) -> &'static str {
+        match self {
+            BybitCategory::Linear => "linear",
+            BybitCategory::Spot => "spot",
+            BybitCategory::Inverse => "inverse",
+        }
+    }
+
+    /// Подходит ли инструмент как источник цены: у linear отсекаются срочные контракты
+    /// (`BTCUSDT-26DEC25`), у inverse берутся только бессрочные (срочные `BTCUSDH25` без дефиса),
+    /// у спота – все торгуемые пары.
+    fn lists(self, instrument: &BybitList) -> bool {
+        match self {
+            BybitCategory::Linear => !instrument.symbol.contains('-'),
+            BybitCategory::Spot => instrument.is_trading(),
+            BybitCategory::Inverse => instrument.contract_type() == "InversePerpetual",
+        }
+    }
+}
+
+/// Bybit закрывает соединение без ping от клиента примерно через 20 секунд.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+const SUBSCRIBE_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+/// Сколько раз отправлять пачку подписки, прежде чем переподключиться.
+const SUBSCRIBE_ATTEMPTS: u32 = 3;
+const SUBSCRIBE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
 pub struct Bybit {
+    category: BybitCategory,
     instrument_api_url: String,
+    tickers_api_url: String,
     ws_url: String,
+    /// Сколько топиков отправлять в одном subscribe-сообщении.
+    subscribe_batch_size: usize,
+    stream: BybitStream,
+    client: reqwest::Client,
 }
 
 impl Bybit {
-    pub fn new() -> Self {
+    pub fn new(config: &BybitConfig, client: reqwest::Client) -> Self {
+        let subscribe_batch_size = match config.subscribe_batch_size {
+            Some(size) if size > 0 => size,
+            Some(size) => {
+                warn!(
+                    "Invalid BYBIT_SUBSCRIBE_BATCH_SIZE '{}', using default {}",
+                    size, DEFAULT_SUBSCRIBE_BATCH_SIZE
+                );
+                DEFAULT_SUBSCRIBE_BATCH_SIZE
+            }
+            None => DEFAULT_SUBSCRIBE_BATCH_SIZE,
+        };
+
+        // BYBIT_STREAM=kline возвращает подписку на дневные свечи
+        let stream = match config.stream.as_deref() {
+            Some(value) if value.eq_ignore_ascii_case("kline") => BybitStream::Kline,
+            Some(value) if !value.eq_ignore_ascii_case("tickers") => {
+                warn!("Unknown BYBIT_STREAM '{}', using tickers", value);
+                BybitStream::Tickers
+            }
+            _ => BybitStream::Tickers,
+        };
+
+        // BYBIT_CATEGORY=spot берет цену со спота, inverse – с инверсных контрактов
+        let category = match config.category.as_deref() {
+            Some(value) if value.eq_ignore_ascii_case("spot") => BybitCategory::Spot,
+            Some(value) if value.eq_ignore_ascii_case("inverse") => BybitCategory::Inverse,
+            Some(value) if !value.eq_ignore_ascii_case("linear") => {
+                warn!("Unknown BYBIT_CATEGORY '{}', using linear", value);
+                BybitCategory::Linear
+            }
+            _ => BybitCategory::Linear,
+        };
+
         Self {
-            instrument_api_url: "https://api.bybit.com/v5/market/instruments-info?category=linear"
-                .into(),
-            ws_url: "wss://stream.bybit.com/v5/public/linear".into(),
+            category,
+            instrument_api_url: format!("{}/v5/market/instruments-info?category={}", REST_BASE_URL, category.as_str()),
+            tickers_api_url: format!("{}/v5/market/tickers?category={}", REST_BASE_URL, category.as_str()),
+            ws_url: format!("{}/{}", WS_BASE_URL, category.as_str()),
+            subscribe_batch_size,
+            stream,
+            client,
         }
     }
 
-    pub async fn get_tickers(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-        let response = reqwest::get(&self.instrument_api_url).await
+    async fn get_instruments(&self) -> Result<BybitApiResponse, Box<dyn std::error::Error>> {
+        let response = self.client.get(&self.instrument_api_url).send().await
             .map_err(|e| {
                 error!("Failed to fetch Bybit tickers: {}", e);
                 e
@@ -36,110 +165,644 @@ impl Bybit {
                 error!("Failed to parse Bybit API response: {}", e);
                 e
             })?;
+        Ok(response_data)
+    }
+
+    pub async fn get_tickers(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let response_data = self.get_instruments().await?;
 
         let tickers: Vec<String> = response_data
             .result
             .list
             .iter()
-            .filter_map(|ticker| {
-                if !ticker.symbol.contains("-") {
-                    Some(ticker.symbol.clone())
-                } else {
-                    None
-                }
-            })
+            .filter(|ticker| self.category.lists(ticker))
+            .map(|ticker| ticker.symbol.clone())
             .collect();
 
         Ok(tickers)
     }
-    pub async fn bybit_ws(&self, common_tickers: &[String], common_tickers_set: &HashSet<String>, shared_state: &Arc<SharedState>) {
-        let (mut ws_stream, _) = match connect_async(&self.ws_url).await {
-            Ok(stream) => {
-                info!("Bybit WebSocket connected successfully");
-                stream
-            }
-            Err(e) => {
-                error!("Failed to connect to Bybit WebSocket: {}", e);
-                return;
-            }
-        };
 
-        let args: Vec<String> = common_tickers
+    /// Шаг цены, шаг и минимум количества, минимальная стоимость ордера по каноническим тикерам
+    /// из `priceFilter`/`lotSizeFilter`, пересчитанные за одну монету.
+    pub async fn get_instrument_info(&self) -> Result<HashMap<String, InstrumentSpec>, Box<dyn std::error::Error>> {
+        let response_data = self.get_instruments().await?;
+
+        let specs = response_data
+            .result
+            .list
             .iter()
-            .map(|ticker| format!("kline.D.{}", ticker))
+            .filter(|instrument| self.category.lists(instrument))
+            .filter_map(Self::instrument_spec)
             .collect();
+        Ok(specs)
+    }
 
-        let subscribe_message = serde_json::json!({
-            "op": "subscribe",
-            "args": args
-        })
-        .to_string();
+    /// Параметры контракта за одну монету. None, если шаг цены или количества не указан.
+    fn instrument_spec(instrument: &BybitList) -> Option<(String, InstrumentSpec)> {
+        let canonical = canonical_symbol(&instrument.symbol);
+        let spec = InstrumentSpec {
+            tick_size: canonical.unit_price(instrument.tick_size()?),
+            qty_step: canonical.unit_size(instrument.qty_step()?),
+            min_qty: canonical.unit_size(instrument.min_qty().unwrap_or(0.0)),
+            min_notional: instrument.min_notional(),
+        };
+        Some((canonical.symbol, spec))
+    }
 
-        if let Err(e) = ws_stream.send(Message::Text(subscribe_message)).await {
-            error!("Failed to subscribe to Bybit topics: {}", e);
+    /// Периодически запрашивает ставки фандинга всех контрактов категории и сохраняет их в `SharedState`.
+    /// У спота фандинга нет – тогда Bybit в сравнении фандинга не участвует.
+    pub async fn bybit_funding_poller(&self, shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        if self.category == BybitCategory::Spot {
+            info!("Bybit spot has no funding rates, Bybit funding comparison disabled");
             return;
         }
-        info!("Subscribed to Bybit topics");
-
-        while let Some(message) = ws_stream.next().await {
-            match message {
-                Ok(Message::Text(text)) => match serde_json::from_str::<BybitWsResponse>(&text) {
-                    Ok(parse_msg) => {
-                        // Пропускаем сообщения без topic (подтверждения подписки, heartbeat и т.д.)
-                        if let Some(topic) = &parse_msg.topic {
-                            if let Some(data) = &parse_msg.data {
-                                if !data.is_empty() {
-                                    // Безопасное извлечение символа из topic
-                                    let symbol = match topic.split(".").last() {
-                                        Some(s) => s.to_string(),
-                                        None => {
-                                            warn!("Invalid topic format: {}", topic);
-                                            continue;
-                                        }
-                                    };
-                                    
-                                    if common_tickers_set.contains(&symbol) {
-                                        // Безопасный парсинг цены
-                                        let price: f64 = match data[0].close.parse::<f64>() {
-                                            Ok(p) => {
-                                                // Валидация цены
-                                                if p <= 0.0 || !p.is_finite() {
-                                                    warn!("Invalid price for {}: {}", symbol, p);
-                                                    continue;
-                                                }
-                                                p
-                                            }
-                                            Err(e) => {
-                                                warn!("Failed to parse price for {}: {} (value: {})", symbol, e, data[0].close);
-                                                continue;
-                                            }
-                                        };
-                                        
-                                        {
-                                            let mut bybit_prices = shared_state.bybit_prices.write().await;
-                                            bybit_prices.insert(symbol.clone(), price);
-                                        }
-                                        
-                                        if let Err(e) = compare_prices(shared_state, &symbol).await {
-                                            error!("Failed comparing price in bybit for {}: {}", symbol, e);
-                                        }
-                                    }
+        poll_funding_rates(BYBIT, shared_state, || self.fetch_funding_rates(), shutdown).await;
+    }
+
+    async fn fetch_funding_rates(&self) -> Result<HashMap<String, f64>, reqwest::Error> {
+        let response: TickersResponse = self
+            .client
+            .get(&self.tickers_api_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let funding = response
+            .result
+            .list
+            .into_iter()
+            .filter_map(|ticker| {
+                let rate = ticker.funding_rate.parse::<f64>().ok()?;
+                let hours = ticker
+                    .funding_interval_hour
+                    .and_then(|h| h.parse::<f64>().ok())
+                    .unwrap_or(DEFAULT_FUNDING_INTERVAL_HOURS);
+                Some((canonical_symbol(&ticker.symbol).symbol, per_funding_period(rate, hours)?))
+            })
+            .collect();
+        Ok(funding)
+    }
+
+    /// Подписывается пачками по `subscribe_batch_size` топиков: сначала отправляет все пачки, затем
+    /// собирает подтверждения по `req_id`.
+    ///
+    /// Отклоненные и неподтвержденные за `SUBSCRIBE_ACK_TIMEOUT` пачки отправляет повторно, до
+    /// `SUBSCRIBE_ATTEMPTS` раз; если Bybit так и не подтвердил подписку, возвращает ошибку –
+    /// вызывающий переподключается, а не читает соединение, по которому данные не придут.
+    /// Пока идет подписка, данные уже подписанных топиков обрабатываются, ping отправляется, а
+    /// остановка проверяется: Ok(true) – пришел сигнал остановки.
+    ///
+    /// С `with_orderbook` дополнительно подписывается на лучшие bid/ask (`orderbook.1.*`).
+    async fn subscribe_in_batches(
+        &self,
+        ws_stream: &mut WsStream,
+        common_tickers: &[String],
+        common_tickers_set: &HashSet<String>,
+        with_orderbook: bool,
+        shared_state: &SharedState,
+        shutdown: &mut Shutdown,
+    ) -> Result<bool, tungstenite::Error> {
+        let mut args: Vec<String> = common_tickers
+            .iter()
+            .map(|ticker| format!("{}{}", self.stream.topic_prefix(), ticker))
+            .collect();
+        if with_orderbook {
+            args.extend(common_tickers.iter().map(|ticker| format!("orderbook.1.{}", ticker)));
+        }
+        let batches: Vec<&[String]> = args.chunks(self.subscribe_batch_size).collect();
+
+        let mut unconfirmed: Vec<usize> = (0..batches.len()).collect();
+        let mut failures: HashMap<usize, String> = HashMap::new();
+        let mut ping_interval = tokio::time::interval(PING_INTERVAL);
+        ping_interval.tick().await; // Первый tick срабатывает сразу
+
+        for attempt in 1..=SUBSCRIBE_ATTEMPTS {
+            if attempt > 1 && sleep_or_shutdown(SUBSCRIBE_RETRY_DELAY, shutdown).await {
+                return Ok(true);
+            }
+
+            // Свой req_id у каждой попытки: запоздавший ответ на прошлую не примется за текущую
+            let mut pending: HashMap<String, usize> = HashMap::new();
+            for &batch_index in &unconfirmed {
+                let req_id = format!("subscribe-{}-{}", batch_index, attempt);
+                let subscribe_message = serde_json::json!({
+                    "req_id": req_id,
+                    "op": "subscribe",
+                    "args": batches[batch_index]
+                })
+                .to_string();
+                ws_stream.send(Message::Text(subscribe_message)).await?;
+                pending.insert(req_id, batch_index);
+            }
+
+            let deadline = tokio::time::Instant::now() + SUBSCRIBE_ACK_TIMEOUT;
+            while !pending.is_empty() {
+                let message = tokio::select! {
+                    _ = wait_for_shutdown(shutdown) => return Ok(true),
+                    _ = ping_interval.tick() => {
+                        ws_stream.send(Message::Text(serde_json::json!({ "op": "ping" }).to_string())).await?;
+                        continue;
+                    }
+                    message = tokio::time::timeout_at(deadline, ws_stream.next()) => message,
+                };
+                match message {
+                    Ok(Some(Ok(Message::Text(text)))) => match Self::match_subscribe_ack(&text, &pending) {
+                        Some((req_id, Ok(()))) => {
+                            let batch_index = pending.remove(&req_id).unwrap_or_default();
+                            unconfirmed.retain(|i| *i != batch_index);
+                        }
+                        Some((req_id, Err(reason))) => {
+                            let batch_index = pending.remove(&req_id).unwrap_or_default();
+                            failures.insert(batch_index, format!("rejected: {}", reason));
+                        }
+                        None => Self::handle_message(shared_state, common_tickers_set, &text),
+                    },
+                    Ok(Some(Ok(Message::Ping(payload)))) => {
+                        ws_stream.send(Message::Pong(payload)).await?;
+                    }
+                    Ok(Some(Ok(Message::Close(_)))) | Ok(None) => {
+                        return Err(tungstenite::Error::ConnectionClosed);
+                    }
+                    Ok(Some(Ok(_))) => {}
+                    Ok(Some(Err(e))) => return Err(e),
+                    Err(_) => break,
+                }
+            }
+            for batch_index in pending.into_values() {
+                failures.insert(batch_index, format!("no ack within {:?}", SUBSCRIBE_ACK_TIMEOUT));
+            }
+
+            if unconfirmed.is_empty() {
+                return Ok(false);
+            }
+            for batch_index in &unconfirmed {
+                warn!(
+                    "Bybit subscription batch {} ({:?}) {} (attempt {}/{})",
+                    batch_index, batches[*batch_index], failures[batch_index], attempt, SUBSCRIBE_ATTEMPTS
+                );
+            }
+        }
+
+        let batch_index = unconfirmed[0];
+        Err(tungstenite::Error::Io(std::io::Error::other(format!(
+            "{} subscription batches not confirmed, batch {} {}",
+            unconfirmed.len(),
+            batch_index,
+            failures[&batch_index]
+        ))))
+    }
+
+    /// Ответ на одну из ожидающих подписок `pending` (req_id -> пачка): req_id и Ok – подтверждена,
+    /// Err – отклонена с причиной из `ret_msg`. None – сообщение не об ожидающей подписке.
+    fn match_subscribe_ack(text: &str, pending: &HashMap<String, usize>) -> Option<(String, Result<(), String>)> {
+        let response = serde_json::from_str::<BybitWsResponse>(text).ok()?;
+        if response.op.as_deref() != Some("subscribe") {
+            return None;
+        }
+        let req_id = match response.req_id {
+            Some(req_id) => req_id,
+            // Ответ без req_id можно отнести к подписке, только если она одна
+            None if pending.len() == 1 => pending.keys().next()?.clone(),
+            None => return None,
+        };
+        if !pending.contains_key(&req_id) {
+            return None;
+        }
+        if response.success == Some(true) {
+            Some((req_id, Ok(())))
+        } else {
+            Some((req_id, Err(response.ret_msg.unwrap_or_default())))
+        }
+    }
+
+    /// Символ и изменения лучших bid/ask из сообщения `orderbook.1.*`: первый уровень с
+    /// объемом > 0; удаленный уровень (объем 0) без замены очищает сторону. Сторона, которой
+    /// нет в дельте, не меняется, а пустая сторона снимка очищается.
+    fn parse_orderbook_update(response: &BybitWsResponse) -> Option<(CanonicalSymbol, BookSideUpdate, BookSideUpdate)> {
+        let book = response.data.as_ref()?.orderbook()?;
+        let canonical = canonical_symbol(&book.s);
+        let side = |levels: &[[String; 2]]| {
+            if levels.is_empty() && response.is_snapshot() {
+                return BookSideUpdate::Cleared;
+            }
+            BookSideUpdate::from_levels(levels.iter().filter_map(|[price, size]| {
+                let (price, size) = (price.parse::<f64>().ok()?, size.parse::<f64>().ok()?);
+                Some((canonical.unit_price(price), canonical.unit_size(size)))
+            }))
+        };
+        let (bid, ask) = (side(&book.b), side(&book.a));
+        Some((canonical, bid, ask))
+    }
+
+    /// Символ и цена из сообщения `tickers.*`/`kline.*`.
+    ///
+    /// Для tickers цена – lastPrice: он всегда есть в снимке и только иногда в дельте.
+    /// Для kline – close самой поздней свечи, даже незакрытой (см. `BybitWsPayload::latest_kline`);
+    /// время цены – время последней сделки в этой свече, так что возраст и порядок цен
+    /// считаются одинаково для закрытых и открытых свечей.
+    ///
+    /// None для сообщений без topic или цены; некорректная цена (пустая, `null`, с запятой,
+    /// неположительная) логируется и тоже дает None, чтобы цикл чтения продолжил работу.
+    fn parse_price_update(response: &BybitWsResponse) -> Option<(CanonicalSymbol, f64)> {
+        let topic = response.topic.as_deref()?;
+        // Дельта-обновления tickers могут не содержать lastPrice
+        let price_str = response.data.as_ref()?.price()?;
+
+        let Some(raw_symbol) = topic.rsplit('.').next().filter(|s| !s.is_empty()) else {
+            warn!("Invalid topic format: {}", topic);
+            return None;
+        };
+        let canonical = canonical_symbol(raw_symbol);
+
+        match price_str.parse::<f64>() {
+            Ok(p) if p > 0.0 && p.is_finite() => Some((canonical, p)),
+            Ok(p) => {
+                warn!("Invalid price for {}: {}", canonical.symbol, p);
+                None
+            }
+            Err(e) => {
+                warn!("Failed to parse price for {}: {} (value: {})", canonical.symbol, e, price_str);
+                None
+            }
+        }
+    }
+
+    /// Обрабатывает текстовое сообщение Bybit: обновляет стакан или цену символа из
+    /// `common_tickers_set`, ответы на ping и подписку пропускает.
+    fn handle_message(shared_state: &SharedState, common_tickers_set: &HashSet<String>, text: &str) {
+            shared_state.record_feed_message(BYBIT);
+            match serde_json::from_str::<BybitWsResponse>(text) {
+                Ok(parse_msg) => {
+                    // Ответ на наш ping: {"op":"ping","ret_msg":"pong",...}
+                    if parse_msg.op.as_deref() == Some("ping") {
+                        debug!("Received pong from Bybit: {:?}", parse_msg.ret_msg);
+                        return;
+                    }
+
+                    if let Some((canonical, bid, ask)) = Self::parse_orderbook_update(&parse_msg) {
+                        if common_tickers_set.contains(&canonical.symbol) {
+                            shared_state.update_book(BYBIT, &canonical.symbol, bid, ask);
+                        }
+                        return;
+                    }
+
+                    // Сообщения без цены (подтверждения подписки, дельты без lastPrice)
+                    // и с некорректной ценой пропускаем
+                    if let Some((canonical, price)) = Self::parse_price_update(&parse_msg) {
+                        let symbol = &canonical.symbol;
+                        if common_tickers_set.contains(symbol) {
+                            // kline всегда приходят снимками; у tickers снимок – первое
+                            // сообщение после подписки
+                            match parse_msg.kline_confirmed() {
+                                Some(true) => debug!("Bybit candle closed for {} at {}", symbol, price),
+                                None if parse_msg.is_snapshot() => {
+                                    debug!("Bybit ticker snapshot for {}: {}", symbol, price)
                                 }
+                                _ => {}
                             }
+                            shared_state.set_price_at(
+                                BYBIT,
+                                symbol,
+                                canonical.unit_price(price),
+                                parse_msg.timestamp(),
+                            );
                         }
-                        // Игнорируем сообщения без topic (подтверждения подписки и т.д.)
                     }
-                    Err(e) => {
-                        warn!("Failed parsing Bybit data: {}", e);
+                }
+                Err(e) => {
+                    warn!("Failed parsing Bybit data: {}", e);
+                    shared_state.record_parse_error(BYBIT);
+                }
+            }
+    }
+
+    /// Подписывается на потоки Bybit (tickers или kline) и обновляет цены Bybit в `SharedState`.
+    ///
+    /// - common_tickers: тикеры Bybit как на бирже, используются только для построения аргументов подписки;
+    /// - common_tickers_set: авторитетный набор канонических тикеров (см. `canonical_symbol`) —
+    ///   входящие сообщения приводятся к ним и фильтруются по нему за O(1).
+    pub async fn bybit_ws(
+        &self,
+        common_tickers: &[String],
+        common_tickers_set: &HashSet<String>,
+        shared_state: &Arc<SharedState>,
+        mut shutdown: Shutdown,
+    ) {
+        const MAX_RECONNECT_ATTEMPTS: u32 = 0; // 0 = бесконечные попытки
+
+        // Стакан нужен только для проверки спреда по стакану (DEPTH_CHECK_NOTIONAL_USDT)
+        let with_orderbook = shared_state.depth_check_notional.is_some();
+        let mut reconnect_count = 0u32;
+        let reconnect = shared_state.reconnect_config(BYBIT);
+        let mut backoff = Backoff::new(reconnect.base_delay, reconnect.max_delay);
+
+        // Внешний цикл для переподключений
+        loop {
+            let (mut ws_stream, _) = match connect_ws(&self.ws_url).await {
+                Ok(stream) => {
+                    if reconnect_count == 0 {
+                        info!("Bybit {} WebSocket connected successfully", self.category.as_str());
+                    } else {
+                        info!("Bybit WebSocket reconnected (attempt {})", reconnect_count + 1);
                     }
-                },
-                Ok(data) => {
-                    warn!("Received unparseable data from Bybit: {:?}", data);
+                    stream
                 }
                 Err(e) => {
-                    error!("Bybit WebSocket error: {}", e);
+                    error!("Failed to connect to Bybit WebSocket: {}", e);
+                    reconnect_count += 1;
+                    shared_state.record_reconnect(BYBIT);
+                    if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
+                        error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
+                        return;
+                    }
+                    let delay = backoff.next_delay();
+                    warn!("Retrying in {:?}...", delay);
+                    if sleep_or_shutdown(delay, &mut shutdown).await {
+                        info!("Bybit WebSocket stopped");
+                        return;
+                    }
+                    continue;
+                }
+            };
+
+            // Подписку нужно восстанавливать при каждом переподключении
+            let subscribed = self
+                .subscribe_in_batches(
+                    &mut ws_stream,
+                    common_tickers,
+                    common_tickers_set,
+                    with_orderbook,
+                    shared_state,
+                    &mut shutdown,
+                )
+                .await;
+            if let Ok(true) = subscribed {
+                if let Err(e) = ws_stream.close(None).await {
+                    warn!("Failed to close Bybit WebSocket: {}", e);
+                }
+                info!("Bybit WebSocket stopped");
+                return;
+            }
+            if let Err(e) = subscribed {
+                error!("Failed to subscribe to Bybit topics: {}", e);
+                reconnect_count += 1;
+                shared_state.record_reconnect(BYBIT);
+                if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
+                    error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
+                    return;
+                }
+                let delay = backoff.next_delay();
+                warn!("Retrying subscription in {:?}...", delay);
+                if sleep_or_shutdown(delay, &mut shutdown).await {
+                    info!("Bybit WebSocket stopped");
+                    return;
+                }
+                continue;
+            }
+            info!("Subscribed to Bybit topics");
+            shared_state.set_feed_status(BYBIT, FeedStatus::Live);
+            reconnect_count = 0; // Сбрасываем счетчик при успешной подписке
+            backoff.reset();
+
+            // Внутренний цикл для обработки сообщений
+            let mut last_message_time = std::time::Instant::now();
+            let mut connection_alive = true;
+
+            let mut ping_interval = tokio::time::interval(PING_INTERVAL);
+            ping_interval.tick().await; // Первый tick срабатывает сразу
+
+            while connection_alive {
+                // Дедлайн считается от последнего сообщения, чтобы отправка ping не сбрасывала таймаут
+                let deadline = tokio::time::Instant::from_std(last_message_time + reconnect.heartbeat_timeout);
+                // Остановка проверяется только между сообщениями, поэтому начатая
+                // обработка сообщения доводится до конца
+                let message = tokio::select! {
+                    _ = wait_for_shutdown(&mut shutdown) => {
+                        if let Err(e) = ws_stream.close(None).await {
+                            warn!("Failed to close Bybit WebSocket: {}", e);
+                        }
+                        info!("Bybit WebSocket stopped");
+                        return;
+                    }
+                    _ = ping_interval.tick() => {
+                        let ping_message = serde_json::json!({ "op": "ping" }).to_string();
+                        if let Err(e) = ws_stream.send(Message::Text(ping_message)).await {
+                            warn!("Failed to send ping to Bybit: {}", e);
+                            connection_alive = false;
+                        }
+                        continue;
+                    }
+                    // Используем timeout для обнаружения "тихих" разрывов соединения
+                    message = tokio::time::timeout_at(deadline, ws_stream.next()) => message,
+                };
+
+                match message {
+                    Ok(Some(Ok(Message::Text(text)))) => {
+                        last_message_time = std::time::Instant::now();
+                        Self::handle_message(shared_state, common_tickers_set, &text);
+                    }
+                    Ok(Some(Ok(Message::Ping(payload)))) => {
+                        // Отвечаем на ping
+                        last_message_time = std::time::Instant::now();
+                        if let Err(e) = ws_stream.send(Message::Pong(payload)).await {
+                            warn!("Failed to send pong to Bybit: {}", e);
+                            connection_alive = false;
+                        }
+                    }
+                    Ok(Some(Ok(Message::Close(_)))) => {
+                        warn!("Bybit WebSocket connection closed by server");
+                        connection_alive = false;
+                    }
+                    Ok(Some(Ok(data))) => {
+                        warn!("Received unparseable data from Bybit: {:?}", data);
+                    }
+                    Ok(Some(Err(e))) => {
+                        error!("Bybit WebSocket error: {}", e);
+                        connection_alive = false;
+                    }
+                    Ok(None) => {
+                        warn!("Bybit WebSocket stream ended");
+                        connection_alive = false;
+                    }
+                    Err(_) => {
+                        // Timeout - возможно соединение тихо разорвано
+                        let elapsed = last_message_time.elapsed();
+                        warn!("No messages received from Bybit for {:?}. Connection may be lost.", elapsed);
+                        connection_alive = false;
+                    }
                 }
             }
+
+            // Соединение потеряно, пытаемся переподключиться
+            error!("Bybit WebSocket connection lost. Attempting to reconnect...");
+            reconnect_count += 1;
+            shared_state.record_reconnect(BYBIT);
+
+            if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
+                error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
+                return;
+            }
+
+            let delay = backoff.next_delay();
+            warn!("Reconnecting in {:?}... (attempt {}{})",
+                  delay,
+                  reconnect_count,
+                  if MAX_RECONNECT_ATTEMPTS > 0 {
+                      format!("/{}", MAX_RECONNECT_ATTEMPTS)
+                  } else {
+                      "".to_string()
+                  });
+            if sleep_or_shutdown(delay, &mut shutdown).await {
+                info!("Bybit WebSocket stopped");
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline_message(close: &str) -> BybitWsResponse {
+        let text = format!(
+            r#"{{"topic":"kline.D.BTCUSDT","type":"snapshot","ts":1,"data":[{{"start":0,"end":1,"interval":"D","open":"1","close":{},"high":"1","low":"1","volume":"1","turnover":"1","confirm":false,"timestamp":1718000000123}}]}}"#,
+            serde_json::to_string(close).unwrap()
+        );
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn valid_close_is_parsed() {
+        let message = kline_message("65000.5");
+        let (canonical, price) = Bybit::parse_price_update(&message).unwrap();
+        assert_eq!(canonical.symbol, "BTCUSDT");
+        assert_eq!(price, 65000.5);
+        // Время свечи, а не ts сообщения
+        assert_eq!(message.timestamp(), Some(1718000000123));
+    }
+
+    #[test]
+    fn latest_candle_is_used_and_confirmed_wins_ties() {
+        let candle = |start: u64, close: &str, confirm: bool, timestamp: u64| {
+            serde_json::json!({"start":start,"end":start + 1,"interval":"D","open":"1","close":close,"high":"1",
+                "low":"1","volume":"1","turnover":"1","confirm":confirm,"timestamp":timestamp})
+        };
+        let message = |data: serde_json::Value| -> BybitWsResponse {
+            serde_json::from_value(serde_json::json!({"topic":"kline.D.BTCUSDT","type":"delta","ts":1,"data":data}))
+                .unwrap()
+        };
+
+        // Закрытая вчерашняя и открытая сегодняшняя свечи – берется сегодняшняя
+        let rolled = message(serde_json::json!([candle(2, "101", false, 20), candle(1, "100", true, 10)]));
+        assert_eq!(Bybit::parse_price_update(&rolled).unwrap().1, 101.0);
+        assert_eq!((rolled.timestamp(), rolled.kline_confirmed()), (Some(20), Some(false)));
+        assert!(!rolled.is_snapshot());
+
+        // Две записи одной свечи – берется закрытая
+        let closed = message(serde_json::json!([candle(1, "100", true, 10), candle(1, "99", false, 9)]));
+        assert_eq!(Bybit::parse_price_update(&closed).unwrap().1, 100.0);
+        assert_eq!(closed.kline_confirmed(), Some(true));
+        assert!(kline_message("1").is_snapshot());
+    }
+
+    #[test]
+    fn non_numeric_close_is_skipped() {
+        for close in ["", "null", "1,5", "abc", "-1", "0", "NaN", "inf"] {
+            assert!(Bybit::parse_price_update(&kline_message(close)).is_none(), "{:?}", close);
         }
     }
+
+    #[test]
+    fn ticker_delta_without_last_price_is_skipped() {
+        let message: BybitWsResponse = serde_json::from_str(
+            r#"{"topic":"tickers.BTCUSDT","type":"delta","ts":1,"data":{"symbol":"BTCUSDT","markPrice":"1"}}"#,
+        )
+        .unwrap();
+        assert!(Bybit::parse_price_update(&message).is_none());
+    }
+
+    #[test]
+    fn instrument_spec_is_scaled_to_coins() {
+        let instrument: BybitList = serde_json::from_str(
+            r#"{"symbol":"1000PEPEUSDT","contractType":"LinearPerpetual","status":"Trading","baseCoin":"1000PEPE",
+            "quoteCoin":"USDT","launchTime":"0","deliveryTime":"0","deliveryFeeRate":"","priceScale":"7",
+            "leverageFilter":{"minLeverage":"1","maxLeverage":"25.00","leverageStep":"0.01"},
+            "priceFilter":{"minPrice":"0.0000001","maxPrice":"1.9999998","tickSize":"0.0000001"},
+            "lotSizeFilter":{"maxOrderQty":"1000000","minOrderQty":"100","qtyStep":"100","postOnlyMaxOrderQty":"1000000",
+            "minNotionalValue":"5"},
+            "unifiedMarginTrade":true,"fundingInterval":480,"settleCoin":"USDT","copyTrading":"both",
+            "upperFundingRate":"0.005","lowerFundingRate":"-0.005"}"#,
+        )
+        .unwrap();
+        let (symbol, spec) = Bybit::instrument_spec(&instrument).unwrap();
+        assert_eq!(symbol, "PEPEUSDT");
+        assert!((spec.tick_size - 0.0000000001).abs() < 1e-18);
+        assert_eq!((spec.qty_step, spec.min_qty), (100_000.0, 100_000.0));
+        assert_eq!(spec.min_notional, Some(5.0));
+    }
+
+    #[test]
+    fn subscribe_ack_is_matched_by_req_id() {
+        let ack = |success: bool, req_id: &str| {
+            format!(r#"{{"success":{},"ret_msg":"error:invalid topic","conn_id":"c1","req_id":"{}","op":"subscribe"}}"#, success, req_id)
+        };
+        let pending: HashMap<String, usize> = [("subscribe-0-2".to_string(), 0), ("subscribe-1-2".to_string(), 1)].into();
+        assert_eq!(
+            Bybit::match_subscribe_ack(&ack(true, "subscribe-1-2"), &pending),
+            Some(("subscribe-1-2".to_string(), Ok(())))
+        );
+        assert_eq!(
+            Bybit::match_subscribe_ack(&ack(false, "subscribe-0-2"), &pending),
+            Some(("subscribe-0-2".to_string(), Err("error:invalid topic".to_string())))
+        );
+        // Запоздавший ответ на прошлую попытку и данные топиков пропускаются
+        assert_eq!(Bybit::match_subscribe_ack(&ack(true, "subscribe-0-1"), &pending), None);
+        assert_eq!(Bybit::match_subscribe_ack(r#"{"op":"pong","success":true}"#, &pending), None);
+        assert_eq!(Bybit::match_subscribe_ack(&serde_json::to_string(&kline_message("1")).unwrap(), &pending), None);
+
+        // Ответ без req_id относится к подписке, только если она одна
+        let no_req_id = r#"{"success":true,"ret_msg":"","op":"subscribe"}"#;
+        assert_eq!(Bybit::match_subscribe_ack(no_req_id, &pending), None);
+        let single: HashMap<String, usize> = [("subscribe-3-1".to_string(), 3)].into();
+        assert_eq!(Bybit::match_subscribe_ack(no_req_id, &single), Some(("subscribe-3-1".to_string(), Ok(()))));
+    }
+
+    #[test]
+    fn spot_instrument_is_parsed_and_listed() {
+        let instrument: BybitList = serde_json::from_str(
+            r#"{"symbol":"BTCUSDT","baseCoin":"BTC","quoteCoin":"USDT","innovation":"0","status":"Trading",
+            "marginTrading":"utaOnly","stTag":"0",
+            "lotSizeFilter":{"basePrecision":"0.000001","quotePrecision":"0.00000001","minOrderQty":"0.000048",
+            "maxOrderQty":"71.73956243","minOrderAmt":"1","maxOrderAmt":"2000000"},
+            "priceFilter":{"tickSize":"0.01"},"riskParameters":{"priceLimitRatioX":"0.01","priceLimitRatioY":"0.02"}}"#,
+        )
+        .unwrap();
+        assert!(BybitCategory::Spot.lists(&instrument));
+        assert!(!BybitCategory::Inverse.lists(&instrument));
+        let (symbol, spec) = Bybit::instrument_spec(&instrument).unwrap();
+        assert_eq!(symbol, "BTCUSDT");
+        assert_eq!((spec.tick_size, spec.qty_step), (0.01, 0.000001));
+        assert_eq!(spec.min_notional, Some(1.0));
+    }
+
+    #[test]
+    fn orderbook_top_is_scaled_to_coins() {
+        let message: BybitWsResponse = serde_json::from_str(
+            r#"{"topic":"orderbook.1.1000PEPEUSDT","type":"delta","ts":1,"data":{"s":"1000PEPEUSDT","b":[["0.0120","500"]],"a":[["0.0121","0"]],"u":2,"seq":3}}"#,
+        )
+        .unwrap();
+        assert!(Bybit::parse_price_update(&message).is_none());
+
+        let (canonical, bid, ask) = Bybit::parse_orderbook_update(&message).unwrap();
+        assert_eq!(canonical.symbol, "PEPEUSDT");
+        let BookSideUpdate::Level(bid) = bid else {
+            panic!("expected a bid level, got {:?}", bid);
+        };
+        assert!((bid.price - 0.000012).abs() < 1e-12);
+        assert_eq!(bid.size, 500_000.0);
+        assert_eq!(ask, BookSideUpdate::Cleared, "removed level clears the ask");
+    }
 }
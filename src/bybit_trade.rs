@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::Sha256;
+use thiserror::Error;
+use tokio::sync::OnceCell;
+
+use crate::config::BybitConfig;
+use crate::utils::{canonical_symbol, BybitApiResponse};
+
+type HmacSha256 = Hmac<Sha256>;
+
+const MAINNET_BASE_URL: &str = "https://api.bybit.com";
+/// Окно, в течение которого Bybit принимает подписанный запрос после timestamp.
+const RECV_WINDOW_MS: u64 = 5000;
+/// Сколько раз опрашиваем статус маркет-ордера и с каким интервалом.
+const ORDER_STATUS_POLL_ATTEMPTS: u32 = 10;
+const ORDER_STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Клиент для маркет-ордеров на Bybit linear perpetual (API v5).
+///
+/// Ключи берутся из `[bybit] api_key/api_secret` (BYBIT_API_KEY, BYBIT_API_SECRET). Ордера
+/// выставляются с positionIdx=0, то есть аккаунт должен быть в one-way режиме. Используется
+/// как нога хеджа (`HedgeExecutor`).
+pub struct BybitTrader {
+    api_key: String,
+    api_secret: String,
+    http_client: Client,
+    base_url: String,
+    /// Контракты по каноническому тикеру, загружаются при первом ордере.
+    contracts: OnceCell<HashMap<String, Contract>>,
+    /// Режим без отправки ордеров (BYBIT_DRY_RUN, по умолчанию включен).
+    dry_run: bool,
+}
+
+/// Контракт Bybit для канонического тикера: `PEPEUSDT` торгуется как `1000PEPEUSDT`.
+#[derive(Debug, Clone)]
+struct Contract {
+    symbol: String,
+    multiplier: f64,
+    /// Шаг количества в контрактах Bybit.
+    qty_step: f64,
+}
+
+#[derive(Debug, Clone)]
+pub enum BybitTradeOutcome {
+    /// Маркет-ордер исполнен. Объем – в монетах, цена – за монету.
+    Filled {
+        symbol: String,
+        is_buy: bool,
+        quantity: f64,
+        avg_price: f64,
+        order_id: String,
+    },
+    /// Режим BYBIT_DRY_RUN: ордер рассчитан, но не отправлен.
+    DryRun {
+        symbol: String,
+        is_buy: bool,
+        quantity: f64,
+        /// Тело запроса в том виде, в котором оно ушло бы в Bybit.
+        body: String,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum BybitTradeError {
+    #[error("missing env var: {0}")]
+    MissingEnv(String),
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("serialization error: {0}")]
+    Serde(#[from] serde_json::Error),
+    #[error("api error {code}: {msg}")]
+    ApiCode { code: i32, msg: String },
+    #[error("api error: {0}")]
+    Api(String),
+    #[error("internal error: {0}")]
+    Internal(String),
+    #[error("order {order_id} for {symbol} was rejected, status {status}")]
+    OrderRejected {
+        symbol: String,
+        order_id: String,
+        status: String,
+    },
+    #[error("order {order_id} for {symbol} was not filled within {waited:?}")]
+    OrderNotFilled {
+        symbol: String,
+        order_id: String,
+        waited: Duration,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiResponse {
+    ret_code: i32,
+    #[serde(default)]
+    ret_msg: String,
+    /// При ошибке Bybit присылает `{}`, поэтому разбираем результат после проверки кода.
+    #[serde(default)]
+    result: Value,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatedOrder {
+    order_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OrderList {
+    #[serde(default)]
+    list: Vec<OrderInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrderInfo {
+    #[serde(default)]
+    order_status: String,
+    #[serde(default)]
+    cum_exec_qty: String,
+    #[serde(default)]
+    avg_price: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PositionList {
+    #[serde(default)]
+    list: Vec<PositionInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PositionInfo {
+    /// Buy – long, Sell – short, пустая строка – позиции нет.
+    #[serde(default)]
+    side: String,
+    #[serde(default)]
+    size: String,
+    #[serde(default)]
+    avg_price: String,
+}
+
+impl BybitTrader {
+    pub fn from_config(config: &BybitConfig, http_client: Client) -> Result<Self, BybitTradeError> {
+        let api_key = config.api_key.as_deref().map(str::trim).unwrap_or_default();
+        if api_key.is_empty() {
+            return Err(BybitTradeError::MissingEnv("BYBIT_API_KEY".into()));
+        }
+        let api_secret = config.api_secret.as_deref().map(str::trim).unwrap_or_default();
+        if api_secret.is_empty() {
+            return Err(BybitTradeError::MissingEnv("BYBIT_API_SECRET".into()));
+        }
+
+        Ok(Self {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+            http_client,
+            base_url: MAINNET_BASE_URL.to_string(),
+            contracts: OnceCell::new(),
+            // Как и для BingX, без явного BYBIT_DRY_RUN=false реальные ордера не отправляются
+            dry_run: config.dry_run.unwrap_or(true),
+        })
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Маркет-ордер на `quantity` монет по каноническому тикеру (`PEPEUSDT`).
+    /// `reduce_only` = true – только уменьшение позиции (закрытие ноги).
+    ///
+    /// Объем округляется вниз до шага контракта. Ждет исполнения и возвращает фактические объем и
+    /// среднюю цену; в режиме BYBIT_DRY_RUN ордер только логируется – возвращается `DryRun`.
+    pub async fn market_order(
+        &self,
+        symbol: &str,
+        is_buy: bool,
+        quantity: f64,
+        reduce_only: bool,
+    ) -> Result<BybitTradeOutcome, BybitTradeError> {
+        let contract = self.contract(symbol).await?;
+        let (contracts, contracts_str) = round_to_step(quantity / contract.multiplier, contract.qty_step);
+        if contracts <= 0.0 {
+            return Err(BybitTradeError::Internal(format!(
+                "quantity {} for {} is below the step {}",
+                quantity, contract.symbol, contract.qty_step
+            )));
+        }
+
+        let body = serde_json::json!({
+            "category": "linear",
+            "symbol": contract.symbol,
+            "side": if is_buy { "Buy" } else { "Sell" },
+            "orderType": "Market",
+            "qty": contracts_str,
+            "positionIdx": 0,
+            "reduceOnly": reduce_only,
+        })
+        .to_string();
+
+        info!(
+            "Bybit: placing market {} order on {}, qty={}, reduce_only={}",
+            if is_buy { "BUY" } else { "SELL" },
+            contract.symbol,
+            contracts_str,
+            reduce_only
+        );
+
+        if self.dry_run {
+            info!("Bybit [dry run]: would send order {}", body);
+            return Ok(BybitTradeOutcome::DryRun {
+                symbol: contract.symbol,
+                is_buy,
+                quantity: contracts * contract.multiplier,
+                body,
+            });
+        }
+
+        let created: CreatedOrder = self.post_signed("/v5/order/create", body).await?;
+        let (filled, avg_price) = self.wait_for_fill(&contract.symbol, &created.order_id).await?;
+        info!(
+            "Bybit: order {} filled on {}: qty={}, avg_price={}",
+            created.order_id, contract.symbol, filled, avg_price
+        );
+
+        Ok(BybitTradeOutcome::Filled {
+            symbol: contract.symbol,
+            is_buy,
+            quantity: filled * contract.multiplier,
+            avg_price: avg_price / contract.multiplier,
+            order_id: created.order_id,
+        })
+    }
+
+    /// Открытая позиция по каноническому тикеру в сторону `is_buy` (long для true): объем в монетах
+    /// и цена входа за монету. None – позиции в эту сторону нет.
+    pub async fn position(&self, symbol: &str, is_buy: bool) -> Result<Option<(f64, f64)>, BybitTradeError> {
+        let contract = self.contract(symbol).await?;
+        let query = format!("category=linear&symbol={}", contract.symbol);
+        let positions: PositionList = self.get_signed("/v5/position/list", &query).await?;
+        Ok(positions
+            .list
+            .into_iter()
+            .filter(|p| p.side == if is_buy { "Buy" } else { "Sell" })
+            .map(|p| (p.size.parse::<f64>().unwrap_or(0.0), p.avg_price.parse::<f64>().unwrap_or(0.0)))
+            .find(|(size, _)| *size > 0.0)
+            .map(|(size, avg_price)| (size * contract.multiplier, avg_price / contract.multiplier)))
+    }
+
+    /// Контракт Bybit для канонического тикера. Список загружается при первом обращении и
+    /// кэшируется; при ошибке загрузка повторится в следующий раз.
+    async fn contract(&self, symbol: &str) -> Result<Contract, BybitTradeError> {
+        let contracts = self
+            .contracts
+            .get_or_try_init(|| async {
+                let url = format!("{}/v5/market/instruments-info?category=linear&limit=1000", self.base_url);
+                let response: BybitApiResponse = self.http_client.get(&url).send().await?.json().await?;
+                let contracts: HashMap<String, Contract> = response
+                    .result
+                    .list
+                    .iter()
+                    .filter(|instrument| !instrument.symbol.contains('-'))
+                    .filter_map(|instrument| {
+                        let canonical = canonical_symbol(&instrument.symbol);
+                        let contract = Contract {
+                            symbol: instrument.symbol.clone(),
+                            multiplier: canonical.multiplier,
+                            qty_step: instrument.qty_step()?,
+                        };
+                        Some((canonical.symbol, contract))
+                    })
+                    .collect();
+                info!("Bybit: loaded {} contracts for trading", contracts.len());
+                Ok::<_, BybitTradeError>(contracts)
+            })
+            .await?;
+        contracts
+            .get(symbol)
+            .cloned()
+            .ok_or_else(|| BybitTradeError::Api(format!("no Bybit contract for {}", symbol)))
+    }
+
+    /// Ждет исполнения маркет-ордера и возвращает исполненный объем и среднюю цену в контрактах.
+    async fn wait_for_fill(&self, symbol: &str, order_id: &str) -> Result<(f64, f64), BybitTradeError> {
+        let query = format!("category=linear&symbol={}&orderId={}", symbol, order_id);
+        let mut last_status = String::from("UNKNOWN");
+
+        for attempt in 1..=ORDER_STATUS_POLL_ATTEMPTS {
+            tokio::time::sleep(ORDER_STATUS_POLL_INTERVAL).await;
+
+            let order = match self.get_signed::<OrderList>("/v5/order/realtime", &query).await {
+                Ok(orders) => match orders.list.into_iter().next() {
+                    Some(order) => order,
+                    None => continue,
+                },
+                Err(e) => {
+                    warn!("Bybit: failed to query order {} status (attempt {}): {}", order_id, attempt, e);
+                    continue;
+                }
+            };
+
+            let executed = order.cum_exec_qty.parse::<f64>().unwrap_or(0.0);
+            let avg_price = order.avg_price.parse::<f64>().unwrap_or(0.0);
+            match order.order_status.as_str() {
+                "Filled" => return Ok((executed, avg_price)),
+                // Частично исполненный и затем отмененный ордер все равно изменил позицию
+                "PartiallyFilledCanceled" | "Cancelled" | "Rejected" | "Deactivated" if executed > 0.0 => {
+                    warn!(
+                        "Bybit: order {} for {} ended with status {} after partial fill of {}",
+                        order_id, symbol, order.order_status, executed
+                    );
+                    return Ok((executed, avg_price));
+                }
+                "PartiallyFilledCanceled" | "Cancelled" | "Rejected" | "Deactivated" => {
+                    return Err(BybitTradeError::OrderRejected {
+                        symbol: symbol.to_string(),
+                        order_id: order_id.to_string(),
+                        status: order.order_status,
+                    });
+                }
+                _ => last_status = order.order_status,
+            }
+        }
+
+        warn!("Bybit: order {} for {} is still {}", order_id, symbol, last_status);
+        Err(BybitTradeError::OrderNotFilled {
+            symbol: symbol.to_string(),
+            order_id: order_id.to_string(),
+            waited: ORDER_STATUS_POLL_INTERVAL * ORDER_STATUS_POLL_ATTEMPTS,
+        })
+    }
+
+    /// Подпись v5: HMAC-SHA256 от `timestamp + api_key + recv_window + payload`, где payload –
+    /// строка запроса для GET и тело для POST.
+    fn signed_headers(&self, payload: &str) -> Result<[(&'static str, String); 4], BybitTradeError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            .to_string();
+        let mut mac = HmacSha256::new_from_slice(self.api_secret.as_bytes())
+            .map_err(|e| BybitTradeError::Internal(format!("failed to create HMAC instance: {}", e)))?;
+        mac.update(format!("{}{}{}{}", timestamp, self.api_key, RECV_WINDOW_MS, payload).as_bytes());
+        Ok([
+            ("X-BAPI-API-KEY", self.api_key.clone()),
+            ("X-BAPI-TIMESTAMP", timestamp),
+            ("X-BAPI-RECV-WINDOW", RECV_WINDOW_MS.to_string()),
+            ("X-BAPI-SIGN", hex::encode(mac.finalize().into_bytes())),
+        ])
+    }
+
+    async fn get_signed<T: DeserializeOwned>(&self, path: &str, query: &str) -> Result<T, BybitTradeError> {
+        let mut request = self.http_client.get(format!("{}{}?{}", self.base_url, path, query));
+        for (name, value) in self.signed_headers(query)? {
+            request = request.header(name, value);
+        }
+        Self::parse_response(request.send().await?.text().await?)
+    }
+
+    async fn post_signed<T: DeserializeOwned>(&self, path: &str, body: String) -> Result<T, BybitTradeError> {
+        let mut request = self
+            .http_client
+            .post(format!("{}{}", self.base_url, path))
+            .header("Content-Type", "application/json");
+        for (name, value) in self.signed_headers(&body)? {
+            request = request.header(name, value);
+        }
+        Self::parse_response(request.body(body).send().await?.text().await?)
+    }
+
+    fn parse_response<T: DeserializeOwned>(text: String) -> Result<T, BybitTradeError> {
+        let response: ApiResponse = serde_json::from_str(&text)?;
+        if response.ret_code != 0 {
+            return Err(BybitTradeError::ApiCode {
+                code: response.ret_code,
+                msg: response.ret_msg,
+            });
+        }
+        Ok(serde_json::from_value(response.result)?)
+    }
+}
+
+/// Округляет количество вниз до шага и форматирует его с точностью шага.
+fn round_to_step(quantity: f64, step: f64) -> (f64, String) {
+    // Небольшой допуск, чтобы 12.345 / 0.001 = 12344.999... не теряло шаг
+    let rounded = (quantity / step + 1e-9).floor() * step;
+    let decimals = (0..=10)
+        .find(|&d| {
+            let scaled = step * 10f64.powi(d);
+            (scaled - scaled.round()).abs() < 1e-9
+        })
+        .unwrap_or(10) as usize;
+    (rounded, format!("{:.*}", decimals, rounded))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantity_is_rounded_down_to_step() {
+        assert_eq!(round_to_step(12.3456, 0.001).1, "12.345");
+        assert_eq!(round_to_step(0.3, 0.1).1, "0.3");
+        assert_eq!(round_to_step(1234.9, 10.0), (1230.0, "1230".to_string()));
+        assert_eq!(round_to_step(0.0004, 0.001), (0.0, "0.000".to_string()));
+    }
+}
@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::config::Config;
+
+// Аргументы командной строки. Заданные флаги перекрывают config.toml и окружение.
+#[derive(Debug, Parser)]
+#[command(version, about = "Cross-exchange perpetual futures arbitrage monitor")]
+pub struct Args {
+    /// Path to the TOML config [default: CONFIG_PATH or ./config.toml]
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log BingX orders instead of sending them
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Minimum net spread, in percent, to report an opportunity
+    #[arg(long, value_name = "PCT")]
+    pub min_spread: Option<f64>,
+
+    /// Comma-separated exchange feeds to run, e.g. bybit,hyperliquid [default: all]
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub exchanges: Option<Vec<String>>,
+
+    /// Replay a recorded NDJSON price file instead of connecting to exchanges
+    #[arg(long, value_name = "PATH")]
+    pub replay: Option<PathBuf>,
+
+    /// Replay speed multiplier; 0 replays as fast as possible
+    #[arg(long, value_name = "X", default_value_t = 1.0, requires = "replay")]
+    pub replay_speed: f64,
+
+    /// Comma-separated spread thresholds, in percent, to tally in replay [default: --min-spread]
+    #[arg(long, value_name = "LIST", value_delimiter = ',', requires = "replay")]
+    pub replay_thresholds: Option<Vec<f64>>,
+
+    /// Check exchange REST access, Telegram and BingX credentials, then exit (non-zero on failure)
+    #[arg(long, conflicts_with = "replay")]
+    pub preflight: bool,
+}
+
+impl Args {
+    /// Переносит заданные флаги в конфиг поверх значений из файла и окружения.
+    pub fn apply(&self, config: &mut Config) {
+        if self.dry_run {
+            config.bingx.dry_run = Some(true);
+        }
+        if let Some(min_spread) = self.min_spread {
+            config.thresholds.min_spread_percent = Some(min_spread);
+        }
+        if let Some(exchanges) = &self.exchanges {
+            config.exchanges = Some(exchanges.clone());
+        }
+    }
+}
@@ -1,7 +1,13 @@
-use crate::share_state::SharedState;
-use std::{collections::HashSet, error, sync::Arc, sync::LazyLock};
-use log::{info, error};
+use crate::share_state::{BookTop, PriceQuote, SharedState, ASTER, BYBIT, HYPERLIQUID};
+use std::{collections::{HashMap, HashSet}, error, fs, sync::Arc, sync::OnceLock, time::Duration};
+use log::{debug, info, error, warn};
+use crate::alert_digest::PendingAlert;
+use crate::alert_tier::AlertTier;
 use crate::bingx::BingXTradeOutcome;
+use crate::hedge::{HedgeExecutor, HedgeLeg, HedgeResult};
+use crate::metrics::record_alert;
+use crate::telegram::TelegramNotifier;
+use crate::utils::{wait_for_shutdown, Shutdown};
 
 const EXCLUDED_TOKENS: &[&str] = &[
     "PIXELUSDT",
@@ -17,100 +23,479 @@ const EXCLUDED_TOKENS: &[&str] = &[
     "BNTUSDT",
 ];
 
+const EXCLUDED_TOKENS_FILE: &str = "excluded_tokens.txt";
+
 // Кэшируем HashSet исключенных токенов, чтобы не создавать его каждый раз
-static EXCLUDED_TOKENS_SET: LazyLock<HashSet<&'static str>> = LazyLock::new(|| {
-    EXCLUDED_TOKENS.iter().copied().collect()
-});
+static EXCLUDED_TOKENS_SET: OnceLock<HashSet<String>> = OnceLock::new();
 
-pub async fn compare_prices(
-    shared_state: &Arc<SharedState>,
-    symbol: &str,
-) -> Result<(), Box<dyn error::Error>> {
-    // Пропускаем токены из списка исключений
-    if EXCLUDED_TOKENS_SET.contains(symbol) {
-        return Ok(());
+/// Собирает список исключений: встроенный список + `excluded_tokens` из конфига
+/// + файл `excluded_tokens.txt` (по одному токену на строку, `#` – комментарий).
+fn load_excluded_tokens(configured: &[String]) -> HashSet<String> {
+    let mut tokens: HashSet<String> = EXCLUDED_TOKENS.iter().map(|t| t.to_string()).collect();
+
+    tokens.extend(
+        configured
+            .iter()
+            .map(|t| t.trim().to_uppercase())
+            .filter(|t| !t.is_empty()),
+    );
+
+    match fs::read_to_string(EXCLUDED_TOKENS_FILE) {
+        Ok(content) => {
+            tokens.extend(
+                content
+                    .lines()
+                    .map(|line| line.trim().to_uppercase())
+                    .filter(|line| !line.is_empty() && !line.starts_with('#')),
+            );
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            warn!("Failed to read {}: {}", EXCLUDED_TOKENS_FILE, e);
+        }
     }
-    let bybit_price = {
-        let bybit_prices = shared_state.bybit_prices.read().await;
-        *bybit_prices.get(symbol).unwrap_or(&0.0)
+
+    info!("Excluding {} tokens from comparison", tokens.len());
+    tokens
+}
+
+/// Загружает список исключений заранее, чтобы он был прочитан при старте, а не на первом тике.
+pub fn init_excluded_tokens(configured: &[String]) {
+    EXCLUDED_TOKENS_SET.get_or_init(|| load_excluded_tokens(configured));
+}
+
+/// Входит ли символ в список исключений.
+pub fn is_excluded(symbol: &str) -> bool {
+    EXCLUDED_TOKENS_SET.get_or_init(|| load_excluded_tokens(&[])).contains(symbol)
+}
+
+/// Возвращает цену, если она есть и свежее `max_age`; иначе None – цена не участвует в сравнении.
+fn fresh_price(quote: Option<PriceQuote>, max_age: Duration, venue: &str, symbol: &str) -> Option<f64> {
+    let quote = quote?;
+    let age = quote.updated_at.elapsed();
+    if age > max_age {
+        // Проверяется на каждом проходе по всем тикерам: warn! засыпал бы лог, а возраст цен фида и так виден в /status
+        debug!(
+            "Skipping stale {} price for {}: {} is {:?} old (max {:?})",
+            venue, symbol, quote.price, age, max_age
+        );
+        return None;
+    }
+    Some(quote.price)
+}
+
+/// Относительный допуск сравнения цен по умолчанию (PRICE_EPSILON).
+pub const DEFAULT_PRICE_EPSILON: f64 = 1e-9;
+
+static PRICE_EPSILON: OnceLock<f64> = OnceLock::new();
+
+/// Задает допуск сравнения цен (PRICE_EPSILON) один раз при старте. Некорректное значение
+/// заменяется значением по умолчанию.
+pub fn init_price_epsilon(configured: Option<f64>) {
+    let epsilon = match configured {
+        Some(v) if (0.0..1.0).contains(&v) => v,
+        Some(v) => {
+            warn!("Invalid PRICE_EPSILON '{}', using default {}", v, DEFAULT_PRICE_EPSILON);
+            DEFAULT_PRICE_EPSILON
+        }
+        None => DEFAULT_PRICE_EPSILON,
     };
+    let _ = PRICE_EPSILON.set(epsilon);
+}
+
+/// Относительный допуск сравнения цен: разница в пределах этой доли от большей цены – шум f64.
+pub fn price_epsilon() -> f64 {
+    PRICE_EPSILON.get().copied().unwrap_or(DEFAULT_PRICE_EPSILON)
+}
+
+/// Равны ли цены с точностью до `price_epsilon()` от большей из них. Допуск относительный,
+/// так что цены в доли цента сравниваются так же, как цены в тысячи долларов.
+pub fn prices_equal(a: f64, b: f64) -> bool {
+    (a - b).abs() <= a.abs().max(b.abs()) * price_epsilon()
+}
+
+/// Какая из двух цен выше.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpreadDirection {
+    /// Первая цена (`a`) выше второй.
+    AAboveB,
+    /// Вторая цена (`b`) выше первой.
+    BAboveA,
+}
+
+impl SpreadDirection {
+    /// Биржи для сделки по спреду между `a` и `b`: (где покупать, где продавать).
+    /// Покупаем там, где дешевле, продаем там, где дороже.
+    pub fn buy_sell<'a>(self, a: &'a str, b: &'a str) -> (&'a str, &'a str) {
+        match self {
+            SpreadDirection::AAboveB => (b, a),
+            SpreadDirection::BAboveA => (a, b),
+        }
+    }
+}
+
+/// Расхождение двух цен: модуль разницы в процентах от первой цены и направление.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Spread {
+    pub percent: f64,
+    pub direction: SpreadDirection,
+}
 
-    let hyperliquid_price = {
-        let hyperliquid_prices = shared_state.hyperliquid_prices.read().await;
-        *hyperliquid_prices.get(symbol).unwrap_or(&0.0)
+/// Считает спред между ценами `a` и `b` (в процентах от `a`) и возвращает его, если он
+/// не меньше `threshold`. Нулевые, отрицательные, бесконечные и NaN цены, а также равные
+/// (см. `prices_equal`) цены дают None.
+pub fn evaluate_spread(a: f64, b: f64, threshold: f64) -> Option<Spread> {
+    if !(a > 0.0 && b > 0.0 && a.is_finite() && b.is_finite()) || prices_equal(a, b) {
+        return None;
+    }
+    let percent = ((a - b) / a).abs() * 100.0;
+    if threshold.is_nan() || percent < threshold {
+        return None;
+    }
+    let direction = if a > b {
+        SpreadDirection::AAboveB
+    } else {
+        SpreadDirection::BAboveA
     };
+    Some(Spread { percent, direction })
+}
+
+/// Режим порога сигнала (THRESHOLD_MODE). Для дорогих монет 0.1% – крупная сумма, для
+/// дешевых – шум в пределах шага цены, поэтому порог можно задать и в цене.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ThresholdMode {
+    /// Спред в процентах не меньше MIN_SPREAD_PERCENT (по умолчанию).
+    Percent,
+    /// Разница цен не меньше заданной суммы в USDT (MIN_SPREAD_ABSOLUTE).
+    Absolute(f64),
+    /// Разница цен не меньше N шагов цены биржи (MIN_SPREAD_TICKS).
+    TickMultiple(f64),
+}
+
+impl ThresholdMode {
+    /// Порог в процентах для `evaluate_spread_in_mode`: MIN_SPREAD_PERCENT в режиме процентов,
+    /// в остальных режимах процентный спред не ограничивается.
+    pub fn percent_threshold(self, min_spread_percent: f64) -> f64 {
+        match self {
+            ThresholdMode::Percent => min_spread_percent,
+            ThresholdMode::Absolute(_) | ThresholdMode::TickMultiple(_) => 0.0,
+        }
+    }
+}
+
+/// База сравнения спредов (REFERENCE_VENUE).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceVenue {
+    /// Спред считается от цены этой биржи, остальные биржи сравниваются только с ней
+    /// (по умолчанию Bybit).
+    Venue(&'static str),
+    /// Сравниваются все пары бирж (`all`).
+    All,
+}
+
+impl ReferenceVenue {
+    /// Пары бирж для сравнения из `venues` в их порядке; первая биржа пары – база процента.
+    pub fn comparison_pairs(self, venues: &[&'static str]) -> Vec<(&'static str, &'static str)> {
+        match self {
+            ReferenceVenue::Venue(reference) => venues
+                .iter()
+                .filter(|venue| **venue != reference)
+                .map(|venue| (reference, *venue))
+                .collect(),
+            ReferenceVenue::All => venues
+                .iter()
+                .enumerate()
+                .flat_map(|(i, a)| venues[i + 1..].iter().map(move |b| (*a, *b)))
+                .collect(),
+        }
+    }
 
-    let aster_price = {
-        let aster_prices = shared_state.aster_prices.read().await;
-        *aster_prices.get(symbol).unwrap_or(&0.0)
+    /// Биржа, от цены которой считается спред для торговли на BingX: в режиме всех пар – Bybit.
+    pub fn trading_reference(self) -> &'static str {
+        match self {
+            ReferenceVenue::Venue(reference) => reference,
+            ReferenceVenue::All => BYBIT,
+        }
+    }
+}
+
+/// `evaluate_spread` с дополнительным условием режима `mode`. `threshold` – порог в процентах
+/// (см. `ThresholdMode::percent_threshold`), `tick_size` – шаг цены пары; без него режим
+/// шагов сигналов не дает. Разница ровно на пороге не отсекается из-за погрешности f64
+/// (допуск `price_epsilon()`).
+pub fn evaluate_spread_in_mode(
+    a: f64,
+    b: f64,
+    threshold: f64,
+    mode: ThresholdMode,
+    tick_size: Option<f64>,
+) -> Option<Spread> {
+    let spread = evaluate_spread(a, b, threshold)?;
+    let diff = (a - b).abs() + a.max(b) * price_epsilon();
+    let passes = match mode {
+        ThresholdMode::Percent => true,
+        ThresholdMode::Absolute(min_diff) => diff >= min_diff,
+        ThresholdMode::TickMultiple(ticks) => tick_size.is_some_and(|tick| tick > 0.0 && diff >= ticks * tick),
     };
+    passes.then_some(spread)
+}
 
-    // Сравниваем Bybit с Hyperliquid
-    if bybit_price != 0.0 && hyperliquid_price != 0.0 {
-        let difference = ((bybit_price - hyperliquid_price) / bybit_price).abs() * 100.0;
+/// Спред в процентах, который реально получить, купив `notional` USDT по лучшему ask на `buy`
+/// и продав по лучшему bid на `sell`. None, если стакан неполный или на лучшем уровне
+/// не хватает объема.
+pub fn executable_spread(buy: &BookTop, sell: &BookTop, notional: f64) -> Option<f64> {
+    let (ask, bid) = (buy.ask?, sell.bid?);
+    if !(ask.price > 0.0 && bid.price > 0.0) {
+        return None;
+    }
+    if ask.price * ask.size < notional || bid.price * bid.size < notional {
+        return None;
+    }
+    Some((bid.price - ask.price) / ask.price * 100.0)
+}
 
-        if difference >= 5.0 {
-            let message = format!(
-                ">5.0%: {}, bybit price: {}, hyperliquid price: {}, difference: {:.5}%",
-                symbol, bybit_price, hyperliquid_price, difference
+/// Проверка по стакану (DEPTH_CHECK_NOTIONAL_USDT): держится ли спред между `a` и `b` не ниже
+/// `threshold` на лучших bid/ask. Если проверка выключена – всегда true.
+fn depth_confirms(
+    shared_state: &SharedState,
+    symbol: &str,
+    (a, b): (&str, &str),
+    direction: SpreadDirection,
+    threshold: f64,
+) -> bool {
+    let Some(notional) = shared_state.depth_check_notional else {
+        return true;
+    };
+    let (buy, sell) = direction.buy_sell(a, b);
+    let max_age = shared_state.max_price_age;
+    // Покупаем по ask, продаем по bid: свежими должны быть именно эти стороны
+    let buy_book = shared_state
+        .get_book(buy, symbol)
+        .filter(|book| book.ask_updated_at.elapsed() <= max_age);
+    let sell_book = shared_state
+        .get_book(sell, symbol)
+        .filter(|book| book.bid_updated_at.elapsed() <= max_age);
+    let (Some(buy_book), Some(sell_book)) = (buy_book, sell_book) else {
+        debug!("No fresh order book for {} on {}/{}, skipping signal", symbol, buy, sell);
+        return false;
+    };
+    match executable_spread(&buy_book, &sell_book, notional) {
+        Some(spread) if spread >= threshold => true,
+        spread => {
+            debug!(
+                "{} spread buy {} / sell {} is not executable for {} USDT: {:?} (need {}%)",
+                symbol, buy, sell, notional, spread, threshold
             );
-            
-            // Логируем в консоль
-            info!("{}", message);
-            
-            // Отправляем в Telegram, если доступно
-            if let Some(telegram) = &shared_state.telegram {
-                telegram
-                    .send_arbitrage_opportunity(symbol, bybit_price, hyperliquid_price, "Hyperliquid", difference)
-                    .await;
-            }
+            false
         }
     }
+}
 
-    // Сравниваем Bybit с ASTER
-    if bybit_price != 0.0 && aster_price != 0.0 {
-        let difference = ((bybit_price - aster_price) / bybit_price).abs() * 100.0;
+/// Комиссии тейкера на обеих ногах пары бирж, в процентах.
+fn pair_fees(shared_state: &SharedState, (a, b): (&str, &str)) -> f64 {
+    shared_state.taker_fee_percent(a) + shared_state.taker_fee_percent(b)
+}
 
-        if difference >= 5.0 {
-            let message = format!(
-                ">5.0%: {}, bybit price: {}, aster price: {}, difference: {:.5}%",
-                symbol, bybit_price, aster_price, difference
-            );
-            
-            // Логируем в консоль
-            info!("{}", message);
-            
-            // Отправляем в Telegram, если доступно
-            if let Some(telegram) = &shared_state.telegram {
-                telegram
-                    .send_arbitrage_opportunity(symbol, bybit_price, aster_price, "ASTER", difference)
-                    .await;
-            }
+/// Порог по валовому спреду пары: min_spread после вычета комиссий на обеих ногах
+/// (в режимах абсолютной разницы и шагов цены – хотя бы покрыть комиссии).
+/// Один и тот же для сигналов и для сделок BingX.
+fn gross_threshold(shared_state: &SharedState, pair: (&str, &str), min_spread: f64) -> f64 {
+    shared_state.threshold_mode.percent_threshold(min_spread) + pair_fees(shared_state, pair)
+}
+
+/// Сравнивает цены двух бирж и сообщает о возможности, если чистый спред
+/// (разница минус комиссии тейкера на обеих ногах) не меньше `min_spread`.
+async fn check_pair(
+    shared_state: &Arc<SharedState>,
+    symbol: &str,
+    (reference_name, reference_price): (&str, f64),
+    (other_name, other_price): (&str, f64),
+    min_spread: f64,
+) {
+    // Неправдоподобный спред – ошибка данных (новый или делистнутый символ): ни статистики, ни сигнала
+    let observed = evaluate_spread(reference_price, other_price, 0.0).map_or(0.0, |s| s.percent);
+    if !shared_state.is_plausible_spread(observed) {
+        warn!(
+            "Suppressing implausible {:.2}% spread for {}: {} {} vs {} {} (MAX_PLAUSIBLE_SPREAD_PERCENT)",
+            observed, symbol, reference_name, reference_price, other_name, other_price
+        );
+        return;
+    }
+    // В статистику идет любой спред пары со свежими ценами, в том числе ниже порога
+    shared_state.spread_stats.record(symbol, observed);
+
+    let fees = pair_fees(shared_state, (reference_name, other_name));
+    let mode = shared_state.threshold_mode;
+    let threshold = gross_threshold(shared_state, (reference_name, other_name), min_spread);
+    let tick_size = shared_state.tick_size(symbol, (reference_name, other_name));
+    let Some(spread) = evaluate_spread_in_mode(reference_price, other_price, threshold, mode, tick_size) else {
+        return;
+    };
+    let difference = spread.percent;
+    let net_difference = difference - fees;
+    if net_difference <= 0.0 {
+        return;
+    }
+    if !depth_confirms(shared_state, symbol, (reference_name, other_name), spread.direction, threshold) {
+        return;
+    }
+    let (buy_exchange, sell_exchange) = spread.direction.buy_sell(reference_name, other_name);
+    let tier = shared_state.alert_tiers.classify(net_difference, min_spread);
+    let decimals = shared_state.price_decimals(symbol, (reference_name, other_name));
+
+    // Логируем структурированными полями: в LOG_FORMAT=json каждое поле – отдельный ключ.
+    // Уровень лога следует уровню сигнала: INFO – info, WARN – warn, URGENT – error
+    macro_rules! log_opportunity {
+        ($level:expr) => {
+            tracing::event!(
+                $level,
+                symbol,
+                tier = tier.label(),
+                reference_exchange = reference_name,
+                reference_price,
+                other_exchange = other_name,
+                other_price,
+                difference,
+                net_difference,
+                min_spread,
+                buy_exchange,
+                sell_exchange,
+                "Arbitrage opportunity: {} {:.*} vs {} {:.*}, buy on {} / sell on {}",
+                reference_name,
+                decimals,
+                reference_price,
+                other_name,
+                decimals,
+                other_price,
+                buy_exchange,
+                sell_exchange
+            )
+        };
+    }
+    match tier {
+        AlertTier::Info => log_opportunity!(tracing::Level::INFO),
+        AlertTier::Warn => log_opportunity!(tracing::Level::WARN),
+        AlertTier::Urgent => log_opportunity!(tracing::Level::ERROR),
+    }
+    record_alert();
+
+    if let Some(opportunity_log) = &shared_state.opportunity_log {
+        opportunity_log.record(symbol, (reference_name, reference_price), (other_name, other_price), difference);
+    }
+    if let Some(database) = &shared_state.database {
+        database.record_opportunity(symbol, reference_name, other_name, difference);
+    }
+
+    // Отправляем в Telegram, если доступно: в сводку или сразу
+    if let Some(telegram) = &shared_state.telegram {
+        if let Some(alert_digest) = &shared_state.alert_digest {
+            alert_digest.push(PendingAlert {
+                symbol: symbol.to_string(),
+                reference_name: reference_name.to_string(),
+                reference_price,
+                other_name: other_name.to_string(),
+                other_price,
+                price_decimals: decimals,
+                difference,
+                net_difference,
+                tier,
+                hits: 1,
+            });
+        } else if let Err(e) = telegram
+            .send_arbitrage_opportunity(
+                symbol,
+                reference_name,
+                reference_price,
+                other_price,
+                other_name,
+                difference,
+                net_difference,
+                spread.direction,
+                tier,
+                decimals,
+            )
+            .await
+        {
+            error!("Failed to send Telegram alert for {}: {}", symbol, e);
         }
     }
+}
 
-    // Если инициализирован клиент BingX – пробуем автоматически открыть позицию по заданным правилам.
-    // Открываем позицию только если есть арбитражная возможность (разница >= 5.0%) хотя бы с одним DEX
-    if let Some(bingx) = &shared_state.bingx {
-        // Проверяем, есть ли арбитражная возможность хотя бы с одним DEX
-        let hyperliquid_diff = if bybit_price != 0.0 && hyperliquid_price != 0.0 {
-            ((bybit_price - hyperliquid_price) / bybit_price).abs() * 100.0
-        } else {
-            0.0
+pub async fn compare_prices(
+    shared_state: &Arc<SharedState>,
+    symbol: &str,
+    hedge: Option<&HedgeExecutor>,
+) -> Result<(), Box<dyn error::Error>> {
+    // Пропускаем токены из списка исключений
+    if is_excluded(symbol) {
+        return Ok(());
+    }
+    let max_age = shared_state.max_price_age;
+
+    // Только биржи со свежей ценой: без цены пара не сравнивается
+    let prices: HashMap<&str, f64> = shared_state
+        .compared_venues
+        .iter()
+        .filter_map(|&venue| Some((venue, fresh_price(shared_state.get_price(venue, symbol), max_age, venue, symbol)?)))
+        .collect();
+    let price = |venue: &str| prices.get(venue).copied();
+
+    let min_spread = shared_state.min_spread_for(symbol);
+
+    // Сравниваем биржи попарно относительно REFERENCE_VENUE (или все пары)
+    for &(reference_name, other_name) in &shared_state.comparison_pairs {
+        let (Some(reference_price), Some(other_price)) = (price(reference_name), price(other_name)) else {
+            continue;
+        };
+        check_pair(shared_state, symbol, (reference_name, reference_price), (other_name, other_price), min_spread).await;
+    }
+
+    if let Some(hedge) = hedge.filter(|_| !shared_state.is_trading_paused()) {
+        try_hedge(shared_state, hedge, symbol, min_spread).await;
+    }
+
+    // Если инициализирован клиент BingX, торговля включена (ENABLE_BINGX_TRADING) и не на паузе –
+    // пробуем автоматически открыть позицию по заданным правилам.
+    // Открываем позицию только если есть арбитражная возможность (разница >= min_spread) хотя бы с одним DEX
+    let bingx = shared_state
+        .bingx
+        .as_ref()
+        .filter(|_| shared_state.bingx_trading_enabled && !shared_state.is_trading_paused());
+    if let Some(bingx) = bingx {
+        // Размер и направление сделки считаются от цены референсной биржи; без нее BingX не торгуем
+        let reference = shared_state.reference_venue.trading_reference();
+        let Some(reference_price) = fresh_price(shared_state.get_price(reference, symbol), max_age, reference, symbol)
+        else {
+            return Ok(());
         };
-        
-        let aster_diff = if bybit_price != 0.0 && aster_price != 0.0 {
-            ((bybit_price - aster_price) / bybit_price).abs() * 100.0
-        } else {
-            0.0
+        let hyperliquid_price = price(HYPERLIQUID);
+        let aster_price = price(ASTER);
+
+        // Проверяем, есть ли арбитражная возможность хотя бы с одним DEX
+        let mode = shared_state.threshold_mode;
+        let spread_with = |venue: &str, price: Option<f64>| {
+            let threshold = gross_threshold(shared_state, (reference, venue), min_spread);
+            let tick_size = shared_state.tick_size(symbol, (reference, venue));
+            evaluate_spread_in_mode(reference_price, price?, threshold, mode, tick_size)
+                // Неправдоподобный спред не торгуем – предупреждение уже в логе пары
+                .filter(|s| shared_state.is_plausible_spread(s.percent))
+                .filter(|s| depth_confirms(shared_state, symbol, (reference, venue), s.direction, threshold))
         };
-        
-        // Открываем позицию если разница >= 5.0% хотя бы с одним DEX
-        if hyperliquid_diff >= 5.0 || aster_diff >= 5.0 {
-            match bingx
-                .handle_arbitrage_opportunity(symbol, bybit_price, hyperliquid_price, aster_price)
-                .await
-            {
+        let hyperliquid_spread = spread_with(HYPERLIQUID, hyperliquid_price);
+        let aster_spread = spread_with(ASTER, aster_price);
+
+        // Открываем позицию если разница >= min_spread хотя бы с одним DEX
+        if hyperliquid_spread.is_some() || aster_spread.is_some() {
+            // Позицию меньше минимальной стоимости ордера BingX отклонит – не тратим запросы
+            let outcome = match bingx.min_notional_skip(symbol).await {
+                Some(reason) => Ok(BingXTradeOutcome::Skipped { reason }),
+                None => {
+                    bingx
+                        .handle_arbitrage_opportunity(symbol, reference_price, hyperliquid_price, aster_price)
+                        .await
+                }
+            };
+            match outcome {
                 Ok(BingXTradeOutcome::Opened {
                     symbol: opened_symbol,
                     direction,
@@ -118,14 +503,25 @@ pub async fn compare_prices(
                     leverage,
                     entry_price,
                     take_profit_price,
+                    take_profit_placed,
+                    stop_loss_price,
+                    stop_loss_placed,
+                    trailing_stop_percent,
+                    trailing_stop_placed,
                 }) => {
                     info!(
-                        "BingX position opened: symbol={}, direction={}, qty={}, leverage={}, entry_price={}, take_profit_price={}",
-                        opened_symbol, direction, quantity, leverage, entry_price, take_profit_price
+                        "BingX position opened: symbol={}, direction={}, qty={}, leverage={}, entry_price={}, take_profit_price={:?} (placed={}), stop_loss_price={:?} (placed={}), trailing_stop_percent={:?} (placed={})",
+                        opened_symbol, direction, quantity, leverage, entry_price,
+                        take_profit_price, take_profit_placed, stop_loss_price, stop_loss_placed,
+                        trailing_stop_percent, trailing_stop_placed
                     );
 
                     if let Some(telegram) = &shared_state.telegram {
-                        let max_diff = hyperliquid_diff.max(aster_diff);
+                        let max_diff = [hyperliquid_spread, aster_spread]
+                            .into_iter()
+                            .flatten()
+                            .map(|spread| spread.percent)
+                            .fold(0.0, f64::max);
                         let msg = format!(
                             "✅ <b>BingX position opened</b>\n\n\
                             Symbol: <code>{}</code>\n\
@@ -133,33 +529,109 @@ pub async fn compare_prices(
                             Qty: <code>{:.8}</code>\n\
                             Leverage: <code>{:.0}x</code>\n\
                             Entry Price: <code>{:.8}</code>\n\
-                            Take Profit: <code>{:.8}</code> (+3%)\n\
-                            Bybit: <code>{:.8}</code>\n\
-                            Hyperliquid: <code>{:.8}</code>\n\
-                            ASTER: <code>{:.8}</code>\n\
+                            Take Profit: {}\n\
+                            Stop Loss: {}\n\
+                            Trailing Stop: {}\n\
+                            {}: <code>{:.8}</code>\n\
+                            Hyperliquid: {}\n\
+                            ASTER: {}\n\
                             Max Diff: <code>{:.5}%</code>",
                             opened_symbol,
                             direction,
                             quantity,
                             leverage,
                             entry_price,
-                            take_profit_price,
-                            bybit_price,
-                            hyperliquid_price,
-                            aster_price,
+                            protective_order_status(take_profit_price, take_profit_placed),
+                            protective_order_status(stop_loss_price, stop_loss_placed),
+                            trailing_stop_status(trailing_stop_percent, trailing_stop_placed),
+                            reference,
+                            reference_price,
+                            optional_price(hyperliquid_price),
+                            optional_price(aster_price),
                             max_diff
                         );
-                        telegram.send_message(&msg).await;
+                        if let Err(e) = telegram.send_message(&msg).await {
+                            error!("Failed to send Telegram message: {}", e);
+                        }
+                    }
+                }
+                Ok(BingXTradeOutcome::LimitPlaced { symbol: order_symbol, direction, order_id, quantity, price, time_in_force }) => {
+                    info!(
+                        "BingX limit order placed: symbol={}, direction={}, order_id={}, qty={}, price={}, time_in_force={:?}",
+                        order_symbol, direction, order_id, quantity, price, time_in_force
+                    );
+                }
+                Ok(BingXTradeOutcome::Closed { symbol: closed_symbol, direction, quantity }) => {
+                    info!(
+                        "BingX position closed: symbol={}, direction={}, qty={}",
+                        closed_symbol, direction, quantity
+                    );
+                }
+                Ok(BingXTradeOutcome::DryRun { symbol: order_symbol, direction, side, quantity, params }) => {
+                    info!(
+                        "BingX dry run order: symbol={}, direction={}, side={}, qty={}",
+                        order_symbol, direction, side, quantity
+                    );
+
+                    if let Some(telegram) = &shared_state.telegram {
+                        let params = params
+                            .iter()
+                            .map(|(key, value)| format!("{}={}", key, value))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let msg = format!(
+                            "🧪 <b>BingX dry run</b>\n\n\
+                            Symbol: <code>{}</code>\n\
+                            Side: <code>{} {}</code>\n\
+                            Qty: <code>{:.8}</code>\n\
+                            Params: <code>{}</code>",
+                            order_symbol,
+                            side,
+                            direction,
+                            quantity,
+                            TelegramNotifier::escape_html(&params)
+                        );
+                        if let Err(e) = telegram.send_message(&msg).await {
+                            error!("Failed to send Telegram message: {}", e);
+                        }
                     }
                 }
                 Ok(BingXTradeOutcome::Skipped { reason }) => {
                     info!("BingX trade skipped for {}: {}", symbol, reason);
+
+                    let telegram = shared_state.telegram.as_ref().filter(|_| shared_state.should_notify_trade(symbol, "skipped"));
+                    if let Some(telegram) = telegram {
+                        let msg = format!(
+                            "ℹ️ <b>BingX trade skipped</b>\n\n\
+                            Symbol: <code>{}</code>\n\
+                            Reason: {}",
+                            symbol,
+                            TelegramNotifier::escape_html(&reason)
+                        );
+                        if let Err(e) = telegram.send_message(&msg).await {
+                            error!("Failed to send Telegram message: {}", e);
+                        }
+                    }
                 }
                 Err(e) => {
                     error!(
                         "Failed to handle arbitrage opportunity on BingX for {}: {}",
                         symbol, e
                     );
+
+                    let telegram = shared_state.telegram.as_ref().filter(|_| shared_state.should_notify_trade(symbol, "failed"));
+                    if let Some(telegram) = telegram {
+                        let msg = format!(
+                            "❌ <b>BingX trade failed</b>\n\n\
+                            Symbol: <code>{}</code>\n\
+                            Error: {}",
+                            symbol,
+                            TelegramNotifier::escape_html(&e.to_string())
+                        );
+                        if let Err(e) = telegram.send_message(&msg).await {
+                            error!("Failed to send Telegram message: {}", e);
+                        }
+                    }
                 }
             }
         }
@@ -167,3 +639,279 @@ pub async fn compare_prices(
 
     Ok(())
 }
+
+/// Хедж (HEDGE_NOTIONAL_USDT): если спред между фидами двух бирж хеджа проходит тот же порог,
+/// что и сделки BingX, покупаем на дешевой бирже и продаем на дорогой.
+async fn try_hedge(shared_state: &SharedState, hedge: &HedgeExecutor, symbol: &str, min_spread: f64) {
+    if hedge.is_open(symbol) || hedge.is_cooling_down(symbol) {
+        return;
+    }
+    let (a, b) = hedge.venues();
+    let pair = (a.price_feed(), b.price_feed());
+    let max_age = shared_state.max_price_age;
+    let (Some(a_price), Some(b_price)) = (
+        fresh_price(shared_state.get_price(pair.0, symbol), max_age, pair.0, symbol),
+        fresh_price(shared_state.get_price(pair.1, symbol), max_age, pair.1, symbol),
+    ) else {
+        return;
+    };
+
+    let threshold = gross_threshold(shared_state, pair, min_spread);
+    let tick_size = shared_state.tick_size(symbol, pair);
+    let Some(spread) = evaluate_spread_in_mode(a_price, b_price, threshold, shared_state.threshold_mode, tick_size)
+        .filter(|s| shared_state.is_plausible_spread(s.percent))
+        .filter(|s| depth_confirms(shared_state, symbol, pair, s.direction, threshold))
+    else {
+        return;
+    };
+    let (long, short) = match spread.direction {
+        SpreadDirection::AAboveB => (b, a),
+        SpreadDirection::BAboveA => (a, b),
+    };
+
+    let msg = match hedge.execute_hedge(symbol, long, short, hedge.notional()).await {
+        Ok(result) => hedge_message(&result, spread.percent),
+        Err(e) => {
+            error!("Failed to open hedge for {}: {}", symbol, e);
+            format!(
+                "❌ <b>Hedge failed</b>\n\n\
+                Symbol: <code>{}</code>\n\
+                Error: {}",
+                symbol,
+                TelegramNotifier::escape_html(&e.to_string())
+            )
+        }
+    };
+    if let Some(telegram) = &shared_state.telegram {
+        if let Err(e) = telegram.send_message(&msg).await {
+            error!("Failed to send Telegram message: {}", e);
+        }
+    }
+}
+
+/// Сообщение Telegram об исходе хеджа с состоянием каждой ноги.
+fn hedge_message(result: &HedgeResult, spread_percent: f64) -> String {
+    let title = if result.is_hedged() {
+        "✅ <b>Hedge opened</b>"
+    } else if result.has_naked_exposure() {
+        "🚨 <b>Hedge left an unhedged position</b> – manual action required"
+    } else {
+        "❌ <b>Hedge not opened</b>"
+    };
+    let leg = |leg: &HedgeLeg| {
+        format!(
+            "{} {}: <code>{}</code>",
+            leg.direction,
+            leg.venue.name(),
+            TelegramNotifier::escape_html(&format!("{:?}", leg.status))
+        )
+    };
+    format!(
+        "{}\n\n\
+        Symbol: <code>{}</code>\n\
+        Qty: <code>{:.8}</code>\n\
+        Spread: <code>{:.5}%</code>\n\
+        {}\n\
+        {}",
+        title,
+        result.symbol,
+        result.quantity,
+        spread_percent,
+        leg(&result.long),
+        leg(&result.short)
+    )
+}
+
+/// Раз в `compare_interval` сравнивает цены по всем общим тикерам.
+///
+/// Не зависит от частоты сообщений конкретного фида: если Bybit замолчал, расхождения
+/// между остальными биржами все равно обнаруживаются. Остановка проверяется между
+/// проходами, так что начатый проход (и ордер на BingX) доводится до конца.
+pub async fn compare_periodically(
+    shared_state: &Arc<SharedState>,
+    hedge: Option<&HedgeExecutor>,
+    symbols: &[String],
+    mut shutdown: Shutdown,
+) {
+    let mut interval = tokio::time::interval(shared_state.compare_interval);
+    // Если проход затянулся, не догоняем пропущенные тики пачкой
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    info!(
+        "Comparing {} tickers every {:?}",
+        symbols.len(),
+        shared_state.compare_interval
+    );
+
+    loop {
+        tokio::select! {
+            _ = wait_for_shutdown(&mut shutdown) => {
+                info!("Periodic price comparison stopped");
+                return;
+            }
+            _ = interval.tick() => {}
+        }
+
+        for symbol in symbols {
+            if let Err(e) = compare_prices(shared_state, symbol, hedge).await {
+                error!("Failed comparing price for {}: {}", symbol, e);
+            }
+        }
+    }
+}
+
+/// Цена биржи для Telegram или прочерк, если свежей цены нет.
+fn optional_price(price: Option<f64>) -> String {
+    match price {
+        Some(price) => format!("<code>{:.8}</code>", price),
+        None => "—".to_string(),
+    }
+}
+
+/// Строка статуса TP/SL для Telegram: цена и предупреждение, если ордер не выставлен.
+fn protective_order_status(price: Option<f64>, placed: bool) -> String {
+    match price {
+        None => "—".to_string(),
+        Some(price) if placed => format!("<code>{:.8}</code>", price),
+        Some(price) => format!("<code>{:.8}</code> ⚠️ not placed", price),
+    }
+}
+
+/// Строка статуса трейлинг-стопа для Telegram: откат и предупреждение, если ордер не выставлен.
+fn trailing_stop_status(percent: Option<f64>, placed: bool) -> String {
+    match percent {
+        None => "—".to_string(),
+        Some(percent) if placed => format!("<code>{}%</code>", percent),
+        Some(percent) => format!("<code>{}%</code> ⚠️ not placed", percent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_prices_give_no_spread() {
+        assert_eq!(evaluate_spread(0.0, 100.0, 1.0), None);
+        assert_eq!(evaluate_spread(100.0, 0.0, 1.0), None);
+        assert_eq!(evaluate_spread(0.0, 0.0, 0.0), None);
+    }
+
+    #[test]
+    fn equal_prices_give_no_spread() {
+        assert_eq!(evaluate_spread(100.0, 100.0, 0.0), None);
+    }
+
+    #[test]
+    fn spread_is_measured_from_first_price() {
+        let spread = evaluate_spread(100.0, 90.0, 5.0).unwrap();
+        assert!((spread.percent - 10.0).abs() < 1e-9);
+        assert_eq!(spread.direction, SpreadDirection::AAboveB);
+
+        let spread = evaluate_spread(100.0, 110.0, 5.0).unwrap();
+        assert!((spread.percent - 10.0).abs() < 1e-9);
+        assert_eq!(spread.direction, SpreadDirection::BAboveA);
+        assert_eq!(spread.direction.buy_sell(BYBIT, ASTER), (BYBIT, ASTER));
+        assert_eq!(SpreadDirection::AAboveB.buy_sell(BYBIT, ASTER), (ASTER, BYBIT));
+    }
+
+    #[test]
+    fn percent_mode_boundary() {
+        let mode = ThresholdMode::Percent;
+        let threshold = mode.percent_threshold(5.0);
+        assert!(evaluate_spread_in_mode(100.0, 105.0, threshold, mode, None).is_some());
+        assert!(evaluate_spread_in_mode(100.0, 104.99, threshold, mode, None).is_none());
+    }
+
+    #[test]
+    fn absolute_mode_boundary() {
+        let mode = ThresholdMode::Absolute(50.0);
+        let threshold = mode.percent_threshold(5.0);
+        // 0.08% от цены BTC, но ровно 50 USDT
+        assert!(evaluate_spread_in_mode(65000.0, 65050.0, threshold, mode, None).is_some());
+        assert!(evaluate_spread_in_mode(65000.0, 65049.99, threshold, mode, None).is_none());
+    }
+
+    #[test]
+    fn tick_multiple_mode_boundary() {
+        let mode = ThresholdMode::TickMultiple(3.0);
+        let threshold = mode.percent_threshold(5.0);
+        let tick = Some(0.0001);
+        assert!(evaluate_spread_in_mode(0.0123, 0.0126, threshold, mode, tick).is_some());
+        assert!(evaluate_spread_in_mode(0.0123, 0.0125, threshold, mode, tick).is_none());
+        assert!(evaluate_spread_in_mode(0.0123, 0.0126, threshold, mode, None).is_none());
+    }
+
+    #[test]
+    fn comparison_pairs_follow_reference_venue() {
+        use crate::share_state::BINANCE;
+
+        let venues = [BYBIT, HYPERLIQUID, ASTER, BINANCE];
+        assert_eq!(
+            ReferenceVenue::Venue(BINANCE).comparison_pairs(&venues),
+            vec![(BINANCE, BYBIT), (BINANCE, HYPERLIQUID), (BINANCE, ASTER)]
+        );
+        assert_eq!(
+            ReferenceVenue::All.comparison_pairs(&venues[..3]),
+            vec![(BYBIT, HYPERLIQUID), (BYBIT, ASTER), (HYPERLIQUID, ASTER)]
+        );
+        assert_eq!(ReferenceVenue::All.trading_reference(), BYBIT);
+        assert_eq!(ReferenceVenue::Venue(HYPERLIQUID).trading_reference(), HYPERLIQUID);
+    }
+
+    #[test]
+    fn threshold_boundary_is_inclusive() {
+        assert!(evaluate_spread(100.0, 95.0, 5.0).is_some());
+        assert!(evaluate_spread(100.0, 95.0, 5.000001).is_none());
+        assert!(evaluate_spread(100.0, 95.1, 5.0).is_none());
+    }
+
+    #[test]
+    fn sub_penny_prices() {
+        // Соседние цены в доли цента – это спред почти 0.1%, а не шум
+        let spread = evaluate_spread(0.00001234, 0.00001235, 0.05).unwrap();
+        assert!((spread.percent - 0.081037).abs() < 1e-5);
+        assert!(evaluate_spread(0.00001234, 0.00001235, 0.1).is_none());
+        // Разница на уровне погрешности f64 – равные цены
+        assert!(prices_equal(0.1 + 0.2, 0.3));
+        assert!(!prices_equal(0.00001234, 0.00001235));
+        assert_eq!(evaluate_spread(0.1 + 0.2, 0.3, 0.0), None);
+    }
+
+    #[test]
+    fn negative_and_non_finite_inputs_give_no_spread() {
+        assert_eq!(evaluate_spread(-100.0, 90.0, 1.0), None);
+        assert_eq!(evaluate_spread(100.0, -90.0, 1.0), None);
+        assert_eq!(evaluate_spread(f64::NAN, 90.0, 1.0), None);
+        assert_eq!(evaluate_spread(100.0, f64::NAN, 1.0), None);
+        assert_eq!(evaluate_spread(f64::INFINITY, 90.0, 1.0), None);
+        assert_eq!(evaluate_spread(100.0, 90.0, f64::NAN), None);
+    }
+
+    #[test]
+    fn trade_threshold_includes_fees_of_both_legs() {
+        let state = SharedState::new(&crate::config::Config::default(), None);
+        // Bybit 0.055% + Hyperliquid 0.045% по умолчанию
+        let threshold = gross_threshold(&state, (BYBIT, HYPERLIQUID), 0.5);
+        assert!((threshold - 0.6).abs() < 1e-9, "{}", threshold);
+    }
+
+    #[test]
+    fn executable_spread_needs_size_on_top_level() {
+        use crate::share_state::BookLevel;
+        use std::time::Instant;
+
+        let book = |bid: (f64, f64), ask: (f64, f64)| BookTop {
+            bid: Some(BookLevel { price: bid.0, size: bid.1 }),
+            ask: Some(BookLevel { price: ask.0, size: ask.1 }),
+            bid_updated_at: Instant::now(),
+            ask_updated_at: Instant::now(),
+        };
+        let cheap = book((99.0, 10.0), (100.0, 10.0));
+        let rich = book((105.0, 10.0), (106.0, 10.0));
+
+        let spread = executable_spread(&cheap, &rich, 500.0).unwrap();
+        assert!((spread - 5.0).abs() < 1e-9);
+        // 10 монет по 100 – только 1000 USDT на лучшем ask
+        assert_eq!(executable_spread(&cheap, &rich, 2000.0), None);
+    }
+}
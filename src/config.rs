@@ -0,0 +1,576 @@
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::share_state::EXCHANGES;
+
+/// Файл, который читается, если путь не задан ни `--config`, ни CONFIG_PATH.
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Настройки бота из `config.toml`.
+///
+/// Каждое поле необязательно: если его нет в файле, берется прежняя переменная окружения
+/// (она указана в комментарии к полю), а если нет и ее – значение по умолчанию модуля.
+/// Так секреты можно держать только в окружении или `.env`, а остальное – в файле.
+///
+/// Логирование (LOG_FILE, LOG_FORMAT) настраивается только через окружение: оно
+/// инициализируется раньше, чем читается конфиг.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Фиды бирж, которые запускаются (EXCHANGES, через запятую). По умолчанию – все.
+    pub exchanges: Option<Vec<String>>,
+    /// Останавливать бот, если одна из бирж пересечения не вернула тикеры (REQUIRE_ALL_EXCHANGES).
+    /// По умолчанию true; false – продолжить с остальными биржами.
+    pub require_all_exchanges: Option<bool>,
+    /// Котируемая валюта тикеров (QUOTE_CURRENCY), по умолчанию USDT. Например, USDC для
+    /// рынков с маржой в USDC.
+    pub quote_currency: Option<String>,
+    pub thresholds: ThresholdsConfig,
+    /// Пороги MIN_SPREAD_PERCENT для отдельных символов, например `BTCUSDT = 0.05`
+    /// (SYMBOL_THRESHOLDS, `BTCUSDT=0.05,DOGEUSDT=0.3`).
+    pub symbol_thresholds: Option<HashMap<String, f64>>,
+    pub trading: TradingConfig,
+    pub fees: FeesConfig,
+    /// Дополнительные исключенные токены (EXCLUDED_TOKENS, через запятую).
+    pub excluded_tokens: Option<Vec<String>>,
+    /// Белый список токенов (INCLUDED_TOKENS, через запятую): если задан, отслеживаются только
+    /// общие тикеры из него; исключения EXCLUDED_TOKENS действуют поверх.
+    pub included_tokens: Option<Vec<String>>,
+    pub alerts: AlertsConfig,
+    pub storage: StorageConfig,
+    pub monitoring: MonitoringConfig,
+    pub proxy: ProxyConfig,
+    pub telegram: TelegramConfig,
+    pub bingx: BingXConfig,
+    pub bybit: BybitConfig,
+    pub aster: AsterConfig,
+    pub hyperliquid: HyperliquidConfig,
+    /// Тайминги WebSocket-фидов по биржам: секции `[feeds.<биржа>]`, имя без учета регистра.
+    pub feeds: HashMap<String, FeedConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThresholdsConfig {
+    /// MIN_SPREAD_PERCENT
+    pub min_spread_percent: Option<f64>,
+    /// THRESHOLD_MODE – `percent` (по умолчанию), `absolute` или `ticks`
+    pub mode: Option<String>,
+    /// MIN_SPREAD_ABSOLUTE – порог разницы цен в USDT для режима `absolute`
+    pub min_spread_absolute: Option<f64>,
+    /// MIN_SPREAD_TICKS – порог разницы в шагах цены для режима `ticks`
+    pub min_spread_ticks: Option<f64>,
+    /// MAX_PRICE_AGE_SECS
+    pub max_price_age_secs: Option<u64>,
+    /// REFERENCE_VENUE – биржа, от цены которой считается спред (по умолчанию Bybit);
+    /// `all` – сравнивать все пары бирж
+    pub reference_venue: Option<String>,
+    /// PRICE_EPSILON – относительный допуск, в пределах которого цены считаются равными (по умолчанию 1e-9)
+    pub price_epsilon: Option<f64>,
+    /// COMPARE_INTERVAL_MS
+    pub compare_interval_ms: Option<u64>,
+    /// DEPTH_CHECK_NOTIONAL_USDT – объем сделки для проверки спреда по стакану; не задан – проверка выключена
+    pub depth_check_notional_usdt: Option<f64>,
+    /// MIN_FUNDING_DIFF – разница фандинга за 8 часов в процентах; не задан – сравнение фандинга выключено
+    pub min_funding_diff_percent: Option<f64>,
+    /// MAX_PRICE_JUMP_PERCENT – допустимый скачок цены между тиками одной биржи; 0 – проверка выключена
+    pub max_price_jump_percent: Option<f64>,
+    /// MAX_PLAUSIBLE_SPREAD_PERCENT – больший спред считается ошибкой данных и не дает сигналов; 0 – без ограничения
+    pub max_plausible_spread_percent: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TradingConfig {
+    /// ENABLE_BINGX_TRADING
+    pub enable_bingx_trading: Option<bool>,
+    /// DAILY_LOSS_LIMIT_USDT
+    pub daily_loss_limit_usdt: Option<f64>,
+    /// HEDGE_NOTIONAL_USDT – объем каждой ноги хеджа в USDT; не задан – хедж выключен
+    pub hedge_notional_usdt: Option<f64>,
+    /// HEDGE_VENUES – две биржи хеджа через запятую из BingX, Bybit, Hyperliquid
+    /// (по умолчанию Bybit и Hyperliquid)
+    pub hedge_venues: Option<Vec<String>>,
+}
+
+/// Комиссии тейкера в процентах.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeesConfig {
+    /// BYBIT_TAKER_FEE_PERCENT
+    pub bybit_taker_percent: Option<f64>,
+    /// HYPERLIQUID_TAKER_FEE_PERCENT
+    pub hyperliquid_taker_percent: Option<f64>,
+    /// ASTER_TAKER_FEE_PERCENT
+    pub aster_taker_percent: Option<f64>,
+    /// BINANCE_TAKER_FEE_PERCENT
+    pub binance_taker_percent: Option<f64>,
+    /// OKX_TAKER_FEE_PERCENT
+    pub okx_taker_percent: Option<f64>,
+    /// KUCOIN_TAKER_FEE_PERCENT
+    pub kucoin_taker_percent: Option<f64>,
+    /// GATE_TAKER_FEE_PERCENT
+    pub gate_taker_percent: Option<f64>,
+    /// MEXC_TAKER_FEE_PERCENT
+    pub mexc_taker_percent: Option<f64>,
+    /// DYDX_TAKER_FEE_PERCENT
+    pub dydx_taker_percent: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// ALERT_DIGEST_SECS
+    pub digest_secs: Option<u64>,
+    /// ALERT_WARN_MULTIPLIER – во сколько раз спред выше порога для уровня WARN (по умолчанию 2)
+    pub warn_multiplier: Option<f64>,
+    /// ALERT_URGENT_MULTIPLIER – то же для уровня URGENT (по умолчанию 5)
+    pub urgent_multiplier: Option<f64>,
+    /// ALERT_PRICE_DECIMALS – знаков после запятой в ценах сигналов, если шаг цены
+    /// символа не известен (по умолчанию 8)
+    pub price_decimals: Option<usize>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// OPPORTUNITY_LOG
+    pub opportunity_log: Option<String>,
+    /// DATABASE_PATH
+    pub database_path: Option<String>,
+    /// DATABASE_SNAPSHOT_INTERVAL_SECS
+    pub database_snapshot_interval_secs: Option<u64>,
+    /// RECORD_PATH – NDJSON-файл для записи всех цен (для --replay)
+    pub record_path: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MonitoringConfig {
+    /// METRICS_ADDR
+    pub metrics_addr: Option<String>,
+    /// HEALTH_MAX_AGE_SECS
+    pub health_max_age_secs: Option<u64>,
+    /// STATUS_LOG_SECS – интервал строки состояния фидов в логе, 0 – отключить
+    pub status_log_secs: Option<u64>,
+}
+
+/// Прокси для исходящих соединений: `http://` или `socks5://` (для HTTPS_PROXY еще `https://`),
+/// с `user:password@` при необходимости.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProxyConfig {
+    /// HTTPS_PROXY – прокси для REST-запросов к биржам и Telegram
+    pub https: Option<String>,
+    /// WS_PROXY – прокси для WebSocket-фидов; не задан – используется HTTPS_PROXY
+    pub ws: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelegramConfig {
+    /// TELEGRAM_BOT_TOKEN
+    pub bot_token: Option<String>,
+    /// TELEGRAM_CHAT_ID (через запятую)
+    pub chat_ids: Option<Vec<String>>,
+    /// TELEGRAM_PARSE_MODE: "html" или "markdownv2"
+    pub parse_mode: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BingXConfig {
+    /// BINGX_BASE_URL
+    pub base_url: Option<String>,
+    /// BINGX_API_KEY
+    pub api_key: Option<String>,
+    /// BINGX_API_SECRET
+    pub api_secret: Option<String>,
+    /// BINGX_DEPOSIT_FRACTION
+    pub deposit_fraction: Option<f64>,
+    /// BINGX_LEVERAGE
+    pub leverage: Option<u32>,
+    /// BINGX_RECV_WINDOW_MS
+    pub recv_window_ms: Option<u64>,
+    /// BINGX_RETRYABLE_CODES (через запятую)
+    pub retryable_codes: Option<Vec<i32>>,
+    /// BINGX_TAKE_PROFIT_PERCENT
+    pub take_profit_percent: Option<f64>,
+    /// BINGX_STOP_LOSS_PERCENT
+    pub stop_loss_percent: Option<f64>,
+    /// BINGX_TRAILING_PCT – откат трейлинг-стопа в процентах; не задан – без трейлинг-стопа
+    pub trailing_percent: Option<f64>,
+    /// BINGX_DRY_RUN
+    pub dry_run: Option<bool>,
+    /// BINGX_MIN_BALANCE_USDT
+    pub min_balance_usdt: Option<f64>,
+    /// BINGX_POSITION_CACHE_MS – сколько переиспользовать число открытых позиций; 0 – без кэша
+    pub position_cache_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BybitConfig {
+    /// BYBIT_SUBSCRIBE_BATCH_SIZE
+    pub subscribe_batch_size: Option<usize>,
+    /// BYBIT_STREAM: "tickers" или "kline"
+    pub stream: Option<String>,
+    /// BYBIT_CATEGORY: "linear" (по умолчанию), "spot" или "inverse"
+    pub category: Option<String>,
+    /// BYBIT_API_KEY – только для ордеров (нога хеджа), цены ключей не требуют
+    pub api_key: Option<String>,
+    /// BYBIT_API_SECRET
+    pub api_secret: Option<String>,
+    /// BYBIT_DRY_RUN
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AsterConfig {
+    /// ASTER_API_KEY
+    pub api_key: Option<String>,
+    /// ASTER_API_SECRET
+    pub api_secret: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HyperliquidConfig {
+    /// HYPERLIQUID_PRIVATE_KEY – только для ордеров (нога хеджа), цены ключа не требуют
+    pub private_key: Option<String>,
+    /// HYPERLIQUID_DRY_RUN
+    pub dry_run: Option<bool>,
+}
+
+/// Переменные окружения – с префиксом биржи в верхнем регистре, например `DYDX_HEARTBEAT_TIMEOUT_SECS`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FeedConfig {
+    /// <EXCHANGE>_HEARTBEAT_TIMEOUT_SECS – тишина, после которой соединение считается потерянным
+    pub heartbeat_timeout_secs: Option<u64>,
+    /// <EXCHANGE>_RECONNECT_DELAY_MS – начальная задержка переподключения
+    pub reconnect_delay_ms: Option<u64>,
+}
+
+impl Config {
+    /// Читает конфиг по пути `path` (из `--config`) или CONFIG_PATH, иначе `config.toml`, если он
+    /// есть, и дополняет отсутствующие поля переменными окружения.
+    ///
+    /// Явно заданный, но нечитаемый или некорректный файл – ошибка: молча работать с
+    /// настройками по умолчанию вместо указанных опасно.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let env_path = env::var("CONFIG_PATH").ok();
+        let explicit_path = path.or_else(|| env_path.as_deref().map(Path::new));
+
+        let mut config = match explicit_path {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?,
+            None => Self::default(),
+        };
+        config.apply_env();
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read config {}: {}", path.display(), e))?;
+        let config: Self = toml::from_str(&content)
+            .map_err(|e| format!("invalid config {}: {}", path.display(), e))?;
+        info!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Биржи из `exchanges` без учета регистра (`bybit` – `Bybit`). Если список не задан – все.
+    ///
+    /// Неизвестное имя – ошибка: опечатка не должна молча отключать фид.
+    pub fn enabled_exchanges(&self) -> Result<HashSet<&'static str>, String> {
+        let Some(names) = &self.exchanges else {
+            return Ok(EXCHANGES.iter().copied().collect());
+        };
+
+        let mut enabled = HashSet::new();
+        for name in names.iter().map(|n| n.trim()).filter(|n| !n.is_empty()) {
+            match EXCHANGES.iter().find(|exchange| exchange.eq_ignore_ascii_case(name)) {
+                Some(exchange) => {
+                    enabled.insert(*exchange);
+                }
+                None => {
+                    return Err(format!(
+                        "unknown exchange '{}' (expected one of: {})",
+                        name,
+                        EXCHANGES.join(", ")
+                    ))
+                }
+            }
+        }
+        if enabled.is_empty() {
+            return Err("no exchanges enabled".to_string());
+        }
+        Ok(enabled)
+    }
+
+    /// Настройки фида биржи из `[feeds.<биржа>]` без учета регистра имени.
+    pub fn feed(&self, exchange: &str) -> Option<&FeedConfig> {
+        self.feeds
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(exchange))
+            .map(|(_, feed)| feed)
+    }
+
+    /// Заполняет поля, которых нет в файле, из переменных окружения.
+    fn apply_env(&mut self) {
+        env_list(&mut self.exchanges, "EXCHANGES");
+        env_bool(&mut self.require_all_exchanges, "REQUIRE_ALL_EXCHANGES");
+        env_string(&mut self.quote_currency, "QUOTE_CURRENCY");
+        env_map(&mut self.symbol_thresholds, "SYMBOL_THRESHOLDS");
+
+        let t = &mut self.thresholds;
+        env_value(&mut t.min_spread_percent, "MIN_SPREAD_PERCENT");
+        env_string(&mut t.mode, "THRESHOLD_MODE");
+        env_value(&mut t.min_spread_absolute, "MIN_SPREAD_ABSOLUTE");
+        env_value(&mut t.min_spread_ticks, "MIN_SPREAD_TICKS");
+        env_value(&mut t.max_price_age_secs, "MAX_PRICE_AGE_SECS");
+        env_string(&mut t.reference_venue, "REFERENCE_VENUE");
+        env_value(&mut t.price_epsilon, "PRICE_EPSILON");
+        env_value(&mut t.compare_interval_ms, "COMPARE_INTERVAL_MS");
+        env_value(&mut t.depth_check_notional_usdt, "DEPTH_CHECK_NOTIONAL_USDT");
+        env_value(&mut t.min_funding_diff_percent, "MIN_FUNDING_DIFF");
+        env_value(&mut t.max_price_jump_percent, "MAX_PRICE_JUMP_PERCENT");
+        env_value(&mut t.max_plausible_spread_percent, "MAX_PLAUSIBLE_SPREAD_PERCENT");
+
+        env_bool(&mut self.trading.enable_bingx_trading, "ENABLE_BINGX_TRADING");
+        env_value(&mut self.trading.daily_loss_limit_usdt, "DAILY_LOSS_LIMIT_USDT");
+        env_value(&mut self.trading.hedge_notional_usdt, "HEDGE_NOTIONAL_USDT");
+        env_list(&mut self.trading.hedge_venues, "HEDGE_VENUES");
+
+        let f = &mut self.fees;
+        env_value(&mut f.bybit_taker_percent, "BYBIT_TAKER_FEE_PERCENT");
+        env_value(&mut f.hyperliquid_taker_percent, "HYPERLIQUID_TAKER_FEE_PERCENT");
+        env_value(&mut f.aster_taker_percent, "ASTER_TAKER_FEE_PERCENT");
+        env_value(&mut f.binance_taker_percent, "BINANCE_TAKER_FEE_PERCENT");
+        env_value(&mut f.okx_taker_percent, "OKX_TAKER_FEE_PERCENT");
+        env_value(&mut f.kucoin_taker_percent, "KUCOIN_TAKER_FEE_PERCENT");
+        env_value(&mut f.gate_taker_percent, "GATE_TAKER_FEE_PERCENT");
+        env_value(&mut f.mexc_taker_percent, "MEXC_TAKER_FEE_PERCENT");
+        env_value(&mut f.dydx_taker_percent, "DYDX_TAKER_FEE_PERCENT");
+
+        env_list(&mut self.excluded_tokens, "EXCLUDED_TOKENS");
+        env_list(&mut self.included_tokens, "INCLUDED_TOKENS");
+        env_value(&mut self.alerts.digest_secs, "ALERT_DIGEST_SECS");
+        env_value(&mut self.alerts.warn_multiplier, "ALERT_WARN_MULTIPLIER");
+        env_value(&mut self.alerts.urgent_multiplier, "ALERT_URGENT_MULTIPLIER");
+        env_value(&mut self.alerts.price_decimals, "ALERT_PRICE_DECIMALS");
+
+        let s = &mut self.storage;
+        env_string(&mut s.opportunity_log, "OPPORTUNITY_LOG");
+        env_string(&mut s.database_path, "DATABASE_PATH");
+        env_value(&mut s.database_snapshot_interval_secs, "DATABASE_SNAPSHOT_INTERVAL_SECS");
+        env_string(&mut s.record_path, "RECORD_PATH");
+
+        env_string(&mut self.monitoring.metrics_addr, "METRICS_ADDR");
+        env_value(&mut self.monitoring.health_max_age_secs, "HEALTH_MAX_AGE_SECS");
+        env_value(&mut self.monitoring.status_log_secs, "STATUS_LOG_SECS");
+
+        env_string(&mut self.proxy.https, "HTTPS_PROXY");
+        env_string(&mut self.proxy.ws, "WS_PROXY");
+
+        let tg = &mut self.telegram;
+        env_string(&mut tg.bot_token, "TELEGRAM_BOT_TOKEN");
+        env_list(&mut tg.chat_ids, "TELEGRAM_CHAT_ID");
+        env_string(&mut tg.parse_mode, "TELEGRAM_PARSE_MODE");
+
+        let b = &mut self.bingx;
+        env_string(&mut b.base_url, "BINGX_BASE_URL");
+        env_string(&mut b.api_key, "BINGX_API_KEY");
+        env_string(&mut b.api_secret, "BINGX_API_SECRET");
+        env_value(&mut b.deposit_fraction, "BINGX_DEPOSIT_FRACTION");
+        env_value(&mut b.leverage, "BINGX_LEVERAGE");
+        env_value(&mut b.recv_window_ms, "BINGX_RECV_WINDOW_MS");
+        env_list(&mut b.retryable_codes, "BINGX_RETRYABLE_CODES");
+        env_value(&mut b.take_profit_percent, "BINGX_TAKE_PROFIT_PERCENT");
+        env_value(&mut b.stop_loss_percent, "BINGX_STOP_LOSS_PERCENT");
+        env_value(&mut b.trailing_percent, "BINGX_TRAILING_PCT");
+        env_bool(&mut b.dry_run, "BINGX_DRY_RUN");
+        env_value(&mut b.min_balance_usdt, "BINGX_MIN_BALANCE_USDT");
+        env_value(&mut b.position_cache_ms, "BINGX_POSITION_CACHE_MS");
+
+        env_value(&mut self.bybit.subscribe_batch_size, "BYBIT_SUBSCRIBE_BATCH_SIZE");
+        env_string(&mut self.bybit.stream, "BYBIT_STREAM");
+        env_string(&mut self.bybit.category, "BYBIT_CATEGORY");
+        env_string(&mut self.bybit.api_key, "BYBIT_API_KEY");
+        env_string(&mut self.bybit.api_secret, "BYBIT_API_SECRET");
+        env_bool(&mut self.bybit.dry_run, "BYBIT_DRY_RUN");
+
+        env_string(&mut self.aster.api_key, "ASTER_API_KEY");
+        env_string(&mut self.aster.api_secret, "ASTER_API_SECRET");
+
+        env_string(&mut self.hyperliquid.private_key, "HYPERLIQUID_PRIVATE_KEY");
+        env_bool(&mut self.hyperliquid.dry_run, "HYPERLIQUID_DRY_RUN");
+
+        for exchange in EXCHANGES {
+            let key = self
+                .feeds
+                .keys()
+                .find(|name| name.eq_ignore_ascii_case(exchange))
+                .cloned()
+                .unwrap_or_else(|| exchange.to_ascii_lowercase());
+            let prefix = exchange.to_ascii_uppercase();
+            let feed = self.feeds.entry(key).or_default();
+            env_value(&mut feed.heartbeat_timeout_secs, &format!("{}_HEARTBEAT_TIMEOUT_SECS", prefix));
+            env_value(&mut feed.reconnect_delay_ms, &format!("{}_RECONNECT_DELAY_MS", prefix));
+        }
+    }
+}
+
+/// Строка из окружения, если поля нет в файле.
+fn env_string(field: &mut Option<String>, var: &str) {
+    if field.is_none() {
+        *field = env::var(var).ok();
+    }
+}
+
+/// Число из окружения, если поля нет в файле. Нечитаемое значение пропускается –
+/// модуль возьмет значение по умолчанию.
+fn env_value<T>(field: &mut Option<T>, var: &str)
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if field.is_some() {
+        return;
+    }
+    if let Ok(value) = env::var(var) {
+        match value.trim().parse::<T>() {
+            Ok(v) => *field = Some(v),
+            Err(e) => warn!("Invalid {} '{}': {}, using default", var, value, e),
+        }
+    }
+}
+
+/// Флаг из окружения: "true"/"1"/"yes" или "false"/"0"/"no". Пустое значение – как не заданное.
+fn env_bool(field: &mut Option<bool>, var: &str) {
+    if field.is_some() {
+        return;
+    }
+    if let Ok(value) = env::var(var) {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" => {}
+            "true" | "1" | "yes" => *field = Some(true),
+            "false" | "0" | "no" => *field = Some(false),
+            _ => warn!("Invalid {} '{}', using default", var, value),
+        }
+    }
+}
+
+/// Пары `KEY=value` через запятую из окружения. Если хоть одна пара не читается, карта
+/// пропускается целиком.
+fn env_map<T>(field: &mut Option<HashMap<String, T>>, var: &str)
+where
+    T: FromStr,
+{
+    if field.is_some() {
+        return;
+    }
+    if let Ok(value) = env::var(var) {
+        let items: Option<HashMap<String, T>> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                let (key, value) = item.split_once('=')?;
+                Some((key.trim().to_string(), value.trim().parse::<T>().ok()?))
+            })
+            .collect();
+        match items {
+            Some(items) => *field = Some(items),
+            None => warn!("Invalid {} '{}', using default", var, value),
+        }
+    }
+}
+
+/// Список через запятую из окружения. Если хоть один элемент не читается, список
+/// пропускается целиком.
+fn env_list<T>(field: &mut Option<Vec<T>>, var: &str)
+where
+    T: FromStr,
+{
+    if field.is_some() {
+        return;
+    }
+    if let Ok(value) = env::var(var) {
+        let items: Result<Vec<T>, _> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(str::parse::<T>)
+            .collect();
+        match items {
+            Ok(items) => *field = Some(items),
+            Err(_) => warn!("Invalid {} '{}', using default", var, value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sections_and_rejects_unknown_keys() {
+        let config: Config = toml::from_str(
+            r#"
+            excluded_tokens = ["FOOUSDT"]
+
+            [thresholds]
+            min_spread_percent = 2.5
+
+            [symbol_thresholds]
+            BTCUSDT = 0.05
+
+            [bingx]
+            leverage = 5
+            dry_run = false
+            retryable_codes = [100410]
+
+            [telegram]
+            chat_ids = ["-100123", "@channel"]
+
+            [feeds.DYDX]
+            heartbeat_timeout_secs = 90
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.thresholds.min_spread_percent, Some(2.5));
+        assert_eq!(config.thresholds.max_price_age_secs, None);
+        assert_eq!(config.symbol_thresholds.as_ref().and_then(|t| t.get("BTCUSDT")), Some(&0.05));
+        assert_eq!(config.bingx.leverage, Some(5));
+        assert_eq!(config.bingx.dry_run, Some(false));
+        assert_eq!(config.excluded_tokens, Some(vec!["FOOUSDT".to_string()]));
+        assert_eq!(config.telegram.chat_ids.as_ref().map(Vec::len), Some(2));
+        assert_eq!(config.feed("dYdX").and_then(|f| f.heartbeat_timeout_secs), Some(90));
+        assert!(config.feed("Bybit").is_none());
+
+        assert!(toml::from_str::<Config>("[thresholds]\nmin_spread = 1.0").is_err());
+    }
+
+    #[test]
+    fn enabled_exchanges_match_names_case_insensitively() {
+        let mut config = Config::default();
+        assert_eq!(config.enabled_exchanges().unwrap().len(), EXCHANGES.len());
+
+        config.exchanges = Some(vec!["bybit".to_string(), " HYPERLIQUID ".to_string()]);
+        let enabled = config.enabled_exchanges().unwrap();
+        assert_eq!(enabled, HashSet::from(["Bybit", "Hyperliquid"]));
+
+        config.exchanges = Some(vec!["bybit".to_string(), "bitmex".to_string()]);
+        assert!(config.enabled_exchanges().is_err());
+    }
+}
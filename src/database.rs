@@ -0,0 +1,233 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::config::StorageConfig;
+use crate::share_state::SharedState;
+use crate::utils::{sleep_or_shutdown, Shutdown};
+
+/// Сколько записей может ждать в очереди, прежде чем новые начнут отбрасываться.
+const CHANNEL_CAPACITY: usize = 10_000;
+/// Максимум записей в одной транзакции.
+const MAX_BATCH: usize = 1_000;
+const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 60;
+
+/// Миграции схемы по порядку; номер применённой миграции хранится в `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE IF NOT EXISTS prices (
+        exchange TEXT NOT NULL,
+        symbol   TEXT NOT NULL,
+        price    REAL NOT NULL,
+        ts       INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS prices_symbol_ts ON prices (symbol, ts);
+    CREATE TABLE IF NOT EXISTS opportunities (
+        symbol   TEXT NOT NULL,
+        venue_a  TEXT NOT NULL,
+        venue_b  TEXT NOT NULL,
+        diff_pct REAL NOT NULL,
+        ts       INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS opportunities_symbol_ts ON opportunities (symbol, ts);",
+];
+
+#[derive(Debug)]
+struct PriceRow {
+    exchange: String,
+    symbol: String,
+    price: f64,
+    ts: i64,
+}
+
+#[derive(Debug)]
+enum DbRecord {
+    Prices(Vec<PriceRow>),
+    Opportunity {
+        symbol: String,
+        venue_a: String,
+        venue_b: String,
+        diff_pct: f64,
+        ts: i64,
+    },
+}
+
+/// Хранилище истории цен и возможностей в SQLite (DATABASE_PATH).
+///
+/// Запись идет через канал в отдельный поток, который пишет пачками в транзакции,
+/// так что задержки базы не тормозят обработчики WebSocket. Если очередь переполнена,
+/// записи отбрасываются с предупреждением.
+#[derive(Debug)]
+pub struct Database {
+    sender: mpsc::Sender<DbRecord>,
+    snapshot_interval: Duration,
+}
+
+fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+impl Database {
+    /// Открывает базу по пути из DATABASE_PATH и применяет миграции.
+    /// Если путь не задан или база не открывается – хранилище отключено.
+    pub fn from_config(config: &StorageConfig) -> Option<Self> {
+        let path = config.database_path.as_deref()?.trim();
+        if path.is_empty() {
+            return None;
+        }
+
+        let connection = match Self::open(path) {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!("Failed to open database {}: {}. SQLite persistence disabled.", path, e);
+                return None;
+            }
+        };
+
+        let snapshot_interval = match config.database_snapshot_interval_secs {
+            Some(v) if v > 0 => Duration::from_secs(v),
+            Some(v) => {
+                warn!(
+                    "Invalid DATABASE_SNAPSHOT_INTERVAL_SECS '{}', using default {}s",
+                    v, DEFAULT_SNAPSHOT_INTERVAL_SECS
+                );
+                Duration::from_secs(DEFAULT_SNAPSHOT_INTERVAL_SECS)
+            }
+            None => Duration::from_secs(DEFAULT_SNAPSHOT_INTERVAL_SECS),
+        };
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        // Отдельный поток, а не spawn_blocking: он живет до конца процесса и не должен
+        // задерживать остановку рантайма
+        if let Err(e) = thread::Builder::new()
+            .name("sqlite-writer".to_string())
+            .spawn(move || Self::run_writer(connection, receiver))
+        {
+            error!("Failed to start database writer thread: {}. SQLite persistence disabled.", e);
+            return None;
+        }
+
+        info!("Persisting prices and opportunities to SQLite database {}", path);
+        Some(Self {
+            sender,
+            snapshot_interval,
+        })
+    }
+
+    fn open(path: &str) -> rusqlite::Result<Connection> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        Self::migrate(&connection)?;
+        Ok(connection)
+    }
+
+    /// Применяет миграции, которые еще не применены к этой базе.
+    fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+        let version: usize = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            connection.execute_batch(migration)?;
+            connection.pragma_update(None, "user_version", index + 1)?;
+            info!("Applied database migration {}", index + 1);
+        }
+        Ok(())
+    }
+
+    /// Цикл потока записи: ждет запись, добирает накопившиеся и пишет их одной транзакцией.
+    fn run_writer(mut connection: Connection, mut receiver: mpsc::Receiver<DbRecord>) {
+        while let Some(first) = receiver.blocking_recv() {
+            let mut batch = vec![first];
+            while batch.len() < MAX_BATCH {
+                match receiver.try_recv() {
+                    Ok(record) => batch.push(record),
+                    Err(_) => break,
+                }
+            }
+            if let Err(e) = Self::write_batch(&mut connection, &batch) {
+                error!("Failed to write {} record(s) to database: {}", batch.len(), e);
+            }
+        }
+    }
+
+    fn write_batch(connection: &mut Connection, batch: &[DbRecord]) -> rusqlite::Result<()> {
+        let tx = connection.transaction()?;
+        {
+            let mut insert_price =
+                tx.prepare_cached("INSERT INTO prices (exchange, symbol, price, ts) VALUES (?1, ?2, ?3, ?4)")?;
+            let mut insert_opportunity = tx.prepare_cached(
+                "INSERT INTO opportunities (symbol, venue_a, venue_b, diff_pct, ts) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+            for record in batch {
+                match record {
+                    DbRecord::Prices(rows) => {
+                        for row in rows {
+                            insert_price.execute(params![row.exchange, row.symbol, row.price, row.ts])?;
+                        }
+                    }
+                    DbRecord::Opportunity {
+                        symbol,
+                        venue_a,
+                        venue_b,
+                        diff_pct,
+                        ts,
+                    } => {
+                        insert_opportunity.execute(params![symbol, venue_a, venue_b, diff_pct, ts])?;
+                    }
+                }
+            }
+        }
+        tx.commit()
+    }
+
+    fn send(&self, record: DbRecord) {
+        match self.sender.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                warn!("Database write queue is full, dropping record");
+            }
+            Err(TrySendError::Closed(_)) => {
+                warn!("Database writer has stopped, dropping record");
+            }
+        }
+    }
+
+    /// Ставит в очередь запись о найденной возможности.
+    pub fn record_opportunity(&self, symbol: &str, venue_a: &str, venue_b: &str, diff_pct: f64) {
+        self.send(DbRecord::Opportunity {
+            symbol: symbol.to_string(),
+            venue_a: venue_a.to_string(),
+            venue_b: venue_b.to_string(),
+            diff_pct,
+            ts: now_ms(),
+        });
+    }
+
+    /// Периодически сохраняет снимок всех известных цен, пока не придет сигнал остановки.
+    pub async fn snapshot_prices_periodically(&self, shared_state: &Arc<SharedState>, mut shutdown: Shutdown) {
+        loop {
+            if sleep_or_shutdown(self.snapshot_interval, &mut shutdown).await {
+                return;
+            }
+
+            let ts = now_ms();
+            let rows: Vec<PriceRow> = shared_state
+                .price_snapshot()
+                .into_iter()
+                .map(|(exchange, symbol, price)| PriceRow {
+                    exchange: exchange.to_string(),
+                    symbol,
+                    price,
+                    ts,
+                })
+                .collect();
+            if !rows.is_empty() {
+                self.send(DbRecord::Prices(rows));
+            }
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::share_state::{SharedState, DYDX};
+use crate::utils::{canonical_symbol, connect_ws, run_ws_with_reconnect, to_canonical, Shutdown};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use futures_util::SinkExt;
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+#[derive(Debug, Deserialize)]
+struct PerpetualMarketsResponse {
+    #[serde(default)]
+    markets: HashMap<String, PerpetualMarket>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PerpetualMarket {
+    ticker: String,
+    /// ACTIVE – рынок торгуется.
+    status: String,
+}
+
+/// Цена рынка в `contents` канала `v4_markets`; оракульная цена приходит строкой.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarketPrice {
+    #[serde(default)]
+    oracle_price: Option<String>,
+}
+
+/// `contents` канала `v4_markets`: снимок всех рынков в ответе на подписку (`markets`)
+/// и изменения оракульных цен в обновлениях (`oraclePrices`).
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MarketsContents {
+    #[serde(default)]
+    markets: HashMap<String, MarketPrice>,
+    #[serde(default)]
+    oracle_prices: HashMap<String, MarketPrice>,
+}
+
+pub struct DydxStruct {
+    base_url: String,
+    ws_url: String,
+    client: reqwest::Client,
+}
+
+impl DydxStruct {
+    /// Публичный индексер dYdX v4 не требует ключей.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            base_url: "https://indexer.dydx.trade".to_string(),
+            ws_url: "wss://indexer.dydx.trade/v4/ws".to_string(),
+            client,
+        }
+    }
+
+    pub async fn get_tickers(&self) -> Vec<String> {
+        // Получаем список perpetual-рынков
+        let markets_url = format!("{}/v4/perpetualMarkets", self.base_url);
+
+        match self.client.get(&markets_url).send().await {
+            Ok(response) => {
+                match response.json::<PerpetualMarketsResponse>().await {
+                    Ok(response) => {
+                        let tickers: Vec<String> = response
+                            .markets
+                            .into_values()
+                            .filter(|m| m.status == "ACTIVE")
+                            .filter_map(|m| to_canonical(DYDX, &m.ticker))
+                            .collect();
+                        info!("Retrieved {} dYdX tickers", tickers.len());
+                        tickers
+                    }
+                    Err(e) => {
+                        error!("Failed to parse dYdX perpetual markets: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to get dYdX perpetual markets: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Подписывается на `v4_markets` (оракульные цены всех рынков одним каналом) и обновляет
+    /// цены dYdX в `SharedState` только для `tickers`. Индексер сам шлет ping, прикладной не нужен.
+    pub async fn dydx_ws(self, tickers: &[String], shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        if tickers.is_empty() {
+            info!("No common tickers listed on dYdX, dYdX feed disabled");
+            return;
+        }
+
+        let symbols: HashSet<String> = tickers.iter().map(|t| canonical_symbol(t).symbol).collect();
+        let symbols = &symbols;
+        let config = shared_state.reconnect_config(DYDX);
+        let ws_url = &self.ws_url;
+
+        run_ws_with_reconnect(
+            DYDX,
+            shared_state,
+            &config,
+            shutdown,
+            // Подписку нужно восстанавливать при каждом переподключении
+            move || async move {
+                let (mut ws_stream, _) = connect_ws(ws_url).await?;
+                let subscribe_message = serde_json::json!({
+                    "type": "subscribe",
+                    "channel": "v4_markets",
+                    "batched": false
+                })
+                .to_string();
+                ws_stream.send(Message::Text(subscribe_message)).await?;
+                info!("Subscribed to dYdX markets for {} tickers", symbols.len());
+                Ok::<_, tokio_tungstenite::tungstenite::Error>(ws_stream)
+            },
+            move |text| Self::handle_message(shared_state, symbols, text),
+        )
+        .await;
+    }
+
+    async fn handle_message(shared_state: &Arc<SharedState>, symbols: &HashSet<String>, text: String) {
+        let mut json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse dYdX WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(DYDX);
+                return;
+            }
+        };
+
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("subscribed") | Some("channel_data") => {}
+            Some("connected") => {
+                debug!("dYdX WebSocket connection acknowledged");
+                return;
+            }
+            Some("error") => {
+                warn!("dYdX WebSocket error: {}", text);
+                return;
+            }
+            _ => return,
+        }
+
+        let Some(contents) = json.get_mut("contents").map(serde_json::Value::take) else {
+            return;
+        };
+        let contents: MarketsContents = match serde_json::from_value(contents) {
+            Ok(contents) => contents,
+            Err(e) => {
+                warn!("Failed to parse dYdX markets: {}", e);
+                shared_state.record_parse_error(DYDX);
+                return;
+            }
+        };
+
+        for (ticker, market) in contents.markets.iter().chain(contents.oracle_prices.iter()) {
+            let Some(oracle_price) = &market.oracle_price else {
+                continue;
+            };
+            let Some(symbol) = to_canonical(DYDX, ticker) else {
+                continue;
+            };
+            let canonical = canonical_symbol(&symbol);
+            if !symbols.contains(&canonical.symbol) {
+                continue;
+            }
+            let price: f64 = match oracle_price.parse::<f64>() {
+                Ok(p) if p > 0.0 && p.is_finite() => p,
+                _ => {
+                    warn!("Invalid price for {}: {}", symbol, oracle_price);
+                    continue;
+                }
+            };
+            shared_state.set_price(DYDX, &canonical.symbol, canonical.unit_price(price));
+        }
+    }
+}
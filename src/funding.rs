@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+
+use crate::compare_price::is_excluded;
+use crate::share_state::SharedState;
+use crate::utils::{canonical_symbol, sleep_or_shutdown, Shutdown};
+
+/// Как часто обновляем ставки фандинга бирж.
+const FUNDING_POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Как часто сравниваем фандинг по общим тикерам.
+const FUNDING_COMPARE_INTERVAL: Duration = Duration::from_secs(60);
+/// Фандинг меняется медленно: повторный сигнал по символу не чаще раза в час.
+const FUNDING_ALERT_COOLDOWN: Duration = Duration::from_secs(60 * 60);
+/// Период, к которому приводятся ставки всех бирж.
+const FUNDING_PERIOD_HOURS: f64 = 8.0;
+/// Интервал фандинга Binance-подобных бирж, если он не указан в `fundingInfo`.
+const DEFAULT_FUNDING_INTERVAL_HOURS: f64 = 8.0;
+
+/// Приводит ставку за `interval_hours` к 8-часовому периоду.
+pub fn per_funding_period(rate: f64, interval_hours: f64) -> Option<f64> {
+    if !(interval_hours > 0.0 && rate.is_finite()) {
+        return None;
+    }
+    Some(rate * FUNDING_PERIOD_HOURS / interval_hours)
+}
+
+/// Опрашивает ставки фандинга биржи раз в `FUNDING_POLL_INTERVAL` и сохраняет их в `SharedState`.
+/// `fetch` возвращает ставки за 8 часов по каноническим тикерам.
+pub async fn poll_funding_rates<F, Fut>(exchange: &str, shared_state: &SharedState, mut fetch: F, mut shutdown: Shutdown)
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<HashMap<String, f64>, reqwest::Error>>,
+{
+    loop {
+        match fetch().await {
+            Ok(funding) => {
+                debug!("Updated {} {} funding rates", funding.len(), exchange);
+                shared_state.set_funding_rates(exchange, funding).await;
+            }
+            Err(e) => {
+                warn!("Failed to update {} funding rates: {}", exchange, e);
+            }
+        }
+
+        if sleep_or_shutdown(FUNDING_POLL_INTERVAL, &mut shutdown).await {
+            info!("{} funding poller stopped", exchange);
+            return;
+        }
+    }
+}
+
+/// Элемент `/fapi/v1/premiumIndex`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PremiumIndex {
+    symbol: String,
+    last_funding_rate: String,
+}
+
+/// Элемент `/fapi/v1/fundingInfo` – только символы с нестандартным интервалом.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FundingInfo {
+    symbol: String,
+    funding_interval_hours: f64,
+}
+
+/// Ставки фандинга Binance-подобного API (Binance, ASTER) за 8 часов.
+pub async fn fetch_binance_style_funding(
+    client: &reqwest::Client,
+    base_url: &str,
+) -> Result<HashMap<String, f64>, reqwest::Error> {
+    let premium: Vec<PremiumIndex> = client
+        .get(format!("{}/fapi/v1/premiumIndex", base_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+
+    // Без fundingInfo считаем все интервалы стандартными – это лучше, чем не сравнивать вовсе
+    let intervals: HashMap<String, f64> = match fetch_funding_intervals(client, base_url).await {
+        Ok(info) => info
+            .into_iter()
+            .map(|i| (i.symbol, i.funding_interval_hours))
+            .collect(),
+        Err(e) => {
+            debug!("Failed to fetch funding intervals from {}: {}", base_url, e);
+            HashMap::new()
+        }
+    };
+
+    let funding = premium
+        .into_iter()
+        .filter_map(|index| {
+            let rate = index.last_funding_rate.parse::<f64>().ok()?;
+            let hours = intervals
+                .get(&index.symbol)
+                .copied()
+                .unwrap_or(DEFAULT_FUNDING_INTERVAL_HOURS);
+            Some((canonical_symbol(&index.symbol).symbol, per_funding_period(rate, hours)?))
+        })
+        .collect();
+    Ok(funding)
+}
+
+async fn fetch_funding_intervals(client: &reqwest::Client, base_url: &str) -> Result<Vec<FundingInfo>, reqwest::Error> {
+    client
+        .get(format!("{}/fapi/v1/fundingInfo", base_url))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+/// Лучшая пара для фандингового арбитража: шорт там, где ставка выше (шорт получает
+/// положительный фандинг), лонг – там, где ниже.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FundingSpread {
+    pub short_exchange: String,
+    /// Ставка за 8 часов (доля, не проценты).
+    pub short_rate: f64,
+    pub long_exchange: String,
+    pub long_rate: f64,
+    /// Чистый фандинг за 8 часов по двум ногам, в процентах.
+    pub diff_percent: f64,
+}
+
+/// Находит биржи с максимальной и минимальной ставкой. Нужны хотя бы две биржи.
+pub fn best_funding_spread(rates: &[(String, f64)]) -> Option<FundingSpread> {
+    let by_rate = |a: &&(String, f64), b: &&(String, f64)| a.1.total_cmp(&b.1);
+    let (short_exchange, short_rate) = rates.iter().max_by(by_rate)?;
+    let (long_exchange, long_rate) = rates.iter().min_by(by_rate)?;
+    if short_exchange == long_exchange || short_rate <= long_rate {
+        return None;
+    }
+    Some(FundingSpread {
+        short_exchange: short_exchange.clone(),
+        short_rate: *short_rate,
+        long_exchange: long_exchange.clone(),
+        long_rate: *long_rate,
+        diff_percent: (short_rate - long_rate) * 100.0,
+    })
+}
+
+/// Раз в `FUNDING_COMPARE_INTERVAL` сравнивает фандинг по общим тикерам и сообщает, если
+/// разница за 8 часов не меньше MIN_FUNDING_DIFF. Независимо от ценового спреда.
+/// Если MIN_FUNDING_DIFF не задан, сразу завершается.
+pub async fn compare_funding_periodically(shared_state: &Arc<SharedState>, symbols: &[String], mut shutdown: Shutdown) {
+    let Some(min_diff) = shared_state.min_funding_diff_percent else {
+        return;
+    };
+    info!(
+        "Comparing funding rates of {} tickers every {:?} (min difference {}% per {}h)",
+        symbols.len(),
+        FUNDING_COMPARE_INTERVAL,
+        min_diff,
+        FUNDING_PERIOD_HOURS
+    );
+
+    let mut last_alerts: HashMap<String, Instant> = HashMap::new();
+    loop {
+        // Первый проход – после первого опроса ставок
+        if sleep_or_shutdown(FUNDING_COMPARE_INTERVAL, &mut shutdown).await {
+            info!("Funding comparison stopped");
+            return;
+        }
+
+        for symbol in symbols {
+            if is_excluded(symbol) {
+                continue;
+            }
+            let rates = shared_state.funding_rates(symbol).await;
+            let Some(spread) = best_funding_spread(&rates).filter(|s| s.diff_percent >= min_diff) else {
+                continue;
+            };
+            if last_alerts
+                .get(symbol)
+                .is_some_and(|sent| sent.elapsed() < FUNDING_ALERT_COOLDOWN)
+            {
+                continue;
+            }
+            last_alerts.insert(symbol.clone(), Instant::now());
+
+            tracing::info!(
+                symbol = symbol.as_str(),
+                short_exchange = spread.short_exchange.as_str(),
+                short_rate = spread.short_rate,
+                long_exchange = spread.long_exchange.as_str(),
+                long_rate = spread.long_rate,
+                diff_percent = spread.diff_percent,
+                min_funding_diff = min_diff,
+                "Funding opportunity"
+            );
+
+            if let Some(telegram) = &shared_state.telegram {
+                if let Err(e) = telegram.send_funding_opportunity(symbol, &spread).await {
+                    error!("Failed to send Telegram funding alert for {}: {}", symbol, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_highest_rate_to_short_and_lowest_to_long() {
+        let rates = vec![
+            ("Bybit".to_string(), 0.0001),
+            ("Hyperliquid".to_string(), 0.0012),
+            ("Binance".to_string(), -0.0003),
+        ];
+        let spread = best_funding_spread(&rates).unwrap();
+        assert_eq!(spread.short_exchange, "Hyperliquid");
+        assert_eq!(spread.long_exchange, "Binance");
+        assert!((spread.diff_percent - 0.15).abs() < 1e-9);
+
+        assert!(best_funding_spread(&rates[..1]).is_none());
+        assert_eq!(per_funding_period(0.0001, 1.0), Some(0.0008));
+        assert_eq!(per_funding_period(0.0001, 0.0), None);
+    }
+}
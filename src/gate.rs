@@ -0,0 +1,198 @@
+use crate::share_state::{SharedState, GATE};
+use std::{sync::Arc, time::Duration};
+use crate::utils::{canonical_symbol, connect_ws, from_canonical, run_ws_with_reconnect, to_canonical, PingConfig, ReconnectConfig, Shutdown, WsStream};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// Сколько контрактов подписываем одним сообщением.
+const SUBSCRIBE_BATCH_SIZE: usize = 100;
+/// Gate закрывает неактивные соединения; держим их живыми прикладным futures.ping.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+struct ContractInfo {
+    name: String,
+    #[serde(default)]
+    in_delisting: bool,
+}
+
+/// Элемент `result` в обновлении `futures.tickers`.
+#[derive(Debug, Deserialize)]
+struct TickerUpdate {
+    contract: String,
+    last: String,
+}
+
+pub struct GateStruct {
+    base_url: String,
+    ws_url: String,
+    client: reqwest::Client,
+}
+
+impl GateStruct {
+    /// Публичные эндпоинты Gate.io не требуют ключей.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            base_url: "https://api.gateio.ws".to_string(),
+            ws_url: "wss://fx-ws.gateio.ws/v4/ws/usdt".to_string(),
+            client,
+        }
+    }
+
+    pub async fn get_tickers(&self) -> Vec<String> {
+        // Получаем список USDT perpetual-контрактов
+        let contracts_url = format!("{}/api/v4/futures/usdt/contracts", self.base_url);
+
+        match self.client.get(&contracts_url).send().await {
+            Ok(response) => {
+                match response.json::<Vec<ContractInfo>>().await {
+                    Ok(contracts) => {
+                        let tickers: Vec<String> = contracts
+                            .into_iter()
+                            .filter(|c| !c.in_delisting)
+                            .filter_map(|c| to_canonical(GATE, &c.name))
+                            .collect();
+                        info!("Retrieved {} Gate tickers", tickers.len());
+                        tickers
+                    }
+                    Err(e) => {
+                        error!("Failed to parse Gate contracts: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to get Gate contracts: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Сообщение Gate WS: каждое должно содержать текущее время в секундах.
+    fn request(channel: &str, event: Option<&str>, payload: Option<Vec<String>>) -> String {
+        let mut message = serde_json::json!({
+            "time": chrono::Utc::now().timestamp(),
+            "channel": channel,
+        });
+        if let Some(event) = event {
+            message["event"] = serde_json::json!(event);
+        }
+        if let Some(payload) = payload {
+            message["payload"] = serde_json::json!(payload);
+        }
+        message.to_string()
+    }
+
+    /// Подписывается на `futures.tickers` пачками по `SUBSCRIBE_BATCH_SIZE` контрактов.
+    async fn subscribe(ws_stream: &mut WsStream, tickers: &[String]) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        for chunk in tickers.chunks(SUBSCRIBE_BATCH_SIZE) {
+            let contracts: Vec<String> = chunk.iter().map(|t| from_canonical(GATE, t)).collect();
+            let subscribe_message = Self::request("futures.tickers", Some("subscribe"), Some(contracts));
+            ws_stream.send(Message::Text(subscribe_message)).await?;
+        }
+        Ok(())
+    }
+
+    /// Сохраняет последние цены из обновления `futures.tickers`.
+    fn handle_tickers(shared_state: &SharedState, result: serde_json::Value) {
+        let tickers: Vec<TickerUpdate> = match serde_json::from_value(result) {
+            Ok(tickers) => tickers,
+            Err(e) => {
+                warn!("Failed to parse Gate tickers: {}", e);
+                shared_state.record_parse_error(GATE);
+                return;
+            }
+        };
+
+        for ticker in tickers {
+            let Some(symbol) = to_canonical(GATE, &ticker.contract) else {
+                continue;
+            };
+            let price: f64 = match ticker.last.parse::<f64>() {
+                Ok(p) => {
+                    if p <= 0.0 || !p.is_finite() {
+                        warn!("Invalid price for {}: {}", symbol, p);
+                        continue;
+                    }
+                    p
+                }
+                Err(e) => {
+                    warn!("Failed to parse price for {}: {} (value: {})", symbol, e, ticker.last);
+                    continue;
+                }
+            };
+
+            let canonical = canonical_symbol(&symbol);
+            shared_state.set_price(GATE, &canonical.symbol, canonical.unit_price(price));
+        }
+    }
+
+    /// Gate закрывает неактивные соединения, поэтому `run_ws_with_reconnect` шлет `futures.ping`.
+    pub async fn gate_ws(self, tickers: &[String], shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        if tickers.is_empty() {
+            info!("No common tickers listed on Gate, Gate feed disabled");
+            return;
+        }
+
+        let config = ReconnectConfig {
+            ping: Some(PingConfig {
+                interval: PING_INTERVAL,
+                message: || Self::request("futures.ping", None, None),
+            }),
+            ..shared_state.reconnect_config(GATE)
+        };
+        let ws_url = &self.ws_url;
+
+        run_ws_with_reconnect(
+            GATE,
+            shared_state,
+            &config,
+            shutdown,
+            // Подписку нужно восстанавливать при каждом переподключении
+            move || async move {
+                let (mut ws_stream, _) = connect_ws(ws_url).await?;
+                Self::subscribe(&mut ws_stream, tickers).await?;
+                info!("Subscribed to {} Gate tickers", tickers.len());
+                Ok::<_, tokio_tungstenite::tungstenite::Error>(ws_stream)
+            },
+            move |text| Self::handle_message(shared_state, text),
+        )
+        .await;
+    }
+
+    async fn handle_message(shared_state: &Arc<SharedState>, text: String) {
+        let mut json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse Gate WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(GATE);
+                return;
+            }
+        };
+
+        // Ошибки подписки приходят с непустым полем error
+        if json.get("error").is_some_and(|e| !e.is_null()) {
+            warn!("Gate WebSocket error: {}", text);
+            return;
+        }
+
+        let channel = json.get("channel").and_then(|c| c.as_str()).unwrap_or_default();
+        let event = json.get("event").and_then(|e| e.as_str()).unwrap_or_default();
+        match (channel, event) {
+            ("futures.tickers", "update") => {
+                if let Some(result) = json.get_mut("result").map(serde_json::Value::take) {
+                    Self::handle_tickers(shared_state, result);
+                }
+            }
+            ("futures.pong", _) => {
+                debug!("Received pong from Gate");
+            }
+            ("futures.tickers", "subscribe") => {}
+            _ => {
+                debug!("Unexpected Gate message: {}", text);
+            }
+        }
+    }
+}
@@ -0,0 +1,874 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use log::{error, info, warn};
+use thiserror::Error;
+
+use crate::bingx::{BingXClient, BingXError, BingXTradeOutcome};
+use crate::bybit_trade::{BybitTradeError, BybitTradeOutcome, BybitTrader};
+use crate::config::Config;
+use crate::hyperliquid_trade::{HyperLiquidError, HyperLiquidTradeOutcome, HyperLiquidTrader};
+use crate::share_state::{SharedState, BINGX, BYBIT, HYPERLIQUID};
+
+/// Биржи хеджа по умолчанию (HEDGE_VENUES).
+const DEFAULT_HEDGE_VENUES: (HedgeVenue, HedgeVenue) = (HedgeVenue::Bybit, HedgeVenue::Hyperliquid);
+/// Относительный допуск, в пределах которого объемы ног считаются равными (шум f64 при пересчете
+/// контрактов в монеты).
+const QUANTITY_TOLERANCE: f64 = 1e-9;
+/// Сколько символ не хеджируется после неудачной попытки (нога не открылась, вторая закрыта
+/// обратно): иначе, пока держится спред, каждый проход открывал бы и закрывал ногу, платя комиссии.
+const FAILED_HEDGE_COOLDOWN: Duration = Duration::from_secs(300);
+/// Сколько знаков после запятой перебираем, чтобы привести шаги количества к целым числам.
+const MAX_STEP_DECIMALS: i32 = 12;
+
+/// Биржа, на которой открывается нога хеджа.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HedgeVenue {
+    BingX,
+    Bybit,
+    Hyperliquid,
+}
+
+impl HedgeVenue {
+    pub fn name(self) -> &'static str {
+        match self {
+            HedgeVenue::BingX => BINGX,
+            HedgeVenue::Bybit => BYBIT,
+            HedgeVenue::Hyperliquid => HYPERLIQUID,
+        }
+    }
+
+    /// Биржа без учета регистра (`bingx`, `bybit`, `hyperliquid`).
+    pub fn from_name(name: &str) -> Option<Self> {
+        [HedgeVenue::BingX, HedgeVenue::Bybit, HedgeVenue::Hyperliquid]
+            .into_iter()
+            .find(|venue| venue.name().eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Фид, по цене которого ищется спред. У BingX своего фида нет – его цена почти совпадает с Bybit.
+    pub fn price_feed(self) -> &'static str {
+        match self {
+            HedgeVenue::BingX | HedgeVenue::Bybit => BYBIT,
+            HedgeVenue::Hyperliquid => HYPERLIQUID,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HedgeError {
+    #[error("invalid hedge config: {0}")]
+    Config(String),
+    #[error("long and short legs must be on different venues, got {0} for both")]
+    SameVenue(&'static str),
+    #[error("{0} trading client is not configured")]
+    VenueNotConfigured(&'static str),
+    #[error("dry run must be enabled or disabled on both venues")]
+    DryRunMismatch,
+    #[error("invalid notional {0}")]
+    InvalidNotional(f64),
+    #[error("no fresh price for {0}")]
+    NoPrice(String),
+    #[error("notional {notional} USDT for {symbol} is below one quantity step {step}")]
+    QuantityTooSmall { symbol: String, notional: f64, step: f64 },
+    #[error("hedge for {0} is already open")]
+    AlreadyOpen(String),
+    #[error("hedge for {0} failed recently, retry is paused")]
+    CoolingDown(String),
+    #[error("bingx error: {0}")]
+    BingX(#[from] BingXError),
+    #[error("hyperliquid error: {0}")]
+    HyperLiquid(#[from] HyperLiquidError),
+}
+
+/// Итог одной ноги хеджа. Объемы – в монетах, цены – за монету.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LegStatus {
+    /// Ордер исполнен, позиция открыта.
+    Filled { quantity: f64, avg_price: f64 },
+    /// Режим dry run: ордер рассчитан, но не отправлен.
+    DryRun { quantity: f64 },
+    /// Ордер не исполнен.
+    Failed { error: String },
+    /// Исполнение ордера не подтвердилось, и позицию проверить не удалось: нога могла открыться.
+    Unknown { error: String },
+    /// Ордер исполнился, но вторая нога – нет, и позиция закрыта обратно.
+    RolledBack { quantity: f64, avg_price: f64 },
+    /// Ордер исполнился, вторая нога – нет, а закрыть позицию не удалось: она осталась без хеджа.
+    RollbackFailed { quantity: f64, avg_price: f64, error: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct HedgeLeg {
+    pub venue: HedgeVenue,
+    /// LONG / SHORT
+    pub direction: &'static str,
+    pub status: LegStatus,
+}
+
+/// Результат `execute_hedge` по каждой ноге.
+#[derive(Debug, Clone)]
+pub struct HedgeResult {
+    pub symbol: String,
+    /// Запрошенный объем каждой ноги в монетах (кратный шагам обеих бирж).
+    pub quantity: f64,
+    pub long: HedgeLeg,
+    pub short: HedgeLeg,
+}
+
+impl HedgeResult {
+    /// Обе ноги открыты одинаковым объемом (или обе рассчитаны в dry run).
+    pub fn is_hedged(&self) -> bool {
+        match (&self.long.status, &self.short.status) {
+            (LegStatus::Filled { quantity: long, .. }, LegStatus::Filled { quantity: short, .. })
+            | (LegStatus::DryRun { quantity: long }, LegStatus::DryRun { quantity: short }) => {
+                same_quantity(*long, *short)
+            }
+            _ => false,
+        }
+    }
+
+    /// Осталась позиция без противоположной ноги (или ее часть) или исход ноги неизвестен –
+    /// нужно ручное вмешательство.
+    pub fn has_naked_exposure(&self) -> bool {
+        let unbalanced = matches!(
+            (&self.long.status, &self.short.status),
+            (LegStatus::Filled { .. }, LegStatus::Filled { .. })
+        ) && !self.is_hedged();
+        unbalanced
+            || [&self.long, &self.short]
+                .iter()
+                .any(|leg| matches!(leg.status, LegStatus::RollbackFailed { .. } | LegStatus::Unknown { .. }))
+    }
+}
+
+/// Равны ли объемы ног с точностью до `QUANTITY_TOLERANCE` от большего.
+fn same_quantity(a: f64, b: f64) -> bool {
+    (a - b).abs() <= a.abs().max(b.abs()) * QUANTITY_TOLERANCE
+}
+
+/// Наименьший шаг, кратный шагам обеих бирж: объем, округленный до него, принимают обе.
+/// Неизвестный шаг (0) не ограничивает. Шаги переводятся в целые по числу знаков после запятой;
+/// если это не удается, берется больший шаг.
+fn common_step(a: f64, b: f64) -> f64 {
+    if a <= 0.0 {
+        return b.max(0.0);
+    }
+    if b <= 0.0 {
+        return a;
+    }
+    for decimals in 0..=MAX_STEP_DECIMALS {
+        let scale = 10f64.powi(decimals);
+        let (scaled_a, scaled_b) = ((a * scale).round(), (b * scale).round());
+        let exact = |step: f64, scaled: f64| (step * scale - scaled).abs() <= scaled * QUANTITY_TOLERANCE;
+        if scaled_a >= 1.0 && scaled_b >= 1.0 && exact(a, scaled_a) && exact(b, scaled_b) {
+            let (int_a, int_b) = (scaled_a as u64, scaled_b as u64);
+            return (int_a / gcd(int_a, int_b) * int_b) as f64 / scale;
+        }
+    }
+    warn!("Hedge: no common multiple for quantity steps {} and {}, using the larger one", a, b);
+    a.max(b)
+}
+
+/// Исход ноги, чей ордер не подтвердился: по фактической позиции (объем в монетах и цена входа)
+/// нога считается исполненной, без позиции – не открывшейся; если позицию узнать не удалось –
+/// исход неизвестен.
+fn check_unconfirmed(
+    venue: HedgeVenue,
+    symbol: &str,
+    direction: &str,
+    error: String,
+    position: Result<Option<(f64, f64)>, String>,
+) -> Result<LegStatus, String> {
+    match position {
+        Ok(Some((quantity, avg_price))) => {
+            warn!(
+                "Hedge: {} {} order on {} was not confirmed ({}), but a position of {} is open",
+                direction,
+                symbol,
+                venue.name(),
+                error,
+                quantity
+            );
+            Ok(LegStatus::Filled { quantity, avg_price })
+        }
+        Ok(None) => {
+            warn!(
+                "Hedge: {} {} order on {} was not confirmed ({}), no position is open",
+                direction,
+                symbol,
+                venue.name(),
+                error
+            );
+            Err(error)
+        }
+        Err(e) => {
+            error!(
+                "Hedge: {} {} order on {} was not confirmed ({}) and the position could not be checked: {}. MANUAL ACTION REQUIRED.",
+                direction,
+                symbol,
+                venue.name(),
+                error,
+                e
+            );
+            Ok(LegStatus::Unknown { error })
+        }
+    }
+}
+
+fn side(is_buy: bool) -> &'static str {
+    if is_buy {
+        "BUY"
+    } else {
+        "SELL"
+    }
+}
+
+fn gcd(mut a: u64, mut b: u64) -> u64 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// Биржи хеджа из HEDGE_VENUES: ровно две разные биржи с разными фидами цены.
+fn hedge_venues(configured: Option<&[String]>) -> Result<(HedgeVenue, HedgeVenue), HedgeError> {
+    let Some(names) = configured else {
+        return Ok(DEFAULT_HEDGE_VENUES);
+    };
+    let venues = names
+        .iter()
+        .map(|name| {
+            HedgeVenue::from_name(name).ok_or_else(|| {
+                HedgeError::Config(format!(
+                    "unknown hedge venue '{}' (expected {}, {} or {})",
+                    name, BINGX, BYBIT, HYPERLIQUID
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match venues[..] {
+        [a, b] if a.price_feed() != b.price_feed() => Ok((a, b)),
+        [a, b] => Err(HedgeError::Config(format!(
+            "{} and {} are both priced by the {} feed, the spread between them is always zero",
+            a.name(),
+            b.name(),
+            a.price_feed()
+        ))),
+        _ => Err(HedgeError::Config(format!("HEDGE_VENUES needs exactly two venues, got {}", venues.len()))),
+    }
+}
+
+/// Одновременное открытие двух противоположных позиций: LONG на одной бирже и SHORT на другой.
+///
+/// Ноги выставляются маркет-ордерами параллельно и без TP/SL – каждая защищает другую. Если одна
+/// нога не открылась, исполненная сразу закрывается, чтобы не оставлять направленную позицию; если
+/// ноги исполнились разным объемом, излишек большей закрывается.
+///
+/// Хедж по символу открывается один раз: закрывать открытые хеджи нужно вручную.
+pub struct HedgeExecutor {
+    shared_state: Arc<SharedState>,
+    bingx: Option<Arc<BingXClient>>,
+    bybit: Option<Arc<BybitTrader>>,
+    hyperliquid: Option<Arc<HyperLiquidTrader>>,
+    /// Биржи, между которыми ищется спред (HEDGE_VENUES).
+    venues: (HedgeVenue, HedgeVenue),
+    /// Объем каждой ноги в USDT (HEDGE_NOTIONAL_USDT).
+    notional: f64,
+    /// Символы, по которым хедж открыт (остался без второй ноги или с неизвестным исходом) –
+    /// повторно не открываем.
+    opened: Mutex<HashSet<String>>,
+    /// Время последней неудачной попытки по символу, см. `FAILED_HEDGE_COOLDOWN`.
+    failed_at: Mutex<HashMap<String, Instant>>,
+}
+
+impl HedgeExecutor {
+    pub fn new(
+        shared_state: Arc<SharedState>,
+        bingx: Option<Arc<BingXClient>>,
+        bybit: Option<Arc<BybitTrader>>,
+        hyperliquid: Option<Arc<HyperLiquidTrader>>,
+        venues: (HedgeVenue, HedgeVenue),
+        notional: f64,
+    ) -> Self {
+        Self {
+            shared_state,
+            bingx,
+            bybit,
+            hyperliquid,
+            venues,
+            notional,
+            opened: Mutex::new(HashSet::new()),
+            failed_at: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Хедж из `[trading]`: None, если HEDGE_NOTIONAL_USDT не задан. Клиенты Bybit и Hyperliquid
+    /// создаются только для бирж из HEDGE_VENUES, клиент BingX – общий с одноногой торговлей.
+    pub async fn from_config(
+        config: &Config,
+        shared_state: Arc<SharedState>,
+        bingx: Option<Arc<BingXClient>>,
+        http_client: reqwest::Client,
+    ) -> Result<Option<Self>, HedgeError> {
+        let Some(notional) = config.trading.hedge_notional_usdt else {
+            return Ok(None);
+        };
+        if !(notional > 0.0 && notional.is_finite()) {
+            return Err(HedgeError::InvalidNotional(notional));
+        }
+        let venues = hedge_venues(config.trading.hedge_venues.as_deref())?;
+        let uses = |venue: HedgeVenue| venues.0 == venue || venues.1 == venue;
+
+        if uses(HedgeVenue::BingX) && bingx.is_none() {
+            return Err(HedgeError::VenueNotConfigured(BINGX));
+        }
+        let bybit = if uses(HedgeVenue::Bybit) {
+            let trader = BybitTrader::from_config(&config.bybit, http_client)
+                .map_err(|e| HedgeError::Config(format!("{}: {}", BYBIT, e)))?;
+            Some(Arc::new(trader))
+        } else {
+            None
+        };
+        let hyperliquid = if uses(HedgeVenue::Hyperliquid) {
+            Some(Arc::new(HyperLiquidTrader::from_config(&config.hyperliquid).await?))
+        } else {
+            None
+        };
+
+        let executor = Self::new(shared_state, bingx, bybit, hyperliquid, venues, notional);
+        // Реальная нога против нерасчитанной в dry run – та же позиция без хеджа
+        if executor.is_dry_run(venues.0)? != executor.is_dry_run(venues.1)? {
+            return Err(HedgeError::DryRunMismatch);
+        }
+        Ok(Some(executor))
+    }
+
+    /// Биржи, между которыми ищется спред для хеджа.
+    pub fn venues(&self) -> (HedgeVenue, HedgeVenue) {
+        self.venues
+    }
+
+    /// Объем каждой ноги в USDT.
+    pub fn notional(&self) -> f64 {
+        self.notional
+    }
+
+    /// Хедж по символу уже открыт.
+    pub fn is_open(&self, symbol: &str) -> bool {
+        self.opened.lock().unwrap().contains(symbol)
+    }
+
+    /// Недавняя попытка хеджа по символу не удалась – новую пока не делаем.
+    pub fn is_cooling_down(&self, symbol: &str) -> bool {
+        self.failed_at
+            .lock()
+            .unwrap()
+            .get(symbol)
+            .is_some_and(|failed_at| failed_at.elapsed() < FAILED_HEDGE_COOLDOWN)
+    }
+
+    /// Открывает хедж по каноническому тикеру на `notional` USDT с каждой стороны.
+    ///
+    /// Объем считается по свежей цене фида одной из бирж и округляется вниз до шага, кратного
+    /// шагам количества обеих бирж, чтобы ноги были одинаковыми. Ошибки до отправки ордеров
+    /// (неверные параметры, нет цены или клиента биржи) возвращаются как `Err`; исходы ордеров –
+    /// в `HedgeResult`.
+    pub async fn execute_hedge(
+        &self,
+        symbol: &str,
+        long_venue: HedgeVenue,
+        short_venue: HedgeVenue,
+        notional: f64,
+    ) -> Result<HedgeResult, HedgeError> {
+        if long_venue == short_venue {
+            return Err(HedgeError::SameVenue(long_venue.name()));
+        }
+        if !(notional > 0.0 && notional.is_finite()) {
+            return Err(HedgeError::InvalidNotional(notional));
+        }
+        if self.is_open(symbol) {
+            return Err(HedgeError::AlreadyOpen(symbol.to_string()));
+        }
+        if self.is_cooling_down(symbol) {
+            return Err(HedgeError::CoolingDown(symbol.to_string()));
+        }
+        // Реальная нога против нерасчитанной в dry run – та же позиция без хеджа
+        if self.is_dry_run(long_venue)? != self.is_dry_run(short_venue)? {
+            return Err(HedgeError::DryRunMismatch);
+        }
+
+        let max_age = self.shared_state.max_price_age;
+        let price = [long_venue.price_feed(), short_venue.price_feed()]
+            .into_iter()
+            .filter_map(|feed| self.shared_state.get_price(feed, symbol))
+            .find(|quote| quote.price > 0.0 && quote.updated_at.elapsed() <= max_age)
+            .map(|quote| quote.price)
+            .ok_or_else(|| HedgeError::NoPrice(symbol.to_string()))?;
+
+        let step = common_step(
+            self.quantity_step(long_venue, symbol).await?,
+            self.quantity_step(short_venue, symbol).await?,
+        );
+        // Небольшой допуск, чтобы 12.345 / 0.001 = 12344.999... не теряло шаг
+        let quantity = (notional / price / step + 1e-9).floor() * step;
+        if quantity <= 0.0 {
+            return Err(HedgeError::QuantityTooSmall {
+                symbol: symbol.to_string(),
+                notional,
+                step,
+            });
+        }
+
+        info!(
+            "Hedge: opening LONG {} on {} and SHORT on {}, qty={}, price={}",
+            symbol,
+            long_venue.name(),
+            short_venue.name(),
+            quantity,
+            price
+        );
+
+        let (long, short) = tokio::join!(
+            self.open_leg(long_venue, symbol, "LONG", quantity, price),
+            self.open_leg(short_venue, symbol, "SHORT", quantity, price),
+        );
+
+        let (long, short) = match (long, short) {
+            (Ok(long), Ok(short)) => {
+                self.rebalance(symbol, (long_venue, long), (short_venue, short)).await
+            }
+            (Ok(filled), Err(error)) => (
+                self.roll_back(long_venue, symbol, "LONG", filled).await,
+                LegStatus::Failed { error },
+            ),
+            (Err(error), Ok(filled)) => (
+                LegStatus::Failed { error },
+                self.roll_back(short_venue, symbol, "SHORT", filled).await,
+            ),
+            (Err(long), Err(short)) => (LegStatus::Failed { error: long }, LegStatus::Failed { error: short }),
+        };
+
+        let result = HedgeResult {
+            symbol: symbol.to_string(),
+            quantity,
+            long: HedgeLeg {
+                venue: long_venue,
+                direction: "LONG",
+                status: long,
+            },
+            short: HedgeLeg {
+                venue: short_venue,
+                direction: "SHORT",
+                status: short,
+            },
+        };
+        self.record(&result);
+        Ok(result)
+    }
+
+    /// Запоминает исход: открытый хедж или позиция без хеджа блокируют символ, неудачная
+    /// попытка – приостанавливает повторы на `FAILED_HEDGE_COOLDOWN`.
+    fn record(&self, result: &HedgeResult) {
+        let symbol = &result.symbol;
+        if result.is_hedged() {
+            info!("Hedge: {} opened on both legs: {:?} / {:?}", symbol, result.long.status, result.short.status);
+        } else if result.has_naked_exposure() {
+            error!("Hedge: {} left an unhedged position. MANUAL ACTION REQUIRED. {:?}", symbol, result);
+        } else {
+            warn!(
+                "Hedge: {} was not opened, retrying in {:?}: {:?}",
+                symbol, FAILED_HEDGE_COOLDOWN, result
+            );
+            self.failed_at.lock().unwrap().insert(symbol.clone(), Instant::now());
+            return;
+        }
+        self.opened.lock().unwrap().insert(symbol.clone());
+    }
+
+    fn is_dry_run(&self, venue: HedgeVenue) -> Result<bool, HedgeError> {
+        match venue {
+            HedgeVenue::BingX => Ok(self.bingx_client()?.is_dry_run()),
+            HedgeVenue::Bybit => Ok(self.bybit_client()?.is_dry_run()),
+            HedgeVenue::Hyperliquid => Ok(self.hyperliquid_client()?.is_dry_run()),
+        }
+    }
+
+    fn bingx_client(&self) -> Result<&BingXClient, HedgeError> {
+        self.bingx.as_deref().ok_or(HedgeError::VenueNotConfigured(BINGX))
+    }
+
+    fn bybit_client(&self) -> Result<&BybitTrader, HedgeError> {
+        self.bybit.as_deref().ok_or(HedgeError::VenueNotConfigured(BYBIT))
+    }
+
+    fn hyperliquid_client(&self) -> Result<&HyperLiquidTrader, HedgeError> {
+        self.hyperliquid.as_deref().ok_or(HedgeError::VenueNotConfigured(HYPERLIQUID))
+    }
+
+    /// Шаг количества биржи в монетах. Для Bybit – из параметров контрактов в `SharedState`.
+    async fn quantity_step(&self, venue: HedgeVenue, symbol: &str) -> Result<f64, HedgeError> {
+        match venue {
+            HedgeVenue::BingX => Ok(self.bingx_client()?.quantity_step(symbol).await?),
+            HedgeVenue::Bybit => Ok(self
+                .shared_state
+                .instrument_spec(BYBIT, symbol)
+                .map(|spec| spec.qty_step)
+                .filter(|step| *step > 0.0)
+                // Без загруженных параметров объем ограничивает шаг второй биржи, а до шага
+                // контракта Bybit его округлит `BybitTrader`
+                .unwrap_or(0.0)),
+            HedgeVenue::Hyperliquid => Ok(self.hyperliquid_client()?.quantity_step(symbol)?),
+        }
+    }
+
+    /// Открывает ногу. Ok – `Filled`, `DryRun` или `Unknown`, Err – текст ошибки.
+    ///
+    /// Если ордер BingX или Bybit не подтвердился за время ожидания, он мог исполниться: исход
+    /// ноги определяется по фактической позиции (см. `check_unconfirmed`).
+    async fn open_leg(
+        &self,
+        venue: HedgeVenue,
+        symbol: &str,
+        direction: &'static str,
+        quantity: f64,
+        price: f64,
+    ) -> Result<LegStatus, String> {
+        match venue {
+            HedgeVenue::BingX => {
+                let bingx = self.bingx_client().map_err(|e| e.to_string())?;
+                match bingx.open_market_quantity(symbol, direction, quantity, price).await {
+                    Ok(BingXTradeOutcome::Opened { quantity, entry_price, .. }) => Ok(LegStatus::Filled {
+                        quantity,
+                        avg_price: entry_price,
+                    }),
+                    Ok(BingXTradeOutcome::DryRun { quantity, .. }) => Ok(LegStatus::DryRun { quantity }),
+                    Ok(other) => Err(format!("unexpected BingX outcome: {:?}", other)),
+                    Err(e @ BingXError::OrderNotFilled { .. }) => {
+                        let position = bingx.position_quantity(symbol, direction).await.map_err(|e| e.to_string());
+                        check_unconfirmed(venue, symbol, direction, e.to_string(), position)
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            HedgeVenue::Bybit => {
+                let bybit = self.bybit_client().map_err(|e| e.to_string())?;
+                match bybit.market_order(symbol, direction == "LONG", quantity, false).await {
+                    Ok(BybitTradeOutcome::Filled { symbol, is_buy, quantity, avg_price, order_id }) => {
+                        info!(
+                            "Hedge: {} leg order {} filled on Bybit: {} {} qty={}, avg_price={}",
+                            direction, order_id, side(is_buy), symbol, quantity, avg_price
+                        );
+                        Ok(LegStatus::Filled { quantity, avg_price })
+                    }
+                    Ok(BybitTradeOutcome::DryRun { symbol, is_buy, quantity, body }) => {
+                        info!(
+                            "Hedge [dry run]: {} leg on Bybit: {} {} qty={}, body={}",
+                            direction, side(is_buy), symbol, quantity, body
+                        );
+                        Ok(LegStatus::DryRun { quantity })
+                    }
+                    Err(e @ BybitTradeError::OrderNotFilled { .. }) => {
+                        let position = bybit.position(symbol, direction == "LONG").await.map_err(|e| e.to_string());
+                        check_unconfirmed(venue, symbol, direction, e.to_string(), position)
+                    }
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+            HedgeVenue::Hyperliquid => {
+                let hyperliquid = self.hyperliquid_client().map_err(|e| e.to_string())?;
+                match hyperliquid.market_order(symbol, direction == "LONG", quantity).await {
+                    Ok(HyperLiquidTradeOutcome::Opened { coin, is_buy, size, avg_price, oid }) => {
+                        info!(
+                            "Hedge: {} leg order {} filled on Hyperliquid: {} {} qty={}, avg_price={}",
+                            direction, oid, side(is_buy), coin, size, avg_price
+                        );
+                        Ok(LegStatus::Filled { quantity: size, avg_price })
+                    }
+                    Ok(HyperLiquidTradeOutcome::DryRun { coin, is_buy, size }) => {
+                        info!("Hedge [dry run]: {} leg on Hyperliquid: {} {} qty={}", direction, side(is_buy), coin, size);
+                        Ok(LegStatus::DryRun { quantity: size })
+                    }
+                    // Маркет-ордер IOC не должен вставать в стакан: если встал, исполнение не подтверждено
+                    Ok(HyperLiquidTradeOutcome::Resting { coin, oid }) => {
+                        Err(format!("market order {} on {} is resting instead of filling", oid, coin))
+                    }
+                    Ok(HyperLiquidTradeOutcome::Skipped { reason }) => Err(reason),
+                    Err(e) => Err(e.to_string()),
+                }
+            }
+        }
+    }
+
+    /// Закрывает `quantity` монет позиции `direction` маркет-ордером.
+    async fn close_leg(
+        &self,
+        venue: HedgeVenue,
+        symbol: &str,
+        direction: &'static str,
+        quantity: f64,
+    ) -> Result<(), String> {
+        match venue {
+            HedgeVenue::BingX => self
+                .bingx_client()
+                .map_err(|e| e.to_string())?
+                .close_market_quantity(symbol, direction, quantity)
+                .await
+                .map(drop)
+                .map_err(|e| e.to_string()),
+            HedgeVenue::Bybit => self
+                .bybit_client()
+                .map_err(|e| e.to_string())?
+                .market_order(symbol, direction != "LONG", quantity, true)
+                .await
+                .map(drop)
+                .map_err(|e| e.to_string()),
+            // Позиция Hyperliquid по монете одна, так что встречный ордер ее уменьшает
+            HedgeVenue::Hyperliquid => match self
+                .hyperliquid_client()
+                .map_err(|e| e.to_string())?
+                .market_order(symbol, direction != "LONG", quantity)
+                .await
+            {
+                Ok(HyperLiquidTradeOutcome::Opened { .. } | HyperLiquidTradeOutcome::DryRun { .. }) => Ok(()),
+                // Вставший в стакан или пропущенный ордер позицию не закрыл
+                Ok(HyperLiquidTradeOutcome::Resting { coin, oid }) => {
+                    Err(format!("market order {} on {} is resting instead of filling", oid, coin))
+                }
+                Ok(HyperLiquidTradeOutcome::Skipped { reason }) => Err(reason),
+                Err(e) => Err(e.to_string()),
+            },
+        }
+    }
+
+    /// Закрывает исполненную ногу, если вторая не открылась. Ноги в dry run закрывать не нужно.
+    async fn roll_back(
+        &self,
+        venue: HedgeVenue,
+        symbol: &str,
+        direction: &'static str,
+        leg: LegStatus,
+    ) -> LegStatus {
+        let LegStatus::Filled { quantity, avg_price } = leg else {
+            return leg;
+        };
+        warn!(
+            "Hedge: closing {} {} leg on {} ({} coins) because the other leg failed",
+            direction,
+            symbol,
+            venue.name(),
+            quantity
+        );
+
+        match self.close_leg(venue, symbol, direction, quantity).await {
+            Ok(()) => LegStatus::RolledBack { quantity, avg_price },
+            Err(error) => {
+                error!(
+                    "Hedge: failed to close {} {} leg on {}: {}. MANUAL ACTION REQUIRED.",
+                    direction,
+                    symbol,
+                    venue.name(),
+                    error
+                );
+                LegStatus::RollbackFailed {
+                    quantity,
+                    avg_price,
+                    error,
+                }
+            }
+        }
+    }
+
+    /// Если обе ноги исполнились, но разным объемом (частичное исполнение), закрывает излишек
+    /// большей ноги, округленный вниз до шага ее биржи. Остаток меньше шага закрыть нельзя –
+    /// он останется в результате как разница объемов.
+    async fn rebalance(
+        &self,
+        symbol: &str,
+        (long_venue, long): (HedgeVenue, LegStatus),
+        (short_venue, short): (HedgeVenue, LegStatus),
+    ) -> (LegStatus, LegStatus) {
+        let (
+            LegStatus::Filled { quantity: long_qty, avg_price: long_price },
+            LegStatus::Filled { quantity: short_qty, avg_price: short_price },
+        ) = (&long, &short)
+        else {
+            return (long, short);
+        };
+        if same_quantity(*long_qty, *short_qty) {
+            return (long, short);
+        }
+
+        let (venue, direction, larger, smaller) = if long_qty > short_qty {
+            (long_venue, "LONG", *long_qty, *short_qty)
+        } else {
+            (short_venue, "SHORT", *short_qty, *long_qty)
+        };
+        let step = self.quantity_step(venue, symbol).await.unwrap_or(0.0);
+        let excess = if step > 0.0 {
+            ((larger - smaller) / step + 1e-9).floor() * step
+        } else {
+            larger - smaller
+        };
+        warn!(
+            "Hedge: {} legs filled unequally ({} LONG vs {} SHORT), closing {} of the {} leg on {}",
+            symbol,
+            long_qty,
+            short_qty,
+            excess,
+            direction,
+            venue.name()
+        );
+        if excess <= 0.0 {
+            return (long, short);
+        }
+
+        let remaining = match self.close_leg(venue, symbol, direction, excess).await {
+            Ok(()) => larger - excess,
+            Err(e) => {
+                error!(
+                    "Hedge: failed to close excess {} of {} {} leg on {}: {}. MANUAL ACTION REQUIRED.",
+                    excess,
+                    direction,
+                    symbol,
+                    venue.name(),
+                    e
+                );
+                larger
+            }
+        };
+        if direction == "LONG" {
+            (LegStatus::Filled { quantity: remaining, avg_price: *long_price }, short)
+        } else {
+            (long, LegStatus::Filled { quantity: remaining, avg_price: *short_price })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(long: LegStatus, short: LegStatus) -> HedgeResult {
+        HedgeResult {
+            symbol: "BTCUSDT".to_string(),
+            quantity: 0.01,
+            long: HedgeLeg {
+                venue: HedgeVenue::BingX,
+                direction: "LONG",
+                status: long,
+            },
+            short: HedgeLeg {
+                venue: HedgeVenue::Bybit,
+                direction: "SHORT",
+                status: short,
+            },
+        }
+    }
+
+    fn executor() -> HedgeExecutor {
+        let state = Arc::new(SharedState::new(&Config::default(), None));
+        HedgeExecutor::new(state, None, None, None, DEFAULT_HEDGE_VENUES, 100.0)
+    }
+
+    #[test]
+    fn leg_outcomes_classify_the_hedge() {
+        let filled = LegStatus::Filled { quantity: 0.01, avg_price: 65000.0 };
+        let failed = LegStatus::Failed { error: "rejected".to_string() };
+
+        assert!(result(filled.clone(), filled.clone()).is_hedged());
+        assert!(result(LegStatus::DryRun { quantity: 0.01 }, LegStatus::DryRun { quantity: 0.01 }).is_hedged());
+
+        let rolled_back = result(LegStatus::RolledBack { quantity: 0.01, avg_price: 65000.0 }, failed.clone());
+        assert!(!rolled_back.is_hedged());
+        assert!(!rolled_back.has_naked_exposure());
+
+        let naked = result(
+            failed,
+            LegStatus::RollbackFailed {
+                quantity: 0.01,
+                avg_price: 65000.0,
+                error: "timeout".to_string(),
+            },
+        );
+        assert!(!naked.is_hedged());
+        assert!(naked.has_naked_exposure());
+    }
+
+    #[test]
+    fn unconfirmed_leg_is_resolved_by_position() {
+        let check = |position| check_unconfirmed(HedgeVenue::Bybit, "BTCUSDT", "LONG", "not filled".to_string(), position);
+        assert_eq!(
+            check(Ok(Some((0.01, 65000.0)))),
+            Ok(LegStatus::Filled { quantity: 0.01, avg_price: 65000.0 })
+        );
+        assert_eq!(check(Ok(None)), Err("not filled".to_string()));
+
+        let unknown = check(Err("timeout".to_string())).unwrap();
+        assert_eq!(unknown, LegStatus::Unknown { error: "not filled".to_string() });
+        let rolled_back = LegStatus::RolledBack { quantity: 0.01, avg_price: 65000.0 };
+        assert!(result(rolled_back, unknown).has_naked_exposure());
+    }
+
+    #[test]
+    fn failed_attempt_pauses_retries() {
+        let executor = executor();
+        let rolled_back = LegStatus::RolledBack { quantity: 0.01, avg_price: 65000.0 };
+        executor.record(&result(rolled_back, LegStatus::Failed { error: "rejected".to_string() }));
+        assert!(executor.is_cooling_down("BTCUSDT"));
+        assert!(!executor.is_open("BTCUSDT"));
+        assert!(!executor.is_cooling_down("ETHUSDT"));
+
+        let filled = LegStatus::Filled { quantity: 0.01, avg_price: 65000.0 };
+        executor.record(&result(filled.clone(), filled));
+        assert!(executor.is_open("BTCUSDT"));
+    }
+
+    #[test]
+    fn partial_fill_is_not_hedged() {
+        let partial = result(
+            LegStatus::Filled { quantity: 0.01, avg_price: 65000.0 },
+            LegStatus::Filled { quantity: 0.006, avg_price: 65010.0 },
+        );
+        assert!(!partial.is_hedged());
+        assert!(partial.has_naked_exposure());
+    }
+
+    #[test]
+    fn quantity_step_is_valid_on_both_venues() {
+        assert!((common_step(0.001, 0.01) - 0.01).abs() < 1e-12);
+        assert!((common_step(0.3, 0.2) - 0.6).abs() < 1e-12);
+        assert!((common_step(25.0, 10.0) - 50.0).abs() < 1e-12);
+        assert!((common_step(0.0, 0.01) - 0.01).abs() < 1e-12);
+    }
+
+    #[test]
+    fn hedge_venues_need_two_different_price_feeds() {
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(hedge_venues(None).unwrap(), DEFAULT_HEDGE_VENUES);
+        assert_eq!(
+            hedge_venues(Some(&names(&["bingx", "HYPERLIQUID"]))).unwrap(),
+            (HedgeVenue::BingX, HedgeVenue::Hyperliquid)
+        );
+        assert!(hedge_venues(Some(&names(&["BingX", "Bybit"]))).is_err());
+        assert!(hedge_venues(Some(&names(&["Bybit"]))).is_err());
+        assert!(hedge_venues(Some(&names(&["Bybit", "Binance"]))).is_err());
+    }
+
+    #[tokio::test]
+    async fn invalid_requests_are_rejected_before_orders() {
+        let executor = executor();
+        assert!(matches!(
+            executor.execute_hedge("BTCUSDT", HedgeVenue::Bybit, HedgeVenue::Bybit, 100.0).await,
+            Err(HedgeError::SameVenue(_))
+        ));
+        assert!(matches!(
+            executor.execute_hedge("BTCUSDT", HedgeVenue::BingX, HedgeVenue::Bybit, -1.0).await,
+            Err(HedgeError::InvalidNotional(_))
+        ));
+        assert!(matches!(
+            executor.execute_hedge("BTCUSDT", HedgeVenue::BingX, HedgeVenue::Bybit, 100.0).await,
+            Err(HedgeError::VenueNotConfigured("BingX"))
+        ));
+    }
+}
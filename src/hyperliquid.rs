@@ -1,9 +1,31 @@
-use crate::share_state::SharedState;
-use hyperliquid_rust_sdk::{BaseUrl, InfoClient, Message, Subscription};
-use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::sync::mpsc::unbounded_channel;
-use tokio::time::sleep;
+use crate::funding::{per_funding_period, poll_funding_rates};
+use crate::share_state::{BookSideUpdate, FeedStatus, SharedState, HYPERLIQUID};
+use hyperliquid_rust_sdk::{BaseUrl, InfoClient, L2BookData, Message, Subscription};
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use crate::utils::{canonical_symbol, sleep_or_shutdown, to_canonical, wait_for_shutdown, Backoff, CanonicalSymbol, Shutdown};
 use log::{error, info, warn};
+use serde::Deserialize;
+
+/// Hyperliquid начисляет фандинг каждый час.
+const FUNDING_INTERVAL_HOURS: f64 = 1.0;
+const INFO_URL: &str = "https://api.hyperliquid.xyz/info";
+
+#[derive(Debug, Deserialize)]
+struct PerpMeta {
+    universe: Vec<PerpAssetMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PerpAssetMeta {
+    name: String,
+}
+
+/// Контекст актива из `metaAndAssetCtxs`; нужен только текущий фандинг.
+#[derive(Debug, Deserialize)]
+struct PerpAssetCtx {
+    funding: String,
+}
 
 pub struct HyperLiquidStruct {
     info_client: InfoClient,
@@ -11,7 +33,7 @@ pub struct HyperLiquidStruct {
 
 impl HyperLiquidStruct {
     pub async fn new() -> Self {
-        let info_client = match InfoClient::new(None, Some(BaseUrl::Mainnet)).await {
+        match Self::try_new().await {
             Ok(client) => {
                 info!("HyperLiquid InfoClient initialized successfully");
                 client
@@ -20,45 +42,155 @@ impl HyperLiquidStruct {
                 error!("Failed to initialize HyperLiquid InfoClient: {}", e);
                 panic!("Failed to initialize HyperLiquid client");
             }
-        };
-        Self { info_client }
+        }
+    }
+
+    /// Как `new`, но ошибку создания клиента возвращает, а не завершает процесс (для --preflight).
+    pub async fn try_new() -> Result<Self, hyperliquid_rust_sdk::Error> {
+        let info_client = InfoClient::new(None, Some(BaseUrl::Mainnet)).await?;
+        Ok(Self { info_client })
     }
 
     fn format_hyperliquid_tickers(tickers: &HashMap<String, String>) -> Vec<String> {
         tickers
             .keys()
-            .map(Self::format_ticker_name)
+            .filter_map(|ticker| Some(Self::format_ticker_name(ticker)?.symbol))
             .collect()
     }
 
-    fn format_ticker_name(ticker: &String) -> String {
-        let formatted_ticker = if ticker.starts_with("k") {
-            ticker.replacen("k", "1000", 1)
-        } else {
-            ticker.to_string()
-        };
-        format!("{}USDT", formatted_ticker)
+    /// Монета Hyperliquid (`BTC`, `kPEPE`) в каноническом тикере проекта (`BTCUSDT` при QUOTE_CURRENCY=USDT).
+    /// None для спотовых пар из `allMids` (`@107`, `PURR/USDC`).
+    fn format_ticker_name(ticker: &str) -> Option<CanonicalSymbol> {
+        to_canonical(HYPERLIQUID, ticker).map(|symbol| canonical_symbol(&symbol))
     }
 
-    pub async fn get_tickers(&self) -> Vec<String> {
-        let tickers = match self.info_client.all_mids().await {
-            Ok(tickers) => tickers,
+    /// Тикеры Hyperliquid в формате проекта. Ошибка запроса возвращается вызывающему.
+    pub async fn get_tickers(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let tickers = self.info_client.all_mids().await?;
+        let format_tickers = Self::format_hyperliquid_tickers(&tickers);
+        info!("Retrieved {} HyperLiquid tickers", format_tickers.len());
+        Ok(format_tickers)
+    }
+
+    /// Периодически запрашивает `metaAndAssetCtxs` и сохраняет ставки фандинга в `SharedState`.
+    /// Работает независимо от WebSocket-подписки на цены.
+    pub async fn hyperliquid_funding_poller(shared_state: &Arc<SharedState>, client: reqwest::Client, shutdown: Shutdown) {
+        poll_funding_rates(HYPERLIQUID, shared_state, || Self::fetch_funding_rates(&client), shutdown).await;
+    }
+
+    async fn fetch_funding_rates(client: &reqwest::Client) -> Result<HashMap<String, f64>, reqwest::Error> {
+        let (meta, ctxs): (PerpMeta, Vec<PerpAssetCtx>) = client
+            .post(INFO_URL)
+            .json(&serde_json::json!({ "type": "metaAndAssetCtxs" }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Контексты идут в том же порядке, что и монеты в universe
+        let funding = meta
+            .universe
+            .iter()
+            .zip(ctxs.iter())
+            .filter_map(|(asset, ctx)| {
+                let rate = ctx.funding.parse::<f64>().ok()?;
+                let rate = per_funding_period(rate, FUNDING_INTERVAL_HOURS)?;
+                Some((Self::format_ticker_name(&asset.name)?.symbol, rate))
+            })
+            .collect();
+        Ok(funding)
+    }
+
+    /// Подписывается на L2-стакан каждой монеты из `common_tickers` – нужен для проверки
+    /// спреда по стакану. Ошибка подписки на отдельную монету не прерывает фид цен.
+    async fn subscribe_l2_books(
+        info_client: &mut InfoClient,
+        common_tickers: &HashSet<String>,
+        sender: &UnboundedSender<Message>,
+    ) {
+        let coins = match info_client.all_mids().await {
+            Ok(mids) => mids.into_keys(),
             Err(e) => {
-                error!("Failed to get HyperLiquid tickers: {}", e);
-                return Vec::new();
+                warn!("Failed to get HyperLiquid coins for order book subscription: {}", e);
+                return;
             }
         };
-        let format_tickers = Self::format_hyperliquid_tickers(&tickers);
-        info!("Retrieved {} HyperLiquid tickers", format_tickers.len());
-        format_tickers
+        let mut subscribed = 0;
+        let is_common = |coin: &String| {
+            Self::format_ticker_name(coin).is_some_and(|canonical| common_tickers.contains(&canonical.symbol))
+        };
+        for coin in coins.filter(is_common) {
+            match info_client.subscribe(Subscription::L2Book { coin: coin.clone() }, sender.clone()).await {
+                Ok(_) => subscribed += 1,
+                Err(e) => warn!("Failed to subscribe to HyperLiquid order book for {}: {}", coin, e),
+            }
+        }
+        info!("Subscribed to {} HyperLiquid order books", subscribed);
     }
 
-    pub async fn hyperliquid_ws(self, shared_state: &Arc<SharedState>) {
+    /// Записывает цены из `allMids` одним вызовом, только для монет из `common_tickers`:
+    /// поток присылает все монеты биржи, а остальные в сравнении не участвуют.
+    /// Возвращает число записанных цен; отброшенные монеты учитываются в `skipped_writes` фида.
+    fn handle_all_mids(
+        shared_state: &SharedState,
+        common_tickers: &HashSet<String>,
+        mids: &HashMap<String, String>,
+    ) -> usize {
+        let mut skipped = 0u64;
+        let prices: Vec<(String, f64)> = mids
+            .iter()
+            .filter_map(|(ticker, price_str)| {
+                let canonical = Self::format_ticker_name(ticker)?;
+                if !common_tickers.contains(&canonical.symbol) {
+                    skipped += 1;
+                    return None;
+                }
+                // Нераспарсенную цену пропускаем, чтобы не затереть последнюю нулем
+                let price = match price_str.parse() {
+                    Ok(p) => canonical.unit_price(p),
+                    Err(e) => {
+                        warn!("Failed to parse price for {}: {} (value: {})", canonical.symbol, e, price_str);
+                        shared_state.record_parse_error(HYPERLIQUID);
+                        return None;
+                    }
+                };
+                Some((canonical.symbol, price))
+            })
+            .collect();
+        shared_state.record_skipped_writes(HYPERLIQUID, skipped);
+        let stored = prices.len();
+        shared_state.set_prices(HYPERLIQUID, prices);
+        stored
+    }
+
+    /// Сохраняет лучшие bid/ask из снимка L2-стакана (`levels[0]` – bids, `levels[1]` – asks).
+    fn handle_l2_book(shared_state: &SharedState, book: &L2BookData) {
+        let Some(canonical) = Self::format_ticker_name(&book.coin) else {
+            return;
+        };
+        // Снимок: пустая сторона стакана очищается
+        let level = |side: usize| {
+            let Some(levels) = book.levels.get(side) else {
+                return BookSideUpdate::Unchanged;
+            };
+            if levels.is_empty() {
+                return BookSideUpdate::Cleared;
+            }
+            BookSideUpdate::from_levels(levels.iter().filter_map(|level| {
+                let (price, size) = (level.px.parse::<f64>().ok()?, level.sz.parse::<f64>().ok()?);
+                Some((canonical.unit_price(price), canonical.unit_size(size)))
+            }))
+        };
+        shared_state.update_book(HYPERLIQUID, &canonical.symbol, level(0), level(1));
+    }
+
+    pub async fn hyperliquid_ws(self, common_tickers: &HashSet<String>, shared_state: &Arc<SharedState>, mut shutdown: Shutdown) {
         const MAX_RECONNECT_ATTEMPTS: u32 = 0; // 0 = бесконечные попытки
-        const RECONNECT_DELAY: Duration = Duration::from_secs(5);
-        const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
         
         let mut reconnect_count = 0u32;
+        let reconnect = shared_state.reconnect_config(HYPERLIQUID);
+        let mut backoff = Backoff::new(reconnect.base_delay, reconnect.max_delay);
         
         // Внешний цикл для переподключений
         loop {
@@ -77,19 +209,24 @@ impl HyperLiquidStruct {
                 Err(e) => {
                     error!("Failed to create HyperLiquid InfoClient: {}", e);
                     reconnect_count += 1;
+                    shared_state.record_reconnect(HYPERLIQUID);
                     if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
                         error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
                         return;
                     }
-                    warn!("Retrying in {:?}...", RECONNECT_DELAY);
-                    sleep(RECONNECT_DELAY).await;
+                    let delay = backoff.next_delay();
+                    warn!("Retrying in {:?}...", delay);
+                    if sleep_or_shutdown(delay, &mut shutdown).await {
+                        info!("HyperLiquid WebSocket stopped");
+                        return;
+                    }
                     continue;
                 }
             };
 
             // Создаем канал для подписки
             let (sender, mut receiver) = unbounded_channel();
-            match info_client.subscribe(Subscription::AllMids, sender).await {
+            match info_client.subscribe(Subscription::AllMids, sender.clone()).await {
                 Ok(_) => {
                     if reconnect_count == 0 {
                         info!("Subscribed to HyperLiquid WebSocket");
@@ -97,47 +234,68 @@ impl HyperLiquidStruct {
                         info!("Reconnected to HyperLiquid WebSocket (attempt {})", reconnect_count + 1);
                     }
                     reconnect_count = 0; // Сбрасываем счетчик при успешном подключении
+                    backoff.reset();
+                    shared_state.set_feed_status(HYPERLIQUID, FeedStatus::Live);
                 }
                 Err(e) => {
                     error!("Failed to subscribe to HyperLiquid WebSocket: {}", e);
                     reconnect_count += 1;
+                    shared_state.record_reconnect(HYPERLIQUID);
                     if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
                         error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
                         return;
                     }
-                    warn!("Retrying subscription in {:?}...", RECONNECT_DELAY);
-                    sleep(RECONNECT_DELAY).await;
+                    let delay = backoff.next_delay();
+                    warn!("Retrying subscription in {:?}...", delay);
+                    if sleep_or_shutdown(delay, &mut shutdown).await {
+                        info!("HyperLiquid WebSocket stopped");
+                        return;
+                    }
                     continue;
                 }
             }
 
+            if shared_state.depth_check_notional.is_some() {
+                Self::subscribe_l2_books(&mut info_client, common_tickers, &sender).await;
+            }
+
             // Внутренний цикл для обработки сообщений
             let mut last_message_time = std::time::Instant::now();
             let mut connection_alive = true;
+            // Сколько монет из allMids реально записывается – один раз на соединение
+            let mut mids_logged = false;
             
             while connection_alive {
-                // Используем timeout для обнаружения "тихих" разрывов соединения
-                // Если сообщения не приходят долго, возможно соединение разорвано
-                match tokio::time::timeout(HEARTBEAT_TIMEOUT, receiver.recv()).await {
+                let message = tokio::select! {
+                    _ = wait_for_shutdown(&mut shutdown) => {
+                        // Клиент и подписка освобождаются при выходе из функции
+                        info!("HyperLiquid WebSocket stopped");
+                        return;
+                    }
+                    // Используем timeout для обнаружения "тихих" разрывов соединения
+                    // Если сообщения не приходят долго, возможно соединение разорвано
+                    message = tokio::time::timeout(reconnect.heartbeat_timeout, receiver.recv()) => message,
+                };
+
+                match message {
                     Ok(Some(message)) => {
                         last_message_time = std::time::Instant::now();
+                        shared_state.record_feed_message(HYPERLIQUID);
                         match message {
                             Message::AllMids(all_mids) => {
-                                for (ticker, price_str) in all_mids.data.mids.iter() {
-                                    let formatted_ticker = Self::format_ticker_name(ticker);
-                                    let price: f64 = match price_str.parse() {
-                                        Ok(p) => p,
-                                        Err(e) => {
-                                            warn!("Failed to parse price for {}: {} (value: {})", formatted_ticker, e, price_str);
-                                            0.0
-                                        }
-                                    };
-                                    {
-                                        let mut hyperliquid_prices = shared_state.hyperliquid_prices.write().await;
-                                        hyperliquid_prices.insert(formatted_ticker.clone(), price);
-                                    }
+                                let stored = Self::handle_all_mids(shared_state, common_tickers, &all_mids.data.mids);
+                                if !mids_logged {
+                                    info!(
+                                        "HyperLiquid allMids: storing {} of {} coins (common tickers only)",
+                                        stored,
+                                        all_mids.data.mids.len()
+                                    );
+                                    mids_logged = true;
                                 }
                             }
+                            Message::L2Book(book) => {
+                                Self::handle_l2_book(shared_state, &book.data);
+                            }
                             _ => {
                                 warn!("Received unexpected message type from HyperLiquid");
                             }
@@ -160,21 +318,62 @@ impl HyperLiquidStruct {
             // Соединение потеряно, пытаемся переподключиться
             error!("HyperLiquid WebSocket connection lost. Attempting to reconnect...");
             reconnect_count += 1;
+            shared_state.record_reconnect(HYPERLIQUID);
             
             if MAX_RECONNECT_ATTEMPTS > 0 && reconnect_count >= MAX_RECONNECT_ATTEMPTS {
                 error!("Max reconnection attempts ({}) reached. Exiting.", MAX_RECONNECT_ATTEMPTS);
                 return;
             }
             
-            warn!("Reconnecting in {:?}... (attempt {}{})", 
-                  RECONNECT_DELAY, 
+            let delay = backoff.next_delay();
+            warn!("Reconnecting in {:?}... (attempt {}{})",
+                  delay,
                   reconnect_count,
                   if MAX_RECONNECT_ATTEMPTS > 0 {
                       format!("/{}", MAX_RECONNECT_ATTEMPTS)
                   } else {
                       "".to_string()
                   });
-            sleep(RECONNECT_DELAY).await;
+            if sleep_or_shutdown(delay, &mut shutdown).await {
+                info!("HyperLiquid WebSocket stopped");
+                return;
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn all_mids_outside_common_tickers_are_not_stored() {
+        let state = SharedState::new(&Config::default(), None);
+        let common = HashSet::from(["BTCUSDT".to_string(), "PEPEUSDT".to_string()]);
+        let mids = HashMap::from([
+            ("BTC".to_string(), "65000.5".to_string()),
+            ("kPEPE".to_string(), "0.012".to_string()),
+            ("DOGE".to_string(), "0.15".to_string()),
+        ]);
+
+        assert_eq!(HyperLiquidStruct::handle_all_mids(&state, &common, &mids), 2);
+        assert_eq!(state.get_price(HYPERLIQUID, "BTCUSDT").map(|q| q.price), Some(65000.5));
+        assert!(state.get_price(HYPERLIQUID, "PEPEUSDT").is_some());
+        assert!(state.get_price(HYPERLIQUID, "DOGEUSDT").is_none());
+        let feed = state.feed_status().into_iter().find(|feed| feed.exchange == HYPERLIQUID).unwrap();
+        assert_eq!(feed.skipped_writes, 1);
+    }
+
+    #[test]
+    fn unparsable_mid_keeps_the_last_price() {
+        let state = SharedState::new(&Config::default(), None);
+        let common = HashSet::from(["BTCUSDT".to_string()]);
+        let mids = HashMap::from([("BTC".to_string(), "65000.5".to_string())]);
+        HyperLiquidStruct::handle_all_mids(&state, &common, &mids);
+
+        let broken = HashMap::from([("BTC".to_string(), "n/a".to_string())]);
+        assert_eq!(HyperLiquidStruct::handle_all_mids(&state, &common, &broken), 0);
+        assert_eq!(state.get_price(HYPERLIQUID, "BTCUSDT").map(|q| q.price), Some(65000.5));
+    }
+}
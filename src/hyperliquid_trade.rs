@@ -0,0 +1,233 @@
+use ethers::signers::LocalWallet;
+use hyperliquid_rust_sdk::{
+    BaseUrl, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus, MarketOrderParams,
+};
+use log::{error, info, warn};
+use thiserror::Error;
+
+use crate::config::HyperliquidConfig;
+use crate::share_state::HYPERLIQUID;
+use crate::utils::{canonical_symbol, from_canonical, quote_currency, to_canonical};
+
+/// Допустимое проскальзывание маркет-ордера Hyperliquid (доля от mid).
+const DEFAULT_SLIPPAGE: f64 = 0.01;
+
+/// Клиент для торговли на Hyperliquid через `ExchangeClient` SDK.
+///
+/// Ключ кошелька берется из `[hyperliquid] private_key` (или HYPERLIQUID_PRIVATE_KEY). Используется как
+/// нога хеджа (`HedgeExecutor`).
+pub struct HyperLiquidTrader {
+    exchange_client: ExchangeClient,
+    /// Режим без отправки ордеров (HYPERLIQUID_DRY_RUN, по умолчанию включен).
+    dry_run: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum HyperLiquidTradeOutcome {
+    /// Маркет-ордер исполнен.
+    Opened {
+        coin: String,
+        is_buy: bool,
+        size: f64,
+        avg_price: f64,
+        oid: u64,
+    },
+    /// Ордер принят, но еще не исполнен (стоит в стакане).
+    Resting { coin: String, oid: u64 },
+    /// Режим HYPERLIQUID_DRY_RUN: ордер рассчитан, но не отправлен.
+    DryRun { coin: String, is_buy: bool, size: f64 },
+    /// Ничего не сделали.
+    Skipped { reason: String },
+}
+
+#[derive(Debug, Error)]
+pub enum HyperLiquidError {
+    #[error("missing env var: {0}")]
+    MissingEnv(String),
+    #[error("invalid private key: {0}")]
+    InvalidKey(String),
+    #[error("sdk error: {0}")]
+    Sdk(#[from] hyperliquid_rust_sdk::Error),
+    #[error("exchange error: {0}")]
+    Exchange(String),
+}
+
+impl HyperLiquidTrader {
+    pub async fn from_config(config: &HyperliquidConfig) -> Result<Self, HyperLiquidError> {
+        let private_key = config
+            .private_key
+            .as_deref()
+            .ok_or_else(|| HyperLiquidError::MissingEnv("HYPERLIQUID_PRIVATE_KEY".into()))?;
+        if private_key.trim().is_empty() {
+            return Err(HyperLiquidError::MissingEnv(
+                "HYPERLIQUID_PRIVATE_KEY is empty".to_string(),
+            ));
+        }
+
+        let wallet: LocalWallet = private_key
+            .trim()
+            .trim_start_matches("0x")
+            .parse()
+            .map_err(|e| HyperLiquidError::InvalidKey(format!("{}", e)))?;
+
+        let exchange_client =
+            ExchangeClient::new(None, wallet, Some(BaseUrl::Mainnet), None, None).await?;
+        info!("HyperLiquid ExchangeClient initialized successfully");
+
+        Ok(Self {
+            exchange_client,
+            // Как и для BingX и Bybit, без явного HYPERLIQUID_DRY_RUN=false реальные ордера не отправляются
+            dry_run: config.dry_run.unwrap_or(true),
+        })
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    /// Монета Hyperliquid, число знаков объема и множитель для канонического тикера:
+    /// `PEPEUSDT` торгуется как `kPEPE`, одна единица которого – 1000 монет.
+    fn asset(&self, symbol: &str) -> Result<(String, u32, f64), HyperLiquidError> {
+        self.exchange_client
+            .meta
+            .universe
+            .iter()
+            .find_map(|asset| {
+                let canonical = canonical_symbol(&to_canonical(HYPERLIQUID, &asset.name)?);
+                (canonical.symbol == symbol).then(|| (asset.name.clone(), asset.sz_decimals, canonical.multiplier))
+            })
+            .ok_or_else(|| HyperLiquidError::Exchange(format!("no Hyperliquid asset for {}", symbol)))
+    }
+
+    /// Шаг количества по каноническому тикеру, в монетах.
+    pub fn quantity_step(&self, symbol: &str) -> Result<f64, HyperLiquidError> {
+        let (_, sz_decimals, multiplier) = self.asset(symbol)?;
+        Ok(10f64.powi(-(sz_decimals as i32)) * multiplier)
+    }
+
+    /// Маркет-ордер на `quantity` монет по каноническому тикеру (`PEPEUSDT`). Объем и цена в
+    /// `Opened` и `DryRun` – в монетах и за монету.
+    pub async fn market_order(
+        &self,
+        symbol: &str,
+        is_buy: bool,
+        quantity: f64,
+    ) -> Result<HyperLiquidTradeOutcome, HyperLiquidError> {
+        let (coin, _, multiplier) = self.asset(symbol)?;
+        let outcome = match self.open_market_position(&coin, is_buy, quantity / multiplier).await? {
+            HyperLiquidTradeOutcome::Opened { coin, is_buy, size, avg_price, oid } => HyperLiquidTradeOutcome::Opened {
+                coin,
+                is_buy,
+                size: size * multiplier,
+                avg_price: avg_price / multiplier,
+                oid,
+            },
+            HyperLiquidTradeOutcome::DryRun { coin, is_buy, size } => HyperLiquidTradeOutcome::DryRun {
+                coin,
+                is_buy,
+                size: size * multiplier,
+            },
+            other => other,
+        };
+        Ok(outcome)
+    }
+
+    /// Открывает позицию маркет-ордером.
+    ///
+    /// - coin: тикер проекта (`BTCUSDT`, `1000PEPEUSDT`) или имя монеты Hyperliquid
+    /// - is_buy: true – LONG, false – SHORT
+    /// - size: размер в монетах, должен соответствовать szDecimals монеты
+    pub async fn open_market_position(
+        &self,
+        coin: &str,
+        is_buy: bool,
+        size: f64,
+    ) -> Result<HyperLiquidTradeOutcome, HyperLiquidError> {
+        let hl_coin = if coin.ends_with(quote_currency()) {
+            from_canonical(HYPERLIQUID, coin)
+        } else {
+            coin.to_string()
+        };
+
+        if size <= 0.0 || !size.is_finite() {
+            return Ok(HyperLiquidTradeOutcome::Skipped {
+                reason: format!("invalid size {}", size),
+            });
+        }
+
+        info!(
+            "HyperLiquid: opening {} market position on {} with size={}",
+            if is_buy { "LONG" } else { "SHORT" },
+            hl_coin,
+            size
+        );
+
+        if self.dry_run {
+            info!("HyperLiquid [dry run]: would send market order for {} size={}", hl_coin, size);
+            return Ok(HyperLiquidTradeOutcome::DryRun {
+                coin: hl_coin,
+                is_buy,
+                size,
+            });
+        }
+
+        let response = self
+            .exchange_client
+            .market_open(MarketOrderParams {
+                asset: &hl_coin,
+                is_buy,
+                sz: size,
+                px: None,
+                slippage: Some(DEFAULT_SLIPPAGE),
+                cloid: None,
+                wallet: None,
+            })
+            .await?;
+
+        let response = match response {
+            ExchangeResponseStatus::Ok(response) => response,
+            ExchangeResponseStatus::Err(e) => {
+                error!("HyperLiquid: order for {} rejected: {}", hl_coin, e);
+                return Err(HyperLiquidError::Exchange(e));
+            }
+        };
+
+        let status = response
+            .data
+            .and_then(|data| data.statuses.into_iter().next())
+            .ok_or_else(|| HyperLiquidError::Exchange("empty order status in response".into()))?;
+
+        match status {
+            ExchangeDataStatus::Filled(filled) => {
+                let filled_size = filled.total_sz.parse::<f64>().unwrap_or(size);
+                let avg_price = filled.avg_px.parse::<f64>().unwrap_or(0.0);
+                info!(
+                    "HyperLiquid: order {} filled on {}: size={}, avg_price={}",
+                    filled.oid, hl_coin, filled_size, avg_price
+                );
+                Ok(HyperLiquidTradeOutcome::Opened {
+                    coin: hl_coin,
+                    is_buy,
+                    size: filled_size,
+                    avg_price,
+                    oid: filled.oid,
+                })
+            }
+            ExchangeDataStatus::Resting(resting) => {
+                warn!("HyperLiquid: market order {} on {} is resting", resting.oid, hl_coin);
+                Ok(HyperLiquidTradeOutcome::Resting {
+                    coin: hl_coin,
+                    oid: resting.oid,
+                })
+            }
+            ExchangeDataStatus::Error(e) => {
+                error!("HyperLiquid: order on {} failed: {}", hl_coin, e);
+                Err(HyperLiquidError::Exchange(e))
+            }
+            other => Err(HyperLiquidError::Exchange(format!(
+                "unexpected order status: {:?}",
+                other
+            ))),
+        }
+    }
+}
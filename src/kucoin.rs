@@ -0,0 +1,257 @@
+use crate::share_state::{SharedState, KUCOIN};
+use std::{sync::Arc, time::Duration};
+use crate::utils::{canonical_symbol, connect_ws, from_canonical, quote_currency, run_ws_with_reconnect, to_canonical, PingConfig, ReconnectConfig, Shutdown, WsStream};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// Код успешного ответа REST API KuCoin.
+const SUCCESS_CODE: &str = "200000";
+/// Сколько символов подписываем одним топиком (лимит KuCoin – 100).
+const SUBSCRIBE_BATCH_SIZE: usize = 100;
+/// Интервал ping: KuCoin присылает `pingInterval` 18 секунд, пинговать реже нельзя.
+const PING_INTERVAL: Duration = Duration::from_secs(18);
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    code: String,
+    #[serde(default)]
+    msg: String,
+    data: Option<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContractInfo {
+    symbol: String,
+    status: String,
+    settle_currency: String,
+}
+
+/// Ответ `bullet-public`: одноразовый токен и адрес WebSocket-сервера.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BulletToken {
+    token: String,
+    instance_servers: Vec<InstanceServer>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstanceServer {
+    endpoint: String,
+    /// Интервал ping в миллисекундах.
+    #[serde(default)]
+    ping_interval: Option<u64>,
+}
+
+/// Данные `tickerV2`: лучшие bid/ask, последней сделки в этом потоке нет.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerV2 {
+    symbol: String,
+    best_bid_price: String,
+    best_ask_price: String,
+}
+
+pub struct KucoinStruct {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl KucoinStruct {
+    /// Публичные эндпоинты KuCoin Futures не требуют ключей.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            base_url: "https://api-futures.kucoin.com".to_string(),
+            client,
+        }
+    }
+
+    pub async fn get_tickers(&self) -> Vec<String> {
+        // Получаем список активных контрактов
+        let contracts_url = format!("{}/api/v1/contracts/active", self.base_url);
+
+        match self.client.get(&contracts_url).send().await {
+            Ok(response) => {
+                match response.json::<ApiResponse<Vec<ContractInfo>>>().await {
+                    Ok(contracts) => {
+                        if contracts.code != SUCCESS_CODE {
+                            error!("KuCoin contracts request failed: code={}, msg={}", contracts.code, contracts.msg);
+                            return Vec::new();
+                        }
+                        let tickers: Vec<String> = contracts
+                            .data
+                            .unwrap_or_default()
+                            .into_iter()
+                            .filter(|c| c.status == "Open" && c.settle_currency == quote_currency())
+                            .filter_map(|c| to_canonical(KUCOIN, &c.symbol))
+                            .collect();
+                        info!("Retrieved {} KuCoin tickers", tickers.len());
+                        tickers
+                    }
+                    Err(e) => {
+                        error!("Failed to parse KuCoin contracts: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to get KuCoin contracts: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Запрашивает новый токен `bullet-public` – он действует только для одного подключения,
+    /// поэтому запрашивается перед каждым (пере)подключением.
+    async fn fetch_bullet_token(&self) -> Result<BulletToken, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!("{}/api/v1/bullet-public", self.base_url);
+        let response: ApiResponse<BulletToken> = self.client.post(&url).send().await?.json().await?;
+        if response.code != SUCCESS_CODE {
+            return Err(format!("bullet-public failed: code={}, msg={}", response.code, response.msg).into());
+        }
+        let token = response.data.ok_or("bullet-public returned no data")?;
+        if token.instance_servers.is_empty() {
+            return Err("bullet-public returned no instance servers".into());
+        }
+        Ok(token)
+    }
+
+    /// Получает токен и подключается к WebSocket.
+    async fn connect(&self) -> Result<WsStream, Box<dyn std::error::Error + Send + Sync>> {
+        let token = self.fetch_bullet_token().await?;
+        let server = &token.instance_servers[0];
+        let connect_id = chrono::Utc::now().timestamp_millis();
+        let ws_url = format!("{}?token={}&connectId={}", server.endpoint, token.token, connect_id);
+        if let Some(server_interval) = server.ping_interval.map(Duration::from_millis) {
+            if server_interval < PING_INTERVAL {
+                warn!(
+                    "KuCoin asks for ping every {:?}, but the feed pings every {:?}",
+                    server_interval, PING_INTERVAL
+                );
+            }
+        }
+
+        let (ws_stream, _) = connect_ws(&ws_url).await?;
+        Ok(ws_stream)
+    }
+
+    /// Подписывается на `/contractMarket/tickerV2` пачками по `SUBSCRIBE_BATCH_SIZE` символов.
+    async fn subscribe(ws_stream: &mut WsStream, tickers: &[String]) -> Result<(), tokio_tungstenite::tungstenite::Error> {
+        for (index, chunk) in tickers.chunks(SUBSCRIBE_BATCH_SIZE).enumerate() {
+            let symbols: Vec<String> = chunk.iter().map(|t| from_canonical(KUCOIN, t)).collect();
+            let subscribe_message = serde_json::json!({
+                "id": index.to_string(),
+                "type": "subscribe",
+                "topic": format!("/contractMarket/tickerV2:{}", symbols.join(",")),
+                "privateChannel": false,
+                "response": true
+            })
+            .to_string();
+            ws_stream.send(Message::Text(subscribe_message)).await?;
+        }
+        Ok(())
+    }
+
+    /// Разбирает сообщение `tickerV2` и сохраняет среднюю цену между лучшими bid и ask.
+    fn handle_ticker(shared_state: &SharedState, data: serde_json::Value) {
+        let ticker: TickerV2 = match serde_json::from_value(data) {
+            Ok(ticker) => ticker,
+            Err(e) => {
+                warn!("Failed to parse KuCoin ticker: {}", e);
+                shared_state.record_parse_error(KUCOIN);
+                return;
+            }
+        };
+        let Some(symbol) = to_canonical(KUCOIN, &ticker.symbol) else {
+            return;
+        };
+
+        let (bid, ask) = match (ticker.best_bid_price.parse::<f64>(), ticker.best_ask_price.parse::<f64>()) {
+            (Ok(bid), Ok(ask)) => (bid, ask),
+            _ => {
+                warn!(
+                    "Failed to parse prices for {} (bid: {}, ask: {})",
+                    symbol, ticker.best_bid_price, ticker.best_ask_price
+                );
+                return;
+            }
+        };
+        let price = (bid + ask) / 2.0;
+        if bid <= 0.0 || ask <= 0.0 || !price.is_finite() {
+            warn!("Invalid price for {}: bid {}, ask {}", symbol, bid, ask);
+            return;
+        }
+
+        let canonical = canonical_symbol(&symbol);
+        shared_state.set_price(KUCOIN, &canonical.symbol, canonical.unit_price(price));
+    }
+
+    /// KuCoin закрывает соединение, если клиент не присылает ping в течение pingTimeout,
+    /// поэтому `run_ws_with_reconnect` шлет `{"type":"ping"}` раз в `PING_INTERVAL`.
+    pub async fn kucoin_ws(self, tickers: &[String], shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        if tickers.is_empty() {
+            info!("No common tickers listed on KuCoin, KuCoin feed disabled");
+            return;
+        }
+
+        let config = ReconnectConfig {
+            ping: Some(PingConfig {
+                interval: PING_INTERVAL,
+                message: || {
+                    let id = format!("ping-{}", chrono::Utc::now().timestamp_millis());
+                    serde_json::json!({ "id": id, "type": "ping" }).to_string()
+                },
+            }),
+            ..shared_state.reconnect_config(KUCOIN)
+        };
+        let this = &self;
+
+        run_ws_with_reconnect(
+            KUCOIN,
+            shared_state,
+            &config,
+            shutdown,
+            // Токен bullet одноразовый и истекает, поэтому получаем новый и подписываемся при каждом подключении
+            move || async move {
+                let mut ws_stream = this.connect().await?;
+                Self::subscribe(&mut ws_stream, tickers).await?;
+                info!("Subscribed to {} KuCoin tickers", tickers.len());
+                Ok::<_, Box<dyn std::error::Error + Send + Sync>>(ws_stream)
+            },
+            move |text| Self::handle_message(shared_state, text),
+        )
+        .await;
+    }
+
+    async fn handle_message(shared_state: &Arc<SharedState>, text: String) {
+        let mut json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse KuCoin WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(KUCOIN);
+                return;
+            }
+        };
+
+        match json.get("type").and_then(|t| t.as_str()) {
+            Some("message") => {
+                if let Some(data) = json.get_mut("data").map(serde_json::Value::take) {
+                    Self::handle_ticker(shared_state, data);
+                }
+            }
+            Some("pong") => {
+                debug!("Received pong from KuCoin");
+            }
+            Some("welcome") | Some("ack") => {}
+            Some("error") => {
+                warn!("KuCoin WebSocket error: {}", text);
+            }
+            _ => {
+                debug!("Unexpected KuCoin message: {}", text);
+            }
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+const DEFAULT_LOG_FILE_MAX_MB: u64 = 10;
+const DEFAULT_LOG_FILE_KEEP: usize = 5;
+
+/// Формат строк лога (LOG_FORMAT).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LogFormat {
+    /// Обычный текст env_logger.
+    Text,
+    /// Одна JSON-запись на строку (tracing-subscriber) для Loki/ELK.
+    Json,
+}
+
+/// Инициализирует логирование: консоль (stderr) и, если задан LOG_FILE, файл с ротацией
+/// по размеру. Уровни фильтруются как раньше – RUST_LOG поверх уровня Info.
+pub fn init() {
+    // Логгер еще не инициализирован, поэтому проблемы с настройкой копим и пишем после init
+    let mut warnings = Vec::new();
+
+    let format = match env::var("LOG_FORMAT") {
+        Ok(value) => match value.trim().to_lowercase().as_str() {
+            "json" => LogFormat::Json,
+            "text" | "" => LogFormat::Text,
+            _ => {
+                warnings.push(format!("Invalid LOG_FORMAT '{}', using text", value));
+                LogFormat::Text
+            }
+        },
+        Err(_) => LogFormat::Text,
+    };
+
+    let mut log_path = None;
+    let mut tee = None;
+    if let Ok(path) = env::var("LOG_FILE") {
+        let path = path.trim().to_string();
+        if !path.is_empty() {
+            let max_mb = env_number("LOG_FILE_MAX_MB", DEFAULT_LOG_FILE_MAX_MB, &mut warnings);
+            let keep = env_number("LOG_FILE_KEEP", DEFAULT_LOG_FILE_KEEP, &mut warnings);
+            match RotatingFile::open(&path, max_mb * 1024 * 1024, keep) {
+                Ok(file) => {
+                    tee = Some(Tee {
+                        file,
+                        file_failed: false,
+                    });
+                    log_path = Some((path, max_mb, keep));
+                }
+                Err(e) => warnings.push(format!("Failed to open log file '{}': {}, logging to console only", path, e)),
+            }
+        }
+    }
+
+    match format {
+        LogFormat::Text => {
+            let mut builder = env_logger::Builder::from_default_env();
+            builder.filter_level(log::LevelFilter::Info);
+            if let Some(tee) = tee {
+                builder.target(env_logger::Target::Pipe(Box::new(tee)));
+            }
+            builder.init();
+        }
+        LogFormat::Json => {
+            // Записи `log::` из остального кода попадают сюда через мост tracing-log
+            let filter = tracing_subscriber::EnvFilter::builder()
+                .with_default_directive(tracing_subscriber::filter::LevelFilter::INFO.into())
+                .from_env_lossy();
+            let subscriber = tracing_subscriber::fmt()
+                .json()
+                .with_env_filter(filter)
+                .with_current_span(false)
+                .with_span_list(false);
+            match tee {
+                Some(tee) => subscriber.with_writer(std::sync::Mutex::new(tee)).init(),
+                None => subscriber.with_writer(io::stderr).init(),
+            }
+        }
+    }
+
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+    if let Some((path, max_mb, keep)) = log_path {
+        log::info!("Logging to {} (rotating at {} MB, keeping {} files)", path, max_mb, keep);
+    }
+}
+
+/// Читает положительное число из окружения; при некорректном значении – значение по умолчанию.
+fn env_number<T>(var: &str, default: T, warnings: &mut Vec<String>) -> T
+where
+    T: std::str::FromStr + PartialOrd + Default + std::fmt::Display,
+{
+    match env::var(var) {
+        Ok(value) => match value.trim().parse::<T>() {
+            Ok(v) if v > T::default() => v,
+            _ => {
+                warnings.push(format!("Invalid {} '{}', using default {}", var, value, default));
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// Пишет каждую строку лога и в stderr, и в файл. Ошибка записи в файл не мешает
+/// выводу в консоль.
+struct Tee {
+    file: RotatingFile,
+    /// Сообщаем об ошибке записи в файл один раз, чтобы не засыпать консоль.
+    file_failed: bool,
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Err(e) = self.file.write_all(buf) {
+            if !self.file_failed {
+                self.file_failed = true;
+                let _ = writeln!(io::stderr(), "Failed to write log file: {}", e);
+            }
+        } else {
+            self.file_failed = false;
+        }
+        io::stderr().write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let _ = self.file.flush();
+        io::stderr().flush()
+    }
+}
+
+/// Файл лога с ротацией по размеру: при превышении `max_bytes` текущий файл становится
+/// `<path>.1`, прежний `<path>.1` – `<path>.2` и так далее; хранится `keep` старых файлов.
+struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    written: u64,
+}
+
+impl RotatingFile {
+    fn open(path: impl AsRef<Path>, max_bytes: u64, keep: usize) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            max_bytes,
+            keep,
+            file,
+            written,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        // Самый старый файл удаляется, остальные сдвигаются на один номер
+        let _ = fs::remove_file(self.rotated_path(self.keep));
+        for index in (1..self.keep).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Строка лога не разрывается между файлами: ротируем до записи
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let n = self.file.write(buf)?;
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_by_size_and_keeps_limited_files() {
+        let dir = env::temp_dir().join(format!("hyperliquid_log_test_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("bot.log");
+
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+        for line in ["line-0001\n", "line-0002\n", "line-0003\n", "line-0004\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        file.flush().unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "line-0004\n");
+        assert_eq!(fs::read_to_string(dir.join("bot.log.1")).unwrap(), "line-0003\n");
+        assert_eq!(fs::read_to_string(dir.join("bot.log.2")).unwrap(), "line-0002\n");
+        assert!(!dir.join("bot.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
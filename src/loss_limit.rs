@@ -0,0 +1,156 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{NaiveDate, Utc};
+use log::{error, info, warn};
+
+use crate::bingx::BingXClient;
+use crate::config::TradingConfig;
+use crate::telegram::TelegramNotifier;
+use crate::utils::{sleep_or_shutdown, Shutdown};
+
+/// Как часто запрашиваем реализованный PnL у BingX.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Реализованный результат за текущие сутки UTC.
+#[derive(Debug, Clone, Copy)]
+struct DayPnl {
+    day: NaiveDate,
+    realized: f64,
+}
+
+/// Дневной лимит убытка (DAILY_LOSS_LIMIT_USDT).
+///
+/// Раз в минуту берет реализованный PnL BingX с начала суток UTC. Когда убыток достигает
+/// лимита, включает стоп на клиенте BingX – новые позиции не открываются до полуночи UTC.
+#[derive(Debug)]
+pub struct DailyLossLimit {
+    limit_usdt: f64,
+    today: Mutex<DayPnl>,
+    breached: AtomicBool,
+}
+
+impl DailyLossLimit {
+    /// DAILY_LOSS_LIMIT_USDT из конфига. Если не задан или равен 0 – лимит отключен.
+    pub fn from_config(config: &TradingConfig) -> Option<Self> {
+        let limit_usdt = match config.daily_loss_limit_usdt? {
+            v if v >= 0.0 && v.is_finite() => v,
+            v => {
+                warn!("Invalid DAILY_LOSS_LIMIT_USDT '{}', daily loss limit disabled", v);
+                0.0
+            }
+        };
+        if limit_usdt == 0.0 {
+            return None;
+        }
+        info!("Daily loss limit: {} USDT", limit_usdt);
+        Some(Self::new(limit_usdt))
+    }
+
+    fn new(limit_usdt: f64) -> Self {
+        Self {
+            limit_usdt,
+            today: Mutex::new(DayPnl {
+                day: Utc::now().date_naive(),
+                realized: 0.0,
+            }),
+            breached: AtomicBool::new(false),
+        }
+    }
+
+    pub fn limit_usdt(&self) -> f64 {
+        self.limit_usdt
+    }
+
+    /// Реализованный PnL за текущие сутки UTC по последнему опросу.
+    pub fn realized_today(&self) -> f64 {
+        self.today.lock().unwrap_or_else(|e| e.into_inner()).realized
+    }
+
+    pub fn is_breached(&self) -> bool {
+        self.breached.load(Ordering::Relaxed)
+    }
+
+    /// Записывает PnL за `day`. С наступлением новых суток стоп снимается.
+    /// Возвращает `true`, если лимит нарушен именно этим обновлением.
+    fn update(&self, day: NaiveDate, realized: f64) -> bool {
+        let mut today = self.today.lock().unwrap_or_else(|e| e.into_inner());
+        if today.day != day && self.breached.swap(false, Ordering::Relaxed) {
+            info!("New UTC day, daily loss limit reset");
+        }
+        *today = DayPnl { day, realized };
+
+        let breached = -realized >= self.limit_usdt;
+        breached && !self.breached.swap(true, Ordering::Relaxed)
+    }
+
+    async fn check(&self, bingx: &BingXClient, telegram: Option<&TelegramNotifier>) {
+        let now = Utc::now();
+        let day = now.date_naive();
+        let day_start_ms = day
+            .and_hms_opt(0, 0, 0)
+            .map(|t| t.and_utc().timestamp_millis())
+            .unwrap_or_else(|| now.timestamp_millis());
+
+        let realized = match bingx.realized_pnl_since(day_start_ms).await {
+            Ok(pnl) => pnl,
+            Err(e) => {
+                error!("Failed to fetch BingX realized PnL: {}", e);
+                return;
+            }
+        };
+
+        let newly_breached = self.update(day, realized);
+        bingx.set_trading_halted(self.is_breached());
+        if !newly_breached {
+            return;
+        }
+
+        error!(
+            "Daily loss limit reached: realized {:.2} USDT today (limit {} USDT). New BingX positions are halted until UTC midnight.",
+            realized, self.limit_usdt
+        );
+        if let Some(telegram) = telegram {
+            let message = format!(
+                "🛑 <b>Daily loss limit reached</b>\n\n\
+                Realized today: <code>{:.2} USDT</code>\n\
+                Limit: <code>{} USDT</code>\n\
+                New BingX positions are halted until UTC midnight.",
+                realized, self.limit_usdt
+            );
+            if let Err(e) = telegram.send_message(&message).await {
+                error!("Failed to send Telegram daily loss alert: {}", e);
+            }
+        }
+    }
+
+    /// Опрашивает PnL раз в `POLL_INTERVAL` до остановки бота.
+    pub async fn monitor(&self, bingx: &BingXClient, telegram: Option<&TelegramNotifier>, mut shutdown: Shutdown) {
+        loop {
+            self.check(bingx, telegram).await;
+            if sleep_or_shutdown(POLL_INTERVAL, &mut shutdown).await {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breach_fires_once_and_resets_next_day() {
+        let limit = DailyLossLimit::new(50.0);
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+
+        assert!(!limit.update(day, -20.0));
+        assert!(limit.update(day, -55.0));
+        assert!(!limit.update(day, -60.0), "alert is sent only once per day");
+        assert!(limit.is_breached());
+
+        assert!(!limit.update(day.succ_opt().unwrap(), 0.0));
+        assert!(!limit.is_breached());
+    }
+}
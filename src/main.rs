@@ -1,46 +1,241 @@
-use crate::share_state::SharedState;
+use crate::share_state::{FeedStatus, SharedState};
 use bybit::Bybit;
 use hyperliquid::HyperLiquidStruct;
 use aster::AsterStruct;
+use binance::BinanceStruct;
+use okx::OkxStruct;
+use kucoin::KucoinStruct;
+use gate::GateStruct;
+use mexc::MexcStruct;
+use dydx::DydxStruct;
 use std::collections::HashSet;
+use std::fmt::Write;
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
+use clap::Parser;
+use futures_util::future::{join_all, FutureExt, LocalBoxFuture};
+use tokio::sync::watch;
 
 mod bybit;
 mod compare_price;
 mod hyperliquid;
+mod hyperliquid_trade;
+mod bybit_trade;
+mod hedge;
 mod share_state;
 mod telegram;
+mod telegram_commands;
 mod utils;
 mod bingx;
 mod aster;
+mod binance;
+mod okx;
+mod kucoin;
+mod gate;
+mod mexc;
+mod dydx;
+mod metrics;
+mod opportunity_log;
+mod database;
+mod alert_digest;
+mod alert_tier;
+mod logging;
+mod loss_limit;
+mod config;
+mod cli;
+mod funding;
+mod replay;
+mod preflight;
+mod recorder;
+mod spread_stats;
 
 use bingx::BingXClient;
+use utils::canonical_symbol;
 
-fn get_common_tickers(bybit_tickers: Vec<String>, hyperliquid_tickers: Vec<String>, aster_tickers: Vec<String>) -> HashSet<String> {
+/// Сколько раз запрашиваем тикеры биржи, пока она возвращает пустой список.
+const TICKER_FETCH_ATTEMPTS: u32 = 3;
+const TICKER_FETCH_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Ждет завершения фида биржи и помечает его `FeedStatus::Dead`: фид выходит из цикла,
+/// когда исчерпаны попытки переподключения, нет общих тикеров или пришел сигнал остановки.
+async fn run_feed(shared_state: &SharedState, exchange: &str, feed: impl Future<Output = ()>) {
+    feed.await;
+    shared_state.set_feed_status(exchange, FeedStatus::Dead);
+}
+
+/// Запрашивает тикеры биржи до `TICKER_FETCH_ATTEMPTS` раз: ошибка запроса или пустой список
+/// обычно означают временный сбой API. Если и последняя попытка неудачна, причина пишется
+/// в лог и возвращается пустой список.
+async fn fetch_tickers<F, Fut, E>(exchange: &str, mut fetch: F) -> Vec<String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Vec<String>, E>>,
+    E: std::fmt::Display,
+{
+    let mut attempt = 1;
+    loop {
+        let problem = match fetch().await {
+            Ok(tickers) if !tickers.is_empty() => return tickers,
+            Ok(_) => "returned no tickers".to_string(),
+            Err(e) => format!("failed to return tickers: {}", e),
+        };
+        if attempt >= TICKER_FETCH_ATTEMPTS {
+            log::error!("{} {} after {} attempts", exchange, problem, TICKER_FETCH_ATTEMPTS);
+            return Vec::new();
+        }
+        log::warn!(
+            "{} {} (attempt {}/{}), retrying in {:?}",
+            exchange, problem, attempt, TICKER_FETCH_ATTEMPTS, TICKER_FETCH_RETRY_DELAY
+        );
+        tokio::time::sleep(TICKER_FETCH_RETRY_DELAY).await;
+        attempt += 1;
+    }
+}
+
+/// Канонические тикеры (см. `canonical_symbol`) в котируемой валюте QUOTE_CURRENCY, которые
+/// есть во всех переданных списках. Без списков возвращает пустое множество.
+fn common_tickers(lists: &[&[String]]) -> HashSet<String> {
+    let quote = utils::quote_currency();
+    let canonical_set = |tickers: &[String]| -> HashSet<String> {
+        tickers
+            .iter()
+            .map(|ticker| canonical_symbol(ticker).symbol)
+            .filter(|symbol| symbol.len() > quote.len() && symbol.ends_with(quote))
+            .collect()
+    };
+    let Some((first, rest)) = lists.split_first() else {
+        return HashSet::new();
+    };
     // Используем HashSet для O(1) поиска вместо O(n)
-    let hyperliquid_set: HashSet<String> = hyperliquid_tickers.into_iter().collect();
-    let aster_set: HashSet<String> = aster_tickers.into_iter().collect();
-    let common_tickers: HashSet<String> = bybit_tickers
+    let rest_sets: Vec<HashSet<String>> = rest.iter().map(|tickers| canonical_set(tickers)).collect();
+    canonical_set(first)
         .into_iter()
-        .filter(|ticker| hyperliquid_set.contains(ticker) && aster_set.contains(ticker))
+        .filter(|ticker| rest_sets.iter().all(|set| set.contains(ticker)))
+        .collect()
+}
+
+/// Оставляет в `common` только тикеры белого списка INCLUDED_TOKENS (имена приводятся к
+/// каноническим, см. `canonical_symbol`). Возвращает отсортированные записи списка, которых
+/// нет среди общих тикеров. Пустой список ничего не ограничивает.
+fn restrict_to_included(common: &mut HashSet<String>, included: &[String]) -> Vec<String> {
+    let included: HashSet<String> = included
+        .iter()
+        .map(|token| token.trim().to_uppercase())
+        .filter(|token| !token.is_empty())
+        .map(|token| canonical_symbol(&token).symbol)
         .collect();
-    common_tickers
+    if included.is_empty() {
+        return Vec::new();
+    }
+    let mut missing: Vec<String> = included.iter().filter(|token| !common.contains(*token)).cloned().collect();
+    missing.sort();
+    common.retain(|ticker| included.contains(ticker));
+    missing
+}
+
+/// Сообщение о запуске: число общих тикеров, инициализированные биржи и состояние торговли.
+fn startup_summary(
+    common_tickers: usize,
+    exchange_ticker_counts: &[(&str, usize)],
+    optional_feed_tickers: &[(&str, usize)],
+    shared_state: &SharedState,
+) -> String {
+    let mut message = format!("🚀 <b>Bot started</b>\n\nCommon tickers: <code>{}</code>\n", common_tickers);
+    for (exchange, count) in exchange_ticker_counts {
+        let _ = writeln!(message, "✅ {}: {} tickers", exchange, count);
+    }
+    // Биржи вне пересечения: сколько общих тикеров на них есть
+    for (exchange, count) in optional_feed_tickers {
+        if *count > 0 {
+            let _ = writeln!(message, "✅ {}: {} common tickers", exchange, count);
+        } else {
+            let _ = writeln!(message, "⚠️ {}: no common tickers", exchange);
+        }
+    }
+    let bingx = match (shared_state.bingx.is_some(), shared_state.bingx_trading_enabled) {
+        (false, _) => "⚪ BingX: not configured",
+        (true, false) => "✅ BingX: connected, trading disabled",
+        (true, true) => "✅ BingX: connected, trading enabled",
+    };
+    message.push_str(bingx);
+    message
 }
 
 #[tokio::main]
 async fn main() {
+    // Аргументы разбираем первыми: на --help и ошибки clap сразу завершает процесс
+    let args = cli::Args::parse();
+
     // Загружаем переменные окружения из .env
     dotenv::dotenv().ok();
     
-    // Инициализируем логирование
-    env_logger::Builder::from_default_env()
-        .filter_level(log::LevelFilter::Info)
-        .init();
+    // Инициализируем логирование (консоль + опционально файл LOG_FILE)
+    logging::init();
+
+    // Конфиг из config.toml (или --config / CONFIG_PATH); незаданные поля берутся из окружения,
+    // флаги командной строки перекрывают и то, и другое
+    let mut config = match config::Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("Failed to load config: {}. Exiting.", e);
+            std::process::exit(1);
+        }
+    };
+    args.apply(&mut config);
+
+    // Прокси проверяется до первого запроса: с опечаткой в адресе не ходим напрямую
+    if let Err(e) = utils::init_proxy(&config.proxy) {
+        log::error!("Invalid proxy settings: {}. Exiting.", e);
+        std::process::exit(1);
+    }
+    if config.proxy.https.is_some() || config.proxy.ws.is_some() {
+        log::warn!("Hyperliquid SDK connections do not use the configured proxy");
+    }
+    // Один HTTP-клиент на все REST-запросы: общий пул соединений вместо клиента на каждый вызов
+    let http_client = match utils::build_http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Failed to create HTTP client: {}. Exiting.", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut enabled_exchanges = match config.enabled_exchanges() {
+        Ok(exchanges) => exchanges,
+        Err(e) => {
+            log::error!("Invalid exchanges list: {}. Exiting.", e);
+            std::process::exit(1);
+        }
+    };
+    let feed_enabled = |exchange: &str| enabled_exchanges.contains(exchange);
 
-    log::info!("Starting arbitrage bot (Bybit + Hyperliquid + ASTER)...");
+    log::info!("Starting arbitrage bot (Bybit + Hyperliquid + ASTER + Binance + OKX + KuCoin + Gate + MEXC + dYdX)...");
+
+    utils::init_quote_currency(config.quote_currency.as_deref());
+    compare_price::init_excluded_tokens(config.excluded_tokens.as_deref().unwrap_or_default());
+    compare_price::init_price_epsilon(config.thresholds.price_epsilon);
+
+    // Режим бэктеста: записанные цены вместо живых фидов, без Telegram и торговли
+    if let Some(path) = &args.replay {
+        let thresholds = args.replay_thresholds.clone().unwrap_or_else(|| {
+            vec![config.thresholds.min_spread_percent.unwrap_or(share_state::DEFAULT_MIN_SPREAD_PERCENT)]
+        });
+        if let Err(e) = replay::run(config, path, args.replay_speed, &thresholds).await {
+            log::error!("Replay of {} failed: {}", path.display(), e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Проверка ключей и доступности бирж перед запуском: отчет в лог, код выхода 1 при сбое
+    if args.preflight {
+        let passed = preflight::run(&config, &enabled_exchanges, http_client.clone()).await;
+        std::process::exit(if passed { 0 } else { 1 });
+    }
 
     // Инициализируем Telegram notifier (если доступен)
-    let telegram_notifier = match crate::telegram::TelegramNotifier::new() {
+    let telegram_notifier = match crate::telegram::TelegramNotifier::new(&config.telegram, http_client.clone()) {
         Ok(notifier) => {
             log::info!("Telegram notifier initialized successfully");
             Some(notifier)
@@ -54,7 +249,7 @@ async fn main() {
     let hyper_liquid = HyperLiquidStruct::new().await;
 
     // Инициализируем ASTER клиента
-    let aster_client = match AsterStruct::new() {
+    let aster_client = match AsterStruct::new(&config.aster, http_client.clone()) {
         Ok(client) => {
             log::info!("ASTER client initialized successfully");
             client
@@ -65,8 +260,8 @@ async fn main() {
         }
     };
 
-    // Инициализируем BingX клиента (если есть ключи в окружении)
-    let bingx_client = match BingXClient::from_env() {
+    // Инициализируем BingX клиента (если заданы ключи)
+    let bingx_client = match BingXClient::from_config(&config.bingx, http_client.clone()) {
         Ok(client) => {
             log::info!("BingX client initialized successfully");
             Some(Arc::new(client))
@@ -77,54 +272,437 @@ async fn main() {
         }
     };
 
-    let bybit = Bybit::new();
+    let bybit = Bybit::new(&config.bybit, http_client.clone());
+    let binance = BinanceStruct::new(http_client.clone());
+    let okx = OkxStruct::new(http_client.clone());
+    let kucoin = KucoinStruct::new(http_client.clone());
+    let gate = GateStruct::new(http_client.clone());
+    let mexc = MexcStruct::new(http_client.clone());
+    let dydx = DydxStruct::new(http_client.clone());
     let shared_state = Arc::new(
         if let Some(telegram) = telegram_notifier {
-            SharedState::with_telegram(telegram, bingx_client.clone())
+            SharedState::with_telegram(&config, telegram, bingx_client.clone())
         } else {
-            SharedState::new(bingx_client.clone())
+            SharedState::new(&config, bingx_client.clone())
         }
     );
 
-    let hyperliquid_tickers = hyper_liquid.get_tickers().await;
+    // Торговля на BingX по умолчанию выключена, чтобы обновление не начало торговать реальными деньгами
+    match (shared_state.bingx.is_some(), shared_state.bingx_trading_enabled) {
+        (true, true) if shared_state.bingx.as_ref().is_some_and(|b| b.is_dry_run()) => {
+            log::warn!("BingX auto-trading is enabled in DRY RUN mode: orders are logged, not sent (set BINGX_DRY_RUN=false to trade)")
+        }
+        (true, true) => log::warn!("BingX auto-trading is ENABLED"),
+        (true, false) => log::info!("BingX auto-trading is disabled (set ENABLE_BINGX_TRADING=true to enable)"),
+        (false, true) => log::warn!("ENABLE_BINGX_TRADING is set, but the BingX client is not initialized"),
+        (false, false) => {}
+    }
+    // Режим позиций (hedge / one-way) определяет параметры ордеров; при ошибке запрос повторится перед ордером
+    if let Some(bingx) = &shared_state.bingx {
+        if let Err(e) = bingx.position_mode().await {
+            log::warn!("BingX: failed to detect account position mode: {}", e);
+        }
+    }
 
-    let bybit_tickers = match bybit.get_tickers().await {
-        Ok(tickers) => tickers,
-        Err(e) => {
-            log::error!("Error calling bybit get tickers: {}", e);
-            std::process::exit(1);
+    // Хедж включается объемом HEDGE_NOTIONAL_USDT; ошибка настройки выключает только его
+    let hedge_executor =
+        match hedge::HedgeExecutor::from_config(&config, shared_state.clone(), bingx_client.clone(), http_client.clone())
+            .await
+        {
+            Ok(executor) => executor,
+            Err(e) => {
+                log::error!("Hedge trading is disabled: {}", e);
+                None
+            }
+        };
+    if let Some(hedge) = &hedge_executor {
+        let (a, b) = hedge.venues();
+        log::warn!(
+            "Hedge trading is ENABLED between {} and {}: {} USDT per leg",
+            a.name(),
+            b.name(),
+            hedge.notional()
+        );
+    }
+
+    // Тикеры запрашиваем только у включенных бирж
+    let hyperliquid_tickers = if feed_enabled(share_state::HYPERLIQUID) {
+        fetch_tickers(share_state::HYPERLIQUID, || hyper_liquid.get_tickers()).await
+    } else {
+        Vec::new()
+    };
+
+    let bybit_tickers = if feed_enabled(share_state::BYBIT) {
+        fetch_tickers(share_state::BYBIT, || bybit.get_tickers()).await
+    } else {
+        Vec::new()
+    };
+
+    // Параметры контрактов Bybit для расчета объема ордеров; шаги цены из них нужны режиму
+    // шагов, который сравнивает разницу с шагом цены Bybit, и округлению цен в сигналах
+    let tick_mode = matches!(shared_state.threshold_mode, compare_price::ThresholdMode::TickMultiple(_));
+    if !feed_enabled(share_state::BYBIT) {
+        if tick_mode {
+            log::warn!("THRESHOLD_MODE=ticks uses Bybit tick sizes, but the Bybit feed is disabled: no signals will fire");
         }
+    } else {
+        match bybit.get_instrument_info().await {
+            Ok(specs) => {
+                log::info!("Loaded {} Bybit instrument specs", specs.len());
+                let ticks = specs.iter().map(|(symbol, spec)| (symbol.clone(), spec.tick_size)).collect();
+                shared_state.set_tick_sizes(share_state::BYBIT, ticks);
+                shared_state.set_instrument_specs(share_state::BYBIT, specs);
+            }
+            Err(e) => log::error!("Failed to load Bybit instrument specs: {}", e),
+        }
+    }
+
+    let aster_tickers = if feed_enabled(share_state::ASTER) {
+        fetch_tickers(share_state::ASTER, || aster_client.get_tickers()).await
+    } else {
+        Vec::new()
     };
+    let binance_tickers = if feed_enabled(share_state::BINANCE) {
+        fetch_tickers(share_state::BINANCE, || binance.get_tickers().map(Ok::<_, std::convert::Infallible>)).await
+    } else {
+        Vec::new()
+    };
+    let okx_tickers = if feed_enabled(share_state::OKX) { okx.get_tickers().await } else { Vec::new() };
+    let kucoin_tickers = if feed_enabled(share_state::KUCOIN) { kucoin.get_tickers().await } else { Vec::new() };
+    let gate_tickers = if feed_enabled(share_state::GATE) { gate.get_tickers().await } else { Vec::new() };
+    let mexc_tickers = if feed_enabled(share_state::MEXC) { mexc.get_tickers().await } else { Vec::new() };
+    let dydx_tickers = if feed_enabled(share_state::DYDX) { dydx.get_tickers().await } else { Vec::new() };
 
-    let aster_tickers = aster_client.get_tickers().await;
+    // Биржа пересечения без тикеров даже после повторов: останавливаемся или, если
+    // REQUIRE_ALL_EXCHANGES=false, продолжаем без нее
+    let empty_exchanges: Vec<&str> = [
+        (share_state::BYBIT, &bybit_tickers),
+        (share_state::HYPERLIQUID, &hyperliquid_tickers),
+        (share_state::ASTER, &aster_tickers),
+        (share_state::BINANCE, &binance_tickers),
+    ]
+    .into_iter()
+    .filter(|(exchange, tickers)| feed_enabled(exchange) && tickers.is_empty())
+    .map(|(exchange, _)| exchange)
+    .collect();
+    if !empty_exchanges.is_empty() {
+        if config.require_all_exchanges.unwrap_or(true) {
+            log::error!(
+                "No tickers from {}. Exiting (set REQUIRE_ALL_EXCHANGES=false to continue without them).",
+                empty_exchanges.join(", ")
+            );
+            std::process::exit(1);
+        }
+        log::warn!("No tickers from {}, continuing without them", empty_exchanges.join(", "));
+        for exchange in &empty_exchanges {
+            enabled_exchanges.remove(exchange);
+        }
+    }
+    let feed_enabled = |exchange: &str| enabled_exchanges.contains(exchange);
+
+    // Пересечение считается по включенным биржам из Bybit, Hyperliquid, ASTER и Binance
+    let intersected: Vec<(&str, &[String])> = [
+        (share_state::BYBIT, bybit_tickers.as_slice()),
+        (share_state::HYPERLIQUID, hyperliquid_tickers.as_slice()),
+        (share_state::ASTER, aster_tickers.as_slice()),
+        (share_state::BINANCE, binance_tickers.as_slice()),
+    ]
+    .into_iter()
+    .filter(|(exchange, _)| feed_enabled(exchange))
+    .collect();
+    let intersected_names: Vec<&str> = intersected.iter().map(|(exchange, _)| *exchange).collect();
+    if intersected.is_empty() {
+        log::error!("At least one of Bybit, Hyperliquid, ASTER or Binance must be enabled");
+        std::process::exit(1);
+    }
+
+    let exchange_ticker_counts: Vec<(&str, usize)> = intersected
+        .iter()
+        .map(|(exchange, tickers)| (*exchange, tickers.len()))
+        .collect();
+
+    // Множество общих тикеров строится один раз; фиды получают ссылки на него
+    let ticker_lists: Vec<&[String]> = intersected.iter().map(|(_, tickers)| *tickers).collect();
+    let mut common_tickers = common_tickers(&ticker_lists);
+    log::info!("Found {} common tickers on {}", common_tickers.len(), intersected_names.join(", "));
+
+    let included_tokens = config.included_tokens.as_deref().unwrap_or_default();
+    let missing_included = restrict_to_included(&mut common_tickers, included_tokens);
+    if !missing_included.is_empty() {
+        log::warn!(
+            "INCLUDED_TOKENS not common to {}, ignored: {}",
+            intersected_names.join(", "),
+            missing_included.join(", ")
+        );
+    }
 
-    let common_tickers = get_common_tickers(bybit_tickers, hyperliquid_tickers, aster_tickers);
-    
     if common_tickers.is_empty() {
-        log::error!("No common tickers found between Bybit, Hyperliquid and ASTER");
+        log::error!("No common tickers found between {}", intersected_names.join(", "));
         std::process::exit(1);
     }
-    
-    log::info!("Found {} common tickers", common_tickers.len());
 
-    {
-        let mut bybit_prices = shared_state.bybit_prices.write().await;
-        let mut hyperliquid_price = shared_state.hyperliquid_prices.write().await;
-        let mut aster_prices = shared_state.aster_prices.write().await;
-        for ticker in &common_tickers {
-            bybit_prices.insert(ticker.clone(), 0.0);
-            hyperliquid_price.insert(ticker.clone(), 0.0);
-            aster_prices.insert(ticker.clone(), 0.0);
+    let excluded_count = common_tickers.iter().filter(|ticker| compare_price::is_excluded(ticker)).count();
+    log::info!(
+        "Tracking {} tickers ({} excluded{})",
+        common_tickers.len() - excluded_count,
+        excluded_count,
+        if included_tokens.is_empty() { "" } else { ", limited by INCLUDED_TOKENS" }
+    );
+    let common_tickers = Arc::new(common_tickers);
+
+    for ticker in common_tickers.iter() {
+        for exchange in &intersected_names {
+            shared_state.register_symbol(exchange, ticker);
+        }
+    }
+
+    // Vec нужен bybit_ws только для аргументов подписки – в нем тикеры Bybit как на бирже,
+    // фильтрация идет по HashSet канонических тикеров
+    let bybit_subscribe_tickers: Vec<String> = bybit_tickers
+        .into_iter()
+        .filter(|ticker| common_tickers.contains(&canonical_symbol(ticker).symbol))
+        .collect();
+
+    // Периодическое сравнение идет по каноническим тикерам – так цены лежат в SharedState
+    let mut compare_symbols: Vec<String> = common_tickers.iter().cloned().collect();
+    compare_symbols.sort();
+
+    // OKX, KuCoin, Gate, MEXC и dYdX не участвуют в пересечении: подписываемся только на общие
+    // тикеры, которые на них есть
+    let common_on = |exchange: &str, tickers: Vec<String>| -> Vec<String> {
+        let tickers: Vec<String> = tickers
+            .into_iter()
+            .filter(|ticker| common_tickers.contains(&canonical_symbol(ticker).symbol))
+            .collect();
+        if feed_enabled(exchange) {
+            log::info!("{} common tickers are listed on {}", tickers.len(), exchange);
+        }
+        tickers
+    };
+    let okx_subscribe_tickers = common_on(share_state::OKX, okx_tickers);
+    let kucoin_subscribe_tickers = common_on(share_state::KUCOIN, kucoin_tickers);
+    let gate_subscribe_tickers = common_on(share_state::GATE, gate_tickers);
+    let mexc_subscribe_tickers = common_on(share_state::MEXC, mexc_tickers);
+    let dydx_subscribe_tickers = common_on(share_state::DYDX, dydx_tickers);
+
+    if let Some(telegram) = &shared_state.telegram {
+        let optional_feed_tickers: Vec<(&str, usize)> = [
+            (share_state::OKX, okx_subscribe_tickers.len()),
+            (share_state::KUCOIN, kucoin_subscribe_tickers.len()),
+            (share_state::GATE, gate_subscribe_tickers.len()),
+            (share_state::MEXC, mexc_subscribe_tickers.len()),
+            (share_state::DYDX, dydx_subscribe_tickers.len()),
+        ]
+        .into_iter()
+        .filter(|(exchange, _)| feed_enabled(exchange))
+        .collect();
+        let message = startup_summary(
+            common_tickers.len(),
+            &exchange_ticker_counts,
+            &optional_feed_tickers,
+            &shared_state,
+        );
+        if let Err(e) = telegram.send_message(&message).await {
+            log::error!("Failed to send Telegram startup message: {}", e);
+        }
+    }
+
+    // Ctrl-C переводит сигнал остановки в true, фиды выходят из своих циклов
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => {
+                log::info!("Ctrl-C received, shutting down...");
+                let _ = shutdown_tx.send(true);
+            }
+            Err(e) => {
+                log::error!("Failed to listen for Ctrl-C: {}", e);
+            }
         }
+    });
+
+    for exchange in share_state::EXCHANGES.iter().filter(|exchange| !feed_enabled(exchange)) {
+        log::info!("{} feed disabled by exchanges list", exchange);
+        shared_state.set_feed_status(exchange, FeedStatus::Dead);
     }
 
-    // Конвертируем HashSet в Vec для передачи в bybit_ws (для совместимости)
-    let common_tickers_vec: Vec<String> = common_tickers.iter().cloned().collect();
-    let common_tickers_set = common_tickers;
+    // Фиды только включенных бирж (--exchanges / EXCHANGES)
+    let mut feeds: Vec<LocalBoxFuture<'_, ()>> = Vec::new();
+    // Ставки фандинга остальных бирж нужны только для сравнения фандинга (MIN_FUNDING_DIFF)
+    if shared_state.min_funding_diff_percent.is_some() {
+        if feed_enabled(share_state::BYBIT) {
+            feeds.push(bybit.bybit_funding_poller(&shared_state, shutdown_rx.clone()).boxed_local());
+        }
+        if feed_enabled(share_state::ASTER) {
+            feeds.push(aster_client.funding_poller(&shared_state, shutdown_rx.clone()).boxed_local());
+        }
+        if feed_enabled(share_state::BINANCE) {
+            feeds.push(binance.funding_poller(&shared_state, shutdown_rx.clone()).boxed_local());
+        }
+    }
+    if feed_enabled(share_state::HYPERLIQUID) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::HYPERLIQUID,
+                hyper_liquid.hyperliquid_ws(&common_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+        feeds.push(HyperLiquidStruct::hyperliquid_funding_poller(&shared_state, http_client.clone(), shutdown_rx.clone()).boxed_local());
+    }
+    if feed_enabled(share_state::BYBIT) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::BYBIT,
+                bybit.bybit_ws(&bybit_subscribe_tickers, &common_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::ASTER) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::ASTER,
+                aster_client.aster_ws(&common_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::BINANCE) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::BINANCE,
+                binance.binance_ws(&shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::OKX) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::OKX,
+                okx.okx_ws(&okx_subscribe_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::KUCOIN) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::KUCOIN,
+                kucoin.kucoin_ws(&kucoin_subscribe_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::GATE) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::GATE,
+                gate.gate_ws(&gate_subscribe_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::MEXC) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::MEXC,
+                mexc.mexc_ws(&mexc_subscribe_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
+    if feed_enabled(share_state::DYDX) {
+        feeds.push(
+            run_feed(
+                &shared_state,
+                share_state::DYDX,
+                dydx.dydx_ws(&dydx_subscribe_tickers, &shared_state, shutdown_rx.clone()),
+            )
+            .boxed_local(),
+        );
+    }
 
     tokio::join!(
-        hyper_liquid.hyperliquid_ws(&shared_state),
-        bybit.bybit_ws(&common_tickers_vec, &common_tickers_set, &shared_state),
-        aster_client.aster_ws(&shared_state)
+        join_all(feeds),
+        compare_price::compare_periodically(&shared_state, hedge_executor.as_ref(), &compare_symbols, shutdown_rx.clone()),
+        funding::compare_funding_periodically(&shared_state, &compare_symbols, shutdown_rx.clone()),
+        metrics::serve_metrics(shared_state.clone(), &config.monitoring, shutdown_rx.clone()),
+        metrics::log_status_periodically(&shared_state, &config.monitoring, shutdown_rx.clone()),
+        telegram_commands::run_command_loop(&shared_state, shutdown_rx.clone()),
+        async {
+            if let Some(opportunity_log) = &shared_state.opportunity_log {
+                opportunity_log.flush_periodically(shutdown_rx.clone()).await;
+            }
+        },
+        async {
+            if let (Some(alert_digest), Some(telegram)) = (&shared_state.alert_digest, &shared_state.telegram) {
+                alert_digest.flush_periodically(telegram, shutdown_rx.clone()).await;
+            }
+        },
+        async {
+            if let (Some(loss_limit), Some(bingx)) = (&shared_state.daily_loss_limit, &shared_state.bingx) {
+                loss_limit.monitor(bingx, shared_state.telegram.as_deref(), shutdown_rx.clone()).await;
+            }
+        },
+        async {
+            if let Some(recorder) = &shared_state.recorder {
+                recorder.write_until_shutdown(shutdown_rx.clone()).await;
+            }
+        },
+        async {
+            if let Some(database) = &shared_state.database {
+                database.snapshot_prices_periodically(&shared_state, shutdown_rx.clone()).await;
+            }
+        }
     );
+
+    if let Some(telegram) = &shared_state.telegram {
+        if let Err(e) = telegram.send_message("🛑 <b>Bot stopped</b>").await {
+            log::error!("Failed to send Telegram stop message: {}", e);
+        }
+    }
+    log::info!("Bot stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tickers(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn common_tickers_intersects_all_lists() {
+        let common = common_tickers(&[
+            &tickers(&["BTCUSDT", "ETHUSDT", "1000PEPEUSDT"]),
+            &tickers(&["BTC-USDT", "kPEPEUSDT", "SOLUSDT"]),
+            &tickers(&["PEPEUSDT", "BTCUSDT", "ETHUSDT"]),
+        ]);
+        assert_eq!(common, HashSet::from(["BTCUSDT".to_string(), "PEPEUSDT".to_string()]));
+
+        assert_eq!(common_tickers(&[&tickers(&["BTCUSDT"])]).len(), 1);
+        assert!(common_tickers(&[]).is_empty());
+    }
+
+    #[test]
+    fn included_tokens_restrict_common_tickers() {
+        let mut common: HashSet<String> = tickers(&["BTCUSDT", "ETHUSDT", "PEPEUSDT"]).into_iter().collect();
+        assert!(restrict_to_included(&mut common, &[]).is_empty());
+        assert_eq!(common.len(), 3);
+
+        let missing = restrict_to_included(&mut common, &tickers(&[" btcusdt", "1000PEPEUSDT", "SOLUSDT", ""]));
+        assert_eq!(missing, tickers(&["SOLUSDT"]));
+        assert_eq!(common, HashSet::from(["BTCUSDT".to_string(), "PEPEUSDT".to_string()]));
+    }
 }
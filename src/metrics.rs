@@ -0,0 +1,290 @@
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{error, info, warn};
+
+use crate::config::MonitoringConfig;
+use crate::share_state::SharedState;
+use crate::utils::{canonical_symbol, sleep_or_shutdown, wait_for_shutdown, Shutdown};
+
+/// Фид считается живым, если обновлял цены не позже HEALTH_MAX_AGE_SECS назад.
+const DEFAULT_HEALTH_MAX_AGE_SECS: u64 = 60;
+/// Интервал строки состояния фидов в логе (STATUS_LOG_SECS).
+const DEFAULT_STATUS_LOG_SECS: u64 = 60;
+
+/// Число отправленных арбитражных сигналов с момента запуска.
+static ALERTS_FIRED: AtomicU64 = AtomicU64::new(0);
+
+pub fn record_alert() {
+    ALERTS_FIRED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn alerts_fired() -> u64 {
+    ALERTS_FIRED.load(Ordering::Relaxed)
+}
+
+fn health_max_age(config: &MonitoringConfig) -> Duration {
+    let secs = match config.health_max_age_secs {
+        Some(v) if v > 0 => v,
+        Some(v) => {
+            warn!(
+                "Invalid HEALTH_MAX_AGE_SECS '{}', using default {}s",
+                v, DEFAULT_HEALTH_MAX_AGE_SECS
+            );
+            DEFAULT_HEALTH_MAX_AGE_SECS
+        }
+        None => DEFAULT_HEALTH_MAX_AGE_SECS,
+    };
+    Duration::from_secs(secs)
+}
+
+/// Пишет в лог состояние всех фидов раз в STATUS_LOG_SECS (по умолчанию минута), чтобы
+/// частичный обрыв (одна биржа переподключается или остановилась) был виден сразу.
+/// 0 отключает лог. Останавливается по сигналу остановки.
+pub async fn log_status_periodically(shared_state: &SharedState, config: &MonitoringConfig, mut shutdown: Shutdown) {
+    let secs = config.status_log_secs.unwrap_or(DEFAULT_STATUS_LOG_SECS);
+    if secs == 0 {
+        info!("Feed status log disabled (STATUS_LOG_SECS = 0)");
+        return;
+    }
+    let interval = Duration::from_secs(secs);
+    while !sleep_or_shutdown(interval, &mut shutdown).await {
+        info!("Feed status: {}", shared_state.feed_status_line());
+    }
+}
+
+/// HTTP-сервер мониторинга: `/health`, `/metrics` (формат Prometheus) и `/prices[/SYMBOL]` (JSON).
+///
+/// Запускается, только если задан METRICS_ADDR (например, `127.0.0.1:9100`);
+/// иначе сразу возвращается. Останавливается по сигналу остановки.
+pub async fn serve_metrics(shared_state: Arc<SharedState>, config: &MonitoringConfig, mut shutdown: Shutdown) {
+    let Some(addr) = &config.metrics_addr else {
+        return;
+    };
+    let addr: SocketAddr = match addr.trim().parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            error!("Invalid METRICS_ADDR '{}': {}. Metrics server disabled.", addr, e);
+            return;
+        }
+    };
+    let max_age = health_max_age(config);
+
+    let make_service = make_service_fn(move |_conn| {
+        let shared_state = shared_state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                handle_request(shared_state.clone(), max_age, request)
+            }))
+        }
+    });
+
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder.serve(make_service),
+        Err(e) => {
+            error!("Failed to bind metrics server to {}: {}", addr, e);
+            return;
+        }
+    };
+    info!("Metrics server listening on http://{}", addr);
+
+    let graceful = server.with_graceful_shutdown(async move { wait_for_shutdown(&mut shutdown).await });
+    if let Err(e) = graceful.await {
+        error!("Metrics server error: {}", e);
+    }
+    info!("Metrics server stopped");
+}
+
+async fn handle_request(
+    shared_state: Arc<SharedState>,
+    max_age: Duration,
+    request: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/health") => health(&shared_state, max_age),
+        (&Method::GET, "/metrics") => metrics(&shared_state),
+        (&Method::GET, "/prices") => all_prices(&shared_state),
+        (&Method::GET, path) if path.starts_with("/prices/") => symbol_prices(&shared_state, &path["/prices/".len()..]),
+        _ => text_response(StatusCode::NOT_FOUND, "not found\n".to_string()),
+    };
+    Ok(response)
+}
+
+/// 200, если все фиды с символами обновлялись не позже `max_age` назад, иначе 503.
+fn health(shared_state: &SharedState, max_age: Duration) -> Response<Body> {
+    let mut body = String::new();
+    let mut healthy = true;
+
+    for feed in shared_state.feed_status() {
+        // Биржа без символов (например, OKX без общих тикеров) не влияет на здоровье
+        if feed.symbols == 0 {
+            continue;
+        }
+        match feed.last_update.map(|t| t.elapsed()) {
+            Some(age) if age <= max_age => {
+                let _ = writeln!(body, "{}: ok ({:.1}s ago)", feed.exchange, age.as_secs_f64());
+            }
+            Some(age) => {
+                healthy = false;
+                let _ = writeln!(body, "{}: stale ({:.1}s ago)", feed.exchange, age.as_secs_f64());
+            }
+            None => {
+                healthy = false;
+                let _ = writeln!(body, "{}: no prices yet", feed.exchange);
+            }
+        }
+    }
+
+    let status = if healthy { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    text_response(status, body)
+}
+
+fn metrics(shared_state: &SharedState) -> Response<Body> {
+    let now = SystemTime::now();
+    let mut body = String::new();
+
+    let feeds = shared_state.feed_status();
+
+    let _ = writeln!(body, "# HELP arb_feed_last_update_timestamp_seconds Unix time of the last price update per exchange.");
+    let _ = writeln!(body, "# TYPE arb_feed_last_update_timestamp_seconds gauge");
+    for feed in &feeds {
+        let timestamp = feed
+            .last_update
+            .and_then(|t| now.checked_sub(t.elapsed()))
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0);
+        let _ = writeln!(
+            body,
+            "arb_feed_last_update_timestamp_seconds{{exchange=\"{}\"}} {:.3}",
+            feed.exchange, timestamp
+        );
+    }
+
+    let _ = writeln!(body, "# HELP arb_feed_symbols Number of symbols tracked per exchange.");
+    let _ = writeln!(body, "# TYPE arb_feed_symbols gauge");
+    for feed in &feeds {
+        let _ = writeln!(body, "arb_feed_symbols{{exchange=\"{}\"}} {}", feed.exchange, feed.symbols);
+    }
+
+    let _ = writeln!(body, "# HELP arb_feed_reconnects_total WebSocket reconnect attempts per feed.");
+    let _ = writeln!(body, "# TYPE arb_feed_reconnects_total counter");
+    for feed in &feeds {
+        let _ = writeln!(body, "arb_feed_reconnects_total{{exchange=\"{}\"}} {}", feed.exchange, feed.reconnects);
+    }
+
+    let _ = writeln!(body, "# HELP arb_feed_messages_total WebSocket messages received per feed.");
+    let _ = writeln!(body, "# TYPE arb_feed_messages_total counter");
+    for feed in &feeds {
+        let _ = writeln!(body, "arb_feed_messages_total{{exchange=\"{}\"}} {}", feed.exchange, feed.messages);
+    }
+
+    let _ = writeln!(body, "# HELP arb_feed_parse_errors_total WebSocket messages that failed to parse per feed.");
+    let _ = writeln!(body, "# TYPE arb_feed_parse_errors_total counter");
+    for feed in &feeds {
+        let _ = writeln!(body, "arb_feed_parse_errors_total{{exchange=\"{}\"}} {}", feed.exchange, feed.parse_errors);
+    }
+
+    let _ = writeln!(body, "# HELP arb_feed_skipped_writes_total Prices outside the common tickers dropped without a write per feed.");
+    let _ = writeln!(body, "# TYPE arb_feed_skipped_writes_total counter");
+    for feed in &feeds {
+        let _ = writeln!(body, "arb_feed_skipped_writes_total{{exchange=\"{}\"}} {}", feed.exchange, feed.skipped_writes);
+    }
+
+    let _ = writeln!(body, "# HELP arb_feed_last_message_timestamp_seconds Unix time of the last WebSocket message per feed.");
+    let _ = writeln!(body, "# TYPE arb_feed_last_message_timestamp_seconds gauge");
+    for feed in &feeds {
+        let timestamp = feed.last_message_unix_ms.unwrap_or(0) as f64 / 1000.0;
+        let _ = writeln!(
+            body,
+            "arb_feed_last_message_timestamp_seconds{{exchange=\"{}\"}} {:.3}",
+            feed.exchange, timestamp
+        );
+    }
+
+    let _ = writeln!(body, "# HELP arb_spread_percent Observed spread percentiles across all symbols over the last hour.");
+    let _ = writeln!(body, "# TYPE arb_spread_percent gauge");
+    let summary = shared_state.spread_stats.summary(None);
+    if let Some(s) = summary {
+        for (quantile, value) in [("0.5", s.p50), ("0.9", s.p90), ("0.99", s.p99)] {
+            let _ = writeln!(body, "arb_spread_percent{{quantile=\"{}\"}} {:.6}", quantile, value);
+        }
+    }
+    let _ = writeln!(body, "# HELP arb_spread_samples Spread samples in the last-hour window.");
+    let _ = writeln!(body, "# TYPE arb_spread_samples gauge");
+    let _ = writeln!(body, "arb_spread_samples {}", summary.map_or(0, |s| s.count));
+
+    let _ = writeln!(body, "# HELP arb_alerts_total Arbitrage alerts fired since start.");
+    let _ = writeln!(body, "# TYPE arb_alerts_total counter");
+    let _ = writeln!(body, "arb_alerts_total {}", ALERTS_FIRED.load(Ordering::Relaxed));
+
+    text_response(StatusCode::OK, body)
+}
+
+/// Цены биржи для JSON: ключ – имя биржи в нижнем регистре.
+fn venue_prices(prices: impl IntoIterator<Item = (&'static str, f64)>) -> serde_json::Map<String, serde_json::Value> {
+    prices
+        .into_iter()
+        .map(|(exchange, price)| (exchange.to_lowercase(), serde_json::json!(price)))
+        .collect()
+}
+
+fn unix_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// `GET /prices/BTCUSDT`: `{"bybit":65000.1,"hyperliquid":65001.0,...,"ts":<мс Unix>}`;
+/// 404, если ни одна биржа еще не прислала цену символа.
+fn symbol_prices(shared_state: &SharedState, symbol: &str) -> Response<Body> {
+    let symbol = canonical_symbol(&symbol.to_uppercase()).symbol;
+    let prices = shared_state.symbol_prices(&symbol);
+    if prices.is_empty() {
+        let body = serde_json::json!({ "error": format!("no prices for {}", symbol) });
+        return json_response(StatusCode::NOT_FOUND, body);
+    }
+    let mut body = venue_prices(prices);
+    body.insert("ts".to_string(), serde_json::json!(unix_ms()));
+    json_response(StatusCode::OK, serde_json::Value::Object(body))
+}
+
+/// `GET /prices`: `{"ts":<мс Unix>,"symbols":{"BTCUSDT":{"bybit":65000.1,...},...}}`.
+fn all_prices(shared_state: &SharedState) -> Response<Body> {
+    let mut symbols: BTreeMap<String, Vec<(&'static str, f64)>> = BTreeMap::new();
+    for (exchange, symbol, price) in shared_state.price_snapshot() {
+        symbols.entry(symbol).or_default().push((exchange, price));
+    }
+    let symbols: serde_json::Map<String, serde_json::Value> = symbols
+        .into_iter()
+        .map(|(symbol, prices)| (symbol, serde_json::Value::Object(venue_prices(prices))))
+        .collect();
+    json_response(StatusCode::OK, serde_json::json!({ "ts": unix_ms(), "symbols": symbols }))
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("text/plain; version=0.0.4"),
+    );
+    response
+}
@@ -0,0 +1,173 @@
+use crate::share_state::{SharedState, MEXC};
+use crate::utils::{canonical_symbol, connect_ws, from_canonical, quote_currency, run_ws_with_reconnect, to_canonical, PingConfig, ReconnectConfig, Shutdown};
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::SinkExt;
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// MEXC закрывает соединение, если клиент не присылает ping примерно минуту; шлем раз в 15 секунд.
+const PING_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Deserialize)]
+struct ContractDetailResponse {
+    success: bool,
+    #[serde(default)]
+    data: Vec<ContractInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ContractInfo {
+    symbol: String,
+    settle_coin: String,
+    /// 0 – контракт торгуется.
+    state: i32,
+}
+
+/// Данные `push.ticker`; lastPrice приходит числом.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TickerData {
+    symbol: String,
+    last_price: f64,
+}
+
+pub struct MexcStruct {
+    base_url: String,
+    ws_url: String,
+    client: reqwest::Client,
+}
+
+impl MexcStruct {
+    /// Публичные эндпоинты MEXC Futures не требуют ключей.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            base_url: "https://contract.mexc.com".to_string(),
+            ws_url: "wss://contract.mexc.com/edge".to_string(),
+            client,
+        }
+    }
+
+    pub async fn get_tickers(&self) -> Vec<String> {
+        // Получаем список контрактов
+        let detail_url = format!("{}/api/v1/contract/detail", self.base_url);
+
+        match self.client.get(&detail_url).send().await {
+            Ok(response) => {
+                match response.json::<ContractDetailResponse>().await {
+                    Ok(detail) => {
+                        if !detail.success {
+                            error!("MEXC contract detail request failed");
+                            return Vec::new();
+                        }
+                        let tickers: Vec<String> = detail
+                            .data
+                            .into_iter()
+                            .filter(|c| c.state == 0 && c.settle_coin == quote_currency())
+                            .filter_map(|c| to_canonical(MEXC, &c.symbol))
+                            .collect();
+                        info!("Retrieved {} MEXC tickers", tickers.len());
+                        tickers
+                    }
+                    Err(e) => {
+                        error!("Failed to parse MEXC contract detail: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to get MEXC contract detail: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Подписывается на `sub.ticker` для каждого тикера и обновляет цены MEXC в `SharedState`.
+    /// MEXC требует `{"method":"ping"}` от клиента – его шлет `run_ws_with_reconnect`.
+    pub async fn mexc_ws(self, tickers: &[String], shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        if tickers.is_empty() {
+            info!("No common tickers listed on MEXC, MEXC feed disabled");
+            return;
+        }
+
+        let config = ReconnectConfig {
+            ping: Some(PingConfig {
+                interval: PING_INTERVAL,
+                message: || serde_json::json!({ "method": "ping" }).to_string(),
+            }),
+            ..shared_state.reconnect_config(MEXC)
+        };
+        let ws_url = &self.ws_url;
+
+        run_ws_with_reconnect(
+            MEXC,
+            shared_state,
+            &config,
+            shutdown,
+            // Подписку нужно восстанавливать при каждом переподключении
+            move || async move {
+                let (mut ws_stream, _) = connect_ws(ws_url).await?;
+                for ticker in tickers {
+                    let subscribe_message = serde_json::json!({
+                        "method": "sub.ticker",
+                        "param": { "symbol": from_canonical(MEXC, ticker) }
+                    })
+                    .to_string();
+                    ws_stream.send(Message::Text(subscribe_message)).await?;
+                }
+                info!("Subscribed to {} MEXC tickers", tickers.len());
+                Ok::<_, tokio_tungstenite::tungstenite::Error>(ws_stream)
+            },
+            move |text| Self::handle_message(shared_state, text),
+        )
+        .await;
+    }
+
+    async fn handle_message(shared_state: &Arc<SharedState>, text: String) {
+        let mut json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse MEXC WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(MEXC);
+                return;
+            }
+        };
+
+        match json.get("channel").and_then(|c| c.as_str()) {
+            Some("push.ticker") => {}
+            Some("pong") => {
+                debug!("Received pong from MEXC");
+                return;
+            }
+            Some("rs.error") => {
+                warn!("MEXC WebSocket error: {}", text);
+                return;
+            }
+            _ => return,
+        }
+
+        let Some(data) = json.get_mut("data").map(serde_json::Value::take) else {
+            return;
+        };
+        let ticker: TickerData = match serde_json::from_value(data) {
+            Ok(ticker) => ticker,
+            Err(e) => {
+                warn!("Failed to parse MEXC ticker: {}", e);
+                shared_state.record_parse_error(MEXC);
+                return;
+            }
+        };
+        let Some(symbol) = to_canonical(MEXC, &ticker.symbol) else {
+            return;
+        };
+        if ticker.last_price <= 0.0 || !ticker.last_price.is_finite() {
+            warn!("Invalid price for {}: {}", symbol, ticker.last_price);
+            return;
+        }
+
+        let canonical = canonical_symbol(&symbol);
+        shared_state.set_price(MEXC, &canonical.symbol, canonical.unit_price(ticker.last_price));
+    }
+}
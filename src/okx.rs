@@ -0,0 +1,169 @@
+use crate::share_state::{SharedState, OKX};
+use std::{sync::Arc, time::Duration};
+use crate::utils::{canonical_symbol, connect_ws, from_canonical, quote_currency, run_ws_with_reconnect, to_canonical, PingConfig, ReconnectConfig, Shutdown};
+use log::{debug, error, info, warn};
+use serde::Deserialize;
+use futures_util::SinkExt;
+use tokio_tungstenite::tungstenite::protocol::Message;
+
+/// OKX разрывает соединение, если в течение 30 секунд не было ни сообщений, ни ping; шлем раз в 20 секунд.
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Deserialize)]
+struct InstrumentsResponse {
+    code: String,
+    #[serde(default)]
+    msg: String,
+    #[serde(default)]
+    data: Vec<InstrumentInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstrumentInfo {
+    inst_id: String,
+    state: String,
+    settle_ccy: String,
+}
+
+pub struct OkxStruct {
+    base_url: String,
+    ws_url: String,
+    client: reqwest::Client,
+}
+
+impl OkxStruct {
+    /// Публичные эндпоинты OKX не требуют ключей.
+    pub fn new(client: reqwest::Client) -> Self {
+        Self {
+            base_url: "https://www.okx.com".to_string(),
+            ws_url: "wss://ws.okx.com:8443/ws/v5/public".to_string(),
+            client,
+        }
+    }
+
+    pub async fn get_tickers(&self) -> Vec<String> {
+        // Получаем список USDT perpetual-свопов
+        let instruments_url = format!("{}/api/v5/public/instruments?instType=SWAP", self.base_url);
+
+        match self.client.get(&instruments_url).send().await {
+            Ok(response) => {
+                match response.json::<InstrumentsResponse>().await {
+                    Ok(instruments) => {
+                        if instruments.code != "0" {
+                            error!("OKX instruments request failed: code={}, msg={}", instruments.code, instruments.msg);
+                            return Vec::new();
+                        }
+                        let tickers: Vec<String> = instruments
+                            .data
+                            .into_iter()
+                            .filter(|i| i.state == "live" && i.settle_ccy == quote_currency())
+                            .filter_map(|i| to_canonical(OKX, &i.inst_id))
+                            .collect();
+                        info!("Retrieved {} OKX tickers", tickers.len());
+                        tickers
+                    }
+                    Err(e) => {
+                        error!("Failed to parse OKX instruments: {}", e);
+                        Vec::new()
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Failed to get OKX instruments: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// OKX разрывает соединение, если в течение 30 секунд не было ни сообщений, ни ping,
+    /// поэтому `run_ws_with_reconnect` шлет текстовый `ping`.
+    pub async fn okx_ws(self, tickers: &[String], shared_state: &Arc<SharedState>, shutdown: Shutdown) {
+        let config = ReconnectConfig {
+            ping: Some(PingConfig {
+                interval: PING_INTERVAL,
+                message: || "ping".to_string(),
+            }),
+            ..shared_state.reconnect_config(OKX)
+        };
+        let ws_url = &self.ws_url;
+
+        run_ws_with_reconnect(
+            OKX,
+            shared_state,
+            &config,
+            shutdown,
+            // Подписку нужно восстанавливать при каждом переподключении
+            move || async move {
+                let (mut ws_stream, _) = connect_ws(ws_url).await?;
+                let args: Vec<serde_json::Value> = tickers
+                    .iter()
+                    .map(|ticker| serde_json::json!({ "channel": "tickers", "instId": from_canonical(OKX, ticker) }))
+                    .collect();
+                let subscribe_message = serde_json::json!({
+                    "op": "subscribe",
+                    "args": args
+                })
+                .to_string();
+                ws_stream.send(Message::Text(subscribe_message)).await?;
+                info!("Subscribed to {} OKX tickers", tickers.len());
+                Ok::<_, tokio_tungstenite::tungstenite::Error>(ws_stream)
+            },
+            move |text| Self::handle_message(shared_state, text),
+        )
+        .await;
+    }
+
+    async fn handle_message(shared_state: &Arc<SharedState>, text: String) {
+        // Ответ на текстовый ping
+        if text == "pong" {
+            debug!("Received pong from OKX");
+            return;
+        }
+
+        let json = match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to parse OKX WebSocket message: {} (text: {})", e, text);
+                shared_state.record_parse_error(OKX);
+                return;
+            }
+        };
+
+        // Ошибки подписки приходят как {"event":"error","msg":...}
+        if json.get("event").and_then(|e| e.as_str()) == Some("error") {
+            warn!("OKX subscription error: {}", text);
+            return;
+        }
+
+        let Some(data_array) = json.get("data").and_then(|d| d.as_array()) else {
+            return;
+        };
+        for ticker_data in data_array {
+            let Some(inst_id) = ticker_data.get("instId").and_then(|s| s.as_str()) else {
+                continue;
+            };
+            let Some(symbol) = to_canonical(OKX, inst_id) else {
+                continue;
+            };
+            if let Some(price_str) = ticker_data.get("last").and_then(|p| p.as_str()) {
+                let price: f64 = match price_str.parse::<f64>() {
+                    Ok(p) => {
+                        if p <= 0.0 || !p.is_finite() {
+                            warn!("Invalid price for {}: {}", symbol, p);
+                            continue;
+                        }
+                        p
+                    }
+                    Err(e) => {
+                        warn!("Failed to parse price for {}: {} (value: {})", symbol, e, price_str);
+                        continue;
+                    }
+                };
+
+                let canonical = canonical_symbol(&symbol);
+                shared_state.set_price(OKX, &canonical.symbol, canonical.unit_price(price));
+            }
+        }
+    }
+}
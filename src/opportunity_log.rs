@@ -0,0 +1,96 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{error, info, warn};
+
+use crate::config::StorageConfig;
+use crate::utils::{sleep_or_shutdown, Shutdown};
+
+/// Как часто буфер сбрасывается на диск.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const HEADER: &str = "timestamp,symbol,venue_a,price_a,venue_b,price_b,gross_diff_pct";
+
+/// CSV-журнал найденных арбитражных возможностей (OPPORTUNITY_LOG).
+///
+/// Запись идет в буфер под мьютексом, на диск он сбрасывается раз в `FLUSH_INTERVAL`
+/// и при остановке, поэтому цикл чтения Bybit почти не ждет файловую систему.
+#[derive(Debug)]
+pub struct OpportunityLog {
+    path: String,
+    writer: Mutex<BufWriter<File>>,
+}
+
+impl OpportunityLog {
+    /// Открывает журнал по пути из OPPORTUNITY_LOG. Если путь не задан или файл
+    /// не открывается – журнал отключен.
+    pub fn from_config(config: &StorageConfig) -> Option<Self> {
+        let path = config.opportunity_log.as_deref()?.trim();
+        if path.is_empty() {
+            return None;
+        }
+        match Self::open(path) {
+            Ok(log) => {
+                info!("Logging arbitrage opportunities to {}", path);
+                Some(log)
+            }
+            Err(e) => {
+                error!("Failed to open opportunity log {}: {}. CSV logging disabled.", path, e);
+                None
+            }
+        }
+    }
+
+    /// Открывает файл на дозапись; в новый (пустой) файл пишет заголовок.
+    fn open(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let is_empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+        if is_empty {
+            writeln!(writer, "{}", HEADER)?;
+            writer.flush()?;
+        }
+        Ok(Self {
+            path: path.to_string(),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Добавляет строку в буфер журнала.
+    pub fn record(
+        &self,
+        symbol: &str,
+        (venue_a, price_a): (&str, f64),
+        (venue_b, price_b): (&str, f64),
+        gross_diff_pct: f64,
+    ) {
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writeln!(
+            writer,
+            "{},{},{},{},{},{},{:.5}",
+            timestamp, symbol, venue_a, price_a, venue_b, price_b, gross_diff_pct
+        ) {
+            warn!("Failed to write opportunity to {}: {}", self.path, e);
+        }
+    }
+
+    pub fn flush(&self) {
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.flush() {
+            warn!("Failed to flush opportunity log {}: {}", self.path, e);
+        }
+    }
+
+    /// Периодически сбрасывает буфер на диск; при остановке сбрасывает последний раз и выходит.
+    pub async fn flush_periodically(&self, mut shutdown: Shutdown) {
+        loop {
+            let stop = sleep_or_shutdown(FLUSH_INTERVAL, &mut shutdown).await;
+            self.flush();
+            if stop {
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::fmt::Display;
+
+use log::{error, info};
+
+use crate::aster::AsterStruct;
+use crate::binance::BinanceStruct;
+use crate::bingx::{BingXClient, BingXError};
+use crate::bybit::Bybit;
+use crate::config::Config;
+use crate::dydx::DydxStruct;
+use crate::gate::GateStruct;
+use crate::hyperliquid::HyperLiquidStruct;
+use crate::kucoin::KucoinStruct;
+use crate::mexc::MexcStruct;
+use crate::okx::OkxStruct;
+use crate::share_state::{ASTER, BINANCE, BINGX, BYBIT, DYDX, EXCHANGES, GATE, HYPERLIQUID, KUCOIN, MEXC, OKX};
+use crate::telegram::TelegramNotifier;
+
+/// Итог одной проверки с пояснением.
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Pass(String),
+    Fail(String),
+    /// Проверка не нужна: биржа выключена или ключи не заданы.
+    Skip(String),
+}
+
+/// Отчет --preflight: каждая проверка сразу пишется в лог строкой PASS/FAIL/SKIP.
+#[derive(Debug, Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+impl Report {
+    fn record(&mut self, check: &str, outcome: Outcome) {
+        match outcome {
+            Outcome::Pass(detail) => {
+                self.passed += 1;
+                info!("[PASS] {}: {}", check, detail);
+            }
+            Outcome::Fail(detail) => {
+                self.failed += 1;
+                error!("[FAIL] {}: {}", check, detail);
+            }
+            Outcome::Skip(detail) => {
+                self.skipped += 1;
+                info!("[SKIP] {}: {}", check, detail);
+            }
+        }
+    }
+}
+
+/// Список тикеров как результат проверки REST: пустой список – тоже сбой.
+fn tickers_outcome<E: Display>(result: Result<Vec<String>, E>) -> Outcome {
+    match result {
+        Ok(tickers) if tickers.is_empty() => Outcome::Fail("no tickers returned".to_string()),
+        Ok(tickers) => Outcome::Pass(format!("{} tickers", tickers.len())),
+        Err(e) => Outcome::Fail(e.to_string()),
+    }
+}
+
+/// Проверка перед запуском (--preflight): REST каждой включенной биржи через `get_tickers`,
+/// тестовое сообщение в Telegram и, если заданы ключи BingX, баланс и открытые позиции.
+/// Фиды не запускаются, ордера не отправляются. Возвращает true, если ни одна проверка не упала.
+pub async fn run(config: &Config, enabled_exchanges: &HashSet<&'static str>, http_client: reqwest::Client) -> bool {
+    info!("Running preflight checks...");
+    let mut report = Report::default();
+
+    for exchange in EXCHANGES {
+        let check = format!("{} REST", exchange);
+        if !enabled_exchanges.contains(exchange) {
+            report.record(&check, Outcome::Skip("disabled by exchanges list".to_string()));
+            continue;
+        }
+        let outcome = match *exchange {
+            BYBIT => tickers_outcome(Bybit::new(&config.bybit, http_client.clone()).get_tickers().await),
+            HYPERLIQUID => match HyperLiquidStruct::try_new().await {
+                Ok(hyper_liquid) => tickers_outcome(hyper_liquid.get_tickers().await),
+                Err(e) => Outcome::Fail(format!("failed to create client: {}", e)),
+            },
+            ASTER => match AsterStruct::new(&config.aster, http_client.clone()) {
+                Ok(aster) => tickers_outcome(aster.get_tickers().await),
+                Err(e) => Outcome::Fail(e.to_string()),
+            },
+            BINANCE => tickers_outcome(Ok::<_, Infallible>(BinanceStruct::new(http_client.clone()).get_tickers().await)),
+            OKX => tickers_outcome(Ok::<_, Infallible>(OkxStruct::new(http_client.clone()).get_tickers().await)),
+            KUCOIN => tickers_outcome(Ok::<_, Infallible>(KucoinStruct::new(http_client.clone()).get_tickers().await)),
+            GATE => tickers_outcome(Ok::<_, Infallible>(GateStruct::new(http_client.clone()).get_tickers().await)),
+            MEXC => tickers_outcome(Ok::<_, Infallible>(MexcStruct::new(http_client.clone()).get_tickers().await)),
+            DYDX => tickers_outcome(Ok::<_, Infallible>(DydxStruct::new(http_client.clone()).get_tickers().await)),
+            _ => Outcome::Skip("no REST check".to_string()),
+        };
+        report.record(&check, outcome);
+    }
+
+    let telegram = if config.telegram.bot_token.is_none() && config.telegram.chat_ids.is_none() {
+        Outcome::Skip("TELEGRAM_BOT_TOKEN and TELEGRAM_CHAT_ID are not set".to_string())
+    } else {
+        match TelegramNotifier::new(&config.telegram, http_client.clone()) {
+            Ok(telegram) => match telegram.send_message("🧪 <b>Preflight check</b>\n\nTelegram alerts are delivered").await {
+                Ok(()) => Outcome::Pass("test message sent".to_string()),
+                Err(e) => Outcome::Fail(e.to_string()),
+            },
+            Err(e) => Outcome::Fail(e.to_string()),
+        }
+    };
+    report.record("Telegram", telegram);
+
+    match BingXClient::from_config(&config.bingx, http_client) {
+        Ok(bingx) => {
+            let balance = match bingx.get_available_usdt().await {
+                Ok(usdt) => Outcome::Pass(format!("{:.2} USDT available", usdt)),
+                Err(e) => Outcome::Fail(e.to_string()),
+            };
+            report.record(&format!("{} balance", BINGX), balance);
+            let positions = match bingx.count_open_positions().await {
+                Ok(count) => Outcome::Pass(format!("{} open positions", count)),
+                Err(e) => Outcome::Fail(e.to_string()),
+            };
+            report.record(&format!("{} positions", BINGX), positions);
+        }
+        Err(BingXError::MissingEnv(e)) => report.record(BINGX, Outcome::Skip(e)),
+        Err(e) => report.record(BINGX, Outcome::Fail(e.to_string())),
+    }
+
+    let summary = format!(
+        "Preflight finished: {} passed, {} failed, {} skipped",
+        report.passed, report.failed, report.skipped
+    );
+    if report.failed > 0 {
+        error!("{}", summary);
+    } else {
+        info!("{}", summary);
+    }
+    report.failed == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_or_failed_ticker_lists_fail() {
+        let tickers = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        assert_eq!(tickers_outcome(Ok::<_, String>(tickers)), Outcome::Pass("2 tickers".to_string()));
+        assert!(matches!(tickers_outcome(Ok::<_, String>(Vec::new())), Outcome::Fail(_)));
+        assert_eq!(
+            tickers_outcome(Err::<Vec<String>, _>("timeout")),
+            Outcome::Fail("timeout".to_string())
+        );
+
+        let mut report = Report::default();
+        report.record("Bybit REST", Outcome::Pass("2 tickers".to_string()));
+        report.record("Telegram", Outcome::Skip("not configured".to_string()));
+        report.record("BingX balance", Outcome::Fail("invalid signature".to_string()));
+        assert_eq!((report.passed, report.failed, report.skipped), (1, 1, 1));
+    }
+}
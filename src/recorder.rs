@@ -0,0 +1,157 @@
+use std::fs::OpenOptions;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use log::{error, info, warn};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use crate::config::StorageConfig;
+use crate::replay::PriceEvent;
+use crate::utils::{wait_for_shutdown, Shutdown};
+
+/// Сколько обновлений может ждать в очереди, прежде чем новые начнут отбрасываться.
+const CHANNEL_CAPACITY: usize = 100_000;
+/// Как часто буфер сбрасывается на диск.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Запись всех принятых цен в NDJSON (RECORD_PATH) для режима `--replay`.
+///
+/// Формат строки – `PriceEvent`: `{"ts":...,"exchange":"bybit","symbol":"BTCUSDT","price":...}`.
+/// Обработчики WebSocket только кладут событие в канал; в файл пишет отдельная задача через
+/// буфер, который сбрасывается раз в `FLUSH_INTERVAL` и при остановке. Если очередь
+/// переполнена, события отбрасываются – запись никогда не тормозит фиды.
+#[derive(Debug)]
+pub struct PriceRecorder {
+    path: String,
+    sender: mpsc::Sender<PriceEvent>,
+    /// Забирается задачей записи при запуске.
+    receiver: Mutex<Option<mpsc::Receiver<PriceEvent>>>,
+    /// Отброшено из-за переполненной очереди с последнего сброса.
+    dropped: AtomicU64,
+    file: Mutex<Option<std::fs::File>>,
+}
+
+impl PriceRecorder {
+    /// Открывает файл записи по пути из RECORD_PATH. Если путь не задан или файл
+    /// не открывается – запись отключена.
+    pub fn from_config(config: &StorageConfig) -> Option<Self> {
+        let path = config.record_path.as_deref()?.trim();
+        if path.is_empty() {
+            return None;
+        }
+        let file = match OpenOptions::new().create(true).append(true).open(path) {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to open price record {}: {}. Price recording disabled.", path, e);
+                return None;
+            }
+        };
+
+        let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+        info!("Recording price updates to {}", path);
+        Some(Self {
+            path: path.to_string(),
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            dropped: AtomicU64::new(0),
+            file: Mutex::new(Some(file)),
+        })
+    }
+
+    /// Ставит обновление цены в очередь записи. Не ждет: при полной очереди событие теряется.
+    pub fn record(&self, exchange: &str, symbol: &str, price: f64) {
+        let event = PriceEvent {
+            ts: chrono::Utc::now().timestamp_millis(),
+            exchange: exchange.to_ascii_lowercase(),
+            symbol: symbol.to_string(),
+            price,
+        };
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+            // Задача записи уже завершилась – бот останавливается
+            Err(TrySendError::Closed(_)) => {}
+        }
+    }
+
+    /// Пишет события из очереди в файл до остановки бота; при остановке дописывает
+    /// накопившееся и сбрасывает буфер.
+    pub async fn write_until_shutdown(&self, mut shutdown: Shutdown) {
+        let receiver = self.receiver.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let file = self.file.lock().unwrap_or_else(|e| e.into_inner()).take();
+        let (Some(mut receiver), Some(file)) = (receiver, file) else {
+            return;
+        };
+        let mut writer = BufWriter::new(tokio::fs::File::from_std(file));
+        let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            tokio::select! {
+                Some(event) = receiver.recv() => {
+                    self.write_event(&mut writer, &event).await;
+                }
+                _ = flush.tick() => {
+                    self.flush(&mut writer).await;
+                }
+                _ = wait_for_shutdown(&mut shutdown) => {
+                    receiver.close();
+                    while let Some(event) = receiver.recv().await {
+                        self.write_event(&mut writer, &event).await;
+                    }
+                    self.flush(&mut writer).await;
+                    info!("Price recording to {} stopped", self.path);
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn write_event(&self, writer: &mut BufWriter<tokio::fs::File>, event: &PriceEvent) {
+        let line = format_line(event);
+        if let Err(e) = writer.write_all(line.as_bytes()).await {
+            warn!("Failed to write price record to {}: {}", self.path, e);
+        }
+    }
+
+    async fn flush(&self, writer: &mut BufWriter<tokio::fs::File>) {
+        if let Err(e) = writer.flush().await {
+            warn!("Failed to flush price record {}: {}", self.path, e);
+        }
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!("Price record queue is full, dropped {} updates", dropped);
+        }
+    }
+}
+
+/// Строка NDJSON с переводом строки.
+fn format_line(event: &PriceEvent) -> String {
+    let mut line = serde_json::to_string(event).unwrap_or_default();
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_format_matches_replay() {
+        let event = PriceEvent {
+            ts: 1718000000000,
+            exchange: "bybit".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            price: 65000.5,
+        };
+        let line = format_line(&event);
+        assert_eq!(
+            line,
+            "{\"ts\":1718000000000,\"exchange\":\"bybit\",\"symbol\":\"BTCUSDT\",\"price\":65000.5}\n"
+        );
+        assert_eq!(serde_json::from_str::<PriceEvent>(line.trim_end()).unwrap(), event);
+    }
+}
@@ -0,0 +1,150 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::compare_price::compare_prices;
+use crate::config::Config;
+use crate::metrics;
+use crate::share_state::{SharedState, EXCHANGES};
+
+/// Одно обновление цены из записи: строка NDJSON вида
+/// `{"ts":1718000000000,"exchange":"bybit","symbol":"BTCUSDT","price":65000.5}`.
+/// `ts` – миллисекунды Unix, `exchange` – имя биржи без учета регистра, `symbol` – канонический тикер.
+/// Такие файлы пишет `PriceRecorder` (RECORD_PATH).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceEvent {
+    pub ts: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub price: f64,
+}
+
+/// Читает запись и сортирует события по времени (порядок равных `ts` сохраняется).
+/// Некорректные строки и неизвестные биржи пропускаются с предупреждением.
+pub fn read_events(path: &Path) -> io::Result<Vec<PriceEvent>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut events = Vec::new();
+    let mut skipped = 0usize;
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<PriceEvent>(&line) {
+            Ok(mut event) => match EXCHANGES.iter().find(|e| e.eq_ignore_ascii_case(&event.exchange)) {
+                Some(exchange) => {
+                    event.exchange = exchange.to_string();
+                    events.push(event);
+                }
+                None => skipped += 1,
+            },
+            Err(e) => {
+                if skipped == 0 {
+                    warn!("Invalid replay line {}: {}", index + 1, e);
+                }
+                skipped += 1;
+            }
+        }
+    }
+    if skipped > 0 {
+        warn!("Skipped {} invalid replay lines in {}", skipped, path.display());
+    }
+    events.sort_by_key(|event| event.ts);
+    Ok(events)
+}
+
+/// Режим бэктеста: прогоняет записанные цены через `compare_prices` без подключения к биржам
+/// и считает, сколько возможностей дал бы каждый порог из `thresholds`.
+///
+/// Между событиями ждет интервал из записи, деленный на `speed`; `speed` = 0 – без пауз.
+/// Возраст цены (MAX_PRICE_AGE_SECS) считается по времени воспроизведения, поэтому при
+/// большом ускорении старые цены дольше считаются свежими.
+pub async fn run(mut config: Config, path: &Path, speed: f64, thresholds: &[f64]) -> io::Result<()> {
+    let events = read_events(path)?;
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        warn!("No price events in {}", path.display());
+        return Ok(());
+    };
+    info!(
+        "Replaying {} price events from {} ({:.1}s of data, speed {})",
+        events.len(),
+        path.display(),
+        (last.ts - first.ts) as f64 / 1000.0,
+        if speed > 0.0 { format!("{}x", speed) } else { "max".to_string() }
+    );
+
+    // Без побочных эффектов: журнал, база и запись цен выключены; проверка по стакану тоже –
+    // стаканов в записи нет
+    config.storage = Default::default();
+    config.thresholds.depth_check_notional_usdt = None;
+
+    let mut runs: Vec<(f64, Arc<SharedState>, u64)> = Vec::new();
+    for &threshold in thresholds {
+        if !(threshold > 0.0 && threshold.is_finite()) {
+            warn!("Invalid replay threshold '{}', skipped", threshold);
+            continue;
+        }
+        config.thresholds.min_spread_percent = Some(threshold);
+        runs.push((threshold, Arc::new(SharedState::new(&config, None)), 0));
+    }
+
+    let mut previous_ts = first.ts;
+    for event in &events {
+        if speed > 0.0 && event.ts > previous_ts {
+            let delay = (event.ts - previous_ts) as f64 / 1000.0 / speed;
+            tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+        }
+        previous_ts = event.ts;
+
+        for (_, shared_state, opportunities) in runs.iter_mut() {
+            shared_state.set_price(&event.exchange, &event.symbol, event.price);
+            // Replay однопоточный: прирост счетчика сигналов – это сигналы этого вызова
+            let before = metrics::alerts_fired();
+            if let Err(e) = compare_prices(shared_state, &event.symbol, None).await {
+                warn!("Error comparing prices for {}: {}", event.symbol, e);
+            }
+            *opportunities += metrics::alerts_fired() - before;
+        }
+    }
+
+    info!("Replay finished: {} events", events.len());
+    for (threshold, _, opportunities) in &runs {
+        info!("Threshold {}%: {} opportunities", threshold, opportunities);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn events_are_sorted_and_invalid_lines_skipped() {
+        let path = env::temp_dir().join(format!("hyperliquid_replay_test_{}.ndjson", std::process::id()));
+        fs::write(
+            &path,
+            concat!(
+                "{\"ts\":2000,\"exchange\":\"hyperliquid\",\"symbol\":\"BTCUSDT\",\"price\":65100.0}\n",
+                "not json\n",
+                "\n",
+                "{\"ts\":1000,\"exchange\":\"bybit\",\"symbol\":\"BTCUSDT\",\"price\":65000.0}\n",
+                "{\"ts\":1500,\"exchange\":\"bitmex\",\"symbol\":\"BTCUSDT\",\"price\":1.0}\n",
+            ),
+        )
+        .unwrap();
+
+        let events = read_events(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].ts, events[0].exchange.as_str()), (1000, "Bybit"));
+        assert_eq!((events[1].ts, events[1].exchange.as_str()), (2000, "Hyperliquid"));
+    }
+}
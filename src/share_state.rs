@@ -1,37 +1,1403 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use dashmap::{mapref::entry::Entry, DashMap};
+use log::{debug, error, info, warn};
 use tokio::sync::RwLock;
 
-use crate::{bingx::BingXClient, telegram::TelegramNotifier};
+use crate::{
+    alert_digest::AlertDigest,
+    alert_tier::AlertTiers,
+    bingx::BingXClient,
+    compare_price::{ReferenceVenue, ThresholdMode},
+    config::{AlertsConfig, Config, FeesConfig, ThresholdsConfig},
+    database::Database,
+    loss_limit::DailyLossLimit,
+    opportunity_log::OpportunityLog,
+    recorder::PriceRecorder,
+    spread_stats::SpreadStats,
+    telegram::TelegramNotifier,
+    utils::{canonical_symbol, ReconnectConfig},
+};
+
+/// Цена и момент ее получения – нужен, чтобы не сравнивать с "замерзшим" фидом.
+#[derive(Debug, Clone, Copy)]
+pub struct PriceQuote {
+    pub price: f64,
+    pub updated_at: Instant,
+    /// Серверное время цены, мс Unix, если биржа его присылает.
+    pub server_ts_ms: Option<u64>,
+    /// Цена пришла от фида; false – заглушка символа из `register_symbol` до первой цены.
+    pub received: bool,
+}
+
+impl PriceQuote {
+    pub fn new(price: f64) -> Self {
+        Self {
+            price,
+            updated_at: Instant::now(),
+            server_ts_ms: None,
+            received: true,
+        }
+    }
+
+    /// Цена с серверным временем: возраст считается от него, а не от момента получения.
+    /// Время "из будущего" (расхождение часов) считается текущим.
+    pub fn at_server_time(price: f64, server_ts_ms: u64) -> Self {
+        let now_ms = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        let age = Duration::from_millis(now_ms.saturating_sub(server_ts_ms));
+        let now = Instant::now();
+        Self {
+            price,
+            updated_at: now.checked_sub(age).unwrap_or(now),
+            server_ts_ms: Some(server_ts_ms),
+            received: true,
+        }
+    }
+
+    /// Заглушка символа, для которого фид еще не прислал цену.
+    fn placeholder() -> Self {
+        Self {
+            received: false,
+            ..Self::new(0.0)
+        }
+    }
+}
+
+/// Уровень стакана: цена и объем в монетах (с учетом множителя контракта).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+/// Изменение одной стороны вершины стакана в обновлении фида.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BookSideUpdate {
+    /// Стороны нет в обновлении – остается прежней.
+    Unchanged,
+    /// Лучший уровень удален, а нового в обновлении нет – сторона пуста.
+    Cleared,
+    Level(BookLevel),
+}
+
+impl BookSideUpdate {
+    /// Сторона из уровней `(цена, объем)` по порядку от лучшего: первый уровень с объемом > 0.
+    /// Только удаленные уровни (объем 0) – `Cleared`, корректных уровней нет – `Unchanged`.
+    pub fn from_levels(levels: impl IntoIterator<Item = (f64, f64)>) -> Self {
+        let mut seen = false;
+        for (price, size) in levels {
+            if !(price > 0.0 && price.is_finite() && size >= 0.0 && size.is_finite()) {
+                continue;
+            }
+            if size > 0.0 {
+                return BookSideUpdate::Level(BookLevel { price, size });
+            }
+            seen = true;
+        }
+        if seen {
+            BookSideUpdate::Cleared
+        } else {
+            BookSideUpdate::Unchanged
+        }
+    }
+}
+
+/// Лучшие bid/ask биржи по символу для проверки спреда по стакану.
+#[derive(Debug, Clone, Copy)]
+pub struct BookTop {
+    pub bid: Option<BookLevel>,
+    pub ask: Option<BookLevel>,
+    /// Когда сторона в последний раз пришла в обновлении фида: без обновлений сторона
+    /// устаревает, даже если другая сторона обновляется.
+    pub bid_updated_at: Instant,
+    pub ask_updated_at: Instant,
+}
+
+/// Параметры контракта для расчета объема ордера, за одну монету канонического символа
+/// (для `1000PEPEUSDT` – за один PEPE).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InstrumentSpec {
+    pub tick_size: f64,
+    pub qty_step: f64,
+    pub min_qty: f64,
+    /// Минимальная стоимость ордера в USDT; None – биржа ее не сообщает.
+    pub min_notional: Option<f64>,
+}
+
+/// Состояние соединения фида биржи.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeedStatus {
+    /// Первое подключение еще не завершено.
+    #[default]
+    Connecting,
+    /// Подключен и подписан.
+    Live,
+    /// Соединение потеряно, идут попытки переподключения.
+    Reconnecting,
+    /// Фид остановлен: попытки исчерпаны, нет символов или пришел сигнал остановки.
+    Dead,
+}
+
+impl FeedStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            FeedStatus::Connecting => "connecting",
+            FeedStatus::Live => "live",
+            FeedStatus::Reconnecting => "reconnecting",
+            FeedStatus::Dead => "dead",
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => FeedStatus::Live,
+            2 => FeedStatus::Reconnecting,
+            3 => FeedStatus::Dead,
+            _ => FeedStatus::Connecting,
+        }
+    }
+}
+
+/// Счетчики WebSocket-фида биржи с момента запуска. Обновляются обработчиками фидов.
+#[derive(Debug, Default)]
+pub struct FeedStats {
+    /// Принятые текстовые сообщения (включая служебные).
+    pub messages: AtomicU64,
+    /// Сообщения, которые не удалось разобрать.
+    pub parse_errors: AtomicU64,
+    pub reconnects: AtomicU64,
+    /// Цены символов вне общих тикеров, отброшенные без записи в карту цен.
+    pub skipped_writes: AtomicU64,
+    /// Unix-время последнего сообщения в миллисекундах; 0 – сообщений еще не было.
+    pub last_message_unix_ms: AtomicU64,
+    /// `FeedStatus` как u8.
+    status: AtomicU8,
+}
+
+impl FeedStats {
+    pub fn record_message(&self) {
+        self.messages.fetch_add(1, Ordering::Relaxed);
+        let now = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        self.last_message_unix_ms.store(now, Ordering::Relaxed);
+    }
+
+    pub fn record_parse_error(&self) {
+        self.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_skipped_writes(&self, count: u64) {
+        self.skipped_writes.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Попытка переподключения переводит фид в `Reconnecting`.
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+        self.set_status(FeedStatus::Reconnecting);
+    }
+
+    pub fn set_status(&self, status: FeedStatus) {
+        self.status.store(status as u8, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> FeedStatus {
+        FeedStatus::from_u8(self.status.load(Ordering::Relaxed))
+    }
+}
+
+/// Сводка по одному фиду цен для /health, /metrics и /status.
+#[derive(Debug, Clone, Copy)]
+pub struct FeedSummary {
+    pub exchange: &'static str,
+    pub status: FeedStatus,
+    pub symbols: usize,
+    /// Время последней полученной от фида цены; None – цен еще не было.
+    pub last_update: Option<Instant>,
+    pub messages: u64,
+    pub parse_errors: u64,
+    pub reconnects: u64,
+    /// Цены вне общих тикеров, не записанные в карту цен.
+    pub skipped_writes: u64,
+    /// Unix-время последнего сообщения в миллисекундах; None – сообщений еще не было.
+    pub last_message_unix_ms: Option<u64>,
+}
+
+pub const BYBIT: &str = "Bybit";
+pub const HYPERLIQUID: &str = "Hyperliquid";
+pub const ASTER: &str = "ASTER";
+pub const BINANCE: &str = "Binance";
+pub const OKX: &str = "OKX";
+pub const KUCOIN: &str = "KuCoin";
+pub const GATE: &str = "Gate";
+pub const MEXC: &str = "MEXC";
+pub const DYDX: &str = "dYdX";
+/// Торговая биржа без фида цен – в `EXCHANGES` не входит.
+pub const BINGX: &str = "BingX";
+
+/// Все биржи, для которых хранятся цены. Новая биржа добавляется сюда одной строкой.
+pub const EXCHANGES: &[&str] = &[BYBIT, HYPERLIQUID, ASTER, BINANCE, OKX, KUCOIN, GATE, MEXC, DYDX];
 
 #[derive(Debug)]
 pub struct SharedState {
-    pub bybit_prices: RwLock<HashMap<String, f64>>,
-    pub hyperliquid_prices: RwLock<HashMap<String, f64>>,
-    pub aster_prices: RwLock<HashMap<String, f64>>,
-    pub telegram: Option<TelegramNotifier>,
+    /// Цены по биржам: биржа -> (символ -> цена). Карта каждой биржи – шардированная
+    /// `DashMap`, так что обновления разных символов почти не ждут друг друга.
+    prices: HashMap<String, DashMap<String, PriceQuote>>,
+    /// Получена ли уже первая цена от фида – для разового уведомления "feed live".
+    feeds_live: HashMap<String, AtomicBool>,
+    /// Счетчики сообщений, ошибок разбора и переподключений по биржам.
+    feed_stats: HashMap<String, FeedStats>,
+    /// Таймаут тишины и задержки переподключения фидов по биржам (`[feeds.<биржа>]`).
+    reconnect: HashMap<String, ReconnectConfig>,
+    pub telegram: Option<Arc<TelegramNotifier>>,
     /// Опциональный клиент BingX. Если не инициализирован – торги на BingX отключены.
     pub bingx: Option<std::sync::Arc<BingXClient>>,
+    /// Разрешено ли автоматически открывать позиции на BingX (ENABLE_BINGX_TRADING, по умолчанию выключено).
+    pub bingx_trading_enabled: bool,
+    /// Торговля приостановлена командой /pause из Telegram.
+    trading_paused: AtomicBool,
+    /// Время последнего сообщения в Telegram о пропуске или ошибке сделки BingX:
+    /// (символ, вид сообщения) -> время, см. `should_notify_trade`.
+    trade_notices: DashMap<(String, &'static str), Instant>,
+    /// Минимальная разница цен в процентах для арбитражного сигнала (MIN_SPREAD_PERCENT).
+    pub min_spread_percent: f64,
+    /// Пороги отдельных символов (SYMBOL_THRESHOLDS) поверх `min_spread_percent`, см. `min_spread_for`.
+    symbol_thresholds: HashMap<String, f64>,
+    /// Режим порога сигнала (THRESHOLD_MODE): проценты, разница в USDT или шаги цены.
+    pub threshold_mode: ThresholdMode,
+    /// База сравнения спредов (REFERENCE_VENUE): биржа или все пары.
+    pub reference_venue: ReferenceVenue,
+    /// Включенные биржи (EXCHANGES), цены которых сравниваются, в порядке `EXCHANGES`.
+    pub compared_venues: Vec<&'static str>,
+    /// Пары бирж для сравнения по `reference_venue`; первая биржа пары – база процента.
+    pub comparison_pairs: Vec<(&'static str, &'static str)>,
+    /// Шаги цены по биржам: биржа -> (символ -> шаг за монету). Заполняется для режима шагов.
+    tick_sizes: HashMap<String, DashMap<String, f64>>,
+    /// Параметры контрактов по биржам для расчета объема ордера: биржа -> (символ -> параметры).
+    instrument_specs: HashMap<String, DashMap<String, InstrumentSpec>>,
+    /// CSV-журнал возможностей (OPPORTUNITY_LOG), None – журнал отключен.
+    pub opportunity_log: Option<OpportunityLog>,
+    /// Хранилище истории в SQLite (DATABASE_PATH), None – отключено.
+    pub database: Option<Database>,
+    /// Запись всех принятых цен в NDJSON (RECORD_PATH), None – отключена.
+    pub recorder: Option<PriceRecorder>,
+    /// Очередь сводки сигналов (ALERT_DIGEST_SECS), None – сигналы отправляются сразу.
+    pub alert_digest: Option<AlertDigest>,
+    /// Границы уровней сигналов INFO/WARN/URGENT (ALERT_WARN_MULTIPLIER, ALERT_URGENT_MULTIPLIER).
+    pub alert_tiers: AlertTiers,
+    /// Наблюдаемые спреды за последний час для /stats и /metrics.
+    pub spread_stats: SpreadStats,
+    /// Дневной лимит убытка BingX (DAILY_LOSS_LIMIT_USDT), None – отключен.
+    pub daily_loss_limit: Option<DailyLossLimit>,
+    /// Комиссии тейкера в процентах по биржам (<EXCHANGE>_TAKER_FEE_PERCENT).
+    taker_fees: HashMap<String, f64>,
+    /// Лучшие bid/ask по биржам: биржа -> (символ -> вершина стакана). Заполняется, только
+    /// если включена проверка по стакану.
+    books: HashMap<String, DashMap<String, BookTop>>,
+    /// Объем сделки в USDT для проверки спреда по стакану (DEPTH_CHECK_NOTIONAL_USDT), None – выключена.
+    pub depth_check_notional: Option<f64>,
+    /// Ставки фандинга за 8 часов: биржа -> (символ -> ставка), обновляются периодически.
+    funding_rates: RwLock<HashMap<String, HashMap<String, f64>>>,
+    /// Порог разницы фандинга за 8 часов в процентах (MIN_FUNDING_DIFF), None – сравнение выключено.
+    pub min_funding_diff_percent: Option<f64>,
+    /// Максимальный скачок цены в процентах относительно предыдущей цены той же биржи
+    /// (MAX_PRICE_JUMP_PERCENT). Больший скачок считается сбойным тиком и отбрасывается.
+    max_price_jump_percent: Option<f64>,
+    /// Наибольший правдоподобный спред в процентах (MAX_PLAUSIBLE_SPREAD_PERCENT), None – без
+    /// ограничения. Больший спред – сбой данных (новый или делистнутый символ), а не возможность.
+    max_plausible_spread_percent: Option<f64>,
+    /// Знаков после запятой в ценах сигналов, если шаг цены не известен (ALERT_PRICE_DECIMALS).
+    default_price_decimals: usize,
+    /// Максимальный возраст цены (MAX_PRICE_AGE_SECS), после которого она не участвует в сравнении.
+    pub max_price_age: Duration,
+    /// Период сравнения цен по таймеру (COMPARE_INTERVAL_MS).
+    pub compare_interval: Duration,
+}
+
+pub const DEFAULT_MIN_SPREAD_PERCENT: f64 = 5.0;
+const DEFAULT_MAX_PRICE_AGE_SECS: u64 = 10;
+const DEFAULT_COMPARE_INTERVAL_MS: u64 = 1000;
+const DEFAULT_MAX_PRICE_JUMP_PERCENT: f64 = 20.0;
+const DEFAULT_MAX_PLAUSIBLE_SPREAD_PERCENT: f64 = 30.0;
+const DEFAULT_PRICE_DECIMALS: usize = 8;
+/// Больше знаков цены биржи не дают: точность f64 все равно кончается.
+const MAX_PRICE_DECIMALS: usize = 12;
+/// Как часто повторяется сообщение о пропуске или ошибке сделки BingX по одному символу.
+const TRADE_NOTICE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// MIN_SPREAD_PERCENT из конфига. При некорректном значении возвращает значение по умолчанию.
+fn min_spread_percent(config: &ThresholdsConfig) -> f64 {
+    match config.min_spread_percent {
+        Some(v) if v > 0.0 && v.is_finite() => v,
+        Some(v) => {
+            warn!(
+                "Invalid MIN_SPREAD_PERCENT '{}', using default {}%",
+                v, DEFAULT_MIN_SPREAD_PERCENT
+            );
+            DEFAULT_MIN_SPREAD_PERCENT
+        }
+        None => DEFAULT_MIN_SPREAD_PERCENT,
+    }
+}
+
+/// SYMBOL_THRESHOLDS из конфига по каноническим тикерам. Некорректные пороги пропускаются.
+fn symbol_thresholds(config: &Config) -> HashMap<String, f64> {
+    let Some(configured) = &config.symbol_thresholds else {
+        return HashMap::new();
+    };
+    let thresholds: HashMap<String, f64> = configured
+        .iter()
+        .filter_map(|(symbol, &threshold)| {
+            if !(threshold > 0.0 && threshold.is_finite()) {
+                warn!("Invalid threshold '{}' for {}, using MIN_SPREAD_PERCENT", threshold, symbol);
+                return None;
+            }
+            Some((canonical_symbol(&symbol.trim().to_uppercase()).symbol, threshold))
+        })
+        .collect();
+    if !thresholds.is_empty() {
+        info!("Per-symbol spread thresholds for {} symbols", thresholds.len());
+    }
+    thresholds
+}
+
+/// THRESHOLD_MODE и его порог из конфига. Без корректного порога для выбранного режима
+/// используется режим процентов.
+fn threshold_mode(config: &ThresholdsConfig) -> ThresholdMode {
+    let Some(mode) = config.mode.as_deref().map(str::trim) else {
+        return ThresholdMode::Percent;
+    };
+    let positive = |value: Option<f64>| value.filter(|v| *v > 0.0 && v.is_finite());
+    let threshold_mode = match mode.to_ascii_lowercase().as_str() {
+        "percent" | "" => return ThresholdMode::Percent,
+        "absolute" => positive(config.min_spread_absolute).map(ThresholdMode::Absolute),
+        "ticks" => positive(config.min_spread_ticks).map(ThresholdMode::TickMultiple),
+        _ => {
+            warn!("Invalid THRESHOLD_MODE '{}', using percent", mode);
+            return ThresholdMode::Percent;
+        }
+    };
+    match threshold_mode {
+        Some(threshold_mode) => {
+            info!("Threshold mode: {:?}", threshold_mode);
+            threshold_mode
+        }
+        None => {
+            warn!(
+                "THRESHOLD_MODE '{}' needs a positive MIN_SPREAD_ABSOLUTE/MIN_SPREAD_TICKS, using percent",
+                mode
+            );
+            ThresholdMode::Percent
+        }
+    }
+}
+
+/// Включенные биржи из конфига в порядке `EXCHANGES`. При некорректном списке – все биржи
+/// (main в этом случае завершается раньше).
+fn compared_venues(config: &Config) -> Vec<&'static str> {
+    let enabled = config.enabled_exchanges().unwrap_or_default();
+    EXCHANGES
+        .iter()
+        .copied()
+        .filter(|exchange| enabled.is_empty() || enabled.contains(exchange))
+        .collect()
+}
+
+/// REFERENCE_VENUE из конфига: имя биржи без учета регистра или `all`. По умолчанию и при
+/// некорректном значении – Bybit.
+fn reference_venue(config: &ThresholdsConfig, venues: &[&'static str]) -> ReferenceVenue {
+    let Some(name) = config.reference_venue.as_deref().map(str::trim).filter(|n| !n.is_empty()) else {
+        return ReferenceVenue::Venue(BYBIT);
+    };
+    if name.eq_ignore_ascii_case("all") {
+        info!("Comparing spreads across all venue pairs");
+        return ReferenceVenue::All;
+    }
+    let Some(reference) = EXCHANGES.iter().copied().find(|exchange| exchange.eq_ignore_ascii_case(name)) else {
+        warn!(
+            "Invalid REFERENCE_VENUE '{}' (expected one of: {}, all), using {}",
+            name,
+            EXCHANGES.join(", "),
+            BYBIT
+        );
+        return ReferenceVenue::Venue(BYBIT);
+    };
+    if !venues.contains(&reference) {
+        warn!("REFERENCE_VENUE {} feed is disabled: no spreads will be compared", reference);
+    } else {
+        info!("Comparing spreads relative to {}", reference);
+    }
+    ReferenceVenue::Venue(reference)
+}
+
+/// MAX_PRICE_AGE_SECS из конфига. При некорректном значении возвращает значение по умолчанию.
+fn max_price_age(config: &ThresholdsConfig) -> Duration {
+    let secs = match config.max_price_age_secs {
+        Some(v) if v > 0 => v,
+        Some(v) => {
+            warn!(
+                "Invalid MAX_PRICE_AGE_SECS '{}', using default {}s",
+                v, DEFAULT_MAX_PRICE_AGE_SECS
+            );
+            DEFAULT_MAX_PRICE_AGE_SECS
+        }
+        None => DEFAULT_MAX_PRICE_AGE_SECS,
+    };
+    Duration::from_secs(secs)
+}
+
+/// COMPARE_INTERVAL_MS из конфига. При некорректном значении возвращает значение по умолчанию.
+fn compare_interval(config: &ThresholdsConfig) -> Duration {
+    let millis = match config.compare_interval_ms {
+        Some(v) if v > 0 => v,
+        Some(v) => {
+            warn!(
+                "Invalid COMPARE_INTERVAL_MS '{}', using default {}ms",
+                v, DEFAULT_COMPARE_INTERVAL_MS
+            );
+            DEFAULT_COMPARE_INTERVAL_MS
+        }
+        None => DEFAULT_COMPARE_INTERVAL_MS,
+    };
+    Duration::from_millis(millis)
+}
+
+/// DEPTH_CHECK_NOTIONAL_USDT из конфига. Не задан или некорректен – проверка по стакану выключена.
+fn depth_check_notional(config: &ThresholdsConfig) -> Option<f64> {
+    match config.depth_check_notional_usdt? {
+        v if v > 0.0 && v.is_finite() => {
+            info!("Order book depth check enabled for {} USDT", v);
+            Some(v)
+        }
+        v => {
+            warn!("Invalid DEPTH_CHECK_NOTIONAL_USDT '{}', depth check disabled", v);
+            None
+        }
+    }
+}
+
+/// MIN_FUNDING_DIFF из конфига. Не задан или некорректен – сравнение фандинга выключено.
+fn min_funding_diff_percent(config: &ThresholdsConfig) -> Option<f64> {
+    match config.min_funding_diff_percent? {
+        v if v > 0.0 && v.is_finite() => Some(v),
+        v => {
+            warn!("Invalid MIN_FUNDING_DIFF '{}', funding comparison disabled", v);
+            None
+        }
+    }
+}
+
+/// MAX_PRICE_JUMP_PERCENT из конфига. 0 выключает проверку, некорректное значение – значение по умолчанию.
+fn max_price_jump_percent(config: &ThresholdsConfig) -> Option<f64> {
+    match config.max_price_jump_percent {
+        Some(0.0) => {
+            info!("Price jump guard disabled");
+            None
+        }
+        Some(v) if v > 0.0 && v.is_finite() => Some(v),
+        Some(v) => {
+            warn!(
+                "Invalid MAX_PRICE_JUMP_PERCENT '{}', using default {}%",
+                v, DEFAULT_MAX_PRICE_JUMP_PERCENT
+            );
+            Some(DEFAULT_MAX_PRICE_JUMP_PERCENT)
+        }
+        None => Some(DEFAULT_MAX_PRICE_JUMP_PERCENT),
+    }
+}
+
+/// MAX_PLAUSIBLE_SPREAD_PERCENT из конфига (по умолчанию 30%). 0 снимает ограничение, некорректное значение – значение по умолчанию.
+fn max_plausible_spread_percent(config: &ThresholdsConfig) -> Option<f64> {
+    match config.max_plausible_spread_percent {
+        Some(0.0) => {
+            info!("Plausible spread cap disabled");
+            None
+        }
+        Some(v) if v > 0.0 && v.is_finite() => Some(v),
+        Some(v) => {
+            warn!(
+                "Invalid MAX_PLAUSIBLE_SPREAD_PERCENT '{}', using default {}%",
+                v, DEFAULT_MAX_PLAUSIBLE_SPREAD_PERCENT
+            );
+            Some(DEFAULT_MAX_PLAUSIBLE_SPREAD_PERCENT)
+        }
+        None => Some(DEFAULT_MAX_PLAUSIBLE_SPREAD_PERCENT),
+    }
+}
+
+/// ALERT_PRICE_DECIMALS из конфига. При некорректном значении возвращает значение по умолчанию.
+fn default_price_decimals(config: &AlertsConfig) -> usize {
+    match config.price_decimals {
+        Some(v) if v <= MAX_PRICE_DECIMALS => v,
+        Some(v) => {
+            warn!(
+                "Invalid ALERT_PRICE_DECIMALS '{}' (at most {}), using default {}",
+                v, MAX_PRICE_DECIMALS, DEFAULT_PRICE_DECIMALS
+            );
+            DEFAULT_PRICE_DECIMALS
+        }
+        None => DEFAULT_PRICE_DECIMALS,
+    }
+}
+
+/// Сколько знаков после запятой нужно, чтобы показать цену с шагом `tick` без потерь:
+/// 0.1 – 1, 0.00025 – 5, 5 – 0. None – шаг некорректен.
+fn decimals_for_tick(tick: f64) -> Option<usize> {
+    if !(tick > 0.0 && tick.is_finite()) {
+        return None;
+    }
+    let decimals = (0..=MAX_PRICE_DECIMALS).find(|&decimals| {
+        let scaled = tick * 10f64.powi(decimals as i32);
+        (scaled - scaled.round()).abs() <= scaled * 1e-9
+    });
+    Some(decimals.unwrap_or(MAX_PRICE_DECIMALS))
+}
+
+/// Комиссии тейкера по умолчанию, в процентах.
+/// Есть для каждой биржи из `EXCHANGES`.
+const DEFAULT_TAKER_FEES: &[(&str, f64)] = &[
+    (BYBIT, 0.055),
+    (HYPERLIQUID, 0.045),
+    (ASTER, 0.035),
+    (BINANCE, 0.05),
+    (OKX, 0.05),
+    (KUCOIN, 0.06),
+    (GATE, 0.05),
+    (MEXC, 0.02),
+    (DYDX, 0.05),
+];
+
+/// Комиссии тейкера из конфига. При некорректном значении используется значение по умолчанию.
+fn taker_fees(config: &FeesConfig) -> HashMap<String, f64> {
+    DEFAULT_TAKER_FEES
+        .iter()
+        .map(|&(exchange, default)| {
+            let configured = match exchange {
+                BYBIT => config.bybit_taker_percent,
+                HYPERLIQUID => config.hyperliquid_taker_percent,
+                ASTER => config.aster_taker_percent,
+                BINANCE => config.binance_taker_percent,
+                OKX => config.okx_taker_percent,
+                KUCOIN => config.kucoin_taker_percent,
+                GATE => config.gate_taker_percent,
+                MEXC => config.mexc_taker_percent,
+                DYDX => config.dydx_taker_percent,
+                _ => None,
+            };
+            let fee = match configured {
+                Some(v) if v >= 0.0 && v.is_finite() => v,
+                Some(v) => {
+                    warn!("Invalid {} taker fee '{}', using default {}%", exchange, v, default);
+                    default
+                }
+                None => default,
+            };
+            (exchange.to_string(), fee)
+        })
+        .collect()
+}
+
+/// Тайминги фидов из `[feeds.<биржа>]`. Нулевые значения заменяются значениями по умолчанию.
+fn reconnect_configs(config: &Config) -> HashMap<String, ReconnectConfig> {
+    for name in config.feeds.keys() {
+        if !EXCHANGES.iter().any(|exchange| exchange.eq_ignore_ascii_case(name)) {
+            warn!("Unknown exchange '{}' in [feeds], ignored", name);
+        }
+    }
+
+    EXCHANGES
+        .iter()
+        .map(|exchange| {
+            let mut reconnect = ReconnectConfig::default();
+            if let Some(feed) = config.feed(exchange) {
+                match feed.heartbeat_timeout_secs {
+                    Some(secs) if secs > 0 => reconnect.heartbeat_timeout = Duration::from_secs(secs),
+                    Some(secs) => warn!(
+                        "Invalid {} heartbeat timeout '{}', using default {:?}",
+                        exchange, secs, reconnect.heartbeat_timeout
+                    ),
+                    None => {}
+                }
+                match feed.reconnect_delay_ms {
+                    Some(ms) if ms > 0 => {
+                        reconnect.base_delay = Duration::from_millis(ms);
+                        reconnect.max_delay = reconnect.max_delay.max(reconnect.base_delay);
+                    }
+                    Some(ms) => warn!(
+                        "Invalid {} reconnect delay '{}', using default {:?}",
+                        exchange, ms, reconnect.base_delay
+                    ),
+                    None => {}
+                }
+            }
+            (exchange.to_string(), reconnect)
+        })
+        .collect()
+}
+
+fn empty_prices<T>() -> HashMap<String, DashMap<String, T>> {
+    EXCHANGES
+        .iter()
+        .map(|exchange| (exchange.to_string(), DashMap::new()))
+        .collect()
 }
 
 impl SharedState {
-    pub fn new(bingx: Option<std::sync::Arc<BingXClient>>) -> Self {
+    pub fn new(config: &Config, bingx: Option<std::sync::Arc<BingXClient>>) -> Self {
+        let compared_venues = compared_venues(config);
+        let reference_venue = reference_venue(&config.thresholds, &compared_venues);
         SharedState {
-            bybit_prices: RwLock::new(HashMap::new()),
-            hyperliquid_prices: RwLock::new(HashMap::new()),
-            aster_prices: RwLock::new(HashMap::new()),
+            prices: empty_prices(),
+            books: empty_prices(),
+            feeds_live: EXCHANGES
+                .iter()
+                .map(|exchange| (exchange.to_string(), AtomicBool::new(false)))
+                .collect(),
+            feed_stats: EXCHANGES
+                .iter()
+                .map(|exchange| (exchange.to_string(), FeedStats::default()))
+                .collect(),
+            reconnect: reconnect_configs(config),
             telegram: None,
             bingx,
+            bingx_trading_enabled: config.trading.enable_bingx_trading.unwrap_or(false),
+            trading_paused: AtomicBool::new(false),
+            trade_notices: DashMap::new(),
+            min_spread_percent: min_spread_percent(&config.thresholds),
+            symbol_thresholds: symbol_thresholds(config),
+            threshold_mode: threshold_mode(&config.thresholds),
+            reference_venue,
+            comparison_pairs: reference_venue.comparison_pairs(&compared_venues),
+            compared_venues,
+            tick_sizes: empty_prices(),
+            instrument_specs: empty_prices(),
+            max_price_age: max_price_age(&config.thresholds),
+            compare_interval: compare_interval(&config.thresholds),
+            opportunity_log: OpportunityLog::from_config(&config.storage),
+            database: Database::from_config(&config.storage),
+            recorder: PriceRecorder::from_config(&config.storage),
+            alert_digest: AlertDigest::from_config(&config.alerts),
+            alert_tiers: AlertTiers::from_config(&config.alerts),
+            spread_stats: SpreadStats::default(),
+            daily_loss_limit: DailyLossLimit::from_config(&config.trading),
+            taker_fees: taker_fees(&config.fees),
+            depth_check_notional: depth_check_notional(&config.thresholds),
+            funding_rates: RwLock::new(HashMap::new()),
+            min_funding_diff_percent: min_funding_diff_percent(&config.thresholds),
+            max_price_jump_percent: max_price_jump_percent(&config.thresholds),
+            max_plausible_spread_percent: max_plausible_spread_percent(&config.thresholds),
+            default_price_decimals: default_price_decimals(&config.alerts),
         }
     }
 
-    pub fn with_telegram(telegram: TelegramNotifier, bingx: Option<std::sync::Arc<BingXClient>>) -> Self {
-        SharedState {
-            bybit_prices: RwLock::new(HashMap::new()),
-            hyperliquid_prices: RwLock::new(HashMap::new()),
-            aster_prices: RwLock::new(HashMap::new()),
-            telegram: Some(telegram),
-            bingx,
+    pub fn with_telegram(config: &Config, telegram: TelegramNotifier, bingx: Option<std::sync::Arc<BingXClient>>) -> Self {
+        Self {
+            telegram: Some(Arc::new(telegram)),
+            ..Self::new(config, bingx)
+        }
+    }
+
+    /// Записывает цену символа на бирже с текущим временем. Сбойный тик (см. `is_price_jump`)
+    /// отбрасывается.
+    pub fn set_price(&self, exchange: &str, symbol: &str, price: f64) {
+        let Some(prices) = self.prices.get(exchange) else {
+            warn!("Price for unknown exchange {} ignored ({})", exchange, symbol);
+            return;
+        };
+        if self.store_price(exchange, prices, symbol.to_string(), PriceQuote::new(price)) {
+            self.mark_feed_live(exchange);
+        }
+    }
+
+    /// Регистрирует символ биржи до первой цены: он учитывается в числе символов фида, но
+    /// `get_price` возвращает для него None. Уже полученную цену не затирает.
+    pub fn register_symbol(&self, exchange: &str, symbol: &str) {
+        let Some(prices) = self.prices.get(exchange) else {
+            warn!("Symbol for unknown exchange {} ignored ({})", exchange, symbol);
+            return;
+        };
+        prices.entry(symbol.to_string()).or_insert_with(PriceQuote::placeholder);
+    }
+
+    /// Как `set_price`, но со временем цены на сервере биржи (если оно есть): по нему считается
+    /// возраст цены, а обновление старше уже сохраненного (пришло не по порядку) отбрасывается.
+    pub fn set_price_at(&self, exchange: &str, symbol: &str, price: f64, server_ts_ms: Option<u64>) {
+        let Some(server_ts_ms) = server_ts_ms else {
+            self.set_price(exchange, symbol, price);
+            return;
+        };
+        let Some(prices) = self.prices.get(exchange) else {
+            warn!("Price for unknown exchange {} ignored ({})", exchange, symbol);
+            return;
+        };
+        let quote = PriceQuote::at_server_time(price, server_ts_ms);
+        if self.store_price(exchange, prices, symbol.to_string(), quote) {
+            self.mark_feed_live(exchange);
+        }
+    }
+
+    /// Записывает пачку цен одной биржи (например, весь снимок `!ticker@arr`) с общим временем.
+    pub fn set_prices(&self, exchange: &str, quotes: impl IntoIterator<Item = (String, f64)>) {
+        let Some(prices) = self.prices.get(exchange) else {
+            warn!("Prices for unknown exchange {} ignored", exchange);
+            return;
+        };
+        let updated_at = Instant::now();
+        let mut live = false;
+        for (symbol, price) in quotes {
+            let quote = PriceQuote {
+                price,
+                updated_at,
+                server_ts_ms: None,
+                received: true,
+            };
+            live |= self.store_price(exchange, prices, symbol, quote);
+        }
+        if live {
+            self.mark_feed_live(exchange);
         }
     }
+
+    /// Сохраняет цену, если она не является скачком относительно предыдущей. Проверка и запись
+    /// идут под блокировкой одной записи, так что параллельные обновления не обходят проверку.
+    fn store_price(
+        &self,
+        exchange: &str,
+        prices: &DashMap<String, PriceQuote>,
+        symbol: String,
+        quote: PriceQuote,
+    ) -> bool {
+        match prices.entry(symbol) {
+            Entry::Occupied(mut entry) => {
+                let previous = *entry.get();
+                if let (Some(previous_ts), Some(ts)) = (previous.server_ts_ms, quote.server_ts_ms) {
+                    if ts < previous_ts {
+                        debug!(
+                            "Discarding out-of-order {} {} price {} ({} ms older than stored)",
+                            exchange,
+                            entry.key(),
+                            quote.price,
+                            previous_ts - ts
+                        );
+                        return false;
+                    }
+                }
+                if self.is_price_jump(&previous, quote.price) {
+                    warn!(
+                        "Discarding {} {} price {}: {:.1}% away from previous {}",
+                        exchange,
+                        entry.key(),
+                        quote.price,
+                        (quote.price - previous.price).abs() / previous.price * 100.0,
+                        previous.price
+                    );
+                    return false;
+                }
+                self.record_price(exchange, entry.key(), quote.price);
+                entry.insert(quote);
+            }
+            Entry::Vacant(entry) => {
+                self.record_price(exchange, entry.key(), quote.price);
+                entry.insert(quote);
+            }
+        }
+        true
+    }
+
+    /// Передает принятую цену в запись (RECORD_PATH).
+    fn record_price(&self, exchange: &str, symbol: &str, price: f64) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record(exchange, symbol, price);
+        }
+    }
+
+    /// Скачок больше MAX_PRICE_JUMP_PERCENT от свежей предыдущей цены. С устаревшей ценой не
+    /// сравниваем: если рынок действительно сдвинулся, новый уровень принимается через
+    /// `max_price_age`, пока старая цена уже не участвует в сравнении.
+    fn is_price_jump(&self, previous: &PriceQuote, price: f64) -> bool {
+        let Some(max_jump) = self.max_price_jump_percent else {
+            return false;
+        };
+        if !previous.received || previous.price <= 0.0 || previous.updated_at.elapsed() > self.max_price_age {
+            return false;
+        }
+        (price - previous.price).abs() / previous.price * 100.0 > max_jump
+    }
+
+    /// При первой реальной цене от фида один раз сообщает в Telegram, что фид заработал.
+    /// Отправка идет в фоне, чтобы не задерживать цикл чтения WebSocket.
+    fn mark_feed_live(&self, exchange: &str) {
+        let Some(live) = self.feeds_live.get(exchange) else {
+            return;
+        };
+        if live.load(Ordering::Relaxed) || live.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        info!("{} feed live", exchange);
+
+        if let Some(telegram) = &self.telegram {
+            let telegram = telegram.clone();
+            let message = format!("🟢 <b>{} feed live</b>", TelegramNotifier::escape_html(exchange));
+            tokio::spawn(async move {
+                if let Err(e) = telegram.send_message(&message).await {
+                    error!("Failed to send Telegram feed live message: {}", e);
+                }
+            });
+        }
+    }
+
+    /// Порог спреда в процентах для символа. Порядок: порог символа из SYMBOL_THRESHOLDS,
+    /// затем общий MIN_SPREAD_PERCENT (`--min-spread`, затем config.toml, затем окружение),
+    /// затем значение по умолчанию.
+    pub fn min_spread_for(&self, symbol: &str) -> f64 {
+        self.symbol_thresholds
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.min_spread_percent)
+    }
+
+    pub fn is_trading_paused(&self) -> bool {
+        self.trading_paused.load(Ordering::Relaxed)
+    }
+
+    pub fn set_trading_paused(&self, paused: bool) {
+        self.trading_paused.store(paused, Ordering::Relaxed);
+    }
+
+    /// Пора ли сообщить в Telegram о пропуске или ошибке (`kind`) сделки BingX по символу. Пока
+    /// держится спред, сравнение повторяется каждый проход, поэтому сообщение одного вида по
+    /// символу уходит не чаще раза в `TRADE_NOTICE_INTERVAL`.
+    pub fn should_notify_trade(&self, symbol: &str, kind: &'static str) -> bool {
+        match self.trade_notices.entry((symbol.to_string(), kind)) {
+            Entry::Occupied(entry) if entry.get().elapsed() < TRADE_NOTICE_INTERVAL => false,
+            Entry::Occupied(mut entry) => {
+                entry.insert(Instant::now());
+                true
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Цены символа на всех биржах; None – цены на бирже нет.
+    pub fn prices_for(&self, symbol: &str) -> Vec<(&'static str, Option<PriceQuote>)> {
+        let mut prices = Vec::with_capacity(EXCHANGES.len());
+        for exchange in EXCHANGES {
+            prices.push((*exchange, self.get_price(exchange, symbol)));
+        }
+        prices
+    }
+
+    /// Комиссия тейкера биржи в процентах. Для бирж без настроенной комиссии – 0.
+    pub fn taker_fee_percent(&self, exchange: &str) -> f64 {
+        self.taker_fees.get(exchange).copied().unwrap_or(0.0)
+    }
+
+    /// Заменяет ставки фандинга биржи свежим снимком.
+    pub async fn set_funding_rates(&self, exchange: &str, funding: HashMap<String, f64>) {
+        self.funding_rates.write().await.insert(exchange.to_string(), funding);
+    }
+
+    /// Последние известные ставки фандинга символа за 8 часов на всех биржах, где он есть.
+    pub async fn funding_rates(&self, symbol: &str) -> Vec<(String, f64)> {
+        self.funding_rates
+            .read()
+            .await
+            .iter()
+            .filter_map(|(exchange, rates)| Some((exchange.clone(), *rates.get(symbol)?)))
+            .collect()
+    }
+
+    /// Счетчики фида биржи; None – неизвестная биржа.
+    pub fn feed_stats(&self, exchange: &str) -> Option<&FeedStats> {
+        self.feed_stats.get(exchange)
+    }
+
+    /// Таймаут тишины и задержки переподключения фида биржи.
+    pub fn reconnect_config(&self, exchange: &str) -> ReconnectConfig {
+        self.reconnect.get(exchange).cloned().unwrap_or_default()
+    }
+
+    /// Отмечает принятое сообщение фида.
+    pub fn record_feed_message(&self, exchange: &str) {
+        if let Some(stats) = self.feed_stats(exchange) {
+            stats.record_message();
+        }
+    }
+
+    /// Отмечает сообщение фида, которое не удалось разобрать.
+    pub fn record_parse_error(&self, exchange: &str) {
+        if let Some(stats) = self.feed_stats(exchange) {
+            stats.record_parse_error();
+        }
+    }
+
+    /// Отмечает цены символов вне общих тикеров, которые фид прислал, но записывать не нужно.
+    pub fn record_skipped_writes(&self, exchange: &str, count: u64) {
+        if let Some(stats) = self.feed_stats(exchange) {
+            stats.record_skipped_writes(count);
+        }
+    }
+
+    /// Отмечает попытку переподключения фида; фид переходит в `FeedStatus::Reconnecting`.
+    pub fn record_reconnect(&self, exchange: &str) {
+        if let Some(stats) = self.feed_stats(exchange) {
+            stats.record_reconnect();
+        }
+    }
+
+    /// Меняет состояние соединения фида.
+    pub fn set_feed_status(&self, exchange: &str, status: FeedStatus) {
+        if let Some(stats) = self.feed_stats(exchange) {
+            stats.set_status(status);
+        }
+    }
+
+    /// Сводка по фидам для мониторинга: число символов, время последней полученной цены и счетчики.
+    pub fn feed_status(&self) -> Vec<FeedSummary> {
+        let mut status = Vec::with_capacity(EXCHANGES.len());
+        for exchange in EXCHANGES {
+            let (Some(prices), Some(stats)) = (self.prices.get(*exchange), self.feed_stats(exchange)) else {
+                continue;
+            };
+            let last_update = prices
+                .iter()
+                .filter(|entry| entry.received)
+                .map(|entry| entry.updated_at)
+                .max();
+            let last_message_unix_ms = stats.last_message_unix_ms.load(Ordering::Relaxed);
+            status.push(FeedSummary {
+                exchange,
+                status: stats.status(),
+                symbols: prices.len(),
+                last_update,
+                messages: stats.messages.load(Ordering::Relaxed),
+                parse_errors: stats.parse_errors.load(Ordering::Relaxed),
+                reconnects: stats.reconnects.load(Ordering::Relaxed),
+                skipped_writes: stats.skipped_writes.load(Ordering::Relaxed),
+                last_message_unix_ms: (last_message_unix_ms > 0).then_some(last_message_unix_ms),
+            });
+        }
+        status
+    }
+
+    /// Состояние всех фидов одной строкой для периодического лога:
+    /// `Bybit live (0.4s), OKX reconnecting (35.2s), dYdX connecting (no prices)`.
+    /// Остановленные фиды без символов (отключенные или без общих тикеров) не показываются.
+    pub fn feed_status_line(&self) -> String {
+        let feeds: Vec<String> = self
+            .feed_status()
+            .iter()
+            .filter(|feed| feed.symbols > 0 || feed.status != FeedStatus::Dead)
+            .map(|feed| {
+                let age = match feed.last_update {
+                    Some(t) => format!("{:.1}s", t.elapsed().as_secs_f64()),
+                    None => "no prices".to_string(),
+                };
+                format!("{} {} ({})", feed.exchange, feed.status.label(), age)
+            })
+            .collect();
+        if feeds.is_empty() {
+            "no feeds".to_string()
+        } else {
+            feeds.join(", ")
+        }
+    }
+
+    /// Все полученные цены: (биржа, символ, цена).
+    pub fn price_snapshot(&self) -> Vec<(&'static str, String, f64)> {
+        let mut snapshot = Vec::new();
+        for exchange in EXCHANGES {
+            let Some(prices) = self.prices.get(*exchange) else {
+                continue;
+            };
+            snapshot.extend(
+                prices
+                    .iter()
+                    .filter(|entry| entry.received)
+                    .map(|entry| (*exchange, entry.key().clone(), entry.price)),
+            );
+        }
+        snapshot
+    }
+
+    /// Цены символа на всех биржах одним снимком: ссылки на записи всех бирж берутся до чтения,
+    /// так что ни одна цена не обновится, пока собираются остальные. Биржи без цены пропускаются.
+    pub fn symbol_prices(&self, symbol: &str) -> Vec<(&'static str, f64)> {
+        let entries: Vec<_> = EXCHANGES
+            .iter()
+            .filter_map(|exchange| Some((*exchange, self.prices.get(*exchange)?.get(symbol)?)))
+            .collect();
+        entries
+            .iter()
+            .filter(|(_, quote)| quote.received)
+            .map(|(exchange, quote)| (*exchange, quote.price))
+            .collect()
+    }
+
+    /// Не превышает ли спред `percent` MAX_PLAUSIBLE_SPREAD_PERCENT.
+    pub fn is_plausible_spread(&self, percent: f64) -> bool {
+        self.max_plausible_spread_percent.is_none_or(|max| percent <= max)
+    }
+
+    /// Возвращает последнюю цену символа на бирже; None – фид еще не прислал цену.
+    pub fn get_price(&self, exchange: &str, symbol: &str) -> Option<PriceQuote> {
+        let prices = self.prices.get(exchange)?;
+        let quote = prices.get(symbol).map(|entry| *entry);
+        quote.filter(|quote| quote.received)
+    }
+
+    /// Заменяет шаги цены биржи (символ -> шаг за монету).
+    pub fn set_tick_sizes(&self, exchange: &str, ticks: HashMap<String, f64>) {
+        let Some(tick_sizes) = self.tick_sizes.get(exchange) else {
+            warn!("Tick sizes for unknown exchange {} ignored", exchange);
+            return;
+        };
+        tick_sizes.clear();
+        for (symbol, tick) in ticks {
+            tick_sizes.insert(symbol, tick);
+        }
+    }
+
+    /// Заменяет параметры контрактов биржи (символ -> параметры за монету).
+    pub fn set_instrument_specs(&self, exchange: &str, specs: HashMap<String, InstrumentSpec>) {
+        let Some(instrument_specs) = self.instrument_specs.get(exchange) else {
+            warn!("Instrument specs for unknown exchange {} ignored", exchange);
+            return;
+        };
+        instrument_specs.clear();
+        for (symbol, spec) in specs {
+            instrument_specs.insert(symbol, spec);
+        }
+    }
+
+    /// Параметры контракта биржи по символу; None – не загружены.
+    /// Нужно хеджу (`HedgeExecutor`) для расчета объема ордеров на Bybit.
+    pub fn instrument_spec(&self, exchange: &str, symbol: &str) -> Option<InstrumentSpec> {
+        self.instrument_specs.get(exchange)?.get(symbol).map(|spec| *spec)
+    }
+
+    /// Шаг цены пары бирж по символу – больший из известных: сделку ограничивает более грубая
+    /// биржа. None, если шаг не известен ни для одной.
+    pub fn tick_size(&self, symbol: &str, (a, b): (&str, &str)) -> Option<f64> {
+        [a, b]
+            .into_iter()
+            .filter_map(|exchange| self.tick_sizes.get(exchange)?.get(symbol).map(|tick| *tick))
+            .reduce(f64::max)
+    }
+
+    /// Знаков после запятой для цен пары бирж по символу в сигналах и логах: по более мелкому
+    /// из известных шагов цены, чтобы цена ни одной из бирж не обрезалась. Шаг не известен –
+    /// ALERT_PRICE_DECIMALS.
+    pub fn price_decimals(&self, symbol: &str, (a, b): (&str, &str)) -> usize {
+        [a, b]
+            .into_iter()
+            .filter_map(|exchange| self.tick_sizes.get(exchange)?.get(symbol).map(|tick| *tick))
+            .filter_map(decimals_for_tick)
+            .max()
+            .unwrap_or(self.default_price_decimals)
+    }
+
+    /// Обновляет вершину стакана. Сторона `Unchanged` остается прежней вместе со временем
+    /// обновления, `Cleared` очищается.
+    pub fn update_book(&self, exchange: &str, symbol: &str, bid: BookSideUpdate, ask: BookSideUpdate) {
+        if bid == BookSideUpdate::Unchanged && ask == BookSideUpdate::Unchanged {
+            return;
+        }
+        let Some(books) = self.books.get(exchange) else {
+            warn!("Order book for unknown exchange {} ignored ({})", exchange, symbol);
+            return;
+        };
+        let now = Instant::now();
+        let mut book = books.entry(symbol.to_string()).or_insert(BookTop {
+            bid: None,
+            ask: None,
+            bid_updated_at: now,
+            ask_updated_at: now,
+        });
+        let apply = |level: &mut Option<BookLevel>, updated_at: &mut Instant, update: BookSideUpdate| {
+            match update {
+                BookSideUpdate::Unchanged => return,
+                BookSideUpdate::Cleared => *level = None,
+                BookSideUpdate::Level(new) => *level = Some(new),
+            }
+            *updated_at = now;
+        };
+        let book = &mut *book;
+        apply(&mut book.bid, &mut book.bid_updated_at, bid);
+        apply(&mut book.ask, &mut book.ask_updated_at, ask);
+    }
+
+    pub fn get_book(&self, exchange: &str, symbol: &str) -> Option<BookTop> {
+        let books = self.books.get(exchange)?;
+        let book = books.get(symbol).map(|entry| *entry);
+        book
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    const SYMBOLS: usize = 500;
+    const ROUNDS: usize = 200;
+    /// Шаги цены между раундами меньше порога скачка.
+    const BASE_PRICE: f64 = 1000.0;
+
+    /// Четыре фида параллельно обновляют 500 символов и читают цены, как `compare_prices`:
+    /// каждый фид сразу видит свою запись, и после всех раундов остаются последние цены.
+    #[test]
+    fn concurrent_updates_of_500_symbols() {
+        let state = Arc::new(SharedState::new(&Config::default(), None));
+        let symbols: Arc<Vec<String>> = Arc::new((0..SYMBOLS).map(|i| format!("SYM{}USDT", i)).collect());
+        let feeds = [BYBIT, HYPERLIQUID, ASTER, BINANCE];
+
+        let writers: Vec<_> = feeds
+            .into_iter()
+            .map(|exchange| {
+                let state = state.clone();
+                let symbols = symbols.clone();
+                thread::spawn(move || {
+                    for round in 1..=ROUNDS {
+                        for symbol in symbols.iter() {
+                            state.set_price(exchange, symbol, BASE_PRICE + round as f64);
+                            assert_eq!(
+                                state.get_price(exchange, symbol).map(|q| q.price),
+                                Some(BASE_PRICE + round as f64)
+                            );
+                            for venue in [BYBIT, HYPERLIQUID, ASTER] {
+                                let _ = state.get_price(venue, symbol);
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        for exchange in feeds {
+            for symbol in symbols.iter() {
+                assert_eq!(state.get_price(exchange, symbol).map(|q| q.price), Some(BASE_PRICE + ROUNDS as f64));
+            }
+        }
+        let stats = state.feed_status();
+        assert!(stats
+            .iter()
+            .filter(|feed| feeds.contains(&feed.exchange))
+            .all(|feed| feed.symbols == SYMBOLS && feed.last_update.is_some()));
+    }
+
+    #[test]
+    fn repeated_trade_notices_are_rate_limited() {
+        let state = SharedState::new(&Config::default(), None);
+        assert!(state.should_notify_trade("BTCUSDT", "skipped"));
+        assert!(!state.should_notify_trade("BTCUSDT", "skipped"));
+        assert!(state.should_notify_trade("BTCUSDT", "failed"));
+        assert!(state.should_notify_trade("ETHUSDT", "skipped"));
+    }
+
+    #[test]
+    fn unknown_exchange_is_ignored() {
+        let state = SharedState::new(&Config::default(), None);
+        state.set_price("Unknown", "BTCUSDT", 1.0);
+        assert!(state.get_price("Unknown", "BTCUSDT").is_none());
+        assert!(state.price_snapshot().is_empty());
+    }
+
+    #[test]
+    fn feed_counters_are_reported_in_status() {
+        let state = SharedState::new(&Config::default(), None);
+        state.record_feed_message(OKX);
+        state.record_feed_message(OKX);
+        state.record_parse_error(OKX);
+        state.record_reconnect(OKX);
+        state.record_feed_message("Unknown");
+
+        let status = state.feed_status();
+        let okx = status.iter().find(|feed| feed.exchange == OKX).unwrap();
+        assert_eq!((okx.messages, okx.parse_errors, okx.reconnects), (2, 1, 1));
+        assert!(okx.last_message_unix_ms.is_some());
+        let bybit = status.iter().find(|feed| feed.exchange == BYBIT).unwrap();
+        assert_eq!((bybit.messages, bybit.last_message_unix_ms), (0, None));
+    }
+
+    #[test]
+    fn feed_status_follows_connection_transitions() {
+        let state = SharedState::new(&Config::default(), None);
+        let status_of = |exchange| state.feed_status().iter().find(|feed| feed.exchange == exchange).unwrap().status;
+        assert_eq!(status_of(BYBIT), FeedStatus::Connecting);
+
+        state.set_feed_status(BYBIT, FeedStatus::Live);
+        state.set_price(BYBIT, "BTCUSDT", 50_000.0);
+        assert_eq!(status_of(BYBIT), FeedStatus::Live);
+        state.record_reconnect(BYBIT);
+        assert_eq!(status_of(BYBIT), FeedStatus::Reconnecting);
+
+        // Остановленный фид без символов в строке состояния не показывается
+        state.set_feed_status(OKX, FeedStatus::Dead);
+        state.set_feed_status(HYPERLIQUID, FeedStatus::Dead);
+        state.register_symbol(HYPERLIQUID, "BTCUSDT");
+        let line = state.feed_status_line();
+        assert!(line.starts_with("Bybit reconnecting ("), "{}", line);
+        assert!(line.contains("Hyperliquid dead (no prices)"), "{}", line);
+        assert!(!line.contains("OKX"), "{}", line);
+    }
+
+    #[test]
+    fn feed_timings_fall_back_to_defaults_when_invalid() {
+        let config: Config = toml::from_str(
+            r#"
+            [feeds.dydx]
+            heartbeat_timeout_secs = 90
+            reconnect_delay_ms = 120000
+
+            [feeds.OKX]
+            heartbeat_timeout_secs = 0
+            "#,
+        )
+        .unwrap();
+        let state = SharedState::new(&config, None);
+        let defaults = ReconnectConfig::default();
+
+        let dydx = state.reconnect_config(DYDX);
+        assert_eq!(dydx.heartbeat_timeout, Duration::from_secs(90));
+        assert_eq!((dydx.base_delay, dydx.max_delay), (Duration::from_secs(120), Duration::from_secs(120)));
+        assert_eq!(state.reconnect_config(OKX).heartbeat_timeout, defaults.heartbeat_timeout);
+        assert_eq!(state.reconnect_config(BYBIT).base_delay, defaults.base_delay);
+    }
+
+    #[test]
+    fn symbol_threshold_overrides_global_minimum() {
+        let config = Config {
+            symbol_thresholds: Some(HashMap::from([
+                ("btcusdt".to_string(), 0.05),
+                ("1000PEPEUSDT".to_string(), 0.3),
+                ("DOGEUSDT".to_string(), -1.0),
+            ])),
+            ..Config::default()
+        };
+        let state = SharedState::new(&config, None);
+        assert_eq!(state.min_spread_for("BTCUSDT"), 0.05);
+        assert_eq!(state.min_spread_for("PEPEUSDT"), 0.3);
+        assert_eq!(state.min_spread_for("DOGEUSDT"), DEFAULT_MIN_SPREAD_PERCENT);
+        assert_eq!(state.min_spread_for("ETHUSDT"), DEFAULT_MIN_SPREAD_PERCENT);
+    }
+
+    #[test]
+    fn out_of_order_server_time_is_discarded() {
+        let state = SharedState::new(&Config::default(), None);
+        let now_ms = chrono::Utc::now().timestamp_millis() as u64;
+        state.set_price_at(BYBIT, "BTCUSDT", 65000.0, Some(now_ms));
+        state.set_price_at(BYBIT, "BTCUSDT", 65100.0, Some(now_ms - 500));
+        let quote = state.get_price(BYBIT, "BTCUSDT").unwrap();
+        assert_eq!((quote.price, quote.server_ts_ms), (65000.0, Some(now_ms)));
+
+        // Возраст считается от серверного времени
+        state.set_price_at(BYBIT, "ETHUSDT", 3500.0, Some(now_ms - 60_000));
+        assert!(state.get_price(BYBIT, "ETHUSDT").unwrap().updated_at.elapsed() >= Duration::from_secs(59));
+    }
+
+    #[test]
+    fn price_jump_is_discarded_until_previous_price_is_stale() {
+        let mut state = SharedState::new(&Config::default(), None);
+        state.register_symbol(BYBIT, "BTCUSDT");
+        assert!(state.get_price(BYBIT, "BTCUSDT").is_none());
+        state.set_price(BYBIT, "BTCUSDT", 65000.0);
+        state.set_price(BYBIT, "BTCUSDT", 650000.0);
+        assert_eq!(state.get_price(BYBIT, "BTCUSDT").map(|q| q.price), Some(65000.0));
+
+        state.set_prices(BYBIT, [("BTCUSDT".to_string(), 66000.0)]);
+        assert_eq!(state.get_price(BYBIT, "BTCUSDT").map(|q| q.price), Some(66000.0));
+
+        state.max_price_age = Duration::ZERO;
+        state.set_price(BYBIT, "BTCUSDT", 90000.0);
+        assert_eq!(state.get_price(BYBIT, "BTCUSDT").map(|q| q.price), Some(90000.0));
+    }
+
+    #[test]
+    fn implausible_spreads_are_capped() {
+        let mut state = SharedState::new(&Config::default(), None);
+        assert!(state.is_plausible_spread(30.0));
+        assert!(!state.is_plausible_spread(900.0));
+        state.max_plausible_spread_percent = None;
+        assert!(state.is_plausible_spread(900.0));
+    }
+
+    #[test]
+    fn deleted_book_levels_clear_the_side() {
+        let state = SharedState::new(&Config::default(), None);
+        let level = |price| BookSideUpdate::Level(BookLevel { price, size: 1.0 });
+        state.update_book(BYBIT, "BTCUSDT", level(99.0), level(100.0));
+        let before = state.get_book(BYBIT, "BTCUSDT").unwrap();
+
+        std::thread::sleep(Duration::from_millis(5));
+        // Удален лучший ask, нового нет; bid в обновлении не пришел
+        state.update_book(BYBIT, "BTCUSDT", BookSideUpdate::Unchanged, BookSideUpdate::from_levels([(100.0, 0.0)]));
+        let book = state.get_book(BYBIT, "BTCUSDT").unwrap();
+        assert_eq!(book.ask, None);
+        assert!(book.ask_updated_at > before.ask_updated_at);
+        assert_eq!(book.bid, Some(BookLevel { price: 99.0, size: 1.0 }));
+        assert_eq!(book.bid_updated_at, before.bid_updated_at);
+
+        assert_eq!(
+            BookSideUpdate::from_levels([(100.0, 0.0), (100.5, 2.0)]),
+            BookSideUpdate::Level(BookLevel { price: 100.5, size: 2.0 })
+        );
+        assert_eq!(BookSideUpdate::from_levels([]), BookSideUpdate::Unchanged);
+    }
+
+    #[test]
+    fn every_exchange_has_a_taker_fee() {
+        let state = SharedState::new(&Config::default(), None);
+        for exchange in EXCHANGES {
+            assert!(state.taker_fee_percent(exchange) > 0.0, "{} has no taker fee", exchange);
+        }
+        let config: Config = toml::from_str("[fees]\nokx_taker_percent = 0.02").unwrap();
+        assert_eq!(SharedState::new(&config, None).taker_fee_percent(OKX), 0.02);
+    }
+
+    #[test]
+    fn price_decimals_follow_finest_tick() {
+        assert_eq!(decimals_for_tick(0.1), Some(1));
+        assert_eq!(decimals_for_tick(0.00025), Some(5));
+        assert_eq!(decimals_for_tick(5.0), Some(0));
+        assert_eq!(decimals_for_tick(0.0), None);
+
+        let state = SharedState::new(&Config::default(), None);
+        state.set_tick_sizes(BYBIT, HashMap::from([("BTCUSDT".to_string(), 0.1)]));
+        state.set_tick_sizes(HYPERLIQUID, HashMap::from([("BTCUSDT".to_string(), 1.0)]));
+        assert_eq!(state.price_decimals("BTCUSDT", (BYBIT, HYPERLIQUID)), 1);
+        // Шаг не известен – ALERT_PRICE_DECIMALS
+        assert_eq!(state.price_decimals("PEPEUSDT", (BYBIT, HYPERLIQUID)), DEFAULT_PRICE_DECIMALS);
+        assert_eq!(format!("{:.*}", state.price_decimals("BTCUSDT", (BYBIT, ASTER)), 95000.0), "95000.0");
+    }
+
+    #[test]
+    fn symbol_prices_skip_venues_without_price() {
+        let state = SharedState::new(&Config::default(), None);
+        state.register_symbol(BYBIT, "BTCUSDT");
+        state.register_symbol(ASTER, "BTCUSDT");
+        state.set_price(BYBIT, "BTCUSDT", 65000.0);
+        state.set_price(HYPERLIQUID, "BTCUSDT", 65010.0);
+        assert_eq!(state.symbol_prices("BTCUSDT"), vec![(BYBIT, 65000.0), (HYPERLIQUID, 65010.0)]);
+        assert!(state.symbol_prices("ETHUSDT").is_empty());
+    }
+
+    #[test]
+    fn sub_penny_price_is_present_after_register() {
+        let state = SharedState::new(&Config::default(), None);
+        state.register_symbol(HYPERLIQUID, "PEPEUSDT");
+        assert!(state.get_price(HYPERLIQUID, "PEPEUSDT").is_none());
+        state.set_price(HYPERLIQUID, "PEPEUSDT", 0.00000123);
+        // Повторная регистрация не затирает пришедшую цену
+        state.register_symbol(HYPERLIQUID, "PEPEUSDT");
+        assert_eq!(state.get_price(HYPERLIQUID, "PEPEUSDT").map(|q| q.price), Some(0.00000123));
+    }
 }
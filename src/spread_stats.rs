@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+
+/// Окно статистики спредов.
+pub const STATS_WINDOW: Duration = Duration::from_secs(60 * 60);
+/// Сколько последних спредов хранится на символ. При частых обновлениях буфер
+/// покрывает меньше `STATS_WINDOW`, зато память ограничена.
+const SAMPLES_PER_SYMBOL: usize = 512;
+
+/// Распределение спредов за окно, в процентах.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpreadSummary {
+    pub count: usize,
+    pub p50: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Недавние наблюдаемые спреды по символам для `/stats` и `/metrics`.
+///
+/// Записывается каждый спред пары бирж со свежими ценами – и ниже порога сигнала, чтобы
+/// по распределению можно было подобрать MIN_SPREAD_PERCENT. На символ – кольцевой буфер
+/// фиксированного размера; записи старше `STATS_WINDOW` не учитываются.
+#[derive(Debug, Default)]
+pub struct SpreadStats {
+    samples: DashMap<String, VecDeque<(Instant, f64)>>,
+}
+
+impl SpreadStats {
+    pub fn record(&self, symbol: &str, percent: f64) {
+        if !percent.is_finite() {
+            return;
+        }
+        let now = Instant::now();
+        let mut samples = match self.samples.get_mut(symbol) {
+            Some(samples) => samples,
+            None => self
+                .samples
+                .entry(symbol.to_string())
+                .or_insert_with(|| VecDeque::with_capacity(SAMPLES_PER_SYMBOL)),
+        };
+        while samples.len() >= SAMPLES_PER_SYMBOL
+            || samples.front().is_some_and(|(at, _)| now.duration_since(*at) > STATS_WINDOW)
+        {
+            samples.pop_front();
+        }
+        samples.push_back((now, percent));
+    }
+
+    /// Сводка по символу или, если `symbol` = None, по всем символам. None – спредов за окно нет.
+    pub fn summary(&self, symbol: Option<&str>) -> Option<SpreadSummary> {
+        let fresh = |samples: &VecDeque<(Instant, f64)>| -> Vec<f64> {
+            samples
+                .iter()
+                .filter(|(at, _)| at.elapsed() <= STATS_WINDOW)
+                .map(|(_, percent)| *percent)
+                .collect()
+        };
+        let mut values = match symbol {
+            Some(symbol) => fresh(&*self.samples.get(symbol)?),
+            None => self.samples.iter().flat_map(|entry| fresh(entry.value())).collect(),
+        };
+        summarize(&mut values)
+    }
+}
+
+fn summarize(values: &mut [f64]) -> Option<SpreadSummary> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f64::total_cmp);
+    Some(SpreadSummary {
+        count: values.len(),
+        p50: percentile(values, 0.5),
+        p90: percentile(values, 0.9),
+        p99: percentile(values, 0.99),
+        max: values[values.len() - 1],
+    })
+}
+
+/// Перцентиль отсортированных значений по ближайшему рангу.
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles_use_nearest_rank_and_buffer_is_bounded() {
+        let stats = SpreadStats::default();
+        for i in 1..=100 {
+            stats.record("BTCUSDT", i as f64 / 100.0);
+        }
+        stats.record("ETHUSDT", 5.0);
+
+        let btc = stats.summary(Some("BTCUSDT")).unwrap();
+        assert_eq!((btc.count, btc.p50, btc.p90, btc.p99, btc.max), (100, 0.5, 0.9, 0.99, 1.0));
+        assert_eq!(stats.summary(None).unwrap().count, 101);
+        assert_eq!(stats.summary(None).unwrap().max, 5.0);
+        assert!(stats.summary(Some("SOLUSDT")).is_none());
+
+        for _ in 0..SAMPLES_PER_SYMBOL {
+            stats.record("ETHUSDT", 0.1);
+        }
+        assert_eq!(stats.summary(Some("ETHUSDT")).unwrap().count, SAMPLES_PER_SYMBOL);
+        assert_eq!(stats.summary(Some("ETHUSDT")).unwrap().max, 0.1);
+    }
+}
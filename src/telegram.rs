@@ -1,49 +1,168 @@
-use std::env;
+use std::fmt::Write;
 use std::time::Duration;
-use log::error;
+use futures_util::future::join_all;
+use log::warn;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::alert_digest::PendingAlert;
+use crate::alert_tier::AlertTier;
+use crate::compare_price::SpreadDirection;
+use crate::funding::FundingSpread;
+use crate::config::TelegramConfig;
+
+/// Сколько раз повторяем отправку сообщения после первой неудачной попытки.
+const SEND_RETRIES: u32 = 3;
+const SEND_BACKOFF_BASE: Duration = Duration::from_millis(500);
+/// Таймаут запроса к Bot API: общий HTTP-клиент своего таймаута не задает.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Лимит Bot API на длину текста одного сообщения.
+const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Режим разметки исходящих сообщений (TELEGRAM_PARSE_MODE).
+///
+/// Сообщения собираются в подмножестве HTML (`<b>`, `<code>` и экранированный текст);
+/// в режиме MarkdownV2 они перед отправкой переводятся в его синтаксис.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMode {
+    Html,
+    MarkdownV2,
+}
+
+impl ParseMode {
+    fn from_config(value: Option<&str>) -> Self {
+        match value {
+            Some(value) => match value.trim().to_ascii_lowercase().as_str() {
+                "html" => ParseMode::Html,
+                "markdownv2" => ParseMode::MarkdownV2,
+                _ => {
+                    warn!("Invalid TELEGRAM_PARSE_MODE '{}', using default HTML", value);
+                    ParseMode::Html
+                }
+            },
+            None => ParseMode::Html,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            ParseMode::Html => "HTML",
+            ParseMode::MarkdownV2 => "MarkdownV2",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TelegramError {
+    #[error("http error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("rate limited, retry after {retry_after:?}: {description}")]
+    RateLimited {
+        retry_after: Duration,
+        description: String,
+    },
+    #[error("api error {status}: {description}")]
+    Api {
+        status: StatusCode,
+        description: String,
+    },
+}
+
+/// Тело ошибки Bot API: `{"ok":false,"description":...,"parameters":{"retry_after":N}}`.
+#[derive(Debug, Default, Deserialize)]
+struct ApiErrorResponse {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    parameters: Option<ResponseParameters>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseParameters {
+    #[serde(default)]
+    retry_after: Option<u64>,
+}
+
+/// Входящее обновление Telegram (getUpdates). Нас интересуют только текстовые сообщения.
+#[derive(Debug, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    #[serde(default)]
+    pub message: Option<IncomingMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IncomingMessage {
+    pub chat: Chat,
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Chat {
+    pub id: i64,
+    #[serde(default)]
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UpdatesResponse {
+    ok: bool,
+    #[serde(default)]
+    result: Vec<Update>,
+    #[serde(default)]
+    description: Option<String>,
+}
 
 #[derive(Debug)]
 pub struct TelegramNotifier {
     bot_token: String,
-    chat_id: String,
+    /// Чаты из TELEGRAM_CHAT_ID (через запятую); сообщения уходят во все.
+    chat_ids: Vec<String>,
+    parse_mode: ParseMode,
     client: reqwest::Client,
 }
 
 impl TelegramNotifier {
-    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        let bot_token = env::var("TELEGRAM_BOT_TOKEN")
-            .map_err(|_| "TELEGRAM_BOT_TOKEN not found in environment")?;
-        let chat_id = env::var("TELEGRAM_CHAT_ID")
-            .map_err(|_| "TELEGRAM_CHAT_ID not found in environment")?;
+    pub fn new(config: &TelegramConfig, client: reqwest::Client) -> Result<Self, Box<dyn std::error::Error>> {
+        let bot_token = config
+            .bot_token
+            .clone()
+            .ok_or("TELEGRAM_BOT_TOKEN not found in config or environment")?;
+        let chat_ids: Vec<String> = config
+            .chat_ids
+            .as_ref()
+            .ok_or("TELEGRAM_CHAT_ID not found in config or environment")?
+            .iter()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
 
         // Валидация входных данных
         if bot_token.is_empty() {
             return Err("TELEGRAM_BOT_TOKEN is empty".into());
         }
-        if chat_id.is_empty() {
+        if chat_ids.is_empty() {
             return Err("TELEGRAM_CHAT_ID is empty".into());
         }
-        // Проверка формата chat_id (должен быть числом или начинаться с @)
-        if chat_id.parse::<i64>().is_err() && !chat_id.starts_with('@') {
-            return Err("TELEGRAM_CHAT_ID has invalid format".into());
+        // Проверка формата каждого chat_id (должен быть числом или начинаться с @)
+        for chat_id in &chat_ids {
+            if chat_id.parse::<i64>().is_err() && !chat_id.starts_with('@') {
+                return Err(format!("TELEGRAM_CHAT_ID has invalid format: '{}'", chat_id).into());
+            }
         }
 
-        // Создаем HTTP клиент с таймаутами для защиты от DoS
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(10))
-            .connect_timeout(Duration::from_secs(5))
-            .build()
-            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
-
         Ok(Self {
             bot_token,
-            chat_id,
+            chat_ids,
+            parse_mode: ParseMode::from_config(config.parse_mode.as_deref()),
             client,
         })
     }
 
     /// Экранирует HTML символы для безопасной вставки в HTML
-    fn escape_html(text: &str) -> String {
+    pub fn escape_html(text: &str) -> String {
         text.chars()
             .flat_map(|c| match c {
                 '<' => "&lt;".chars().collect::<Vec<_>>(),
@@ -56,40 +175,204 @@ impl TelegramNotifier {
             .collect()
     }
 
-    pub async fn send_message(&self, message: &str) {
+    /// Экранирует спецсимволы MarkdownV2 для вставки вне блоков кода.
+    pub fn escape_markdown_v2(text: &str) -> String {
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if matches!(
+                c,
+                '_' | '*' | '[' | ']' | '(' | ')' | '~' | '`' | '>' | '#' | '+' | '-' | '='
+                    | '|' | '{' | '}' | '.' | '!' | '\\'
+            ) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+
+    /// Отправляет сообщение во все настроенные чаты параллельно. Текст задается в HTML и
+    /// при необходимости переводится в MarkdownV2; длинный текст уходит несколькими
+    /// сообщениями по порядку.
+    ///
+    /// Ошибка по отдельному чату логируется; Err возвращается, только если не удалось
+    /// отправить ни в один чат.
+    pub async fn send_message(&self, message: &str) -> Result<(), TelegramError> {
+        let chunks = self.format_chunks(message);
+        if let [chat_id] = self.chat_ids.as_slice() {
+            return self.send_chunks(chat_id, &chunks).await;
+        }
+
+        let results = join_all(
+            self.chat_ids
+                .iter()
+                .map(|chat_id| self.send_chunks(chat_id, &chunks)),
+        )
+        .await;
+
+        let mut last_error = None;
+        let mut delivered = false;
+        for (chat_id, result) in self.chat_ids.iter().zip(results) {
+            match result {
+                Ok(()) => delivered = true,
+                Err(e) => {
+                    warn!("Failed to send Telegram message to chat {}: {}", chat_id, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if !delivered => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Отправляет сообщение в один чат (например, ответ на команду).
+    pub async fn send_message_to(&self, chat_id: &str, message: &str) -> Result<(), TelegramError> {
+        self.send_chunks(chat_id, &self.format_chunks(message)).await
+    }
+
+    /// Делит сообщение на части по лимиту длины и переводит каждую в режим разметки.
+    /// Делится исходный HTML: в каждой части теги уже сбалансированы, так что и MarkdownV2
+    /// получается без разорванных сущностей.
+    fn format_chunks(&self, message: &str) -> Vec<String> {
+        let chunks = split_message(message, MAX_MESSAGE_LEN);
+        match self.parse_mode {
+            ParseMode::Html => chunks,
+            ParseMode::MarkdownV2 => chunks.iter().map(|chunk| html_to_markdown_v2(chunk)).collect(),
+        }
+    }
+
+    async fn send_chunks(&self, chat_id: &str, chunks: &[String]) -> Result<(), TelegramError> {
+        for chunk in chunks {
+            self.send_chunk(chat_id, chunk).await?;
+        }
+        Ok(())
+    }
+
+    /// Отправляет одно сообщение, укладывающееся в лимит длины.
+    ///
+    /// При 429 ждет `retry_after` из ответа, при 5xx и сетевых ошибках – с удвоением задержки;
+    /// всего до `SEND_RETRIES` повторов. Остальные ошибки возвращаются сразу.
+    async fn send_chunk(&self, chat_id: &str, text: &str) -> Result<(), TelegramError> {
         let url = format!(
             "https://api.telegram.org/bot{}/sendMessage",
             self.bot_token
         );
 
         let payload = serde_json::json!({
-            "chat_id": self.chat_id,
-            "text": message,
-            "parse_mode": "HTML"
+            "chat_id": chat_id,
+            "text": text,
+            "parse_mode": self.parse_mode.as_str()
         });
 
-        match self.client.post(&url).json(&payload).send().await {
-            Ok(response) => {
-                if !response.status().is_success() {
-                    if let Ok(text) = response.text().await {
-                        error!("Telegram API error: {}", text);
+        let mut backoff_delay = SEND_BACKOFF_BASE;
+        let mut retries = 0;
+        loop {
+            let error = match self.client.post(&url).timeout(REQUEST_TIMEOUT).json(&payload).send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let body: ApiErrorResponse = response.json().await.unwrap_or_default();
+                    let description = body.description.unwrap_or_default();
+                    if status == StatusCode::TOO_MANY_REQUESTS {
+                        TelegramError::RateLimited {
+                            retry_after: Duration::from_secs(
+                                body.parameters.and_then(|p| p.retry_after).unwrap_or(1),
+                            ),
+                            description,
+                        }
+                    } else {
+                        TelegramError::Api { status, description }
                     }
                 }
+                Err(e) => TelegramError::Http(e),
+            };
+
+            let delay = match &error {
+                TelegramError::RateLimited { retry_after, .. } => *retry_after,
+                TelegramError::Api { status, .. } if status.is_server_error() => backoff_delay,
+                TelegramError::Http(_) => backoff_delay,
+                TelegramError::Api { .. } => return Err(error),
+            };
+            if retries >= SEND_RETRIES {
+                return Err(error);
             }
-            Err(e) => {
-                error!("Failed to send Telegram message: {}", e);
-            }
+            retries += 1;
+            warn!(
+                "Telegram send failed: {}. Retrying in {:?} ({}/{})",
+                error, delay, retries, SEND_RETRIES
+            );
+            tokio::time::sleep(delay).await;
+            backoff_delay *= 2;
         }
     }
 
+    /// Long polling `getUpdates`: ждет новые обновления до `timeout` и возвращает их.
+    /// `offset` – следующий необработанный update_id.
+    pub async fn get_updates(
+        &self,
+        offset: i64,
+        timeout: Duration,
+    ) -> Result<Vec<Update>, Box<dyn std::error::Error + Send + Sync>> {
+        let url = format!(
+            "https://api.telegram.org/bot{}/getUpdates",
+            self.bot_token
+        );
+
+        let payload = serde_json::json!({
+            "offset": offset,
+            "timeout": timeout.as_secs(),
+            "allowed_updates": ["message"]
+        });
+
+        // Обычный таймаут запроса меньше long polling, поэтому задаем свой
+        let response: UpdatesResponse = self
+            .client
+            .post(&url)
+            .timeout(timeout + REQUEST_TIMEOUT)
+            .json(&payload)
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        if !response.ok {
+            return Err(format!(
+                "getUpdates failed: {}",
+                response.description.unwrap_or_default()
+            )
+            .into());
+        }
+        Ok(response.result)
+    }
+
+    /// Пришло ли сообщение из одного из чатов TELEGRAM_CHAT_ID (по id или @username).
+    pub fn is_configured_chat(&self, chat: &Chat) -> bool {
+        self.chat_ids.iter().any(|chat_id| match chat_id.parse::<i64>() {
+            Ok(id) => chat.id == id,
+            Err(_) => chat
+                .username
+                .as_deref()
+                .map(|username| chat_id.trim_start_matches('@').eq_ignore_ascii_case(username))
+                .unwrap_or(false),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn send_arbitrage_opportunity(
         &self,
         symbol: &str,
-        bybit_price: f64,
+        reference_name: &str,
+        reference_price: f64,
         dex_price: f64,
         dex_name: &str,
         difference: f64,
-    ) {
+        net_difference: f64,
+        direction: SpreadDirection,
+        tier: AlertTier,
+        price_decimals: usize,
+    ) -> Result<(), TelegramError> {
         // Валидация и экранирование символа для защиты от HTML injection
         let safe_symbol = if symbol.len() > 50 {
             // Ограничиваем длину символа
@@ -98,17 +381,308 @@ impl TelegramNotifier {
             symbol
         };
         let escaped_symbol = Self::escape_html(safe_symbol);
+        let escaped_reference_name = Self::escape_html(reference_name);
         let escaped_dex_name = Self::escape_html(dex_name);
+        let (buy_name, sell_name) = direction.buy_sell(reference_name, dex_name);
+
+        // Уровень виден по эмодзи и префиксу заголовка; INFO – прежний вид
+        let title = match tier {
+            AlertTier::Info => String::new(),
+            tier => format!("{} ", tier.label()),
+        };
+        let message = format!(
+            "{} <b>{}Арбитражная возможность!</b>\n\n\
+            Символ: <code>{}</code>\n\
+            {} цена: <code>{:.*}</code>\n\
+            {} цена: <code>{:.*}</code>\n\
+            Разница: <code>{:.5}%</code>\n\
+            После комиссий: <code>{:.5}%</code>\n\
+            Купить на <b>{}</b> / продать на <b>{}</b>",
+            tier.emoji(),
+            title,
+            escaped_symbol,
+            escaped_reference_name,
+            price_decimals,
+            reference_price,
+            escaped_dex_name,
+            price_decimals,
+            dex_price,
+            difference,
+            net_difference,
+            Self::escape_html(buy_name),
+            Self::escape_html(sell_name)
+        );
+
+        self.send_message(&message).await
+    }
 
+    /// Сигнал по разнице фандинга – отдельный от ценового спреда вид возможности.
+    pub async fn send_funding_opportunity(&self, symbol: &str, spread: &FundingSpread) -> Result<(), TelegramError> {
+        let symbol: String = symbol.chars().take(50).collect();
         let message = format!(
-            "🔔 <b>Арбитражная возможность!</b>\n\n\
+            "💸 <b>Фандинговая возможность</b>\n\n\
             Символ: <code>{}</code>\n\
-            Bybit цена: <code>{:.8}</code>\n\
-            {} цена: <code>{:.8}</code>\n\
-            Разница: <code>{:.5}%</code>",
-            escaped_symbol, bybit_price, escaped_dex_name, dex_price, difference
+            Шорт {}: фандинг <code>{:.4}%</code>\n\
+            Лонг {}: фандинг <code>{:.4}%</code>\n\
+            Чистый фандинг за 8ч: <code>{:.4}%</code>",
+            Self::escape_html(&symbol),
+            Self::escape_html(&spread.short_exchange),
+            spread.short_rate * 100.0,
+            Self::escape_html(&spread.long_exchange),
+            spread.long_rate * 100.0,
+            spread.diff_percent
+        );
+
+        self.send_message(&message).await
+    }
+
+    /// Отправляет сводку сигналов за `interval` одним сообщением (порядок сохраняется).
+    pub async fn send_arbitrage_digest(
+        &self,
+        alerts: &[PendingAlert],
+        interval: Duration,
+    ) -> Result<(), TelegramError> {
+        let mut message = format!(
+            "🔔 <b>Арбитражные возможности за {}с: {}</b>\n",
+            interval.as_secs(),
+            alerts.len()
+        );
+        for alert in alerts {
+            let symbol: String = alert.symbol.chars().take(50).collect();
+            let _ = write!(
+                message,
+                "\n{} <code>{}</code> {} <code>{:.*}</code> / {} <code>{:.*}</code>: \
+                <code>{:.5}%</code>, после комиссий <code>{:.5}%</code>",
+                alert.tier.emoji(),
+                Self::escape_html(&symbol),
+                Self::escape_html(&alert.reference_name),
+                alert.price_decimals,
+                alert.reference_price,
+                Self::escape_html(&alert.other_name),
+                alert.price_decimals,
+                alert.other_price,
+                alert.difference,
+                alert.net_difference
+            );
+            if alert.hits > 1 {
+                let _ = write!(message, " ×{}", alert.hits);
+            }
+        }
+
+        self.send_message(&message).await
+    }
+}
+
+/// Переводит подмножество HTML, в котором собираются сообщения (`<b>`, `<code>`,
+/// HTML-сущности), в MarkdownV2. Прочие теги передаются как текст.
+fn html_to_markdown_v2(html: &str) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut in_code = false;
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if rest.starts_with('<') {
+            if let Some(end) = rest.find('>') {
+                let marker = match &rest[1..end] {
+                    "b" | "/b" => Some("*"),
+                    "code" | "/code" => {
+                        in_code = !in_code;
+                        Some("`")
+                    }
+                    _ => None,
+                };
+                if let Some(marker) = marker {
+                    out.push_str(marker);
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        // Текст до следующего тега (первый символ берем всегда, даже если это '<')
+        let first = rest.chars().next().map_or(0, char::len_utf8);
+        let next_tag = rest[first..].find('<').map(|i| i + first).unwrap_or(rest.len());
+        let text = unescape_html(&rest[..next_tag]);
+        if in_code {
+            // Внутри кода MarkdownV2 требует экранировать только ` и \
+            for c in text.chars() {
+                if c == '`' || c == '\\' {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+        } else {
+            out.push_str(&TelegramNotifier::escape_markdown_v2(&text));
+        }
+        rest = &rest[next_tag..];
+    }
+    out
+}
+
+fn unescape_html(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Теги разметки, которые понимает `split_message`.
+const TAGS: &[&str] = &["b", "code"];
+
+/// Следующая неделимая часть HTML-сообщения: тег `<b>`/`</b>`/`<code>`/`</code>`,
+/// HTML-сущность `&...;` или один символ.
+fn next_token(html: &str) -> Option<&str> {
+    let c = html.chars().next()?;
+    let end = match c {
+        '<' => TAGS.iter().find_map(|tag| {
+            [format!("<{}>", tag), format!("</{}>", tag)]
+                .into_iter()
+                .find(|t| html.starts_with(t.as_str()))
+                .map(|t| t.len())
+        }),
+        '&' => html.find(';').filter(|&i| i <= 8).map(|i| i + 1),
+        _ => None,
+    };
+    Some(&html[..end.unwrap_or(c.len_utf8())])
+}
+
+/// Учитывает тег `token` в стеке открытых тегов.
+fn apply_tag(open: &mut Vec<&'static str>, token: &str) {
+    let Some(inner) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) else {
+        return;
+    };
+    match inner.strip_prefix('/') {
+        Some(name) => {
+            if let Some(i) = open.iter().rposition(|tag| *tag == name) {
+                open.remove(i);
+            }
+        }
+        None => {
+            if let Some(tag) = TAGS.iter().find(|tag| **tag == inner) {
+                open.push(tag);
+            }
+        }
+    }
+}
+
+fn closing_tags(open: &[&str]) -> String {
+    open.iter().rev().map(|tag| format!("</{}>", tag)).collect()
+}
+
+fn opening_tags(open: &[&str]) -> String {
+    open.iter().map(|tag| format!("<{}>", tag)).collect()
+}
+
+/// Делит сообщение в HTML-разметке на части не длиннее `limit` символов, по возможности
+/// по переводам строк. Теги и HTML-сущности не разрываются: открытые на месте разреза
+/// `<b>`/`<code>` закрываются в конце части и открываются заново в начале следующей –
+/// иначе Telegram отклоняет часть с разорванной разметкой.
+fn split_message(html: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    let mut chunk_chars = 0usize;
+    let mut open: Vec<&'static str> = Vec::new();
+    // Заново открытые теги в начале части: резать внутри них нельзя
+    let mut prefix_len = 0usize;
+    // Позиция после последнего перевода строки в части и открытые на ней теги
+    let mut last_newline: Option<(usize, Vec<&'static str>)> = None;
+    let mut rest = html;
+
+    while let Some(token) = next_token(rest) {
+        rest = &rest[token.len()..];
+        let mut open_after = open.clone();
+        apply_tag(&mut open_after, token);
+        let needed = chunk_chars + token.chars().count() + closing_tags(&open_after).chars().count();
+        if needed > limit && chunk.len() > prefix_len {
+            let (cut, cut_open) = last_newline.take().unwrap_or_else(|| (chunk.len(), open.clone()));
+            let carry = chunk.split_off(cut);
+            chunk.push_str(&closing_tags(&cut_open));
+            chunks.push(std::mem::take(&mut chunk));
+            chunk = opening_tags(&cut_open);
+            prefix_len = chunk.len();
+            chunk.push_str(&carry);
+            chunk_chars = chunk.chars().count();
+        }
+        chunk.push_str(token);
+        chunk_chars += token.chars().count();
+        open = open_after;
+        if token == "\n" {
+            last_newline = Some((chunk.len(), open.clone()));
+        }
+    }
+    if chunk.len() > prefix_len {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_html_escapes_all_special_characters() {
+        assert_eq!(
+            TelegramNotifier::escape_html("<b>&\"'</b> ok"),
+            "&lt;b&gt;&amp;&quot;&#x27;&lt;/b&gt; ok"
         );
+    }
+
+    #[test]
+    fn escape_markdown_v2_escapes_all_special_characters() {
+        let special = "_*[]()~`>#+-=|{}.!\\";
+        let expected: String = special.chars().flat_map(|c| ['\\', c]).collect();
+        assert_eq!(TelegramNotifier::escape_markdown_v2(special), expected);
+        assert_eq!(TelegramNotifier::escape_markdown_v2("BTCUSDT 1,5"), "BTCUSDT 1,5");
+    }
+
+    #[test]
+    fn html_is_converted_to_markdown_v2() {
+        assert_eq!(
+            html_to_markdown_v2("<b>Diff</b>: <code>1.5%</code> (a&amp;b)"),
+            "*Diff*: `1.5%` \\(a&b\\)"
+        );
+        assert_eq!(html_to_markdown_v2("<code>a`b\\</code>"), "`a\\`b\\\\`");
+        assert_eq!(html_to_markdown_v2("1 < 2"), "1 < 2");
+    }
+
+    #[test]
+    fn multibyte_text_is_converted_to_markdown_v2() {
+        assert_eq!(
+            html_to_markdown_v2("🚨 <b>URGENT Арбитражная возможность!</b>\n\nСимвол: <code>BTCUSDT</code>"),
+            "🚨 *URGENT Арбитражная возможность\\!*\n\nСимвол: `BTCUSDT`"
+        );
+        assert_eq!(html_to_markdown_v2("я<b>ё</b>"), "я*ё*");
+    }
+
+    #[test]
+    fn split_keeps_tags_balanced() {
+        let text = format!("<b>{}</b> <code>{}</code>", "a".repeat(30), "1".repeat(30));
+        let chunks = split_message(&text, 25);
+        assert!(chunks.len() > 2);
+        for chunk in &chunks {
+            assert!(chunk.chars().count() <= 25, "{}", chunk);
+            assert_eq!(chunk.matches("<b>").count(), chunk.matches("</b>").count(), "{}", chunk);
+            assert_eq!(chunk.matches("<code>").count(), chunk.matches("</code>").count(), "{}", chunk);
+        }
+        assert!(chunks[1].starts_with("<b>"));
+
+        // Сущность не разрывается
+        let chunks = split_message(&format!("{}&amp;", "a".repeat(8)), 10);
+        assert_eq!(chunks, vec!["a".repeat(8), "&amp;".to_string()]);
+    }
+
+    #[test]
+    fn long_messages_are_split_on_newlines() {
+        let text = format!("{}\n{}", "a".repeat(3000), "b".repeat(3000));
+        let chunks = split_message(&text, MAX_MESSAGE_LEN);
+        assert_eq!(chunks, vec![format!("{}\n", "a".repeat(3000)), "b".repeat(3000)]);
 
-        self.send_message(&message).await;
+        let long_line = "я".repeat(5000);
+        let chunks = split_message(&long_line, MAX_MESSAGE_LEN);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.chars().count() <= MAX_MESSAGE_LEN));
+        assert_eq!(chunks.concat(), long_line);
     }
 }
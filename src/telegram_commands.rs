@@ -0,0 +1,225 @@
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::share_state::SharedState;
+use crate::telegram::TelegramNotifier;
+use crate::utils::{sleep_or_shutdown, wait_for_shutdown, Backoff, Shutdown};
+
+/// Сколько секунд Telegram держит запрос getUpdates, если новых сообщений нет.
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(25);
+
+const HELP: &str = "<b>Commands</b>\n\
+    /status – feed health, trading state and open positions\n\
+    /prices SYMBOL – latest prices, e.g. <code>/prices BTCUSDT</code>\n\
+    /stats [SYMBOL] – spread distribution over the last hour\n\
+    /pause – pause BingX auto-trading\n\
+    /resume – resume BingX auto-trading";
+
+/// Принимает команды из Telegram через long polling и отвечает в тот же чат.
+///
+/// Обрабатываются только сообщения из настроенных TELEGRAM_CHAT_ID, остальные
+/// игнорируются. Без Telegram сразу возвращается.
+pub async fn run_command_loop(shared_state: &Arc<SharedState>, mut shutdown: Shutdown) {
+    let Some(telegram) = &shared_state.telegram else {
+        return;
+    };
+
+    let mut offset = 0i64;
+    let mut backoff = Backoff::default();
+    info!("Listening for Telegram commands");
+
+    loop {
+        let updates = tokio::select! {
+            _ = wait_for_shutdown(&mut shutdown) => {
+                info!("Telegram command loop stopped");
+                return;
+            }
+            updates = telegram.get_updates(offset, LONG_POLL_TIMEOUT) => updates,
+        };
+
+        let updates = match updates {
+            Ok(updates) => {
+                backoff.reset();
+                updates
+            }
+            Err(e) => {
+                let delay = backoff.next_delay();
+                warn!("Failed to get Telegram updates: {}. Retrying in {:?}...", e, delay);
+                if sleep_or_shutdown(delay, &mut shutdown).await {
+                    info!("Telegram command loop stopped");
+                    return;
+                }
+                continue;
+            }
+        };
+
+        for update in updates {
+            // Следующий запрос подтверждает все обновления до этого, чтобы не обработать их повторно
+            offset = offset.max(update.update_id + 1);
+
+            let Some(message) = update.message else {
+                continue;
+            };
+            if !telegram.is_configured_chat(&message.chat) {
+                warn!("Ignoring Telegram message from unknown chat {}", message.chat.id);
+                continue;
+            }
+            let Some(text) = message.text else {
+                continue;
+            };
+
+            if let Some(reply) = handle_command(shared_state, &text).await {
+                // Отвечаем в тот чат, откуда пришла команда, а не во все настроенные
+                let chat_id = message.chat.id.to_string();
+                if let Err(e) = telegram.send_message_to(&chat_id, &reply).await {
+                    warn!("Failed to reply to Telegram command: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Разбирает команду и возвращает ответ. Не-команды игнорируются.
+async fn handle_command(shared_state: &SharedState, text: &str) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    let command = parts.next()?;
+    if !command.starts_with('/') {
+        return None;
+    }
+    // В группах команда приходит как /status@BotName
+    let command = command.split('@').next().unwrap_or(command).to_ascii_lowercase();
+    info!("Telegram command: {}", text);
+
+    let reply = match command.as_str() {
+        "/status" => status(shared_state).await,
+        "/prices" => match parts.next() {
+            Some(symbol) => prices(shared_state, &symbol.to_ascii_uppercase()),
+            None => "Usage: <code>/prices BTCUSDT</code>".to_string(),
+        },
+        "/stats" => stats(shared_state, parts.next().map(|s| s.to_ascii_uppercase()).as_deref()),
+        "/pause" => {
+            shared_state.set_trading_paused(true);
+            warn!("BingX auto-trading paused from Telegram");
+            "⏸ BingX auto-trading paused".to_string()
+        }
+        "/resume" => {
+            shared_state.set_trading_paused(false);
+            warn!("BingX auto-trading resumed from Telegram");
+            "▶️ BingX auto-trading resumed".to_string()
+        }
+        _ => HELP.to_string(),
+    };
+    Some(reply)
+}
+
+async fn status(shared_state: &SharedState) -> String {
+    let mut reply = String::from("📊 <b>Status</b>\n\n");
+
+    for feed in shared_state.feed_status() {
+        let age = match feed.last_update {
+            Some(t) => format!("{:.1}s ago", t.elapsed().as_secs_f64()),
+            None => "no prices".to_string(),
+        };
+        let _ = writeln!(
+            reply,
+            "{}: <b>{}</b>, {} symbols, {} ({} msgs, {} parse errors, {} reconnects)",
+            feed.exchange,
+            feed.status.label(),
+            feed.symbols,
+            age,
+            feed.messages,
+            feed.parse_errors,
+            feed.reconnects
+        );
+    }
+
+    let trading = match (
+        shared_state.bingx.is_some(),
+        shared_state.bingx_trading_enabled,
+        shared_state.is_trading_paused(),
+    ) {
+        (false, _, _) => "not configured",
+        (true, false, _) => "disabled",
+        (true, true, true) => "paused",
+        (true, true, false) if shared_state.bingx.as_ref().is_some_and(|b| b.is_trading_halted()) => "halted (daily loss limit)",
+        (true, true, false) if shared_state.bingx.as_ref().is_some_and(|b| b.is_dry_run()) => "active (dry run)",
+        (true, true, false) => "active",
+    };
+    let _ = write!(reply, "\nBingX trading: <b>{}</b>", trading);
+    if let Some(loss_limit) = &shared_state.daily_loss_limit {
+        let _ = write!(
+            reply,
+            "\nRealized today: {:.2} / -{} USDT",
+            loss_limit.realized_today(),
+            loss_limit.limit_usdt()
+        );
+    }
+
+    if let Some(bingx) = &shared_state.bingx {
+        match bingx.get_open_positions().await {
+            Ok(positions) if positions.is_empty() => reply.push_str("\nOpen positions: none"),
+            Ok(positions) => {
+                reply.push_str("\nOpen positions:");
+                for p in positions {
+                    let _ = write!(
+                        reply,
+                        "\n{} {} <code>{}</code> @ <code>{:.8}</code>, uPnL <code>{:.2}</code> USDT",
+                        TelegramNotifier::escape_html(&p.symbol),
+                        p.side,
+                        p.size,
+                        p.entry_price,
+                        p.unrealized_pnl
+                    );
+                }
+            }
+            Err(e) => {
+                warn!("Failed to fetch BingX positions for /status: {}", e);
+                reply.push_str("\nOpen positions: unavailable");
+            }
+        }
+    }
+    reply
+}
+
+fn stats(shared_state: &SharedState, symbol: Option<&str>) -> String {
+    let title = symbol.map_or("all symbols".to_string(), TelegramNotifier::escape_html);
+    let mut reply = format!("📈 <b>Spreads, last hour ({})</b>\n\n", title);
+    match shared_state.spread_stats.summary(symbol) {
+        Some(s) => {
+            let _ = write!(
+                reply,
+                "Samples: {}\np50: <code>{:.4}%</code>\np90: <code>{:.4}%</code>\np99: <code>{:.4}%</code>\nmax: <code>{:.4}%</code>",
+                s.count, s.p50, s.p90, s.p99, s.max
+            );
+        }
+        None => reply.push_str("No spreads observed yet"),
+    }
+    reply
+}
+
+fn prices(shared_state: &SharedState, symbol: &str) -> String {
+    let mut reply = format!(
+        "💱 <b>{}</b>\n\n",
+        TelegramNotifier::escape_html(symbol)
+    );
+    for (exchange, quote) in shared_state.prices_for(symbol) {
+        match quote {
+            Some(q) => {
+                let _ = writeln!(
+                    reply,
+                    "{}: <code>{:.8}</code> ({:.1}s ago)",
+                    exchange,
+                    q.price,
+                    q.updated_at.elapsed().as_secs_f64()
+                );
+            }
+            None => {
+                let _ = writeln!(reply, "{}: —", exchange);
+            }
+        }
+    }
+    reply
+}
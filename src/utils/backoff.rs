@@ -0,0 +1,53 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+pub const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+pub const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Экспоненциальная задержка переподключения с джиттером.
+///
+/// Задержка удваивается с каждой неудачной попыткой до `max`, затем случайно выбирается
+/// в диапазоне [delay/2, delay], чтобы фиды не переподключались одновременно.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    base: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(RECONNECT_BASE_DELAY, RECONNECT_MAX_DELAY)
+    }
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self { base, max, attempt: 0 }
+    }
+
+    /// Возвращает задержку для следующей попытки и увеличивает счетчик.
+    pub fn next_delay(&mut self) -> Duration {
+        let factor = 2u32.saturating_pow(self.attempt);
+        let delay = self.base.saturating_mul(factor).min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+
+        let half = delay / 2;
+        let jitter_range_ms = (delay - half).as_millis() as u64;
+        if jitter_range_ms == 0 {
+            return delay;
+        }
+        half + Duration::from_millis(random_u64() % (jitter_range_ms + 1))
+    }
+
+    /// Сбрасывает задержку к базовой после успешного подключения.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+/// Случайное число без внешних зависимостей: RandomState заново инициализируется случайными ключами.
+fn random_u64() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
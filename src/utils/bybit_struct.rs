@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct LeverageFilter {
     min_leverage: String,
@@ -12,7 +12,9 @@ pub struct LeverageFilter {
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PriceFilter {
+    #[serde(default)]
     min_price: String,
+    #[serde(default)]
     max_price: String,
     tick_size: String,
 }
@@ -22,48 +24,104 @@ pub struct PriceFilter {
 pub struct LotSizeFilter {
     max_order_qty: String,
     min_order_qty: String,
+    /// У спота шага количества нет – вместо него `basePrecision`.
+    #[serde(default)]
     qty_step: String,
+    #[serde(default)]
     post_only_max_order_qty: String,
+    /// Минимальная стоимость ордера в USDT; есть только у linear-контрактов.
+    #[serde(default)]
+    min_notional_value: Option<String>,
+    /// Точность количества спотовой пары.
+    #[serde(default)]
+    base_precision: Option<String>,
+    /// Минимальная стоимость ордера спотовой пары в котируемой валюте.
+    #[serde(default)]
+    min_order_amt: Option<String>,
 }
 
+/// Инструмент из `/v5/market/instruments-info`. Поля только контрактов помечены `default`:
+/// в ответе по споту их нет.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BybitList {
     pub symbol: String,
-    #[serde(rename = "contractType")]
+    #[serde(rename = "contractType", default)]
     contract_type: String,
     status: String,
     #[serde(rename = "baseCoin")]
     base_coin: String,
     #[serde(rename = "quoteCoin")]
     quote_coin: String,
-    #[serde(rename = "launchTime")]
+    #[serde(rename = "launchTime", default)]
     launch_time: String,
-    #[serde(rename = "deliveryTime")]
+    #[serde(rename = "deliveryTime", default)]
     delivery_time: String,
-    #[serde(rename = "deliveryFeeRate")]
+    #[serde(rename = "deliveryFeeRate", default)]
     delivery_fee_rate: String,
-    #[serde(rename = "priceScale")]
+    #[serde(rename = "priceScale", default)]
     price_scale: String,
-    #[serde(rename = "leverageFilter")]
+    #[serde(rename = "leverageFilter", default)]
     leverage_filter: LeverageFilter,
     #[serde(rename = "priceFilter")]
     price_filter: PriceFilter,
     #[serde(rename = "lotSizeFilter")]
     lot_size_filter: LotSizeFilter,
-    #[serde(rename = "unifiedMarginTrade")]
+    #[serde(rename = "unifiedMarginTrade", default)]
     unified_margin_trade: bool,
-    #[serde(rename = "fundingInterval")]
+    #[serde(rename = "fundingInterval", default)]
     funding_interval: i64,
-    #[serde(rename = "settleCoin")]
+    #[serde(rename = "settleCoin", default)]
     settle_coin: String,
-    #[serde(rename = "copyTrading")]
+    #[serde(rename = "copyTrading", default)]
     copy_trading: String,
-    #[serde(rename = "upperFundingRate")]
+    #[serde(rename = "upperFundingRate", default)]
     upper_funding_rate: String,
-    #[serde(rename = "lowerFundingRate")]
+    #[serde(rename = "lowerFundingRate", default)]
     lower_funding_rate: String,
 }
 
+impl BybitList {
+    /// `Trading` – инструмент торгуется.
+    pub fn is_trading(&self) -> bool {
+        self.status == "Trading"
+    }
+
+    /// `LinearPerpetual`, `InversePerpetual`, `InverseFutures` и т.д.; у спота пусто.
+    pub fn contract_type(&self) -> &str {
+        &self.contract_type
+    }
+
+    /// Шаг цены контракта (`priceFilter.tickSize`) в котировке Bybit.
+    pub fn tick_size(&self) -> Option<f64> {
+        self.price_filter.tick_size.parse().ok().filter(|tick: &f64| *tick > 0.0)
+    }
+
+    /// Шаг количества (`lotSizeFilter.qtyStep`, у спота `basePrecision`) в контрактах Bybit.
+    pub fn qty_step(&self) -> Option<f64> {
+        let step = match self.lot_size_filter.base_precision.as_deref() {
+            Some(precision) if self.lot_size_filter.qty_step.is_empty() => precision,
+            _ => &self.lot_size_filter.qty_step,
+        };
+        step.parse().ok().filter(|step: &f64| *step > 0.0)
+    }
+
+    /// Минимальное количество (`lotSizeFilter.minOrderQty`) в контрактах Bybit.
+    pub fn min_qty(&self) -> Option<f64> {
+        self.lot_size_filter.min_order_qty.parse().ok().filter(|qty: &f64| *qty >= 0.0)
+    }
+
+    /// Минимальная стоимость ордера (`lotSizeFilter.minNotionalValue`, у спота `minOrderAmt`) в USDT.
+    pub fn min_notional(&self) -> Option<f64> {
+        self.lot_size_filter
+            .min_notional_value
+            .as_deref()
+            .or(self.lot_size_filter.min_order_amt.as_deref())?
+            .parse()
+            .ok()
+            .filter(|notional: &f64| *notional > 0.0)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BybitApiResult {
     category: String,
@@ -84,9 +142,11 @@ pub struct BybitApiResponse {
     time: u64,
 }
 
+/// Свеча `kline.*`. `confirm` – свеча закрыта и больше не изменится; пока она открыта,
+/// `close` – цена последней сделки.
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BybitWsData {
-    start: u64,
+    pub start: u64,
     end: u64,
     interval: String,
     open: String,
@@ -95,8 +155,72 @@ pub struct BybitWsData {
     low: String,
     volume: String,
     turnover: String,
-    confirm: bool,
-    timestamp: u64,
+    pub confirm: bool,
+    /// Время последней сделки в свече, мс Unix (серверное время Bybit).
+    pub timestamp: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BybitTickerData {
+    pub symbol: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_price: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mark_price: Option<String>,
+}
+
+/// Данные `orderbook.1.*`: уровни `[цена, объем]`, объем "0" – уровень удален.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BybitOrderbookData {
+    pub s: String,
+    #[serde(default)]
+    pub b: Vec<[String; 2]>,
+    #[serde(default)]
+    pub a: Vec<[String; 2]>,
+}
+
+/// Поле `data`: массив свечей для `kline.*`, объект для `tickers.*` или `orderbook.*`.
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum BybitWsPayload {
+    Kline(Vec<BybitWsData>),
+    Ticker(BybitTickerData),
+    Orderbook(BybitOrderbookData),
+}
+
+impl BybitWsPayload {
+    /// Свеча, от которой берется цена: самая поздняя по `start`, а при двух записях одной
+    /// свечи – закрытая (`confirm`). Незакрытая свеча годится: ее close – последняя сделка,
+    /// ждать закрытия дневной свечи для сравнения цен нельзя.
+    pub fn latest_kline(&self) -> Option<&BybitWsData> {
+        match self {
+            BybitWsPayload::Kline(klines) => klines.iter().max_by_key(|k| (k.start, k.confirm)),
+            _ => None,
+        }
+    }
+
+    /// Цена из сообщения: close свечи `latest_kline` или lastPrice тикера.
+    pub fn price(&self) -> Option<&str> {
+        match self {
+            BybitWsPayload::Kline(_) => self.latest_kline().map(|k| k.close.as_str()),
+            BybitWsPayload::Ticker(ticker) => ticker.last_price.as_deref(),
+            BybitWsPayload::Orderbook(_) => None,
+        }
+    }
+
+    /// Серверное время цены свечи `latest_kline`, мс Unix. У тикеров своего времени нет –
+    /// см. `BybitWsResponse::timestamp`.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.latest_kline().map(|k| k.timestamp)
+    }
+
+    pub fn orderbook(&self) -> Option<&BybitOrderbookData> {
+        match self {
+            BybitWsPayload::Orderbook(book) => Some(book),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -104,12 +228,37 @@ pub struct BybitWsResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub topic: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<Vec<BybitWsData>>,
+    pub data: Option<BybitWsPayload>,
     ts: Option<u64>,
+    /// `snapshot` – полное состояние (первое сообщение после подписки), `delta` – только
+    /// изменившиеся поля.
     #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     type_field: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    op: Option<String>,
+    pub op: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub success: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ret_msg: Option<String>,
+    /// `req_id` запроса, на который отвечает сообщение (subscribe и т.п.).
     #[serde(skip_serializing_if = "Option::is_none")]
-    success: Option<bool>,
+    pub req_id: Option<String>,
+}
+
+impl BybitWsResponse {
+    /// Снимок (`type: "snapshot"`), а не дельта: в нем есть все поля, в том числе цена.
+    pub fn is_snapshot(&self) -> bool {
+        self.type_field.as_deref() == Some("snapshot")
+    }
+
+    /// Закрыта ли свеча, от которой взята цена; None – сообщение не kline.
+    pub fn kline_confirmed(&self) -> Option<bool> {
+        self.data.as_ref()?.latest_kline().map(|k| k.confirm)
+    }
+
+    /// Серверное время цены, мс Unix: время последней сделки свечи для kline,
+    /// время формирования сообщения (`ts`) для остальных потоков.
+    pub fn timestamp(&self) -> Option<u64> {
+        self.data.as_ref().and_then(BybitWsPayload::timestamp).or(self.ts)
+    }
 }
@@ -1,3 +1,14 @@
+mod backoff;
 mod bybit_struct;
+mod proxy;
+mod shutdown;
+mod symbol;
+mod ws;
 pub use bybit_struct::BybitApiResponse;
+pub use bybit_struct::BybitList;
 pub use bybit_struct::BybitWsResponse;
+pub use backoff::Backoff;
+pub use proxy::{build_http_client, connect_ws, init_proxy};
+pub use symbol::{canonical_symbol, from_canonical, init_quote_currency, quote_currency, to_canonical, CanonicalSymbol};
+pub use shutdown::{sleep_or_shutdown, wait_for_shutdown, Shutdown};
+pub use ws::{run_ws_with_reconnect, PingConfig, ReconnectConfig, WsStream};
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+/// Сигнал остановки бота: значение становится `true` после Ctrl-C.
+pub type Shutdown = watch::Receiver<bool>;
+
+/// Ждет сигнала остановки. Если отправитель уже удален, сигнал не придет – ждем бесконечно.
+pub async fn wait_for_shutdown(shutdown: &mut Shutdown) {
+    if shutdown.wait_for(|stop| *stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Спит `delay`, но просыпается раньше при остановке. Возвращает `true`, если пора завершаться.
+pub async fn sleep_or_shutdown(delay: Duration, shutdown: &mut Shutdown) -> bool {
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => false,
+        _ = wait_for_shutdown(shutdown) => true,
+    }
+}
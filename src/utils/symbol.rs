@@ -0,0 +1,297 @@
+use std::sync::OnceLock;
+
+use log::{info, warn};
+
+use crate::share_state::{ASTER, BINANCE, BINGX, BYBIT, DYDX, GATE, HYPERLIQUID, KUCOIN, MEXC, OKX};
+
+/// Котируемая валюта по умолчанию.
+pub const DEFAULT_QUOTE_CURRENCY: &str = "USDT";
+
+static QUOTE_CURRENCY: OnceLock<String> = OnceLock::new();
+
+/// Задает котируемую валюту (QUOTE_CURRENCY) один раз при старте, до запроса тикеров.
+/// Некорректное значение заменяется на USDT.
+pub fn init_quote_currency(configured: Option<&str>) {
+    let quote = match configured.map(|q| q.trim().to_ascii_uppercase()) {
+        Some(q) if !q.is_empty() && q.chars().all(|c| c.is_ascii_alphanumeric()) => q,
+        Some(q) => {
+            warn!("Invalid QUOTE_CURRENCY '{}', using default {}", q, DEFAULT_QUOTE_CURRENCY);
+            DEFAULT_QUOTE_CURRENCY.to_string()
+        }
+        None => DEFAULT_QUOTE_CURRENCY.to_string(),
+    };
+    if quote != DEFAULT_QUOTE_CURRENCY {
+        info!("Quote currency: {}", quote);
+    }
+    let _ = QUOTE_CURRENCY.set(quote);
+}
+
+/// Котируемая валюта тикеров проекта (`USDT` в `BTCUSDT`).
+pub fn quote_currency() -> &'static str {
+    QUOTE_CURRENCY.get().map_or(DEFAULT_QUOTE_CURRENCY, String::as_str)
+}
+
+/// Тикер в едином формате проекта и множитель, в котором биржа котирует контракт.
+///
+/// Одна и та же монета на разных биржах называется по-разному: `kPEPE` на Hyperliquid,
+/// `1000PEPEUSDT` на Bybit/Binance, `SHIB1000USDT` на Bybit. Каноническое имя – монета без
+/// множителя (`PEPEUSDT`), а цена контракта делится на `multiplier`, чтобы получить цену
+/// одной монеты и сравнивать цены в одном масштабе.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CanonicalSymbol {
+    pub symbol: String,
+    pub multiplier: f64,
+}
+
+impl CanonicalSymbol {
+    /// Цена одной монеты по цене контракта биржи.
+    pub fn unit_price(&self, price: f64) -> f64 {
+        price / self.multiplier
+    }
+
+    /// Объем в монетах по объему в контрактах биржи.
+    pub fn unit_size(&self, size: f64) -> f64 {
+        size * self.multiplier
+    }
+}
+
+/// Минимальный множитель в числовом префиксе/суффиксе: `1000PEPE`, `10000SATS`, `1000000MOG`.
+/// Меньшие числа (`1INCH`) – часть названия монеты.
+const MIN_NUMERIC_MULTIPLIER: u64 = 1000;
+
+/// Приводит тикер биржи к каноническому виду (см. [`CanonicalSymbol`]).
+///
+/// Понимает `k`-префикс Hyperliquid (×1000), числовые префиксы и суффиксы из степени
+/// десяти (`1000PEPE`, `SHIB1000`), префикс `1M` (×1 000 000) и формат `BASE-USDT`.
+/// Тикеры не в котируемой валюте (`quote_currency`) возвращаются как есть с множителем 1.
+pub fn canonical_symbol(raw: &str) -> CanonicalSymbol {
+    let quote = quote_currency();
+    let compact = raw.trim().replace('-', "");
+    let Some(base) = compact.strip_suffix(quote) else {
+        return CanonicalSymbol {
+            symbol: compact,
+            multiplier: 1.0,
+        };
+    };
+
+    let (coin, multiplier) = split_multiplier(base);
+    CanonicalSymbol {
+        symbol: format!("{}{}", coin, quote),
+        multiplier,
+    }
+}
+
+/// Тикер биржи в формате проекта: `BTC-USDT-SWAP` (OKX), `XBTUSDTM` (KuCoin), `BTC_USDT` (Gate,
+/// MEXC), `BTC-USD` (dYdX), `1000PEPE-USDT` (BingX), `kPEPE` (Hyperliquid) -> `BTCUSDT`, `1000PEPEUSDT`.
+///
+/// Множитель биржи сохраняется (`kPEPE` -> `1000PEPEUSDT`), чтобы `from_canonical` восстановил
+/// исходный тикер; к одной монете приводит `canonical_symbol`. None – инструмент не бессрочный
+/// контракт в котируемой валюте (`quote_currency`) или биржа неизвестна.
+pub fn to_canonical(exchange: &str, raw: &str) -> Option<String> {
+    let quote = quote_currency();
+    let raw = raw.trim();
+    if exchange == HYPERLIQUID {
+        let coin = match raw.strip_prefix('k') {
+            Some(rest) if starts_with_upper(rest) => format!("1000{}", rest),
+            _ => raw.to_string(),
+        };
+        return is_coin(&coin).then(|| format!("{}{}", coin, quote));
+    }
+
+    let (base, quote) = match exchange {
+        BYBIT | BINANCE | ASTER => (raw.strip_suffix(quote)?, quote),
+        BINGX => (raw.strip_suffix(quote)?.strip_suffix('-')?, quote),
+        OKX => (raw.strip_suffix("-SWAP")?.strip_suffix(quote)?.strip_suffix('-')?, quote),
+        MEXC => (raw.strip_suffix(quote)?.strip_suffix('_')?, quote),
+        // Фид Gate подключен только к USDT-контрактам
+        GATE => (raw.strip_suffix("_USDT")?, "USDT"),
+        // Биткоин на KuCoin называется XBT
+        KUCOIN => match raw.strip_suffix('M')?.strip_suffix(quote)? {
+            "XBT" => ("BTC", quote),
+            base => (base, quote),
+        },
+        // Рынки dYdX котируются в USD (USDC): спред с USDT-биржами включает и курс USDT/USD
+        DYDX => (raw.strip_suffix("-USD")?, quote),
+        _ => return None,
+    };
+    is_coin(base).then(|| format!("{}{}", base, quote))
+}
+
+/// Обратное к `to_canonical`: тикер проекта (`BTCUSDT`, `1000PEPEUSDT`) в формате биржи.
+///
+/// Множитель берется из тикера: `1000PEPEUSDT` -> `kPEPE`, а `PEPEUSDT` -> `PEPE` на Hyperliquid.
+/// Тикеры не в котируемой валюте, уже в формате BingX (`AXS-USDT`) и тикеры неизвестных бирж
+/// возвращаются как есть.
+pub fn from_canonical(exchange: &str, canonical: &str) -> String {
+    let canonical = canonical.trim();
+    let quote = if exchange == GATE { "USDT" } else { quote_currency() };
+    let Some(base) = canonical.strip_suffix(quote).filter(|base| is_coin(base)) else {
+        return canonical.to_string();
+    };
+    match exchange {
+        BINGX => format!("{}-{}", base, quote),
+        OKX => format!("{}-{}-SWAP", base, quote),
+        MEXC | GATE => format!("{}_{}", base, quote),
+        KUCOIN if base == "BTC" => format!("XBT{}M", quote),
+        KUCOIN => format!("{}{}M", base, quote),
+        DYDX => format!("{}-USD", base),
+        HYPERLIQUID => match base.strip_prefix("1000") {
+            Some(rest) if starts_with_upper(rest) => format!("k{}", rest),
+            _ => base.to_string(),
+        },
+        _ => canonical.to_string(),
+    }
+}
+
+/// Имя монеты в тикере: непустое, только латиница и цифры.
+fn is_coin(base: &str) -> bool {
+    !base.is_empty() && base.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+fn starts_with_upper(s: &str) -> bool {
+    s.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+}
+
+fn split_multiplier(base: &str) -> (&str, f64) {
+    // Hyperliquid: kPEPE, kSHIB
+    if let Some(rest) = base.strip_prefix('k') {
+        if starts_with_upper(rest) {
+            return (rest, 1000.0);
+        }
+    }
+
+    // Binance: 1MBABYDOGE
+    if let Some(rest) = base.strip_prefix("1M") {
+        if starts_with_upper(rest) {
+            return (rest, 1_000_000.0);
+        }
+    }
+
+    // Числовой префикс: 1000PEPE, 10000LADYS, 1000000MOG
+    let digits = base.len() - base.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if let Some(multiplier) = power_of_ten(&base[..digits]) {
+        let rest = &base[digits..];
+        if starts_with_upper(rest) {
+            return (rest, multiplier);
+        }
+    }
+
+    // Числовой суффикс: SHIB1000
+    let rest = base.trim_end_matches(|c: char| c.is_ascii_digit());
+    if let Some(multiplier) = power_of_ten(&base[rest.len()..]) {
+        if rest.chars().last().is_some_and(|c| c.is_ascii_uppercase()) {
+            return (rest, multiplier);
+        }
+    }
+
+    (base, 1.0)
+}
+
+/// `"1000"` -> 1000.0; None для чисел, которые не являются степенью десяти не меньше
+/// `MIN_NUMERIC_MULTIPLIER`.
+fn power_of_ten(digits: &str) -> Option<f64> {
+    let zeros = digits.strip_prefix('1')?;
+    if zeros.is_empty() || !zeros.chars().all(|c| c == '0') {
+        return None;
+    }
+    let value = digits.parse::<u64>().ok()?;
+    (value >= MIN_NUMERIC_MULTIPLIER).then_some(value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(raw: &str) -> (String, f64) {
+        let c = canonical_symbol(raw);
+        (c.symbol, c.multiplier)
+    }
+
+    #[test]
+    fn multiplier_conventions_map_to_one_symbol() {
+        for raw in ["kPEPEUSDT", "1000PEPEUSDT", "PEPEUSDT", "1000PEPE-USDT"] {
+            assert_eq!(canonical(raw).0, "PEPEUSDT", "{}", raw);
+        }
+        assert_eq!(canonical("kPEPEUSDT"), ("PEPEUSDT".to_string(), 1000.0));
+        assert_eq!(canonical("SHIB1000USDT"), ("SHIBUSDT".to_string(), 1000.0));
+        assert_eq!(canonical("10000LADYSUSDT"), ("LADYSUSDT".to_string(), 10000.0));
+        assert_eq!(canonical("1000000MOGUSDT"), ("MOGUSDT".to_string(), 1_000_000.0));
+        assert_eq!(canonical("1MBABYDOGEUSDT"), ("BABYDOGEUSDT".to_string(), 1_000_000.0));
+    }
+
+    #[test]
+    fn numbers_in_coin_names_are_kept() {
+        assert_eq!(canonical("1INCHUSDT"), ("1INCHUSDT".to_string(), 1.0));
+        assert_eq!(canonical("BTCUSDT"), ("BTCUSDT".to_string(), 1.0));
+        assert_eq!(canonical("AI16ZUSDT"), ("AI16ZUSDT".to_string(), 1.0));
+        assert_eq!(canonical("1000USDT"), ("1000USDT".to_string(), 1.0));
+        assert_eq!(canonical("BTC-USDC"), ("BTCUSDC".to_string(), 1.0));
+    }
+
+    #[test]
+    fn exchange_symbols_round_trip() {
+        let cases = [
+            (BYBIT, "BTCUSDT", "BTCUSDT"),
+            (BYBIT, "1000PEPEUSDT", "1000PEPEUSDT"),
+            (BYBIT, "SHIB1000USDT", "SHIB1000USDT"),
+            (BINANCE, "1000PEPEUSDT", "1000PEPEUSDT"),
+            (BINANCE, "1MBABYDOGEUSDT", "1MBABYDOGEUSDT"),
+            (ASTER, "ETHUSDT", "ETHUSDT"),
+            (BINGX, "BTC-USDT", "BTCUSDT"),
+            (BINGX, "1000PEPE-USDT", "1000PEPEUSDT"),
+            (OKX, "BTC-USDT-SWAP", "BTCUSDT"),
+            (OKX, "PEPE-USDT-SWAP", "PEPEUSDT"),
+            (OKX, "1INCH-USDT-SWAP", "1INCHUSDT"),
+            (KUCOIN, "XBTUSDTM", "BTCUSDT"),
+            (KUCOIN, "ETHUSDTM", "ETHUSDT"),
+            (KUCOIN, "1000BONKUSDTM", "1000BONKUSDT"),
+            (GATE, "BTC_USDT", "BTCUSDT"),
+            (GATE, "1000SATS_USDT", "1000SATSUSDT"),
+            (MEXC, "BTC_USDT", "BTCUSDT"),
+            (MEXC, "SHIB_USDT", "SHIBUSDT"),
+            (DYDX, "BTC-USD", "BTCUSDT"),
+            (HYPERLIQUID, "BTC", "BTCUSDT"),
+            (HYPERLIQUID, "kPEPE", "1000PEPEUSDT"),
+        ];
+        for (exchange, raw, project) in cases {
+            assert_eq!(to_canonical(exchange, raw).as_deref(), Some(project), "{} {}", exchange, raw);
+            assert_eq!(from_canonical(exchange, project), raw, "{} {}", exchange, project);
+        }
+
+        // Один и тот же PEPE на всех биржах сходится к одному каноническому тикеру
+        for (exchange, raw) in [(HYPERLIQUID, "kPEPE"), (BYBIT, "1000PEPEUSDT"), (BINGX, "1000PEPE-USDT"), (OKX, "PEPE-USDT-SWAP")] {
+            let canonical = canonical_symbol(&to_canonical(exchange, raw).unwrap());
+            assert_eq!(canonical.symbol, "PEPEUSDT", "{} {}", exchange, raw);
+        }
+        assert_eq!(from_canonical(BINGX, "AXS-USDT"), "AXS-USDT");
+    }
+
+    #[test]
+    fn other_instruments_are_not_mapped() {
+        for (exchange, raw) in [
+            (OKX, "BTC-USDT"),
+            (OKX, "BTC-USD-SWAP"),
+            (OKX, "BTC-USDT-240628"),
+            (KUCOIN, "XBTUSDM"),
+            (GATE, "BTC_USD"),
+            (MEXC, "BTC_USD"),
+            (DYDX, "BTC-USDC"),
+            (BINANCE, "BTCUSDT_240628"),
+            (BYBIT, "BTC-26JUN24"),
+            (BINGX, "USDT"),
+            (HYPERLIQUID, "@107"),
+            (HYPERLIQUID, "PURR/USDC"),
+            ("BitMEX", "XBTUSDT"),
+        ] {
+            assert_eq!(to_canonical(exchange, raw), None, "{} {}", exchange, raw);
+        }
+        assert_eq!(from_canonical("BitMEX", "BTCUSDT"), "BTCUSDT");
+        assert_eq!(from_canonical(OKX, "BTCUSDC"), "BTCUSDC");
+    }
+
+    #[test]
+    fn unit_price_divides_by_multiplier() {
+        let price = canonical_symbol("1000PEPEUSDT").unit_price(0.012);
+        assert!((price - 0.000012).abs() < 1e-15);
+    }
+}
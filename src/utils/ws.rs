@@ -0,0 +1,218 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use log::{error, info, warn};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream};
+
+use super::backoff::{Backoff, RECONNECT_BASE_DELAY, RECONNECT_MAX_DELAY};
+use super::shutdown::{sleep_or_shutdown, wait_for_shutdown, Shutdown};
+use crate::share_state::{FeedStatus, SharedState};
+
+pub type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Параметры цикла переподключений WebSocket-фида.
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// 0 = бесконечные попытки
+    pub max_attempts: u32,
+    /// Начальная задержка переподключения, удваивается до `max_delay`.
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Если за это время не пришло ни одного сообщения, соединение считается потерянным.
+    pub heartbeat_timeout: Duration,
+    /// Прикладной ping для бирж, которые закрывают соединение без сообщений от клиента.
+    pub ping: Option<PingConfig>,
+}
+
+/// Текстовое сообщение, которое отправляется в соединение раз в `interval`.
+#[derive(Debug, Clone)]
+pub struct PingConfig {
+    pub interval: Duration,
+    /// Строит сообщение перед каждой отправкой: некоторым биржам нужны текущее время или новый id.
+    pub message: fn() -> String,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 0,
+            base_delay: RECONNECT_BASE_DELAY,
+            max_delay: RECONNECT_MAX_DELAY,
+            heartbeat_timeout: Duration::from_secs(30),
+            ping: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    fn attempts_exhausted(&self, reconnect_count: u32) -> bool {
+        self.max_attempts > 0 && reconnect_count >= self.max_attempts
+    }
+}
+
+/// Держит WebSocket-соединение открытым: подключается через `connect`, передает каждое
+/// текстовое сообщение в `handle_text`, отвечает на ping (и шлет свой, если задан
+/// `config.ping`) и переподключается при ошибке, закрытии или тишине дольше `heartbeat_timeout`.
+/// Сообщения, переподключения и состояние соединения учитываются в `FeedStats` биржи `name`.
+///
+/// Возвращается, когда исчерпаны `max_attempts` попыток подряд или пришел сигнал остановки.
+/// Начатая обработка сообщения всегда доводится до конца.
+pub async fn run_ws_with_reconnect<C, CFut, E, H, HFut>(
+    name: &str,
+    shared_state: &SharedState,
+    config: &ReconnectConfig,
+    mut shutdown: Shutdown,
+    mut connect: C,
+    mut handle_text: H,
+) where
+    C: FnMut() -> CFut,
+    CFut: Future<Output = Result<WsStream, E>>,
+    E: Display,
+    H: FnMut(String) -> HFut,
+    HFut: Future<Output = ()>,
+{
+    let mut reconnect_count = 0u32;
+    let mut backoff = Backoff::new(config.base_delay, config.max_delay);
+
+    // Внешний цикл для переподключений
+    loop {
+        let mut ws_stream = match connect().await {
+            Ok(stream) => {
+                if reconnect_count == 0 {
+                    info!("{} WebSocket connected successfully", name);
+                } else {
+                    info!("{} WebSocket reconnected (attempt {})", name, reconnect_count + 1);
+                }
+                reconnect_count = 0; // Сбрасываем счетчик при успешном подключении
+                backoff.reset();
+                shared_state.set_feed_status(name, FeedStatus::Live);
+                stream
+            }
+            Err(e) => {
+                error!("Failed to connect to {} WebSocket: {}", name, e);
+                reconnect_count += 1;
+                shared_state.record_reconnect(name);
+                if config.attempts_exhausted(reconnect_count) {
+                    error!("Max reconnection attempts ({}) reached. Exiting.", config.max_attempts);
+                    return;
+                }
+                let delay = backoff.next_delay();
+                warn!("Retrying in {:?}...", delay);
+                if sleep_or_shutdown(delay, &mut shutdown).await {
+                    info!("{} WebSocket stopped", name);
+                    return;
+                }
+                continue;
+            }
+        };
+
+        // Внутренний цикл для обработки сообщений
+        let mut last_message_time = std::time::Instant::now();
+        let mut connection_alive = true;
+
+        let mut ping_interval = match &config.ping {
+            Some(ping) => {
+                let mut interval = tokio::time::interval(ping.interval);
+                interval.tick().await; // Первый tick срабатывает сразу
+                Some(interval)
+            }
+            None => None,
+        };
+
+        while connection_alive {
+            // Дедлайн считается от последнего сообщения, чтобы отправка ping не сбрасывала таймаут
+            let deadline = tokio::time::Instant::from_std(last_message_time + config.heartbeat_timeout);
+            let message = tokio::select! {
+                _ = wait_for_shutdown(&mut shutdown) => {
+                    if let Err(e) = ws_stream.close(None).await {
+                        warn!("Failed to close {} WebSocket: {}", name, e);
+                    }
+                    info!("{} WebSocket stopped", name);
+                    return;
+                }
+                _ = async {
+                    match ping_interval.as_mut() {
+                        Some(interval) => interval.tick().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    if let Some(ping) = &config.ping {
+                        if let Err(e) = ws_stream.send(Message::Text((ping.message)())).await {
+                            warn!("Failed to send ping to {}: {}", name, e);
+                            connection_alive = false;
+                        }
+                    }
+                    continue;
+                }
+                // Используем timeout для обнаружения "тихих" разрывов соединения
+                message = tokio::time::timeout_at(deadline, ws_stream.next()) => message,
+            };
+
+            match message {
+                Ok(Some(Ok(Message::Text(text)))) => {
+                    last_message_time = std::time::Instant::now();
+                    shared_state.record_feed_message(name);
+                    handle_text(text).await;
+                }
+                Ok(Some(Ok(Message::Ping(payload)))) => {
+                    // Отвечаем на ping
+                    if let Err(e) = ws_stream.send(Message::Pong(payload)).await {
+                        warn!("Failed to send pong to {}: {}", name, e);
+                        connection_alive = false;
+                    }
+                }
+                Ok(Some(Ok(Message::Pong(_)))) => {
+                    // Игнорируем pong сообщения
+                }
+                Ok(Some(Ok(Message::Binary(_)))) => {
+                    // Игнорируем binary сообщения (если они появятся)
+                }
+                Ok(Some(Ok(Message::Close(_)))) => {
+                    warn!("{} WebSocket connection closed by server", name);
+                    connection_alive = false;
+                }
+                Ok(Some(Err(e))) => {
+                    error!("{} WebSocket error: {}", name, e);
+                    connection_alive = false;
+                }
+                Ok(None) => {
+                    warn!("{} WebSocket stream ended", name);
+                    connection_alive = false;
+                }
+                Err(_) => {
+                    // Timeout - возможно соединение тихо разорвано
+                    let elapsed = last_message_time.elapsed();
+                    warn!("No messages received from {} for {:?}. Connection may be lost.", name, elapsed);
+                    connection_alive = false;
+                }
+            }
+        }
+
+        // Соединение потеряно, пытаемся переподключиться
+        error!("{} WebSocket connection lost. Attempting to reconnect...", name);
+        reconnect_count += 1;
+        shared_state.record_reconnect(name);
+
+        if config.attempts_exhausted(reconnect_count) {
+            error!("Max reconnection attempts ({}) reached. Exiting.", config.max_attempts);
+            return;
+        }
+
+        let delay = backoff.next_delay();
+        warn!("Reconnecting in {:?}... (attempt {}{})",
+              delay,
+              reconnect_count,
+              if config.max_attempts > 0 {
+                  format!("/{}", config.max_attempts)
+              } else {
+                  "".to_string()
+              });
+        if sleep_or_shutdown(delay, &mut shutdown).await {
+            info!("{} WebSocket stopped", name);
+            return;
+        }
+    }
+}